@@ -0,0 +1,247 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use crate::{calendar::CorporateEvent, notify::{self, Channel}, rules::Rule, schedule::HumanDuration, template::{Fields, Value}, Decimal, NewsItem, Stock, StockError};
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlertKind {
+    /// The price moved more than the configured percentage since the last poll.
+    Move,
+    /// The price crossed a target price going up.
+    CrossedAbove(Decimal),
+    /// The price crossed a target price going down.
+    CrossedBelow(Decimal),
+    /// A configured rule started to hold.
+    Rule(Rule),
+    /// The `--script` fired an alert with this message.
+    Script(String),
+    /// The symbol failed this many times in a row and won't be polled for a
+    /// while.
+    CircuitOpened { failures: u32, cooldown: Duration },
+    /// The symbol's last quote is this old, older than a few polls should
+    /// have left it.
+    Stale { age: Duration },
+    /// A headline about the company appeared.
+    News(NewsItem),
+    /// An earnings report or ex-dividend date is this many days away.
+    Upcoming { event: CorporateEvent, days: u32 },
+}
+
+#[derive(Debug, Clone)]
+pub struct AlertEvent {
+    pub kind: AlertKind,
+    pub symbol: String,
+    pub company_name: String,
+    pub previous_price: Decimal,
+    pub price: Decimal,
+    pub change_pct: f64,
+    /// Recent prices of the symbol, oldest first, for charting.
+    pub history: Vec<f64>,
+}
+
+impl AlertEvent {
+    fn new(kind: AlertKind, past_stock: &Stock, nstock: &Stock) -> Self {
+        Self {
+            kind,
+            symbol: nstock.symbol.clone(),
+            company_name: nstock.company_name.clone(),
+            previous_price: past_stock.price,
+            price: nstock.price,
+            change_pct: change_pct(past_stock.price, nstock.price),
+            history: Vec::new(),
+        }
+    }
+
+    pub fn for_rule(rule: &Rule, stock: &Stock) -> Self {
+        Self {
+            kind: AlertKind::Rule(rule.clone()),
+            symbol: stock.symbol.clone(),
+            company_name: stock.company_name.clone(),
+            previous_price: stock.previous_price.unwrap_or(stock.price),
+            price: stock.price,
+            change_pct: stock.change_pct.unwrap_or_default(),
+            history: Vec::new(),
+        }
+    }
+
+    pub fn for_script(message: String, stock: &Stock) -> Self {
+        Self {
+            kind: AlertKind::Script(message),
+            symbol: stock.symbol.clone(),
+            company_name: stock.company_name.clone(),
+            previous_price: stock.previous_price.unwrap_or(stock.price),
+            price: stock.price,
+            change_pct: stock.change_pct.unwrap_or_default(),
+            history: Vec::new(),
+        }
+    }
+
+    /// `past_stock` is the last quote of the symbol, if it ever had one.
+    pub fn circuit_opened(share_code: &str, past_stock: Option<&Stock>, failures: u32, cooldown: Duration) -> Self {
+        let price = past_stock.map(|stock| stock.price).unwrap_or_default();
+
+        Self {
+            kind: AlertKind::CircuitOpened { failures, cooldown },
+            symbol: past_stock.map(|stock| stock.symbol.clone()).unwrap_or(share_code.to_string()),
+            company_name: past_stock.map(|stock| stock.company_name.clone()).unwrap_or_default(),
+            previous_price: price,
+            price,
+            change_pct: 0f64,
+            history: Vec::new(),
+        }
+    }
+
+    /// `stock` is the last quote of the symbol, fetched `age` ago.
+    pub fn stale(stock: &Stock, age: Duration) -> Self {
+        Self {
+            kind: AlertKind::Stale { age },
+            symbol: stock.symbol.clone(),
+            company_name: stock.company_name.clone(),
+            previous_price: stock.price,
+            price: stock.price,
+            change_pct: 0f64,
+            history: Vec::new(),
+        }
+    }
+
+    /// `stock` is the quote `item` came with.
+    pub fn news(stock: &Stock, item: NewsItem) -> Self {
+        Self {
+            kind: AlertKind::News(item),
+            symbol: stock.symbol.clone(),
+            company_name: stock.company_name.clone(),
+            previous_price: stock.previous_price.unwrap_or(stock.price),
+            price: stock.price,
+            change_pct: stock.change_pct.unwrap_or_default(),
+            history: Vec::new(),
+        }
+    }
+
+    /// Has no prices: calendars are fetched apart from the quotes.
+    pub fn upcoming(event: CorporateEvent, days: u32) -> Self {
+        Self {
+            symbol: event.symbol.clone(),
+            kind: AlertKind::Upcoming { event, days },
+            company_name: String::new(),
+            previous_price: Decimal::ZERO,
+            price: Decimal::ZERO,
+            change_pct: 0f64,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn with_history(mut self, history: Vec<f64>) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Whether the alert should go out on `channel`; rules may name the
+    /// channels they notify, every other alert goes everywhere. The console
+    /// always gets it.
+    pub fn wants(&self, channel: Channel) -> bool {
+        match &self.kind {
+            _ if channel == Channel::Console => true,
+            AlertKind::Rule(rule) => rule.notify.is_empty() || rule.notify.contains(&channel),
+            _ => true,
+        }
+    }
+}
+
+/// For notification templates; `alert` is the console alert line.
+impl Fields for AlertEvent {
+    const NAMES: &'static [&'static str] = &["symbol", "company", "price", "previous_price", "change_pct", "alert", "sparkline"];
+
+    fn value(&self, name: &str) -> Value {
+        match name {
+            "symbol" => Value::Text(self.symbol.clone()),
+            "company" => Value::Text(self.company_name.clone()),
+            "price" => Value::Number(self.price),
+            "previous_price" => Value::Number(self.previous_price),
+            "change_pct" => Some(self.change_pct).and_then(Decimal::from_f64).map(Value::Change).unwrap_or(Value::Missing),
+            "alert" => Value::Text(self.to_string()),
+            "sparkline" => Value::Text(notify::sparkline(&self.history)),
+            _ => Value::Missing,
+        }
+    }
+}
+
+impl fmt::Display for AlertEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            AlertKind::Move => write!(f, "{} moved {:+.2}% from {} to {}", self.symbol, self.change_pct, self.previous_price, self.price),
+            AlertKind::CrossedAbove(target) => write!(f, "{} crossed above {} at {} ({:+.2}%)", self.symbol, target, self.price, self.change_pct),
+            AlertKind::CrossedBelow(target) => write!(f, "{} crossed below {} at {} ({:+.2}%)", self.symbol, target, self.price, self.change_pct),
+            AlertKind::Rule(rule) => write!(f, "{} matched rule \"{}\" at {} ({:+.2}%)", self.symbol, rule, self.price, self.change_pct),
+            AlertKind::Script(message) => write!(f, "{} {} at {} ({:+.2}%)", self.symbol, message, self.price, self.change_pct),
+            AlertKind::CircuitOpened { failures, cooldown } => write!(f, "{} failed {} times in a row, pausing it for {}", self.symbol, failures, HumanDuration(*cooldown)),
+            AlertKind::Stale { age } => write!(f, "{} hasn't been refreshed for {}, still at {}", self.symbol, HumanDuration(*age), self.price),
+            AlertKind::Upcoming { event, days: 0 } => write!(f, "{} {} today", self.symbol, event.kind),
+            AlertKind::Upcoming { event, days: 1 } => write!(f, "{} {} tomorrow, on {}", self.symbol, event.kind, event.date),
+            AlertKind::Upcoming { event, days } => write!(f, "{} {} in {} days, on {}", self.symbol, event.kind, days, event.date),
+            AlertKind::News(item) => write!(f, "{} in the news: {} ({}) {}", self.symbol, item.headline, item.source, item.url),
+        }
+    }
+}
+
+/// A `SYMBOL=PRICE` target, e.g. `AAPL=200`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceTarget {
+    pub symbol: String,
+    pub price: Decimal,
+}
+
+impl FromStr for PriceTarget {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (symbol, price) = value.split_once('=')
+            .ok_or_else(|| {
+                StockError::invalid("target", format!("expected SYMBOL=PRICE, got {}", value))
+            })?;
+
+        let price = price.trim().parse::<Decimal>()
+            .map_err(|err| {
+                StockError::invalid("target", format!("{}: {}", value, err))
+            })?;
+
+        let symbol = symbol.trim().to_uppercase().split(':').next().unwrap_or_default().to_string();
+
+        Ok(Self { symbol, price })
+    }
+}
+
+fn change_pct(previous_price: Decimal, price: Decimal) -> f64 {
+    if previous_price.is_zero() {
+        return 0f64;
+    }
+
+    (price - previous_price).to_f64() / previous_price.to_f64() * 100f64
+}
+
+pub fn price_move(past_stock: &Stock, nstock: &Stock, threshold_pct: f64) -> Option<AlertEvent> {
+    if past_stock.price.is_zero() {
+        return None;
+    }
+
+    if change_pct(past_stock.price, nstock.price).abs() <= threshold_pct {
+        return None;
+    }
+
+    Some(AlertEvent::new(AlertKind::Move, past_stock, nstock))
+}
+
+pub fn target_cross(past_stock: &Stock, nstock: &Stock, target: &PriceTarget) -> Option<AlertEvent> {
+    if target.symbol != nstock.symbol || past_stock.price.is_zero() {
+        return None;
+    }
+
+    if past_stock.price < target.price && nstock.price >= target.price {
+        return Some(AlertEvent::new(AlertKind::CrossedAbove(target.price), past_stock, nstock));
+    }
+
+    if past_stock.price > target.price && nstock.price <= target.price {
+        return Some(AlertEvent::new(AlertKind::CrossedBelow(target.price), past_stock, nstock));
+    }
+
+    None
+}
@@ -0,0 +1,107 @@
+use std::{fmt, path::Path};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{candle::CandleSeries, indicators::{IndicatorOptions, PriceHistory}, simulate::{PaperTrader, SessionReport, SimulationOptions}, Decimal, Stock, StockError};
+
+
+/// How the strategies did over past quotes.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BacktestReport {
+    /// Quotes replayed.
+    pub quotes: usize,
+    pub first: Option<DateTime<Utc>>,
+    pub last: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    pub session: SessionReport,
+    /// Percentage of the closed trades that made money; `None` until a
+    /// position has been sold.
+    pub win_rate: Option<f64>,
+    /// Largest fall of the equity from an earlier high, in percent.
+    pub max_drawdown_pct: f64,
+}
+
+impl fmt::Display for BacktestReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} quotes", self.quotes)?;
+
+        if let (Some(first), Some(last)) = (self.first, self.last) {
+            write!(f, " from {} to {}", first.format("%Y-%m-%d %H:%M"), last.format("%Y-%m-%d %H:%M"))?;
+        }
+
+        write!(f, ": {}", self.session)?;
+
+        match self.win_rate {
+            Some(win_rate) => write!(f, ", win rate {:.1}%", win_rate)?,
+            None => write!(f, ", no closed trades")?,
+        }
+
+        write!(f, ", max drawdown {:.2}%", self.max_drawdown_pct)
+    }
+}
+
+/// Replays `quotes`, each with the code its strategies trade, through the
+/// indicators and the paper trader the way live quotes pass through them,
+/// in the order they were fetched.
+pub fn run(mut quotes: Vec<(String, Stock)>, indicators: IndicatorOptions, simulation: SimulationOptions) -> BacktestReport {
+    quotes.sort_by_key(|(_, stock)| stock.fetched_at);
+
+    let mut history = PriceHistory::new(indicators);
+    let mut candles = CandleSeries::new();
+    let mut trader = PaperTrader::new(simulation);
+    let mut peak = trader.equity();
+    let mut max_drawdown_pct = 0f64;
+
+    for (share_code, stock) in quotes.iter_mut() {
+        let closed = candles.record(share_code, stock.price, stock.fetched_at);
+        stock.indicators = Some(history.record_poll(share_code, stock.price.to_f64(), &closed));
+
+        trader.record(share_code, stock);
+
+        let equity = trader.equity();
+        peak = peak.max(equity);
+
+        if !peak.is_zero() {
+            max_drawdown_pct = max_drawdown_pct.max((peak - equity).to_f64() / peak.to_f64() * 100f64);
+        }
+    }
+
+    let session = trader.report();
+    let closed: Vec<Decimal> = session.trades.iter().filter_map(|trade| trade.pnl).collect();
+    let win_rate = (!closed.is_empty())
+        .then(|| closed.iter().filter(|pnl| **pnl > Decimal::ZERO).count() as f64 / closed.len() as f64 * 100f64);
+
+    BacktestReport {
+        quotes: quotes.len(),
+        first: quotes.first().map(|(_, stock)| stock.fetched_at),
+        last: quotes.last().map(|(_, stock)| stock.fetched_at),
+        session,
+        win_rate,
+        max_drawdown_pct,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BarRow {
+    date: NaiveDate,
+    symbol: String,
+    close: Decimal,
+}
+
+/// Reads the daily bars of a CSV file written by `history --output csv` as
+/// quotes at their close, dated at the start of their day in UTC.
+pub fn read_csv(path: &Path) -> Result<Vec<Stock>, StockError> {
+    let file = std::fs::File::open(path)
+        .map_err(|err| {
+            StockError::io(path, err)
+        })?;
+    let mut reader = csv::Reader::from_reader(file);
+
+    reader.deserialize::<BarRow>()
+        .map(|row| {
+            let row = row.map_err(|err| StockError::parse(path.display(), err))?;
+
+            Ok(Stock::new(row.symbol.clone(), row.symbol, row.close).with_fetched_at(row.date.and_time(NaiveTime::MIN).and_utc()))
+        })
+        .collect()
+}
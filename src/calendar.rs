@@ -0,0 +1,114 @@
+use std::{collections::HashSet, fmt, sync::Arc, thread, time::Duration};
+use chrono::{NaiveDate, Utc};
+use serde::Serialize;
+use tracing::{debug, warn};
+
+use crate::{alert::AlertEvent, notify::Dispatcher, scraping::{coingecko, CalendarProvider}, shutdown::Shutdown, symbol, QuoteKind};
+
+/// How often the calendar of every watched equity is fetched again.
+pub const CALENDAR_CHECK: Duration = Duration::from_secs(6 * 60 * 60);
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// The company reports its quarterly results.
+    Earnings,
+    /// The first day the shares trade without the next dividend.
+    ExDividend,
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EventKind::Earnings => write!(f, "reports earnings"),
+            EventKind::ExDividend => write!(f, "goes ex-dividend"),
+        }
+    }
+}
+
+/// A dated event in a company's calendar.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct CorporateEvent {
+    /// Bare, e.g. `AAPL`.
+    pub symbol: String,
+    pub kind: EventKind,
+    pub date: NaiveDate,
+}
+
+impl fmt::Display for CorporateEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} on {}", self.symbol, self.kind, self.date)
+    }
+}
+
+/// Remembers the events already reminded of, so each is reminded of once.
+#[derive(Debug, Clone, Default)]
+pub struct Reminders {
+    days_ahead: u32,
+    sent: HashSet<CorporateEvent>,
+}
+
+impl Reminders {
+    /// Reminds of events up to `days_ahead` days before they happen.
+    pub fn new(days_ahead: u32) -> Self {
+        Self { days_ahead, sent: HashSet::new() }
+    }
+
+    /// The events of `events` that are due a reminder on `today`, with the
+    /// days left until each: those at most `days_ahead` days away and not
+    /// reminded of before. Past events never are.
+    pub fn due(&mut self, events: &[CorporateEvent], today: NaiveDate) -> Vec<(CorporateEvent, u32)> {
+        events.iter()
+            .filter_map(|event| {
+                let days = u32::try_from((event.date - today).num_days()).ok()?;
+
+                (days <= self.days_ahead && self.sent.insert(event.clone())).then(|| (event.clone(), days))
+            })
+            .collect()
+    }
+}
+
+/// Codes with a calendar worth following: equities, not funds, pairs,
+/// indices or crypto.
+pub fn has_calendar(share_code: &str) -> bool {
+    symbol::kind_of(share_code) == QuoteKind::Equity && !coingecko::is_crypto_symbol(share_code)
+}
+
+/// Fetches the calendar of each of `codes` that has one every
+/// [`CALENDAR_CHECK`], and notifies `dispatcher` of the events coming up
+/// within `days_ahead` days, until `shutdown` is requested.
+pub fn watch(provider: Arc<dyn CalendarProvider>, codes: Vec<String>, days_ahead: u32, dispatcher: Dispatcher, shutdown: Shutdown) {
+    let codes = codes.into_iter().filter(|code| has_calendar(code)).collect::<Vec<String>>();
+    let mut reminders = Reminders::new(days_ahead);
+
+    if codes.is_empty() {
+        return;
+    }
+
+    thread::spawn(move || loop {
+        for share_code in codes.iter() {
+            if shutdown.is_requested() {
+                return;
+            }
+
+            let events = match provider.fetch_calendar(share_code) {
+                Ok(events) => events,
+                Err(err) => {
+                    warn!(symbol = %share_code, "Failed to fetch the calendar of {} from {}: {}", share_code, provider.name(), err);
+                    continue;
+                }
+            };
+
+            debug!(symbol = %share_code, "{} has {} upcoming events", share_code, events.len());
+
+            for (event, days) in reminders.due(&events, Utc::now().date_naive()) {
+                dispatcher.dispatch(&AlertEvent::upcoming(event, days));
+            }
+        }
+
+        if shutdown.wait_timeout(CALENDAR_CHECK) {
+            return;
+        }
+    });
+}
@@ -0,0 +1,146 @@
+use std::{collections::{HashMap, VecDeque}, fmt, str::FromStr, time::Duration};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{Decimal, StockError};
+
+/// Candles kept in memory per symbol and interval, the one forming included.
+pub const MAX_CANDLES: usize = 500;
+
+
+/// An OHLC bar starting at `timestamp`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Candle {
+    pub timestamp: DateTime<Utc>,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub volume: Option<u64>,
+}
+
+impl Candle {
+    /// A candle starting at `timestamp` with `price` as its only trade.
+    fn opened_at(timestamp: DateTime<Utc>, price: Decimal) -> Self {
+        Self { timestamp, open: price, high: price, low: price, close: price, volume: None }
+    }
+
+    fn add(&mut self, price: Decimal) {
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        self.close = price;
+    }
+}
+
+/// How long the candles aggregated from polled quotes are: `1m`, `5m` or
+/// `1h`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub enum CandleInterval {
+    OneMinute,
+    FiveMinutes,
+    OneHour,
+}
+
+impl CandleInterval {
+    pub const ALL: [CandleInterval; 3] = [CandleInterval::OneMinute, CandleInterval::FiveMinutes, CandleInterval::OneHour];
+
+    pub fn duration(&self) -> Duration {
+        match self {
+            CandleInterval::OneMinute => Duration::from_secs(60),
+            CandleInterval::FiveMinutes => Duration::from_secs(5 * 60),
+            CandleInterval::OneHour => Duration::from_secs(60 * 60),
+        }
+    }
+
+    /// The start of the candle `timestamp` falls in; candles are aligned to
+    /// the hour in UTC, so a 5m candle starts at :00, :05, ...
+    pub fn start_of(&self, timestamp: DateTime<Utc>) -> DateTime<Utc> {
+        let length = self.duration().as_secs() as i64;
+        let seconds = timestamp.timestamp();
+
+        DateTime::from_timestamp(seconds - seconds.rem_euclid(length), 0).unwrap_or(timestamp)
+    }
+}
+
+impl FromStr for CandleInterval {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "1m" => Ok(CandleInterval::OneMinute),
+            "5m" => Ok(CandleInterval::FiveMinutes),
+            "1h" => Ok(CandleInterval::OneHour),
+            _ => Err(StockError::invalid("candle interval", format!("{}, expected 1m, 5m or 1h", value))),
+        }
+    }
+}
+
+impl TryFrom<String> for CandleInterval {
+    type Error = StockError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for CandleInterval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CandleInterval::OneMinute => write!(f, "1m"),
+            CandleInterval::FiveMinutes => write!(f, "5m"),
+            CandleInterval::OneHour => write!(f, "1h"),
+        }
+    }
+}
+
+/// Aggregates the polled prices of each symbol into candles of every
+/// [`CandleInterval`]. A poll in a later interval than the candle forming
+/// closes it; intervals without a poll get no candle.
+#[derive(Debug, Clone, Default)]
+pub struct CandleSeries {
+    candles: HashMap<(String, CandleInterval), VecDeque<Candle>>,
+}
+
+impl CandleSeries {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `price`, observed at `timestamp`, to the candles of `symbol`,
+    /// returning those it closed. Prices older than the candle forming are
+    /// left out.
+    pub fn record(&mut self, symbol: &str, price: Decimal, timestamp: DateTime<Utc>) -> Vec<(CandleInterval, Candle)> {
+        let mut closed = Vec::new();
+
+        for interval in CandleInterval::ALL {
+            let start = interval.start_of(timestamp);
+            let candles = self.candles.entry((symbol.to_string(), interval)).or_default();
+
+            match candles.back_mut() {
+                Some(forming) if forming.timestamp == start => forming.add(price),
+                Some(forming) if forming.timestamp > start => {}
+                forming => {
+                    closed.extend(forming.map(|forming| (interval, forming.clone())));
+
+                    if candles.len() == MAX_CANDLES {
+                        candles.pop_front();
+                    }
+                    candles.push_back(Candle::opened_at(start, price));
+                }
+            }
+        }
+
+        closed
+    }
+
+    /// The last `limit` candles of `symbol`, oldest first, the last one
+    /// still forming.
+    pub fn candles(&self, symbol: &str, interval: CandleInterval, limit: usize) -> Vec<Candle> {
+        let Some(candles) = self.candles.get(&(symbol.to_string(), interval)) else {
+            return Vec::new();
+        };
+
+        candles.iter().skip(candles.len().saturating_sub(limit)).cloned().collect()
+    }
+}
@@ -0,0 +1,76 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}, time::Duration};
+use chrono::{DateTime, Utc};
+
+/// Failures in a row that open a symbol's circuit.
+pub const DEFAULT_FAILURES: u32 = 5;
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(5 * 60);
+/// Longest a circuit stays open, however often it reopened.
+const MAX_COOLDOWN: Duration = Duration::from_secs(60 * 60);
+
+
+#[derive(Debug, Clone, Default)]
+struct Circuit {
+    /// Failures since the last success.
+    failures: u32,
+    /// Times the circuit opened since the last success.
+    opened: u32,
+    open_until: Option<DateTime<Utc>>,
+}
+
+/// Stops polling symbols that keep failing. After `failures` failed fetches
+/// in a row a symbol's circuit opens and it is left out of polls for the
+/// cooldown. Then it is tried once: a success closes the circuit, a failure
+/// opens it again for twice as long, up to an hour.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    /// 0 never opens a circuit.
+    failures: u32,
+    cooldown: Duration,
+    circuits: Arc<Mutex<HashMap<String, Circuit>>>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(DEFAULT_FAILURES, DEFAULT_COOLDOWN)
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(failures: u32, cooldown: Duration) -> Self {
+        Self { failures, cooldown, circuits: Arc::default() }
+    }
+
+    /// Until when `share_code` is left out of polls, if its circuit is open.
+    pub fn open_until(&self, share_code: &str) -> Option<DateTime<Utc>> {
+        let circuits = self.circuits.lock().unwrap_or_else(|err| err.into_inner());
+
+        circuits.get(share_code)?.open_until.filter(|open_until| *open_until > Utc::now())
+    }
+
+    /// Records a failed fetch of `share_code`, returning how long its circuit
+    /// stays open when this failure opened it.
+    pub fn failed(&self, share_code: &str) -> Option<Duration> {
+        let mut circuits = self.circuits.lock().unwrap_or_else(|err| err.into_inner());
+        let circuit = circuits.entry(share_code.to_string()).or_default();
+
+        circuit.failures += 1;
+
+        if self.failures == 0 || circuit.failures < self.failures {
+            return None;
+        }
+
+        let cooldown = self.cooldown.saturating_mul(2u32.saturating_pow(circuit.opened)).min(MAX_COOLDOWN);
+        circuit.opened += 1;
+        circuit.open_until = Some(Utc::now() + cooldown);
+
+        Some(cooldown)
+    }
+
+    /// Records a successful fetch of `share_code`, returning whether it
+    /// closed an open circuit.
+    pub fn succeeded(&self, share_code: &str) -> bool {
+        let mut circuits = self.circuits.lock().unwrap_or_else(|err| err.into_inner());
+
+        circuits.remove(share_code).is_some_and(|circuit| circuit.opened > 0)
+    }
+}
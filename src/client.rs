@@ -0,0 +1,283 @@
+use std::{collections::{hash_map::Entry, HashMap, HashSet}, fmt, sync::{Arc, Mutex}, time::{Duration, Instant}};
+use chrono::Utc;
+use tracing::warn;
+
+use crate::{fx::FxConverter, http::Fetcher, market, ratelimit::RateLimits, retry::RetryPolicy, scraping::{self, replay::Recorder, selectors::Selectors, ApiKeys, FundamentalsProvider, NewsProvider, QuoteProvider, Source}, symbol, Fundamentals, NewsItem, QuoteKind, Stock, StockError};
+
+/// How long fundamentals are attached to quotes before they are fetched
+/// again; they only change with earnings reports and dividends.
+pub const FUNDAMENTALS_TTL: Duration = Duration::from_secs(60 * 60);
+/// How often each symbol's news is checked for new headlines.
+pub const NEWS_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+
+/// Entry point for embedding the scraper in other programs.
+#[derive(Clone)]
+pub struct StockClient {
+    provider: Arc<dyn QuoteProvider>,
+    retry: RetryPolicy,
+    converter: Option<FxConverter>,
+    fundamentals: Option<Arc<FundamentalsCache>>,
+    news: Option<Arc<NewsFeed>>,
+    recorder: Option<Recorder>,
+}
+
+impl StockClient {
+    pub fn new() -> Self {
+        Self::with_source(Source::default())
+    }
+
+    pub fn with_source(source: Source) -> Self {
+        Self { provider: source.provider(), retry: RetryPolicy::default(), converter: None, fundamentals: None, news: None, recorder: None }
+    }
+
+    /// Like [`StockClient::with_source`], sending requests through `fetcher`,
+    /// waiting as needed to stay within each provider's requests-per-minute
+    /// cap, reading pages with `selectors`, calling APIs with `keys` and
+    /// saving responses to `recorder`.
+    pub fn with_source_using(source: Source, fetcher: &Fetcher, limits: &RateLimits, selectors: &Selectors, keys: &ApiKeys, recorder: Option<&Recorder>) -> Self {
+        Self { provider: source.provider_with(fetcher, limits, selectors, keys, recorder), retry: RetryPolicy::default(), converter: None, fundamentals: None, news: None, recorder: recorder.cloned() }
+    }
+
+    pub fn with_provider(provider: impl QuoteProvider + 'static) -> Self {
+        Self { provider: Arc::new(provider), retry: RetryPolicy::default(), converter: None, fundamentals: None, news: None, recorder: None }
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Converts every fetched quote into the converter's base currency.
+    pub fn convert_to(mut self, converter: FxConverter) -> Self {
+        self.converter = Some(converter);
+        self
+    }
+
+    /// Attaches the fundamentals of every equity quote, fetched from
+    /// `provider` at most once per [`FUNDAMENTALS_TTL`].
+    pub fn fundamentals_from(mut self, provider: Arc<dyn FundamentalsProvider>) -> Self {
+        self.fundamentals = Some(Arc::new(FundamentalsCache { provider, fetched: Mutex::default() }));
+        self
+    }
+
+    /// Attaches the headlines `provider` lists for an equity or ETF that
+    /// weren't attached before, checking at most once per [`NEWS_INTERVAL`].
+    pub fn news_from(mut self, provider: Arc<dyn NewsProvider>) -> Self {
+        self.news = Some(Arc::new(NewsFeed { provider, checked: Mutex::default() }));
+        self
+    }
+
+    pub fn provider(&self) -> &dyn QuoteProvider {
+        self.provider.as_ref()
+    }
+
+    /// Fetches the latest quote for a `SYMBOL:EXCHANGE` code, e.g. `AAPL:NASDAQ`.
+    ///
+    /// Transient failures are retried according to the client's [`RetryPolicy`].
+    /// Quotes whose source doesn't report the market status get it from the
+    /// exchange's calendar, and those it doesn't tell the kind of get it from
+    /// the code.
+    pub fn fetch_quote(&self, symbol: &str) -> Result<Stock, StockError> {
+        let result = self.retry.run(symbol, || self.provider.fetch(symbol));
+        self.settle(symbol, &result);
+
+        self.complete(symbol, result?)
+    }
+
+    /// Groups `symbols` into the batches [`StockClient::fetch_batch`] takes,
+    /// several to a request where the source can quote them together.
+    pub fn batches(&self, symbols: &[String]) -> Vec<Vec<String>> {
+        self.provider.batches(symbols)
+    }
+
+    /// Fetches one of the batches made by [`StockClient::batches`] in a single
+    /// request, returning a result per symbol in the same order and
+    /// completed like [`StockClient::fetch_quote`] does. A failed request is
+    /// retried as a whole; when it keeps failing, every symbol in the batch
+    /// fails with it.
+    pub fn fetch_batch(&self, symbols: &[String]) -> Vec<Result<Stock, StockError>> {
+        if let [symbol] = symbols {
+            return vec![self.fetch_quote(symbol)];
+        }
+
+        match self.retry.run(&symbols.join(","), || self.provider.fetch_batch(symbols)) {
+            Ok(results) => symbols.iter()
+                .zip(results)
+                .map(|(symbol, result)| {
+                    self.settle(symbol, &result);
+                    result.and_then(|stock| self.complete(symbol, stock))
+                })
+                .collect(),
+            Err(err) => failed_batch(symbols, err),
+        }
+    }
+
+    /// Lets the recorder the client was built with know how parsing went.
+    fn settle(&self, symbol: &str, result: &Result<Stock, StockError>) {
+        if let Some(recorder) = &self.recorder {
+            recorder.settle(symbol, result);
+        }
+    }
+
+    /// Fills in what the source left out of `stock` and converts it to the
+    /// base currency.
+    fn complete(&self, symbol: &str, mut stock: Stock) -> Result<Stock, StockError> {
+        if stock.market_status.is_none() {
+            stock.market_status = market::status(symbol, Utc::now());
+        }
+
+        if stock.kind == QuoteKind::Equity {
+            stock.kind = symbol::kind_of(symbol);
+        }
+
+        if let Some(fundamentals) = self.fundamentals.as_ref().filter(|_| stock.kind == QuoteKind::Equity) {
+            stock.fundamentals = fundamentals.get(symbol);
+        }
+
+        if let Some(news) = self.news.as_ref().filter(|_| matches!(stock.kind, QuoteKind::Equity | QuoteKind::Etf)) {
+            stock.news = news.fresh(symbol);
+        }
+
+        // A pair is already the rate between two currencies.
+        if let Some(converter) = self.converter.as_ref().filter(|_| stock.kind != QuoteKind::Fx) {
+            converter.convert(&mut stock)?;
+        }
+
+        Ok(stock)
+    }
+
+    /// Same as [`StockClient::fetch_quote`] for async code: the provider's
+    /// [`QuoteProvider::async_fetch`] is awaited and retries back off on
+    /// tokio's timer. The fundamentals, news and rates attached afterwards
+    /// are fetched on tokio's blocking pool.
+    pub async fn async_fetch_quote(&self, symbol: &str) -> Result<Stock, StockError> {
+        let result = self.retry.async_run(symbol, || Arc::clone(&self.provider).async_fetch(symbol)).await;
+        self.settle(symbol, &result);
+
+        let (client, symbol) = (self.clone(), symbol.to_string());
+
+        scraping::blocking(move || client.complete(&symbol, result?)).await?
+    }
+
+    /// Same as [`StockClient::fetch_batch`] for async code, like
+    /// [`StockClient::async_fetch_quote`].
+    pub async fn async_fetch_batch(&self, symbols: &[String]) -> Vec<Result<Stock, StockError>> {
+        if let [symbol] = symbols {
+            return vec![self.async_fetch_quote(symbol).await];
+        }
+
+        let results = match self.retry.async_run(&symbols.join(","), || Arc::clone(&self.provider).async_fetch_batch(symbols)).await {
+            Ok(results) => results,
+            Err(err) => return failed_batch(symbols, err),
+        };
+
+        let (client, batch) = (self.clone(), symbols.to_vec());
+
+        scraping::blocking(move || {
+            batch.iter()
+                .zip(results)
+                .map(|(symbol, result)| {
+                    client.settle(symbol, &result);
+                    result.and_then(|stock| client.complete(symbol, stock))
+                })
+                .collect()
+        })
+        .await
+        .unwrap_or_else(|err| failed_batch(symbols, err))
+    }
+}
+
+/// The fundamentals of each code and when they were fetched. A failed fetch
+/// keeps what was fetched before, and is only tried again after the TTL.
+struct FundamentalsCache {
+    provider: Arc<dyn FundamentalsProvider>,
+    fetched: Mutex<HashMap<String, (Instant, Option<Fundamentals>)>>,
+}
+
+impl FundamentalsCache {
+    fn get(&self, symbol: &str) -> Option<Fundamentals> {
+        let cached = self.fetched.lock().unwrap_or_else(|err| err.into_inner()).get(symbol).cloned();
+
+        let previous = match cached {
+            Some((fetched_at, fundamentals)) if fetched_at.elapsed() < FUNDAMENTALS_TTL => return fundamentals,
+            Some((_, fundamentals)) => fundamentals,
+            None => None,
+        };
+
+        let fundamentals = match self.provider.fetch_fundamentals(symbol) {
+            Ok(fundamentals) => Some(fundamentals),
+            Err(err) => {
+                warn!(symbol, "Failed to fetch the fundamentals of {} from {}: {}", symbol, self.provider.name(), err);
+                previous
+            }
+        };
+
+        self.fetched.lock().unwrap_or_else(|err| err.into_inner()).insert(symbol.to_string(), (Instant::now(), fundamentals.clone()));
+
+        fundamentals
+    }
+}
+
+/// When each code's news was last checked and the links seen so far.
+struct NewsFeed {
+    provider: Arc<dyn NewsProvider>,
+    checked: Mutex<HashMap<String, (Instant, HashSet<String>)>>,
+}
+
+impl NewsFeed {
+    /// The headlines of `symbol` not returned before, when it is due for a
+    /// check. A failed check is tried again after the interval.
+    fn fresh(&self, symbol: &str) -> Vec<NewsItem> {
+        let lock = || self.checked.lock().unwrap_or_else(|err| err.into_inner());
+
+        match lock().entry(symbol.to_string()) {
+            Entry::Occupied(entry) if entry.get().0.elapsed() < NEWS_INTERVAL => return Vec::new(),
+            Entry::Occupied(mut entry) => entry.get_mut().0 = Instant::now(),
+            Entry::Vacant(entry) => {
+                entry.insert((Instant::now(), HashSet::new()));
+            }
+        }
+
+        match self.provider.fetch_news(symbol) {
+            Ok(items) => match lock().get_mut(symbol) {
+                Some((_, seen)) => items.into_iter().filter(|item| seen.insert(item.url.clone())).collect(),
+                None => items,
+            },
+            Err(err) => {
+                warn!(symbol, "Failed to fetch the news of {} from {}: {}", symbol, self.provider.name(), err);
+                Vec::new()
+            }
+        }
+    }
+}
+
+/// The same failure for every symbol of a batch.
+fn failed_batch(symbols: &[String], err: StockError) -> Vec<Result<Stock, StockError>> {
+    let err = Arc::new(err);
+
+    symbols.iter().map(|_| Err(StockError::Batch(Arc::clone(&err)))).collect()
+}
+
+impl Default for StockClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for StockClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StockClient")
+            .field("provider", &self.provider.name())
+            .field("retry", &self.retry)
+            .field("base_currency", &self.converter.as_ref().map(|converter| converter.base()))
+            .field("fundamentals", &self.fundamentals.as_ref().map(|fundamentals| fundamentals.provider.name()))
+            .field("news", &self.news.as_ref().map(|news| news.provider.name()))
+            .finish()
+    }
+}
+
+/// Shorthand for `StockClient::new().fetch_quote(symbol)`.
+pub fn fetch_quote(symbol: &str) -> Result<Stock, StockError> {
+    StockClient::new().fetch_quote(symbol)
+}
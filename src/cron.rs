@@ -0,0 +1,187 @@
+use std::{fmt, str::FromStr};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use serde::Deserialize;
+
+use crate::StockError;
+
+/// How far ahead to look for the next match before giving up, e.g. on
+/// `0 0 30 2 *`.
+const SEARCH_YEARS: i32 = 5;
+
+const MONTHS: [&str; 12] = ["JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC"];
+const WEEKDAYS: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+
+/// A five-field cron expression, `minute hour day-of-month month day-of-week`,
+/// e.g. `*/5 9-16 * * MON-FRI`. Fields take `*`, numbers, `a-b` ranges,
+/// `/step`s and comma separated lists; months and weekdays also take names.
+/// As in cron, a day matches if either day field does when both are
+/// restricted.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct CronSchedule {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether the day-of-month / day-of-week field was `*`.
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronSchedule {
+    /// The first minute strictly after `after` that matches, read as wall
+    /// clock time in `timezone`. Minutes skipped by a DST change never match.
+    pub fn next_after<Z: TimeZone>(&self, after: DateTime<Utc>, timezone: &Z) -> Option<DateTime<Utc>> {
+        let local = after.with_timezone(timezone).naive_local();
+        let mut time = local.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = local.year() + SEARCH_YEARS;
+
+        while time.year() <= limit {
+            if !has(self.months, time.month()) {
+                time = first_of_next_month(time)?;
+                continue;
+            }
+
+            if !self.day_matches(time) {
+                time = time.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+
+            if !has(self.hours, time.hour()) {
+                time = time.with_minute(0)? + Duration::hours(1);
+                continue;
+            }
+
+            if !has(self.minutes, time.minute()) {
+                time += Duration::minutes(1);
+                continue;
+            }
+
+            match timezone.from_local_datetime(&time).earliest() {
+                Some(matched) => return Some(matched.with_timezone(&Utc)),
+                None => time += Duration::minutes(1),
+            }
+        }
+
+        None
+    }
+
+    fn day_matches(&self, time: NaiveDateTime) -> bool {
+        let day = has(self.days, time.day());
+        let weekday = has(self.weekdays, time.weekday().num_days_from_sunday());
+
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let fields = value.split_whitespace().collect::<Vec<&str>>();
+
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(invalid(value, "expected 5 fields: minute hour day-of-month month day-of-week"));
+        };
+
+        let weekdays_set = parse_field(weekdays, 0, 7, &WEEKDAYS, 0).map_err(|reason| invalid(value, &reason))?;
+
+        let schedule = Self {
+            expression: fields.join(" "),
+            minutes: parse_field(minutes, 0, 59, &[], 0).map_err(|reason| invalid(value, &reason))?,
+            hours: parse_field(hours, 0, 23, &[], 0).map_err(|reason| invalid(value, &reason))?,
+            days: parse_field(days, 1, 31, &[], 0).map_err(|reason| invalid(value, &reason))?,
+            months: parse_field(months, 1, 12, &MONTHS, 1).map_err(|reason| invalid(value, &reason))?,
+            // 7 is Sunday too.
+            weekdays: (weekdays_set | weekdays_set >> 7) & 0x7f,
+            any_day: days == "*",
+            any_weekday: weekdays == "*",
+        };
+
+        match schedule.next_after(Utc::now(), &Utc) {
+            Some(_) => Ok(schedule),
+            None => Err(invalid(value, "never matches")),
+        }
+    }
+}
+
+impl TryFrom<String> for CronSchedule {
+    type Error = StockError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+fn has(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+fn first_of_next_month(time: NaiveDateTime) -> Option<NaiveDateTime> {
+    let (year, month) = match time.month() {
+        12 => (time.year() + 1, 1),
+        month => (time.year(), month + 1),
+    };
+
+    NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)
+}
+
+/// Parses one field into a bit set of the values it matches. `names` are
+/// accepted in place of numbers, the first one standing for `first_name`.
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str], first_name: u32) -> Result<u64, String> {
+    let value = |part: &str| -> Result<u32, String> {
+        let named = names.iter()
+            .position(|name| name.eq_ignore_ascii_case(part))
+            .map(|index| index as u32 + first_name);
+
+        match named.or_else(|| part.parse().ok()) {
+            Some(value) if (min..=max).contains(&value) => Ok(value),
+            _ => Err(format!("{} is not between {} and {}", part, min, max)),
+        }
+    };
+
+    let mut set = 0u64;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|step| *step > 0).ok_or(format!("bad step in {}", part))?),
+            None => (part, 1),
+        };
+
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (value(start)?, value(end)?),
+            // `5/15` runs from 5 to the end of the range.
+            None if step > 1 => (value(range)?, max),
+            None => (value(range)?, value(range)?),
+        };
+
+        if start > end {
+            return Err(format!("{} runs backwards", part));
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+
+    Ok(set)
+}
+
+fn invalid(expression: &str, reason: &str) -> StockError {
+    StockError::invalid("schedule", format!("{:?}: {}", expression, reason))
+}
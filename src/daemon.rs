@@ -0,0 +1,165 @@
+use std::{fs::{self, OpenOptions}, io, os::{fd::AsRawFd, unix::net::UnixDatagram}, path::{Path, PathBuf}, sync::atomic::{AtomicBool, Ordering}, thread, time::Duration};
+use tracing::{info, warn};
+
+use crate::{shutdown::Shutdown, StockError};
+
+pub const LOG_FILE: &str = "scraping-stock.log";
+pub const DEFAULT_PID_FILE: &str = "scraping-stock.pid";
+
+/// Set by the SIGHUP handler, cleared by [`take_hangup`].
+static HANGUP: AtomicBool = AtomicBool::new(false);
+
+
+/// Detaches from the terminal: forks, lets the parent exit, starts a new
+/// session and points stdin at `/dev/null` and stdout/stderr at `log`.
+///
+/// Must run before any thread is started, as only the calling thread
+/// survives the fork.
+pub fn daemonize(log: &Path) -> Result<(), StockError> {
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log)
+        .map_err(|err| {
+            StockError::io(log, err)
+        })?;
+    let null = fs::File::open("/dev/null").map_err(daemon_error)?;
+
+    match unsafe { libc::fork() } {
+        -1 => return Err(daemon_error(io::Error::last_os_error())),
+        0 => {}
+        _ => std::process::exit(0),
+    }
+
+    if unsafe { libc::setsid() } == -1 {
+        return Err(daemon_error(io::Error::last_os_error()));
+    }
+
+    for (from, to) in [(null.as_raw_fd(), libc::STDIN_FILENO), (log.as_raw_fd(), libc::STDOUT_FILENO), (log.as_raw_fd(), libc::STDERR_FILENO)] {
+        if unsafe { libc::dup2(from, to) } == -1 {
+            return Err(daemon_error(io::Error::last_os_error()));
+        }
+    }
+
+    Ok(())
+}
+
+fn daemon_error(err: io::Error) -> StockError {
+    StockError::runtime("daemon", err)
+}
+
+/// Holds the process id in a file for as long as it lives, removing the file
+/// when dropped.
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    /// Writes the current process id to `path`, refusing to if the file
+    /// names a process that is still running.
+    pub fn create(path: &Path) -> Result<Self, StockError> {
+        if let Some(pid) = fs::read_to_string(path).ok().and_then(|content| content.trim().parse::<libc::pid_t>().ok()) {
+            if unsafe { libc::kill(pid, 0) } == 0 {
+                return Err(StockError::AlreadyRunning { path: path.to_path_buf(), pid });
+            }
+        }
+
+        fs::write(path, format!("{}\n", std::process::id()))
+            .map_err(|err| {
+                StockError::io(path, err)
+            })?;
+
+        Ok(Self { path: path.to_path_buf() })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_file(&self.path) {
+            warn!("Failed to remove {}: {}", self.path.display(), err);
+        }
+    }
+}
+
+/// Sends `state` (e.g. `READY=1`) to systemd when running under a unit with
+/// `Type=notify`; does nothing otherwise.
+pub fn sd_notify(state: &str) {
+    let Some(socket_path) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+
+    let result = UnixDatagram::unbound().and_then(|socket| {
+        match socket_path.to_string_lossy().strip_prefix('@') {
+            Some(name) => send_abstract(&socket, name, state),
+            None => socket.send_to(state.as_bytes(), &socket_path).map(|_| ()),
+        }
+    });
+
+    if let Err(err) = result {
+        warn!("Failed to notify systemd: {}", err);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn send_abstract(socket: &UnixDatagram, name: &str, state: &str) -> io::Result<()> {
+    use std::os::linux::net::SocketAddrExt;
+
+    let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+
+    socket.send_to_addr(state.as_bytes(), &addr).map(|_| ())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn send_abstract(_socket: &UnixDatagram, _name: &str, _state: &str) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "abstract notify sockets are Linux only"))
+}
+
+/// Tells systemd the service is ready and, when the unit sets
+/// `WatchdogSec=`, keeps pinging its watchdog at half the interval until
+/// `shutdown` is requested, then reports that it is stopping. Does nothing
+/// outside a `Type=notify` unit.
+pub fn spawn_notifier(shutdown: Shutdown) {
+    if std::env::var_os("NOTIFY_SOCKET").is_none() {
+        return;
+    }
+
+    let watchdog = std::env::var("WATCHDOG_USEC").ok()
+        .and_then(|usec| usec.parse::<u64>().ok())
+        .map(|usec| Duration::from_micros(usec / 2));
+
+    sd_notify("READY=1");
+
+    thread::spawn(move || {
+        match watchdog {
+            Some(interval) => {
+                info!("Pinging the systemd watchdog every {:?}", interval);
+
+                while !shutdown.wait_timeout(interval) {
+                    sd_notify("WATCHDOG=1");
+                }
+            }
+            None => while !shutdown.wait_timeout(Duration::from_secs(60)) {},
+        }
+
+        sd_notify("STOPPING=1");
+    });
+}
+
+/// Only touches an atomic, which is safe to do from a signal handler.
+extern "C" fn on_hangup(_signal: libc::c_int) {
+    HANGUP.store(true, Ordering::SeqCst);
+}
+
+/// Records SIGHUP for [`take_hangup`] instead of letting it end the process,
+/// so it can be used to ask for a reload.
+pub fn catch_hangup() {
+    let handler = on_hangup as extern "C" fn(libc::c_int);
+
+    unsafe { libc::signal(libc::SIGHUP, handler as libc::sighandler_t) };
+}
+
+/// Whether SIGHUP arrived since the last call.
+pub fn take_hangup() -> bool {
+    HANGUP.swap(false, Ordering::SeqCst)
+}
@@ -0,0 +1,262 @@
+use std::{cmp::Ordering, fmt, iter::Sum, ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign}, str::FromStr};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::StockError;
+
+/// Digits kept after the decimal point.
+pub const SCALE: u32 = 8;
+const ONE: i128 = 10i128.pow(SCALE);
+
+
+/// A decimal number with up to eight digits after the point, for prices and
+/// other amounts of money. Unlike `f64` it holds `189.84` exactly, so
+/// `189.84 - 189.8` comes out as `0.04` rather than `0.04000000000000625`,
+/// and amounts are compared, stored and written as they were quoted.
+///
+/// Serialized as a JSON (or CSV) number, and read from numbers or strings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Decimal(i128);
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal(0);
+
+    /// `value` as Rust writes it, the shortest form that reads back as the
+    /// same `f64`, so `0.1` becomes exactly `0.1`. `None` for NaN, the
+    /// infinities and values too large to hold.
+    pub fn from_f64(value: f64) -> Option<Self> {
+        match value.is_finite() {
+            true => value.to_string().parse().ok(),
+            false => None,
+        }
+    }
+
+    /// For arithmetic that doesn't need to be exact, such as ratios and
+    /// indicators.
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / ONE as f64
+    }
+
+    /// Rounded half away from zero to `places` digits after the point.
+    pub fn round_dp(self, places: u32) -> Self {
+        if places >= SCALE {
+            return self;
+        }
+
+        let unit = 10i128.pow(SCALE - places);
+        let remainder = self.0 % unit;
+        let truncated = self.0 - remainder;
+
+        match (remainder.abs() * 2).cmp(&unit) {
+            Ordering::Less => Decimal(truncated),
+            _ => Decimal(truncated + unit * self.0.signum()),
+        }
+    }
+
+    pub fn abs(self) -> Self {
+        Decimal(self.0.abs())
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn is_sign_negative(self) -> bool {
+        self.0 < 0
+    }
+}
+
+impl From<i32> for Decimal {
+    fn from(value: i32) -> Self {
+        Decimal(value as i128 * ONE)
+    }
+}
+
+impl From<u32> for Decimal {
+    fn from(value: u32) -> Self {
+        Decimal(value as i128 * ONE)
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(value: i64) -> Self {
+        Decimal(value as i128 * ONE)
+    }
+}
+
+impl From<u64> for Decimal {
+    fn from(value: u64) -> Self {
+        Decimal(value as i128 * ONE)
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = StockError;
+
+    /// Parses plain decimals such as `189.84`, `-0.5` or `9875`; digits past
+    /// the eighth after the point are rounded off.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || StockError::invalid("number", value.to_string());
+        let text = value.trim();
+
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+
+        if whole.is_empty() && fraction.is_empty() || !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+            return Err(invalid());
+        }
+
+        let units = whole.bytes()
+            .chain(fraction.bytes().chain(std::iter::repeat(b'0')).take(SCALE as usize))
+            .try_fold(0i128, |units, digit| units.checked_mul(10)?.checked_add((digit - b'0') as i128))
+            .ok_or_else(invalid)?;
+
+        let round_up = fraction.as_bytes().get(SCALE as usize).is_some_and(|digit| *digit >= b'5');
+        let units = units + round_up as i128;
+
+        Ok(Decimal(if negative { -units } else { units }))
+    }
+}
+
+impl TryFrom<String> for Decimal {
+    type Error = StockError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// Honours width, alignment, `+` and precision like `f64` does, e.g.
+/// `{:+.2}`. Without a precision trailing zeros are left off.
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match f.precision() {
+            Some(precision) => self.round_dp(precision as u32),
+            None => *self,
+        };
+
+        let units = value.0.unsigned_abs();
+        let whole = units / ONE as u128;
+        let mut fraction = format!("{:0width$}", units % ONE as u128, width = SCALE as usize);
+
+        match f.precision() {
+            Some(precision) if precision <= SCALE as usize => fraction.truncate(precision),
+            Some(precision) => fraction.push_str(&"0".repeat(precision - SCALE as usize)),
+            None => fraction.truncate(fraction.trim_end_matches('0').len()),
+        }
+
+        let digits = match fraction.is_empty() {
+            true => whole.to_string(),
+            false => format!("{}.{}", whole, fraction),
+        };
+
+        f.pad_integral(value.0 >= 0, "", &digits)
+    }
+}
+
+impl Add for Decimal {
+    type Output = Decimal;
+
+    fn add(self, other: Decimal) -> Decimal {
+        Decimal(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Decimal {
+    fn add_assign(&mut self, other: Decimal) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Decimal {
+    type Output = Decimal;
+
+    fn sub(self, other: Decimal) -> Decimal {
+        Decimal(self.0 - other.0)
+    }
+}
+
+impl SubAssign for Decimal {
+    fn sub_assign(&mut self, other: Decimal) {
+        self.0 -= other.0;
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal(-self.0)
+    }
+}
+
+/// Rounded to eight digits after the point. Products too large to hold
+/// exactly, far beyond any price, go through `f64`.
+impl Mul for Decimal {
+    type Output = Decimal;
+
+    fn mul(self, other: Decimal) -> Decimal {
+        match self.0.checked_mul(other.0) {
+            Some(product) => Decimal((product + product.signum() * ONE / 2) / ONE),
+            None => Decimal::from_f64(self.to_f64() * other.to_f64()).unwrap_or(Decimal(i128::MAX)),
+        }
+    }
+}
+
+impl MulAssign for Decimal {
+    fn mul_assign(&mut self, other: Decimal) {
+        *self = *self * other;
+    }
+}
+
+impl Sum for Decimal {
+    fn sum<I: Iterator<Item = Decimal>>(iter: I) -> Self {
+        iter.fold(Decimal::ZERO, Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Decimal> for Decimal {
+    fn sum<I: Iterator<Item = &'a Decimal>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
+impl Serialize for Decimal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.to_f64())
+    }
+}
+
+impl<'de> Deserialize<'de> for Decimal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(DecimalVisitor)
+    }
+}
+
+struct DecimalVisitor;
+
+impl de::Visitor<'_> for DecimalVisitor {
+    type Value = Decimal;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a decimal number")
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Decimal, E> {
+        Ok(Decimal::from(value))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Decimal, E> {
+        Ok(Decimal::from(value))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Decimal, E> {
+        Decimal::from_f64(value).ok_or_else(|| E::custom(format!("{} is not a decimal", value)))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Decimal, E> {
+        value.parse().map_err(E::custom)
+    }
+}
@@ -0,0 +1,106 @@
+use std::{fmt, time::Instant};
+use chrono::{DateTime, Utc};
+
+use crate::{http::Fetcher, ratelimit::RateLimits, scraping::{googlefinance, selectors::Selectors, ApiKeys, Source}, StockClient, StockError};
+
+/// A code every source but IDX quotes, to probe them with.
+const PROBE_CODE: &str = "AAPL:NASDAQ";
+const IDX_PROBE_CODE: &str = "BBCA:IDX";
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Ok,
+    /// Works, but not as it should, e.g. a selector fell back.
+    Warn,
+    Fail,
+    /// Couldn't be checked, e.g. for want of an API key.
+    Skipped,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let outcome = match self {
+            Outcome::Ok => "ok",
+            Outcome::Warn => "warn",
+            Outcome::Fail => "FAIL",
+            Outcome::Skipped => "skip",
+        };
+
+        f.pad(outcome)
+    }
+}
+
+/// The result of one `doctor` check, printed as a line of its report.
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: String,
+    pub outcome: Outcome,
+    pub detail: String,
+}
+
+impl Check {
+    pub fn new(name: impl Into<String>, outcome: Outcome, detail: impl Into<String>) -> Self {
+        Self { name: name.into(), outcome, detail: detail.into() }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{:^4}] {}: {}", self.outcome, self.name, self.detail)
+    }
+}
+
+/// Fetches a probe quote from every source. Sources missing their API key
+/// are skipped.
+pub fn check_sources(fetcher: &Fetcher, selectors: &Selectors, keys: &ApiKeys) -> Vec<Check> {
+    Source::ALL.iter()
+        .map(|source| {
+            let provider = source.provider_with(fetcher, &RateLimits::default(), selectors, keys, None);
+            let code = match source {
+                Source::Idx => IDX_PROBE_CODE,
+                _ => PROBE_CODE,
+            };
+            let started = Instant::now();
+            let name = format!("source {}", source);
+
+            match provider.fetch(code) {
+                Ok(stock) => Check::new(name, Outcome::Ok, format!("{} at {} {} in {:?}", code, stock.price, stock.currency, started.elapsed())),
+                Err(StockError::MissingApiKey(message)) => Check::new(name, Outcome::Skipped, message),
+                Err(err) => Check::new(name, Outcome::Fail, format!("{}: {}", code, err)),
+            }
+        })
+        .collect()
+}
+
+/// Reads a live Google Finance page with `selectors`, warning when the
+/// preferred price selector no longer matches.
+pub fn check_selectors(fetcher: &Fetcher, selectors: &Selectors) -> Check {
+    let name = "selectors";
+    let html_content = match googlefinance::fetch_from_google_finance(fetcher, PROBE_CODE) {
+        Ok(html_content) => html_content,
+        Err(err) => return Check::new(name, Outcome::Fail, format!("{}: {}", PROBE_CODE, err)),
+    };
+
+    let (result, diagnostics) = googlefinance::parse_with_diagnostics(&html_content, PROBE_CODE, &selectors.google());
+
+    match result {
+        Err(err) => Check::new(name, Outcome::Fail, format!("{}: {}, {}", PROBE_CODE, err, diagnostics)),
+        Ok(_) if diagnostics.is_degraded() => Check::new(name, Outcome::Warn, format!("{}: {}", PROBE_CODE, diagnostics)),
+        Ok(_) => Check::new(name, Outcome::Ok, format!("{}: {}", PROBE_CODE, diagnostics)),
+    }
+}
+
+/// Fetches `share_code` as polling would, reporting when it was last
+/// recorded.
+pub fn check_symbol(client: &StockClient, share_code: &str, last_recorded: Option<DateTime<Utc>>) -> Check {
+    let last_recorded = match last_recorded {
+        Some(timestamp) => format!("last recorded {}", timestamp.to_rfc3339()),
+        None => "never recorded".to_string(),
+    };
+
+    match client.fetch_quote(share_code) {
+        Ok(stock) => Check::new(share_code, Outcome::Ok, format!("{} {}, {}", stock.price, stock.currency, last_recorded)),
+        Err(err) => Check::new(share_code, Outcome::Fail, format!("{}, {}", err, last_recorded)),
+    }
+}
@@ -0,0 +1,106 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}, time::{Duration, Instant}};
+use serde::Serialize;
+
+use crate::{http::Fetcher, scraping::yahoofinance, Decimal, Stock, StockError};
+
+const RATE_TTL: Duration = Duration::from_secs(15 * 60);
+
+
+/// Source of exchange rates: how many units of `to` one unit of `from` buys.
+pub trait FxRateProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn rate(&self, from: &str, to: &str) -> Result<Decimal, StockError>;
+}
+
+/// Rates from Yahoo Finance currency pairs such as `IDRUSD=X`.
+#[derive(Debug, Clone, Default)]
+pub struct YahooFxRates {
+    fetcher: Fetcher,
+}
+
+impl YahooFxRates {
+    pub fn new(fetcher: Fetcher) -> Self {
+        Self { fetcher }
+    }
+}
+
+impl FxRateProvider for YahooFxRates {
+    fn name(&self) -> &str {
+        "yahoo"
+    }
+
+    fn rate(&self, from: &str, to: &str) -> Result<Decimal, StockError> {
+        let pair = format!("{}{}=X", from, to);
+        let json_content = yahoofinance::fetch_from_yahoo_finance(&self.fetcher, &pair)?;
+        let quote = yahoofinance::parse_stock_value(json_content, &pair)?;
+
+        match quote.price > Decimal::ZERO {
+            true => Ok(quote.price),
+            false => Err(StockError::FxRateMissing(pair)),
+        }
+    }
+}
+
+/// Records that a price was converted from another currency.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Conversion {
+    pub from_currency: String,
+    pub rate: Decimal,
+}
+
+/// Converts quotes into one base currency, caching rates for 15 minutes.
+#[derive(Clone)]
+pub struct FxConverter {
+    base: String,
+    rates: Arc<dyn FxRateProvider>,
+    cache: Arc<Mutex<HashMap<String, (Decimal, Instant)>>>,
+}
+
+impl FxConverter {
+    pub fn new(base: &str, rates: Arc<dyn FxRateProvider>) -> Self {
+        Self { base: base.to_uppercase(), rates, cache: Arc::new(Mutex::new(HashMap::new())) }
+    }
+
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    pub fn rates_provider(&self) -> &dyn FxRateProvider {
+        self.rates.as_ref()
+    }
+
+    fn rate(&self, from: &str) -> Result<Decimal, StockError> {
+        if let Some((rate, fetched_at)) = self.cache.lock().unwrap_or_else(|err| err.into_inner()).get(from) {
+            if fetched_at.elapsed() < RATE_TTL {
+                return Ok(*rate);
+            }
+        }
+
+        let rate = self.rates.rate(from, &self.base)?;
+
+        self.cache.lock().unwrap_or_else(|err| err.into_inner()).insert(from.to_string(), (rate, Instant::now()));
+
+        Ok(rate)
+    }
+
+    /// Converts `stock` in place. Quotes already in the base currency, or
+    /// whose currency is unknown, are left untouched.
+    pub fn convert(&self, stock: &mut Stock) -> Result<(), StockError> {
+        if stock.currency.is_empty() || stock.currency == self.base {
+            return Ok(());
+        }
+
+        let rate = self.rate(&stock.currency)?;
+
+        stock.conversion = Some(Conversion { from_currency: stock.currency.clone(), rate });
+        stock.price *= rate;
+        stock.details.scale(rate);
+        if let Some(fundamentals) = stock.fundamentals.as_mut() {
+            fundamentals.scale(rate);
+        }
+        stock.currency = self.base.clone();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use std::{collections::{BTreeMap, HashMap}, sync::{Arc, Mutex}};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::StockError;
+
+
+/// How fetching one symbol has been going.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SymbolHealth {
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_failure: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// Failed fetches since the last successful one.
+    pub consecutive_failures: u32,
+}
+
+impl SymbolHealth {
+    pub fn is_failing(&self) -> bool {
+        self.consecutive_failures > 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    /// No symbol's last fetch failed.
+    Ok,
+    /// Some symbols' last fetch failed.
+    Degraded,
+    /// Every symbol's last fetch failed.
+    Failing,
+}
+
+/// What `GET /healthz` answers with.
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub started_at: DateTime<Utc>,
+    /// Keyed by code, including codes not fetched yet.
+    pub symbols: BTreeMap<String, SymbolHealth>,
+}
+
+/// The outcome of the latest fetches of every symbol, shared between the
+/// quote pipeline recording them and whatever reports on them.
+#[derive(Debug, Clone)]
+pub struct Health {
+    started_at: DateTime<Utc>,
+    symbols: Arc<Mutex<HashMap<String, SymbolHealth>>>,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self { started_at: Utc::now(), symbols: Arc::default() }
+    }
+}
+
+impl Health {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn succeeded(&self, share_code: &str) {
+        let mut symbols = self.symbols.lock().unwrap_or_else(|err| err.into_inner());
+        let health = symbols.entry(share_code.to_string()).or_default();
+
+        health.last_success = Some(Utc::now());
+        health.consecutive_failures = 0;
+    }
+
+    pub fn failed(&self, share_code: &str, err: &StockError) {
+        let mut symbols = self.symbols.lock().unwrap_or_else(|err| err.into_inner());
+        let health = symbols.entry(share_code.to_string()).or_default();
+
+        health.last_failure = Some(Utc::now());
+        health.last_error = Some(err.to_string());
+        health.consecutive_failures += 1;
+    }
+
+    pub fn report(&self, codes: &[String]) -> HealthReport {
+        let symbols = self.symbols.lock().unwrap_or_else(|err| err.into_inner());
+        let symbols = codes.iter()
+            .map(|code| (code.clone(), symbols.get(code).cloned().unwrap_or_default()))
+            .collect::<BTreeMap<String, SymbolHealth>>();
+
+        let failing = symbols.values().filter(|health| health.is_failing()).count();
+        let status = match failing {
+            0 => HealthStatus::Ok,
+            failing if failing == symbols.len() => HealthStatus::Failing,
+            _ => HealthStatus::Degraded,
+        };
+
+        HealthReport { status, started_at: self.started_at, symbols }
+    }
+}
@@ -0,0 +1,107 @@
+use std::{sync::{mpsc, Mutex}, thread, time::Duration};
+use fantoccini::{Client, ClientBuilder};
+use serde_json::json;
+use tracing::{debug, warn};
+
+use crate::StockError;
+
+
+struct Render {
+    address: String,
+    reply: mpsc::Sender<Result<String, StockError>>,
+}
+
+/// Renders pages in a headless browser driven over WebDriver (chromedriver,
+/// geckodriver or a Selenium server), for quote pages that only show their
+/// prices once scripts have run.
+///
+/// A thread of its own keeps one browser session open, reconnecting after a
+/// failure, and renders one page at a time.
+pub struct HeadlessBrowser {
+    sender: Mutex<mpsc::Sender<Render>>,
+}
+
+impl HeadlessBrowser {
+    /// Drives the WebDriver server at `webdriver`, e.g.
+    /// `http://localhost:4444`, reading each page `wait` after it loaded.
+    pub fn new(webdriver: &str, wait: Duration) -> Result<Self, StockError> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|err| {
+                StockError::HttpClient(err.into())
+            })?;
+        let (sender, receiver) = mpsc::channel::<Render>();
+        let webdriver = webdriver.to_string();
+
+        thread::spawn(move || {
+            let mut session = None;
+
+            for render in receiver {
+                let result = runtime.block_on(render_page(&webdriver, &mut session, &render.address, wait));
+
+                if result.is_err() {
+                    // Start over with a new session next time.
+                    if let Some(client) = session.take() {
+                        let _ = runtime.block_on(client.close());
+                    }
+                }
+
+                let _ = render.reply.send(result);
+            }
+
+            if let Some(client) = session {
+                let _ = runtime.block_on(client.close());
+            }
+        });
+
+        Ok(Self { sender: Mutex::new(sender) })
+    }
+
+    /// Loads `address` and returns the page as the browser has rendered it.
+    pub fn render(&self, address: &str) -> Result<String, StockError> {
+        let (reply, result) = mpsc::channel();
+        let render = Render { address: address.to_string(), reply };
+
+        let sent = self.sender.lock().unwrap_or_else(|err| err.into_inner()).send(render);
+
+        sent.ok()
+            .and_then(|_| result.recv().ok())
+            .unwrap_or_else(|| Err(StockError::Render("headless browser is not running".into())))
+    }
+}
+
+async fn render_page(webdriver: &str, session: &mut Option<Client>, address: &str, wait: Duration) -> Result<String, StockError> {
+    let client = match session {
+        Some(client) => client,
+        None => {
+            let capabilities = json!({
+                "goog:chromeOptions": {"args": ["--headless=new", "--disable-gpu"]},
+                "moz:firefoxOptions": {"args": ["-headless"]},
+            });
+            let capabilities = capabilities.as_object().cloned().unwrap_or_default();
+
+            let client = ClientBuilder::native()
+                .capabilities(capabilities)
+                .connect(webdriver)
+                .await
+                .map_err(|err| {
+                    warn!("Failed to start a browser session at {}: {}", webdriver, err);
+                    StockError::Render(format!("{}: {}", webdriver, err).into())
+                })?;
+
+            session.insert(client)
+        }
+    };
+
+    debug!("Rendering {}", address);
+
+    client.goto(address).await.map_err(render_error)?;
+    tokio::time::sleep(wait).await;
+
+    client.source().await.map_err(render_error)
+}
+
+fn render_error(err: fantoccini::error::CmdError) -> StockError {
+    StockError::Render(err.into())
+}
@@ -0,0 +1,586 @@
+use std::{collections::HashMap, fmt, future::Future, io::Read, pin::Pin, sync::{atomic::{AtomicUsize, Ordering}, Arc, Mutex}, time::{Duration, Instant}};
+use reqwest::{header::{self, HeaderMap}, StatusCode};
+use tracing::{debug, info};
+
+use crate::{robots::{self, Politeness}, StockError};
+
+#[cfg(feature = "headless")]
+pub mod headless;
+
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_USER_AGENT: &str = "Mozilla/5.0";
+/// How long a rendered page is given to fill in its prices after loading.
+pub const DEFAULT_RENDER_WAIT: Duration = Duration::from_secs(2);
+/// Far above any quote page, which is around 1 MB uncompressed.
+pub const DEFAULT_MAX_BODY_SIZE: u64 = 10 * 1024 * 1024;
+
+/// What the async methods of the provider and fetcher traits return, boxed
+/// so the traits can still be used as trait objects.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// How scraping requests are sent.
+#[derive(Debug, Clone)]
+pub struct HttpOptions {
+    /// Proxy URLs using the `http`, `https`, `socks5` or `socks5h` scheme,
+    /// rotated per request. Empty connects directly.
+    pub proxies: Vec<String>,
+    /// Limit on a whole request, from connecting to reading the body.
+    pub timeout: Duration,
+    /// `User-Agent` headers, rotated per request.
+    pub user_agents: Vec<String>,
+    /// Serve a page fetched less than this long ago from memory instead of
+    /// requesting it again. Zero always requests.
+    pub cache_ttl: Duration,
+    /// Respect robots.txt, space requests to a host at least `host_delay`
+    /// apart and, unless `user_agents` are given, identify as scraping-stock.
+    pub polite: bool,
+    pub host_delay: Duration,
+    /// WebDriver server rendering pages whose prices are filled in by
+    /// scripts (requires the headless feature).
+    pub webdriver: Option<String>,
+    /// How long a rendered page is given to load its prices.
+    pub render_wait: Duration,
+    /// Bytes a response body may take once decompressed; larger ones fail.
+    pub max_body_size: u64,
+    /// Log the URL, status, latency and size of every request, and whether
+    /// it was answered from the cache.
+    pub debug: bool,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        Self {
+            proxies: Vec::new(),
+            timeout: DEFAULT_TIMEOUT,
+            user_agents: vec![DEFAULT_USER_AGENT.to_string()],
+            cache_ttl: Duration::ZERO,
+            polite: false,
+            host_delay: robots::DEFAULT_HOST_DELAY,
+            webdriver: None,
+            render_wait: DEFAULT_RENDER_WAIT,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            debug: false,
+        }
+    }
+}
+
+/// Where [`Fetcher::fetch_page`] got a page from.
+enum Origin {
+    Cache,
+    NotModified,
+    Server(StatusCode),
+}
+
+/// A page kept to answer from the cache or revalidate with the server.
+struct CachedPage {
+    content: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    fetched_at: Instant,
+}
+
+/// Downloads pages for a provider. Implemented by [`Fetcher`]; tests hand
+/// providers their own implementation serving canned responses.
+pub trait HttpFetcher: Send + Sync {
+    fn fetch_page(&self, address: &str) -> Result<String, StockError>;
+
+    /// Same as [`HttpFetcher::fetch_page`] without blocking the thread it is
+    /// awaited on. Calls `fetch_page` by default, which suits fetchers
+    /// answering from memory.
+    fn async_fetch_page<'a>(&'a self, address: &'a str) -> BoxFuture<'a, Result<String, StockError>> {
+        Box::pin(async move { self.fetch_page(address) })
+    }
+
+    /// Loads `address` in a browser and returns the page once its scripts
+    /// have run, or `None` when no browser is set up.
+    fn render_page(&self, _address: &str) -> Option<Result<String, StockError>> {
+        None
+    }
+}
+
+/// Sends scraping requests over long-lived HTTP clients, one per proxy, so
+/// connections and TLS sessions are kept alive between polls. Each proxy has
+/// a blocking client and an async one for [`Fetcher::async_fetch_page`].
+///
+/// Responses are requested gzip, deflate or brotli compressed and unpacked
+/// transparently; bodies larger than the configured maximum are cut off
+/// and fail rather than being read into memory whole.
+///
+/// Pages are cached by URL: within the cache TTL they are served from memory,
+/// after it they are requested with `If-None-Match` / `If-Modified-Since` when
+/// the server sent an `ETag` or `Last-Modified`, so an unchanged page comes
+/// back as a bodiless `304 Not Modified`.
+///
+/// In polite mode every request is checked against the host's robots.txt
+/// first and waits for the host's delay.
+///
+/// Clones share the same clients, cache and delays; build one and hand it to
+/// every provider.
+#[derive(Clone)]
+pub struct Fetcher {
+    clients: Arc<[reqwest::blocking::Client]>,
+    async_clients: Arc<[reqwest::Client]>,
+    user_agents: Arc<[String]>,
+    next: Arc<AtomicUsize>,
+    cache: Arc<Mutex<HashMap<String, CachedPage>>>,
+    cache_ttl: Duration,
+    max_body_size: u64,
+    polite: Option<Arc<Politeness>>,
+    debug: bool,
+    #[cfg(feature = "headless")]
+    browser: Option<Arc<headless::HeadlessBrowser>>,
+}
+
+impl Fetcher {
+    pub fn new(options: &HttpOptions) -> Result<Self, StockError> {
+        #[cfg(not(feature = "headless"))]
+        if options.webdriver.is_some() {
+            return Err(StockError::FeatureDisabled("Rendering pages requires building with --features headless".to_string()));
+        }
+
+        let clients = match options.proxies.is_empty() {
+            true => vec![build_client(options, None)?],
+            false => options.proxies.iter()
+                .map(|url| build_client(options, Some(url)))
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+
+        let async_clients = match options.proxies.is_empty() {
+            true => vec![build_async_client(options, None)?],
+            false => options.proxies.iter()
+                .map(|url| build_async_client(options, Some(url)))
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+
+        let user_agents = match (options.user_agents.is_empty(), options.polite) {
+            (true, true) => vec![robots::USER_AGENT.to_string()],
+            (true, false) => vec![DEFAULT_USER_AGENT.to_string()],
+            (false, _) => options.user_agents.clone(),
+        };
+
+        Ok(Self {
+            clients: clients.into(),
+            async_clients: async_clients.into(),
+            user_agents: user_agents.into(),
+            next: Arc::new(AtomicUsize::new(0)),
+            cache: Arc::new(Mutex::new(HashMap::new())),
+            cache_ttl: options.cache_ttl,
+            max_body_size: options.max_body_size,
+            polite: options.polite.then(|| Arc::new(Politeness::new(options.host_delay))),
+            debug: options.debug,
+            #[cfg(feature = "headless")]
+            browser: match &options.webdriver {
+                Some(webdriver) => Some(Arc::new(headless::HeadlessBrowser::new(webdriver, options.render_wait)?)),
+                None => None,
+            },
+        })
+    }
+
+    /// Downloads `address` as text, through the next proxy and with the next
+    /// user agent when several are configured, unless it is cached.
+    pub fn fetch_page(&self, address: &str) -> Result<String, StockError> {
+        let started = Instant::now();
+        let result = self.fetch(address);
+        self.log(address, started, &result);
+
+        result.map(|(content, _)| content)
+    }
+
+    /// Same as [`Fetcher::fetch_page`] over the async clients, sharing the
+    /// same cache, waiting for the host on tokio's timer instead of blocking.
+    pub async fn async_fetch_page(&self, address: &str) -> Result<String, StockError> {
+        let started = Instant::now();
+        let result = self.async_fetch(address).await;
+        self.log(address, started, &result);
+
+        result.map(|(content, _)| content)
+    }
+
+    fn fetch(&self, address: &str) -> Result<(String, Origin), StockError> {
+        let url = parse_url(address)?;
+
+        if let Some(content) = self.cached(address) {
+            return Ok((content, Origin::Cache));
+        }
+
+        let (etag, last_modified) = self.validators(address);
+        let index = self.next.fetch_add(1, Ordering::Relaxed);
+        let client = &self.clients[index % self.clients.len()];
+        let user_agent = &self.user_agents[index % self.user_agents.len()];
+
+        self.admit(&url, index)?;
+
+        let mut request = client.get(url).header(header::USER_AGENT, user_agent);
+
+        if let Some(etag) = &etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+
+        if let Some(last_modified) = &last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+
+        let res = request.send()
+            .map_err(|err| {
+                StockError::Network(err.into())
+            })?;
+
+        if res.status() == StatusCode::NOT_MODIFIED {
+            if let Some(content) = self.revalidated(address) {
+                return Ok((content, Origin::NotModified));
+            }
+        }
+
+        if !res.status().is_success() {
+            return Err(StockError::status(res.status()));
+        }
+
+        let status = res.status();
+        let (etag, last_modified) = response_validators(res.headers());
+        let content = read_body(res, self.max_body_size)?;
+        self.store(address, &content, etag, last_modified);
+
+        Ok((content, Origin::Server(status)))
+    }
+
+    async fn async_fetch(&self, address: &str) -> Result<(String, Origin), StockError> {
+        let url = parse_url(address)?;
+
+        if let Some(content) = self.cached(address) {
+            return Ok((content, Origin::Cache));
+        }
+
+        let (etag, last_modified) = self.validators(address);
+        let index = self.next.fetch_add(1, Ordering::Relaxed);
+        let client = &self.async_clients[index % self.async_clients.len()];
+        let user_agent = &self.user_agents[index % self.user_agents.len()];
+
+        self.async_admit(&url, index).await?;
+
+        let mut request = client.get(url).header(header::USER_AGENT, user_agent);
+
+        if let Some(etag) = &etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+
+        if let Some(last_modified) = &last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+
+        let res = request.send().await
+            .map_err(|err| {
+                StockError::Network(err.into())
+            })?;
+
+        if res.status() == StatusCode::NOT_MODIFIED {
+            if let Some(content) = self.revalidated(address) {
+                return Ok((content, Origin::NotModified));
+            }
+        }
+
+        if !res.status().is_success() {
+            return Err(StockError::status(res.status()));
+        }
+
+        let status = res.status();
+        let (etag, last_modified) = response_validators(res.headers());
+        let content = async_read_body(res, self.max_body_size).await?;
+        self.store(address, &content, etag, last_modified);
+
+        Ok((content, Origin::Server(status)))
+    }
+
+    /// In debug mode, logs how a request for `address` went.
+    fn log(&self, address: &str, started: Instant, result: &Result<(String, Origin), StockError>) {
+        if !self.debug {
+            return;
+        }
+
+        let elapsed = started.elapsed().as_millis();
+        let address = redact(address);
+
+        match result {
+            Ok((content, Origin::Cache)) => info!("GET {} from the cache, {} bytes", address, content.len()),
+            Ok((content, Origin::NotModified)) => info!("GET {} 304 Not Modified in {} ms, {} bytes from the cache", address, elapsed, content.len()),
+            Ok((content, Origin::Server(status))) => info!("GET {} {} in {} ms, {} bytes", address, status, elapsed, content.len()),
+            Err(err) => info!("GET {} failed in {} ms: {}", address, elapsed, err),
+        }
+    }
+
+    /// The cached copy of `address`, while it is within the cache TTL.
+    fn cached(&self, address: &str) -> Option<String> {
+        let cache = self.cache.lock().unwrap_or_else(|err| err.into_inner());
+        let page = cache.get(address).filter(|page| page.fetched_at.elapsed() < self.cache_ttl)?;
+
+        debug!("Serving {} from the cache", redact(address));
+        Some(page.content.clone())
+    }
+
+    /// The `ETag` and `Last-Modified` of the cached copy of `address`, to
+    /// request it again only if it changed.
+    fn validators(&self, address: &str) -> (Option<String>, Option<String>) {
+        let cache = self.cache.lock().unwrap_or_else(|err| err.into_inner());
+
+        match cache.get(address) {
+            Some(page) => (page.etag.clone(), page.last_modified.clone()),
+            None => (None, None),
+        }
+    }
+
+    /// The cached copy of `address` after a `304 Not Modified`, kept for
+    /// another TTL.
+    fn revalidated(&self, address: &str) -> Option<String> {
+        let mut cache = self.cache.lock().unwrap_or_else(|err| err.into_inner());
+        let page = cache.get_mut(address)?;
+
+        debug!("{} is not modified", redact(address));
+        page.fetched_at = Instant::now();
+        Some(page.content.clone())
+    }
+
+    /// Caches a downloaded page when it can be revalidated or served again.
+    fn store(&self, address: &str, content: &str, etag: Option<String>, last_modified: Option<String>) {
+        if etag.is_some() || last_modified.is_some() || !self.cache_ttl.is_zero() {
+            let page = CachedPage { content: content.to_string(), etag, last_modified, fetched_at: Instant::now() };
+
+            self.cache.lock().unwrap_or_else(|err| err.into_inner()).insert(address.to_string(), page);
+        }
+    }
+
+    /// In polite mode, waits for the host's turn, failing when its robots.txt
+    /// forbids `url`.
+    fn admit(&self, url: &url::Url, index: usize) -> Result<(), StockError> {
+        let Some(politeness) = &self.polite else {
+            return Ok(());
+        };
+
+        let client = &self.clients[index % self.clients.len()];
+        let user_agent = &self.user_agents[index % self.user_agents.len()];
+
+        politeness.admit(url, |robots_url| fetch_robots(client, user_agent, robots_url, self.max_body_size))
+    }
+
+    /// Same as [`Fetcher::admit`], sleeping on tokio's timer.
+    async fn async_admit(&self, url: &url::Url, index: usize) -> Result<(), StockError> {
+        let Some(politeness) = self.polite.clone() else {
+            return Ok(());
+        };
+
+        let client = self.clients[index % self.clients.len()].clone();
+        let user_agent = self.user_agents[index % self.user_agents.len()].clone();
+        let (url, max_body_size) = (url.clone(), self.max_body_size);
+
+        // On the blocking pool, as the host's robots.txt may be due a fetch.
+        let wait = tokio::task::spawn_blocking(move || {
+            politeness.reserve(&url, |robots_url| fetch_robots(&client, &user_agent, robots_url, max_body_size))
+        })
+        .await
+        .map_err(|err| {
+            StockError::runtime("task", err)
+        })??;
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+
+        Ok(())
+    }
+}
+
+impl HttpFetcher for Fetcher {
+    fn fetch_page(&self, address: &str) -> Result<String, StockError> {
+        Fetcher::fetch_page(self, address)
+    }
+
+    fn async_fetch_page<'a>(&'a self, address: &'a str) -> BoxFuture<'a, Result<String, StockError>> {
+        Box::pin(Fetcher::async_fetch_page(self, address))
+    }
+
+    #[cfg(feature = "headless")]
+    fn render_page(&self, address: &str) -> Option<Result<String, StockError>> {
+        let browser = self.browser.as_ref()?;
+        let index = self.next.fetch_add(1, Ordering::Relaxed);
+
+        Some(parse_url(address)
+            .and_then(|url| self.admit(&url, index))
+            .and_then(|_| browser.render(address)))
+    }
+}
+
+impl Default for Fetcher {
+    fn default() -> Self {
+        Self::new(&HttpOptions::default()).expect("default HTTP client")
+    }
+}
+
+impl fmt::Debug for Fetcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Fetcher")
+            .field("clients", &self.clients.len())
+            .field("user_agents", &self.user_agents)
+            .field("cache_ttl", &self.cache_ttl)
+            .field("max_body_size", &self.max_body_size)
+            .field("polite", &self.polite.is_some())
+            .field("debug", &self.debug)
+            .finish()
+    }
+}
+
+fn parse_url(address: &str) -> Result<url::Url, StockError> {
+    url::Url::parse(address)
+        .map_err(|err| {
+            StockError::Url(err)
+        })
+}
+
+/// `address` with the values of query parameters that look like
+/// credentials, such as `apikey` or `token`, masked for logging.
+pub fn redact(address: &str) -> String {
+    let Ok(mut url) = url::Url::parse(address) else {
+        return address.to_string();
+    };
+
+    let secret = |name: &str| ["key", "token", "secret", "password"].iter().any(|word| name.to_lowercase().contains(word));
+
+    if !url.query_pairs().any(|(name, _)| secret(&name)) {
+        return address.to_string();
+    }
+
+    let pairs = url.query_pairs()
+        .map(|(name, value)| {
+            let value = match secret(&name) {
+                true => "***".into(),
+                false => value,
+            };
+
+            (name.into_owned(), value.into_owned())
+        })
+        .collect::<Vec<_>>();
+
+    url.query_pairs_mut().clear().extend_pairs(pairs);
+    url.to_string()
+}
+
+/// Downloads a robots.txt; a site without one (any 4xx) allows everything,
+/// while a server error is retried like any other.
+fn fetch_robots(client: &reqwest::blocking::Client, user_agent: &str, url: &url::Url, max_body_size: u64) -> Result<Option<String>, StockError> {
+    let res = client.get(url.clone())
+        .header(header::USER_AGENT, user_agent)
+        .send()
+        .map_err(|err| {
+            StockError::Network(err.into())
+        })?;
+
+    match res.status() {
+        status if status.is_success() => read_body(res, max_body_size).map(Some),
+        status if status.is_client_error() => Ok(None),
+        status => Err(StockError::HttpStatus {
+            status: status.as_u16(),
+            message: format!("{} for {}", status.canonical_reason().unwrap_or_default(), url),
+        }),
+    }
+}
+
+/// The `ETag` and `Last-Modified` headers of a response.
+fn response_validators(headers: &HeaderMap) -> (Option<String>, Option<String>) {
+    let validator = |name| headers.get(name).and_then(|value| value.to_str().ok()).map(String::from);
+
+    (validator(header::ETAG), validator(header::LAST_MODIFIED))
+}
+
+/// Reads the body of `res` as text, decompressed, failing once it grows past
+/// `max_body_size` bytes. A `Content-Length` over the limit fails before any
+/// of it is read.
+fn read_body(res: reqwest::blocking::Response, max_body_size: u64) -> Result<String, StockError> {
+    // Only the host: query strings may carry API keys.
+    let host = res.url().host_str().unwrap_or_default().to_string();
+    let too_large = || StockError::ResponseTooLarge { host: host.clone(), limit: max_body_size };
+
+    // Compressed responses report the compressed length, if any.
+    if res.content_length().is_some_and(|length| length > max_body_size) {
+        return Err(too_large());
+    }
+
+    let mut body = Vec::new();
+
+    res.take(max_body_size + 1).read_to_end(&mut body)
+        .map_err(|err| {
+            StockError::Network(err.into())
+        })?;
+
+    if body.len() as u64 > max_body_size {
+        return Err(too_large());
+    }
+
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Same as [`read_body`] for a response of an async client, reading it a
+/// chunk at a time.
+async fn async_read_body(mut res: reqwest::Response, max_body_size: u64) -> Result<String, StockError> {
+    let host = res.url().host_str().unwrap_or_default().to_string();
+    let too_large = || StockError::ResponseTooLarge { host: host.clone(), limit: max_body_size };
+
+    if res.content_length().is_some_and(|length| length > max_body_size) {
+        return Err(too_large());
+    }
+
+    let mut body = Vec::new();
+
+    while let Some(chunk) = res.chunk().await
+        .map_err(|err| {
+            StockError::Network(err.into())
+        })? {
+        body.extend_from_slice(&chunk);
+
+        if body.len() as u64 > max_body_size {
+            return Err(too_large());
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+fn build_client(options: &HttpOptions, proxy: Option<&String>) -> Result<reqwest::blocking::Client, StockError> {
+    let builder = reqwest::blocking::Client::builder()
+        .timeout(options.timeout)
+        .gzip(true)
+        .deflate(true)
+        .brotli(true);
+
+    let builder = match proxy {
+        Some(url) => builder.proxy(parse_proxy(url)?),
+        None => builder,
+    };
+
+    builder.build()
+        .map_err(|err| {
+            StockError::HttpClient(err.into())
+        })
+}
+
+fn build_async_client(options: &HttpOptions, proxy: Option<&String>) -> Result<reqwest::Client, StockError> {
+    let builder = reqwest::Client::builder()
+        .timeout(options.timeout)
+        .gzip(true)
+        .deflate(true)
+        .brotli(true);
+
+    let builder = match proxy {
+        Some(url) => builder.proxy(parse_proxy(url)?),
+        None => builder,
+    };
+
+    builder.build()
+        .map_err(|err| {
+            StockError::HttpClient(err.into())
+        })
+}
+
+fn parse_proxy(url: &str) -> Result<reqwest::Proxy, StockError> {
+    reqwest::Proxy::all(url)
+        .map_err(|err| {
+            StockError::invalid("proxy", format!("{}: {}", url, err))
+        })
+}
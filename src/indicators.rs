@@ -0,0 +1,235 @@
+use std::collections::{HashMap, VecDeque};
+use serde::Serialize;
+
+use crate::candle::{Candle, CandleInterval};
+
+pub const DEFAULT_MA_WINDOW: usize = 20;
+pub const DEFAULT_RSI_WINDOW: usize = 14;
+/// EMA windows of the MACD line and of its signal line.
+pub const MACD_FAST: usize = 12;
+pub const MACD_SLOW: usize = 26;
+pub const MACD_SIGNAL: usize = 9;
+/// Prices the Bollinger Bands are computed over, and their distance from the
+/// middle band in standard deviations.
+pub const BOLLINGER_WINDOW: usize = 20;
+pub const BOLLINGER_WIDTH: f64 = 2.0;
+
+
+/// Window sizes, in polls or candles, of the computed indicators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndicatorOptions {
+    /// Prices averaged by the SMA and EMA.
+    pub ma_window: usize,
+    /// Price changes considered by the RSI.
+    pub rsi_window: usize,
+    /// Compute the indicators over the closes of candles this long instead
+    /// of over every poll, windows counting candles.
+    pub candles: Option<CandleInterval>,
+}
+
+impl Default for IndicatorOptions {
+    fn default() -> Self {
+        Self { ma_window: DEFAULT_MA_WINDOW, rsi_window: DEFAULT_RSI_WINDOW, candles: None }
+    }
+}
+
+/// Technical indicators of a symbol as of its latest quote. A value is
+/// `None` until enough quotes have been seen to fill its window.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Indicators {
+    pub ma_window: usize,
+    pub sma: Option<f64>,
+    pub ema: Option<f64>,
+    pub rsi_window: usize,
+    pub rsi: Option<f64>,
+    pub macd: Option<Macd>,
+    pub bollinger: Option<BollingerBands>,
+}
+
+/// MACD 12/26/9: the 12-period EMA less the 26-period one, and a 9-period
+/// EMA of that difference as its signal line.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Macd {
+    pub line: f64,
+    pub signal: f64,
+    /// The line less the signal; positive while the line is above it.
+    pub histogram: f64,
+}
+
+/// Bands [`BOLLINGER_WIDTH`] standard deviations around the 20-period SMA.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BollingerBands {
+    pub middle: f64,
+    pub upper: f64,
+    pub lower: f64,
+}
+
+/// Rolling in-memory price history per symbol, long enough for the EMA, RSI
+/// and MACD smoothing to settle.
+#[derive(Debug, Clone, Default)]
+pub struct PriceHistory {
+    options: IndicatorOptions,
+    /// Prices kept per symbol beyond what the indicators need.
+    min_len: usize,
+    prices: HashMap<String, VecDeque<f64>>,
+}
+
+impl PriceHistory {
+    pub fn new(options: IndicatorOptions) -> Self {
+        Self { options, min_len: 0, prices: HashMap::new() }
+    }
+
+    /// Keeps at least `count` prices per symbol for [`PriceHistory::recent`].
+    pub fn keep_at_least(mut self, count: usize) -> Self {
+        self.min_len = count;
+        self
+    }
+
+    fn capacity(&self) -> usize {
+        let longest = self.options.ma_window
+            .max(self.options.rsi_window + 1)
+            .max(MACD_SLOW + MACD_SIGNAL)
+            .max(BOLLINGER_WINDOW);
+
+        (4 * longest).max(self.min_len)
+    }
+
+    /// The last `count` prices recorded for `symbol`, oldest first.
+    pub fn recent(&self, symbol: &str, count: usize) -> Vec<f64> {
+        let Some(prices) = self.prices.get(symbol) else {
+            return Vec::new();
+        };
+
+        prices.iter().skip(prices.len().saturating_sub(count)).copied().collect()
+    }
+
+    /// Adds `price` to the history of `symbol` and returns its indicators.
+    pub fn record(&mut self, symbol: &str, price: f64) -> Indicators {
+        let capacity = self.capacity();
+        let prices = self.prices.entry(symbol.to_string()).or_default();
+
+        if prices.len() == capacity {
+            prices.pop_front();
+        }
+        prices.push_back(price);
+
+        self.indicators(symbol)
+    }
+
+    /// Adds a polled `price` of `symbol` and returns its indicators. When
+    /// computing over candles, the closes of the candles the poll `closed`
+    /// are added instead.
+    pub fn record_poll(&mut self, symbol: &str, price: f64, closed: &[(CandleInterval, Candle)]) -> Indicators {
+        let Some(interval) = self.options.candles else {
+            return self.record(symbol, price);
+        };
+
+        for (_, candle) in closed.iter().filter(|(closed, _)| *closed == interval) {
+            self.record(symbol, candle.close.to_f64());
+        }
+
+        self.indicators(symbol)
+    }
+
+    /// The indicators of `symbol` as of the last price recorded.
+    pub fn indicators(&self, symbol: &str) -> Indicators {
+        let prices = self.prices.get(symbol).map(|prices| prices.iter().copied().collect::<Vec<f64>>()).unwrap_or_default();
+        let IndicatorOptions { ma_window, rsi_window, .. } = self.options;
+
+        Indicators {
+            ma_window,
+            sma: sma(&prices, ma_window),
+            ema: ema(&prices, ma_window),
+            rsi_window,
+            rsi: rsi(&prices, rsi_window),
+            macd: macd(&prices),
+            bollinger: bollinger(&prices, BOLLINGER_WINDOW, BOLLINGER_WIDTH),
+        }
+    }
+}
+
+/// Simple moving average of the last `window` prices.
+pub fn sma(prices: &[f64], window: usize) -> Option<f64> {
+    if window == 0 || prices.len() < window {
+        return None;
+    }
+
+    Some(prices[prices.len() - window..].iter().sum::<f64>() / window as f64)
+}
+
+/// Exponential moving average, seeded with the SMA of the first `window`
+/// prices.
+pub fn ema(prices: &[f64], window: usize) -> Option<f64> {
+    ema_series(prices, window).last().copied()
+}
+
+/// The EMA as of each price from the `window`th on.
+fn ema_series(prices: &[f64], window: usize) -> Vec<f64> {
+    let Some(seed) = sma(&prices[..window.min(prices.len())], window) else {
+        return Vec::new();
+    };
+    let k = 2f64 / (window as f64 + 1f64);
+
+    let mut series = vec![seed];
+
+    for price in &prices[window..] {
+        let previous = series[series.len() - 1];
+        series.push(price * k + previous * (1f64 - k));
+    }
+
+    series
+}
+
+/// MACD 12/26/9 as of the last price, once there are enough prices for its
+/// signal line.
+pub fn macd(prices: &[f64]) -> Option<Macd> {
+    let fast = ema_series(prices, MACD_FAST);
+    let slow = ema_series(prices, MACD_SLOW);
+    // Both series end at the last price; the fast one starts earlier.
+    let lines: Vec<f64> = fast[fast.len().saturating_sub(slow.len())..].iter()
+        .zip(slow.iter())
+        .map(|(fast, slow)| fast - slow)
+        .collect();
+
+    let line = *lines.last()?;
+    let signal = ema(&lines, MACD_SIGNAL)?;
+
+    Some(Macd { line, signal, histogram: line - signal })
+}
+
+/// Bollinger Bands `width` population standard deviations around the SMA of
+/// the last `window` prices.
+pub fn bollinger(prices: &[f64], window: usize, width: f64) -> Option<BollingerBands> {
+    let middle = sma(prices, window)?;
+    let variance = prices[prices.len() - window..].iter()
+        .map(|price| (price - middle).powi(2))
+        .sum::<f64>() / window as f64;
+    let deviation = variance.sqrt();
+
+    Some(BollingerBands { middle, upper: middle + width * deviation, lower: middle - width * deviation })
+}
+
+/// Wilder's relative strength index over `window` price changes, from 0
+/// (only losses) to 100 (only gains).
+pub fn rsi(prices: &[f64], window: usize) -> Option<f64> {
+    if window == 0 || prices.len() <= window {
+        return None;
+    }
+
+    let changes: Vec<f64> = prices.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    let (seed, rest) = changes.split_at(window);
+
+    let mut gain = seed.iter().filter(|change| **change > 0f64).sum::<f64>() / window as f64;
+    let mut loss = -seed.iter().filter(|change| **change < 0f64).sum::<f64>() / window as f64;
+
+    for change in rest {
+        gain = (gain * (window - 1) as f64 + change.max(0f64)) / window as f64;
+        loss = (loss * (window - 1) as f64 + (-change).max(0f64)) / window as f64;
+    }
+
+    match loss == 0f64 {
+        true if gain == 0f64 => Some(50f64),
+        true => Some(100f64),
+        false => Some(100f64 - 100f64 / (1f64 + gain / loss)),
+    }
+}
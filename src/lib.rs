@@ -0,0 +1,51 @@
+pub mod alert;
+pub mod backtest;
+pub mod calendar;
+pub mod candle;
+pub mod circuit;
+pub mod cli;
+pub mod client;
+pub mod config;
+pub mod cron;
+#[cfg(unix)]
+pub mod daemon;
+pub mod decimal;
+pub mod doctor;
+pub mod fx;
+pub mod health;
+pub mod http;
+pub mod indicators;
+pub mod logging;
+pub mod market;
+pub mod notify;
+pub mod output;
+pub mod pipeline;
+pub mod plugin;
+pub mod portfolio;
+pub mod price;
+pub mod quotes;
+pub mod ratelimit;
+pub mod retry;
+pub mod robots;
+pub mod rules;
+pub mod schedule;
+pub mod script;
+pub mod scraping;
+pub mod secret;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod shutdown;
+pub mod simulate;
+pub mod stats;
+pub mod stock;
+pub mod storage;
+pub mod symbol;
+pub mod template;
+pub mod thread;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod watch;
+
+pub use client::{fetch_quote, StockClient};
+pub use decimal::Decimal;
+pub use stock::{Fundamentals, NewsItem, PriceRange, QuoteDetails, QuoteKind, Status, Stock, StockError, Valuation};
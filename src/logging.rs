@@ -0,0 +1,76 @@
+use std::{fmt, io::{self, IsTerminal}, str::FromStr};
+use serde::{Deserialize, Deserializer};
+use tracing::level_filters::LevelFilter;
+
+use crate::StockError;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per event, for shipping to Loki, ELK and the like.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(StockError::invalid("log format", value.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LogFormat::Text => write!(f, "text"),
+            LogFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Parses `error`, `warn`, `info`, `debug`, `trace` or `off`.
+pub fn parse_level(value: &str) -> Result<LevelFilter, StockError> {
+    value.parse::<LevelFilter>()
+        .map_err(|err| {
+            StockError::invalid("log level", format!("{}: {}", value, err))
+        })
+}
+
+/// Reads an optional level from the config file, see [`parse_level`].
+pub fn deserialize_level<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<LevelFilter>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| parse_level(&value).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// Whether to colour the console: when `wanted`, `NO_COLOR` isn't set and
+/// stderr, where log lines go, is a terminal.
+pub fn use_color(wanted: bool) -> bool {
+    wanted && std::env::var_os("NO_COLOR").is_none() && io::stderr().is_terminal()
+}
+
+/// Sends `tracing` events at `level` and above to stderr, keeping stdout free
+/// for `--output json`/`csv`. Events carry the `poll` and `fetch` spans they
+/// were recorded in, and are coloured with `color`.
+pub fn init(level: LevelFilter, format: LogFormat, color: bool) {
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(io::stderr)
+        .with_ansi(color);
+
+    let result = match format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder.json().with_current_span(false).try_init(),
+    };
+
+    if let Err(err) = result {
+        eprintln!("Failed to set up logging: {}", err);
+    }
+}
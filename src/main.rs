@@ -1,8 +1,3 @@
-pub mod thread;
-pub mod scraping;
-
 fn main() {
-    println!("Hello, world!");
-    
-    scraping::googlefinance::fetch_stock_price();
+    scraping_stock::cli::run();
 }
@@ -0,0 +1,124 @@
+use std::{fmt, str::FromStr};
+use chrono::{DateTime, Datelike, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::Serialize;
+
+use crate::StockError;
+
+
+/// Whether an exchange is trading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketStatus {
+    PreMarket,
+    Open,
+    AfterHours,
+    Closed,
+}
+
+impl fmt::Display for MarketStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MarketStatus::PreMarket => write!(f, "pre-market"),
+            MarketStatus::Open => write!(f, "open"),
+            MarketStatus::AfterHours => write!(f, "after-hours"),
+            MarketStatus::Closed => write!(f, "closed"),
+        }
+    }
+}
+
+impl FromStr for MarketStatus {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().replace('_', "-").as_str() {
+            "pre-market" => Ok(MarketStatus::PreMarket),
+            "open" => Ok(MarketStatus::Open),
+            "after-hours" => Ok(MarketStatus::AfterHours),
+            "closed" => Ok(MarketStatus::Closed),
+            _ => Err(StockError::invalid("market status", value.to_string())),
+        }
+    }
+}
+
+/// Weekly trading hours of an exchange in its local time, as minutes after
+/// midnight. Lunch breaks count as open and holidays aren't known, so a quote
+/// page's own status wins over the calendar when it has one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calendar {
+    pub timezone: Tz,
+    pub pre_open: Option<u32>,
+    pub open: u32,
+    pub close: u32,
+    pub after_close: Option<u32>,
+}
+
+impl Calendar {
+    const fn regular(timezone: Tz, open: u32, close: u32) -> Self {
+        Self { timezone, pre_open: None, open, close, after_close: None }
+    }
+
+    pub fn status_at(&self, now: DateTime<Utc>) -> MarketStatus {
+        let local = now.with_timezone(&self.timezone);
+
+        if matches!(local.weekday(), Weekday::Sat | Weekday::Sun) {
+            return MarketStatus::Closed;
+        }
+
+        let minute = local.hour() * 60 + local.minute();
+
+        if (self.open..self.close).contains(&minute) {
+            MarketStatus::Open
+        } else if self.pre_open.is_some_and(|pre_open| (pre_open..self.open).contains(&minute)) {
+            MarketStatus::PreMarket
+        } else if self.after_close.is_some_and(|after_close| (self.close..after_close).contains(&minute)) {
+            MarketStatus::AfterHours
+        } else {
+            MarketStatus::Closed
+        }
+    }
+}
+
+const fn hm(hour: u32, minute: u32) -> u32 {
+    hour * 60 + minute
+}
+
+/// The calendar of a Google Finance exchange code, `None` for exchanges
+/// without one (and crypto, which never closes).
+pub fn calendar(exchange: &str) -> Option<Calendar> {
+    use chrono_tz::{America, Asia, Australia, Europe};
+
+    let calendar = match exchange {
+        "NASDAQ" | "NYSE" | "NYSEARCA" | "NYSEAMERICAN" | "BATS" | "OTCMKTS" => Calendar {
+            timezone: America::New_York,
+            pre_open: Some(hm(4, 0)),
+            open: hm(9, 30),
+            close: hm(16, 0),
+            after_close: Some(hm(20, 0)),
+        },
+        "INDEXSP" | "INDEXDJX" | "INDEXNASDAQ" => Calendar::regular(America::New_York, hm(9, 30), hm(16, 0)),
+        "TSE" | "CVE" => Calendar::regular(America::Toronto, hm(9, 30), hm(16, 0)),
+        "IDX" => Calendar::regular(Asia::Jakarta, hm(9, 0), hm(16, 0)),
+        "LON" => Calendar::regular(Europe::London, hm(8, 0), hm(16, 30)),
+        "ETR" => Calendar::regular(Europe::Berlin, hm(9, 0), hm(17, 30)),
+        "FRA" => Calendar::regular(Europe::Berlin, hm(8, 0), hm(22, 0)),
+        "EPA" | "AMS" | "EBR" | "ELI" => Calendar::regular(Europe::Paris, hm(9, 0), hm(17, 30)),
+        "TYO" => Calendar::regular(Asia::Tokyo, hm(9, 0), hm(15, 30)),
+        "HKG" => Calendar::regular(Asia::Hong_Kong, hm(9, 30), hm(16, 0)),
+        "ASX" => Calendar::regular(Australia::Sydney, hm(10, 0), hm(16, 0)),
+        "SGX" => Calendar::regular(Asia::Singapore, hm(9, 0), hm(17, 0)),
+        "KRX" | "KOSDAQ" => Calendar::regular(Asia::Seoul, hm(9, 0), hm(15, 30)),
+        "NSE" | "BOM" => Calendar::regular(Asia::Kolkata, hm(9, 15), hm(15, 30)),
+        _ => return None,
+    };
+
+    Some(calendar)
+}
+
+/// The status of the exchange of a `SYMBOL:EXCHANGE` code according to its
+/// calendar.
+pub fn status(share_code: &str, now: DateTime<Utc>) -> Option<MarketStatus> {
+    let (_, exchange) = share_code.split_once(':')?;
+
+    calendar(&exchange.to_uppercase()).map(|calendar| calendar.status_at(now))
+}
@@ -0,0 +1,20 @@
+use tracing::warn;
+
+use crate::{alert::AlertEvent, notify::{Channel, Notifier}, StockError};
+
+
+/// Logs alerts as `ALERT` lines.
+#[derive(Debug, Clone, Default)]
+pub struct ConsoleNotifier;
+
+impl Notifier for ConsoleNotifier {
+    fn channel(&self) -> Channel {
+        Channel::Console
+    }
+
+    fn notify(&self, event: &AlertEvent) -> Result<(), StockError> {
+        warn!(symbol = %event.symbol, "ALERT {}", event);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use notify_rust::Notification;
+
+use crate::{alert::AlertEvent, notify::{Channel, MessageTemplate, Notifier}, StockError};
+
+
+/// Shows alerts as desktop notifications.
+#[derive(Debug, Clone, Default)]
+pub struct DesktopNotifier {
+    template: Option<MessageTemplate>,
+}
+
+impl DesktopNotifier {
+    /// Writes the body with `template` instead of the price change.
+    pub fn with_template(mut self, template: MessageTemplate) -> Self {
+        self.template = Some(template);
+        self
+    }
+
+    fn body(&self, event: &AlertEvent) -> String {
+        self.template.as_ref()
+            .map(|template| template.render(event))
+            .unwrap_or_else(|| format!("{} is now {} (was {})", event.company_name, event.price, event.previous_price))
+    }
+}
+
+impl Notifier for DesktopNotifier {
+    fn channel(&self) -> Channel {
+        Channel::Desktop
+    }
+
+    fn notify(&self, event: &AlertEvent) -> Result<(), StockError> {
+        Notification::new()
+            .summary(&summary(event))
+            .body(&self.body(event))
+            .appname("scraping-stock")
+            .show()
+            .map_err(|err| {
+                StockError::Notification(err.into())
+            })?;
+
+        Ok(())
+    }
+
+    fn describe(&self, event: &AlertEvent) -> String {
+        format!("{}\n{}", summary(event), self.body(event))
+    }
+}
+
+fn summary(event: &AlertEvent) -> String {
+    format!("{} {:+.2}%", event.symbol, event.change_pct)
+}
@@ -0,0 +1,102 @@
+use serde::Serialize;
+
+use crate::{alert::AlertEvent, notify::{self, Channel, MessageTemplate, Notifier}, StockError};
+
+const COLOR_UP: u32 = 0x2ecc71;
+const COLOR_DOWN: u32 = 0xe74c3c;
+
+
+#[derive(Debug, Serialize)]
+struct ExecuteWebhook {
+    embeds: [Embed; 1],
+}
+
+#[derive(Debug, Serialize)]
+struct Embed {
+    title: String,
+    description: String,
+    color: u32,
+    fields: Vec<EmbedField>,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbedField {
+    name: &'static str,
+    value: String,
+    inline: bool,
+}
+
+/// Posts alerts as embeds to a Discord channel webhook.
+#[derive(Debug, Clone)]
+pub struct DiscordNotifier {
+    client: reqwest::blocking::Client,
+    webhook_url: String,
+    template: Option<MessageTemplate>,
+}
+
+impl DiscordNotifier {
+    pub fn new(webhook_url: String) -> Self {
+        Self { client: reqwest::blocking::Client::new(), webhook_url, template: None }
+    }
+
+    /// Writes the embed description with `template` instead of the alert
+    /// line.
+    pub fn with_template(mut self, template: MessageTemplate) -> Self {
+        self.template = Some(template);
+        self
+    }
+}
+
+impl Notifier for DiscordNotifier {
+    fn channel(&self) -> Channel {
+        Channel::Discord
+    }
+
+    fn notify(&self, event: &AlertEvent) -> Result<(), StockError> {
+        execute_webhook(&self.client, &self.webhook_url, &ExecuteWebhook { embeds: [embed(event, self.template.as_ref())] })
+    }
+
+    fn describe(&self, event: &AlertEvent) -> String {
+        let embed = embed(event, self.template.as_ref());
+
+        format!("{}\n{}", embed.title, embed.description)
+    }
+}
+
+fn embed(event: &AlertEvent, template: Option<&MessageTemplate>) -> Embed {
+    let (trend, color) = match event.change_pct < 0f64 {
+        true => ("📉", COLOR_DOWN),
+        false => ("📈", COLOR_UP),
+    };
+
+    let mut fields = vec![
+        EmbedField { name: "Price", value: event.price.to_string(), inline: true },
+        EmbedField { name: "Change", value: format!("{:+.2}%", event.change_pct), inline: true },
+    ];
+
+    if !event.history.is_empty() {
+        fields.push(EmbedField { name: "Trend", value: format!("{} {}", trend, notify::sparkline(&event.history)), inline: false });
+    }
+
+    Embed {
+        title: format!("{} {} ({})", trend, event.symbol, event.company_name),
+        description: template.map(|template| template.render(event)).unwrap_or_else(|| event.to_string()),
+        color,
+        fields,
+    }
+}
+
+fn execute_webhook(client: &reqwest::blocking::Client, webhook_url: &str, message: &ExecuteWebhook) -> Result<(), StockError> {
+    let res = client.post(webhook_url)
+        .json(message)
+        .send()
+        .map_err(|err| {
+            StockError::Network(err.without_url().into())
+        })?;
+
+    if !res.status().is_success() {
+        return Err(StockError::status(res.status()));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use std::time::Duration;
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    Message, SmtpTransport, Transport,
+};
+
+use crate::{alert::AlertEvent, config::{EmailConfig, SmtpTls}, notify::{self, Channel, MessageTemplate, Notifier}, StockError};
+
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+
+/// Mails alerts through an SMTP server.
+#[derive(Debug, Clone)]
+pub struct EmailNotifier {
+    transport: SmtpTransport,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    template: Option<MessageTemplate>,
+}
+
+impl EmailNotifier {
+    pub fn new(config: &EmailConfig) -> Result<Self, StockError> {
+        let host = config.host.as_deref()
+            .ok_or_else(|| invalid("[email] needs a host".to_string()))?;
+        let from = config.from.as_deref().or(config.username.as_deref())
+            .ok_or_else(|| invalid("[email] needs a from address".to_string()))?;
+        let from = mailbox(from)?;
+        let to = config.to.iter()
+            .map(|address| mailbox(address))
+            .collect::<Result<Vec<Mailbox>, StockError>>()?;
+
+        if to.is_empty() {
+            return Err(invalid("[email] needs at least one to address".to_string()));
+        }
+
+        let mut builder = match config.tls {
+            SmtpTls::Starttls => SmtpTransport::starttls_relay(host),
+            SmtpTls::Tls => SmtpTransport::relay(host),
+            SmtpTls::None => Ok(SmtpTransport::builder_dangerous(host)),
+        }.map_err(|err| invalid(format!("{}: {}", host, err)))?;
+
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Self { transport: builder.timeout(Some(SMTP_TIMEOUT)).build(), from, to, template: None })
+    }
+
+    /// Writes the body with `template` instead of the built-in summary.
+    pub fn with_template(mut self, template: MessageTemplate) -> Self {
+        self.template = Some(template);
+        self
+    }
+
+    fn body(&self, event: &AlertEvent) -> String {
+        if let Some(template) = &self.template {
+            return template.render(event);
+        }
+
+        let mut body = format!(
+            "{} ({})\n{}\n\nPrice: {}\nPrevious price: {}\nChange: {:+.2}%\n",
+            event.symbol, event.company_name, event, event.price, event.previous_price, event.change_pct,
+        );
+
+        if !event.history.is_empty() {
+            body.push_str(&format!("Trend: {}\n", notify::sparkline(&event.history)));
+        }
+
+        body
+    }
+}
+
+impl Notifier for EmailNotifier {
+    fn channel(&self) -> Channel {
+        Channel::Email
+    }
+
+    fn notify(&self, event: &AlertEvent) -> Result<(), StockError> {
+        send_email(&self.transport, &self.from, &self.to, event.to_string(), self.body(event))
+    }
+
+    fn describe(&self, event: &AlertEvent) -> String {
+        format!("{}\n\n{}", event, self.body(event))
+    }
+}
+
+fn send_email(transport: &SmtpTransport, from: &Mailbox, to: &[Mailbox], subject: String, body: String) -> Result<(), StockError> {
+    let mut builder = Message::builder()
+        .from(from.clone())
+        .subject(subject)
+        .header(ContentType::TEXT_PLAIN);
+
+    for address in to {
+        builder = builder.to(address.clone());
+    }
+
+    let message = builder.body(body)
+        .map_err(|err| {
+            StockError::Notification(err.into())
+        })?;
+
+    // Permanent SMTP errors (5xx replies) won't go away on a retry.
+    transport.send(&message)
+        .map_err(|err| match err.is_permanent() {
+            true => StockError::Notification(err.into()),
+            false => StockError::Network(err.into()),
+        })?;
+
+    Ok(())
+}
+
+fn mailbox(address: &str) -> Result<Mailbox, StockError> {
+    address.parse::<Mailbox>()
+        .map_err(|err| invalid(format!("{}: {}", address, err)))
+}
+
+fn invalid(message: String) -> StockError {
+    StockError::invalid("notifier", message)
+}
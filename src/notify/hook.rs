@@ -0,0 +1,164 @@
+use std::{fmt, process::{Command, Stdio}, str::FromStr, thread, time::{Duration, Instant}};
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::{alert::AlertEvent, notify::{Channel, Notifier}, template::{Fields, Template}, StockError};
+
+/// How long a hook may run before it is killed.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+
+/// A command line run on an event, e.g. `notify-send "Stock alert" {alert}`.
+/// Words are split on whitespace unless quoted with `'` or `"`, and each is
+/// a template over the event's placeholders. No shell is involved: a word
+/// stays one argument whatever its placeholders are filled in with.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct HookCommand {
+    source: String,
+    words: Vec<Template>,
+}
+
+impl FromStr for HookCommand {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let words = split(value)?.iter()
+            .map(|word| {
+                let template: Template = word.parse()?;
+                template.check_for::<AlertEvent>()?;
+                Ok(template)
+            })
+            .collect::<Result<Vec<Template>, StockError>>()?;
+
+        if words.is_empty() {
+            return Err(StockError::invalid("hook", "empty command".to_string()));
+        }
+
+        Ok(Self { source: value.to_string(), words })
+    }
+}
+
+impl TryFrom<String> for HookCommand {
+    type Error = StockError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for HookCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl HookCommand {
+    /// The program and its arguments for `fields`.
+    pub fn render(&self, fields: &impl Fields) -> Vec<String> {
+        self.words.iter().map(|word| word.render(fields)).collect()
+    }
+}
+
+/// Runs a command on every alert, with the alert's placeholders in the
+/// arguments and as `STOCK_SYMBOL`, `STOCK_PRICE`, ... environment variables.
+/// The command runs in the background; one still running after the timeout
+/// is killed, and failures are logged.
+#[derive(Debug, Clone)]
+pub struct HookNotifier {
+    command: HookCommand,
+    timeout: Duration,
+}
+
+impl HookNotifier {
+    pub fn new(command: HookCommand) -> Self {
+        Self { command, timeout: DEFAULT_TIMEOUT }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl Notifier for HookNotifier {
+    fn channel(&self) -> Channel {
+        Channel::Hook
+    }
+
+    fn notify(&self, event: &AlertEvent) -> Result<(), StockError> {
+        let args = self.command.render(event);
+        let mut command = Command::new(&args[0]);
+        command.args(&args[1..])
+            .stdin(Stdio::null());
+
+        for name in AlertEvent::NAMES {
+            let value: Template = format!("{{{}}}", name).parse()?;
+            command.env(format!("STOCK_{}", name.to_uppercase()), value.render(event));
+        }
+
+        let mut child = command.spawn()
+            .map_err(|err| {
+                StockError::Notification(format!("can't run {}: {}", args[0], err).into())
+            })?;
+
+        let program = args[0].clone();
+        let timeout = self.timeout;
+
+        thread::spawn(move || {
+            let started = Instant::now();
+
+            let failure = loop {
+                match child.try_wait() {
+                    Ok(Some(status)) if status.success() => return,
+                    Ok(Some(status)) => break format!("failed: {}", status),
+                    Ok(None) if started.elapsed() >= timeout => {
+                        let _ = child.kill();
+                        let _ = child.wait();
+                        break format!("still running after {:?}, killed it", timeout);
+                    }
+                    Ok(None) => thread::sleep(POLL_INTERVAL),
+                    Err(err) => break format!("can't be waited for: {}", err),
+                }
+            };
+
+            warn!("Hook {} {}", program, failure);
+        });
+
+        Ok(())
+    }
+
+    fn describe(&self, event: &AlertEvent) -> String {
+        self.command.render(event).join(" ")
+    }
+}
+
+/// Splits `command` into words on whitespace, keeping quoted text together.
+fn split(command: &str) -> Result<Vec<String>, StockError> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+
+    for c in command.chars() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if quote.is_some() {
+        return Err(StockError::invalid("hook", format!("{:?}: unclosed quote", command)));
+    }
+
+    words.extend(word);
+
+    Ok(words)
+}
@@ -0,0 +1,277 @@
+use std::{fmt, str::FromStr, sync::{mpsc, Arc}, thread};
+use serde::Deserialize;
+use tracing::{error, info, warn};
+
+use crate::{alert::AlertEvent, ratelimit::TokenBucket, retry::RetryPolicy, template::Template, StockError};
+
+pub mod console;
+#[cfg(feature = "desktop-notify")]
+pub mod desktop;
+pub mod discord;
+#[cfg(feature = "email")]
+pub mod email;
+pub mod hook;
+pub mod slack;
+pub mod telegram;
+
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+pub const DEFAULT_TEMPLATE: &str = "{symbol} ({company})\n{alert}";
+
+
+/// A backend alerts can be delivered through.
+pub trait Notifier: Send + Sync {
+    fn channel(&self) -> Channel;
+
+    /// Delivers `event`, blocking until it has been handed over.
+    fn notify(&self, event: &AlertEvent) -> Result<(), StockError>;
+
+    /// What [`Notifier::notify`] would deliver for `event`, shown by dry runs.
+    fn describe(&self, event: &AlertEvent) -> String {
+        event.to_string()
+    }
+}
+
+/// A notification channel, as named in a rule's `notify` list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    /// The `ALERT` log line; always notified.
+    Console,
+    Desktop,
+    Telegram,
+    Discord,
+    Slack,
+    Email,
+    /// The `[hooks] on_alert` command.
+    Hook,
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Channel::Console => write!(f, "console"),
+            Channel::Desktop => write!(f, "desktop"),
+            Channel::Telegram => write!(f, "telegram"),
+            Channel::Discord => write!(f, "discord"),
+            Channel::Slack => write!(f, "slack"),
+            Channel::Email => write!(f, "email"),
+            Channel::Hook => write!(f, "hook"),
+        }
+    }
+}
+
+impl Channel {
+    /// Requests per minute the service accepts without throttling us.
+    pub fn default_per_minute(&self) -> Option<u32> {
+        match self {
+            Channel::Console | Channel::Desktop | Channel::Hook => None,
+            Channel::Telegram => Some(20),
+            Channel::Discord => Some(30),
+            Channel::Slack => Some(60),
+            Channel::Email => Some(10),
+        }
+    }
+}
+
+impl FromStr for Channel {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "console" => Ok(Channel::Console),
+            "desktop" => Ok(Channel::Desktop),
+            "telegram" => Ok(Channel::Telegram),
+            "discord" => Ok(Channel::Discord),
+            "slack" => Ok(Channel::Slack),
+            "email" => Ok(Channel::Email),
+            "hook" => Ok(Channel::Hook),
+            _ => Err(StockError::invalid("channel", value.to_string())),
+        }
+    }
+}
+
+/// The text of a notification with `{placeholder}`s filled in from the
+/// alert: `symbol`, `company`, `price`, `previous_price`, `change_pct`,
+/// `alert` (the console alert line) and `sparkline`, each optionally
+/// formatted as in `{price:>10.2}`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct MessageTemplate(Template);
+
+impl Default for MessageTemplate {
+    fn default() -> Self {
+        DEFAULT_TEMPLATE.parse().expect("default notification template")
+    }
+}
+
+impl FromStr for MessageTemplate {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let template: Template = value.parse()?;
+        template.check_for::<AlertEvent>()?;
+
+        Ok(Self(template))
+    }
+}
+
+impl TryFrom<String> for MessageTemplate {
+    type Error = StockError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl MessageTemplate {
+    pub fn render(&self, event: &AlertEvent) -> String {
+        self.0.render(event)
+    }
+}
+
+/// How the dispatcher delivers to one backend.
+#[derive(Debug, Clone)]
+pub struct Delivery {
+    /// Applied to transient failures (network errors, HTTP 429 / 5xx).
+    pub retry: RetryPolicy,
+    pub per_minute: Option<u32>,
+}
+
+impl Delivery {
+    /// Delivered on the spot, without retries or throttling.
+    pub fn immediate() -> Self {
+        Self { retry: RetryPolicy::none(), per_minute: None }
+    }
+
+    fn is_immediate(&self) -> bool {
+        self.retry.max_attempts <= 1 && self.per_minute.is_none()
+    }
+}
+
+#[derive(Clone)]
+enum Backend {
+    Immediate(Arc<dyn Notifier>),
+    Queued(Channel, mpsc::Sender<AlertEvent>),
+}
+
+impl Backend {
+    fn channel(&self) -> Channel {
+        match self {
+            Backend::Immediate(notifier) => notifier.channel(),
+            Backend::Queued(channel, _) => *channel,
+        }
+    }
+}
+
+/// Fans alerts out to every registered backend.
+///
+/// Backends with retries or a rate limit get a queue and a thread of their
+/// own, so a slow or failing service only ever delays its own alerts and
+/// never the polling loop; immediate ones, like the console, are notified
+/// in line so their output stays in order with the quotes.
+#[derive(Clone, Default)]
+pub struct Dispatcher {
+    backends: Vec<Backend>,
+    dry_run: bool,
+}
+
+impl fmt::Debug for Dispatcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Dispatcher")
+            .field("channels", &self.channels())
+            .finish()
+    }
+}
+
+impl Dispatcher {
+    /// A dispatcher logging what every backend but the console would deliver,
+    /// instead of delivering it.
+    pub fn dry_run() -> Self {
+        Self { dry_run: true, ..Self::default() }
+    }
+
+    pub fn register(&mut self, notifier: impl Notifier + 'static, delivery: Delivery) {
+        if self.dry_run && notifier.channel() != Channel::Console {
+            self.backends.push(Backend::Immediate(Arc::new(DryRunNotifier(notifier))));
+            return;
+        }
+
+        if delivery.is_immediate() {
+            self.backends.push(Backend::Immediate(Arc::new(notifier)));
+            return;
+        }
+
+        let channel = notifier.channel();
+        let (sender, receiver) = mpsc::channel::<AlertEvent>();
+
+        thread::spawn(move || {
+            let limit = delivery.per_minute.map(TokenBucket::per_minute);
+
+            for event in receiver {
+                let result = delivery.retry.run(&format!("{}:{}", channel, event.symbol), || {
+                    if let Some(limit) = &limit {
+                        limit.acquire();
+                    }
+
+                    notifier.notify(&event)
+                });
+
+                if let Err(err) = result {
+                    error!("Failed to send {} alert for {}: {}", channel, event.symbol, err);
+                }
+            }
+        });
+
+        self.backends.push(Backend::Queued(channel, sender));
+    }
+
+    pub fn channels(&self) -> Vec<Channel> {
+        self.backends.iter().map(Backend::channel).collect()
+    }
+
+    /// Hands `event` to every backend the alert wants.
+    pub fn dispatch(&self, event: &AlertEvent) {
+        for backend in self.backends.iter().filter(|backend| event.wants(backend.channel())) {
+            match backend {
+                Backend::Immediate(notifier) => {
+                    if let Err(err) = notifier.notify(event) {
+                        error!("Failed to send {} alert for {}: {}", notifier.channel(), event.symbol, err);
+                    }
+                }
+                Backend::Queued(channel, sender) => {
+                    if sender.send(event.clone()).is_err() {
+                        warn!("{} notifier is not running, dropping alert for {}", channel, event.symbol);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Logs what the notifier it wraps would deliver.
+struct DryRunNotifier<N>(N);
+
+impl<N: Notifier> Notifier for DryRunNotifier<N> {
+    fn channel(&self) -> Channel {
+        self.0.channel()
+    }
+
+    fn notify(&self, event: &AlertEvent) -> Result<(), StockError> {
+        info!(symbol = %event.symbol, "Dry run, not sent to {}: {:?}", self.0.channel(), self.0.describe(event));
+
+        Ok(())
+    }
+}
+
+/// Draws `prices` as a line of block characters, lowest to highest.
+pub fn sparkline(prices: &[f64]) -> String {
+    let low = prices.iter().copied().fold(f64::INFINITY, f64::min);
+    let high = prices.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+    prices.iter()
+        .map(|price| match high > low {
+            true => SPARKS[((price - low) / (high - low) * (SPARKS.len() - 1) as f64).round() as usize],
+            false => SPARKS[SPARKS.len() / 2],
+        })
+        .collect()
+}
@@ -0,0 +1,52 @@
+use serde::Serialize;
+
+use crate::{alert::AlertEvent, notify::{Channel, MessageTemplate, Notifier}, StockError};
+
+
+#[derive(Debug, Serialize)]
+struct IncomingWebhook {
+    text: String,
+}
+
+/// Posts alerts to a Slack incoming webhook.
+#[derive(Debug, Clone)]
+pub struct SlackNotifier {
+    client: reqwest::blocking::Client,
+    webhook_url: String,
+    template: MessageTemplate,
+}
+
+impl SlackNotifier {
+    pub fn new(webhook_url: String, template: MessageTemplate) -> Self {
+        Self { client: reqwest::blocking::Client::new(), webhook_url, template }
+    }
+}
+
+impl Notifier for SlackNotifier {
+    fn channel(&self) -> Channel {
+        Channel::Slack
+    }
+
+    fn notify(&self, event: &AlertEvent) -> Result<(), StockError> {
+        post_message(&self.client, &self.webhook_url, self.template.render(event))
+    }
+
+    fn describe(&self, event: &AlertEvent) -> String {
+        self.template.render(event)
+    }
+}
+
+fn post_message(client: &reqwest::blocking::Client, webhook_url: &str, text: String) -> Result<(), StockError> {
+    let res = client.post(webhook_url)
+        .json(&IncomingWebhook { text })
+        .send()
+        .map_err(|err| {
+            StockError::Network(err.without_url().into())
+        })?;
+
+    if !res.status().is_success() {
+        return Err(StockError::status(res.status()));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use serde::Serialize;
+
+use crate::{alert::AlertEvent, notify::{Channel, MessageTemplate, Notifier}, StockError};
+
+const API_URL: &str = "https://api.telegram.org";
+
+
+#[derive(Debug, Serialize)]
+struct SendMessage<'a> {
+    chat_id: &'a str,
+    text: &'a str,
+}
+
+/// Sends alerts to a Telegram chat through the Bot API.
+#[derive(Debug, Clone)]
+pub struct TelegramNotifier {
+    client: reqwest::blocking::Client,
+    bot_token: String,
+    chat_id: String,
+    template: MessageTemplate,
+}
+
+impl TelegramNotifier {
+    pub fn new(bot_token: String, chat_id: String) -> Self {
+        Self { client: reqwest::blocking::Client::new(), bot_token, chat_id, template: MessageTemplate::default() }
+    }
+
+    /// Writes messages with `template` instead of the default.
+    pub fn with_template(mut self, template: MessageTemplate) -> Self {
+        self.template = template;
+        self
+    }
+}
+
+impl Notifier for TelegramNotifier {
+    fn channel(&self) -> Channel {
+        Channel::Telegram
+    }
+
+    fn notify(&self, event: &AlertEvent) -> Result<(), StockError> {
+        send_message(&self.client, &self.bot_token, &self.chat_id, &self.template.render(event))
+    }
+
+    fn describe(&self, event: &AlertEvent) -> String {
+        self.template.render(event)
+    }
+}
+
+fn send_message(client: &reqwest::blocking::Client, bot_token: &str, chat_id: &str, text: &str) -> Result<(), StockError> {
+    let res = client.post(format!("{}/bot{}/sendMessage", API_URL, bot_token))
+        .json(&SendMessage { chat_id, text })
+        .send()
+        .map_err(|err| {
+            StockError::Network(err.without_url().into())
+        })?;
+
+    if !res.status().is_success() {
+        return Err(StockError::status(res.status()));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,947 @@
+use std::{collections::{BTreeMap, HashMap}, fmt, fs::OpenOptions, io::{self, Write}, path::{Path, PathBuf}, str::FromStr};
+use chrono::{DateTime, Days, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::{backtest::BacktestReport, candle::{Candle, CandleInterval}, notify, portfolio::PortfolioSummary, scraping::HistoryProvider, stats::StatsReport, stock::PriceRange, template::Template, Fundamentals, NewsItem, QuoteDetails, Status, Stock, StockError, Valuation};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(StockError::invalid("output format", value.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Csv => write!(f, "csv"),
+        }
+    }
+}
+
+/// A text output line with `{placeholder}`s filled in from the quote, e.g.
+/// `{symbol} {price:>10.2} {change_pct:+.2}%`; see [`Stock`]'s
+/// [`crate::template::Fields`] for the placeholders.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct QuoteTemplate(Template);
+
+impl FromStr for QuoteTemplate {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let template: Template = value.parse()?;
+        template.check_for::<Stock>()?;
+
+        Ok(Self(template))
+    }
+}
+
+impl TryFrom<String> for QuoteTemplate {
+    type Error = StockError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl QuoteTemplate {
+    pub fn render(&self, stock: &Stock) -> String {
+        self.0.render(stock)
+    }
+}
+
+/// ANSI colours for console lines: green for a rise, red for a fall and grey
+/// otherwise, in bold when the change is beyond `highlight_pct` either way.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Colors {
+    pub highlight_pct: f64,
+}
+
+impl Colors {
+    pub fn paint(&self, stock: &Stock, line: &str) -> String {
+        let color = match stock.status {
+            Status::Up => "32",
+            Status::Down => "31",
+            Status::Same => "90",
+        };
+        let bold = match stock.change_pct {
+            Some(change_pct) if change_pct.abs() > self.highlight_pct => "1;",
+            _ => "",
+        };
+
+        format!("\x1b[{}{}m{}\x1b[0m", bold, color, line)
+    }
+}
+
+/// How an [`Output`]'s sinks write quotes.
+#[derive(Debug, Clone, Default)]
+pub struct SinkOptions {
+    /// Include the key statistics of each quote.
+    pub extended: bool,
+    /// Shapes text lines; ignored by the other formats.
+    pub template: Option<QuoteTemplate>,
+    /// Colours text lines logged to the console, not those written to a
+    /// file.
+    pub colors: Option<Colors>,
+}
+
+/// A single stock update as written by the machine-readable outputs.
+#[derive(Debug, Serialize)]
+pub struct QuoteRecord<'a> {
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub stock: &'a Stock,
+    /// Set with `--extended`.
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub details: Option<&'a QuoteDetails>,
+}
+
+impl<'a> QuoteRecord<'a> {
+    /// Stamped with when `stock` was fetched.
+    pub fn new(stock: &'a Stock) -> Self {
+        Self { timestamp: stock.fetched_at, stock, details: None }
+    }
+}
+
+/// The last quote of every symbol as `snapshot` and `GET /snapshot` write
+/// it, with the key statistics.
+#[derive(Debug, Serialize)]
+pub struct SnapshotRecord<'a> {
+    pub taken_at: DateTime<Utc>,
+    /// Keyed by code, `null` for codes without a quote yet.
+    pub quotes: BTreeMap<&'a str, Option<QuoteRecord<'a>>>,
+}
+
+impl<'a> SnapshotRecord<'a> {
+    /// Takes the quotes of `codes` out of a [`crate::quotes::QuoteStore::snapshot`].
+    pub fn new(codes: &'a [String], quotes: &'a HashMap<String, Stock>) -> Self {
+        let quotes = codes.iter()
+            .map(|code| {
+                let record = quotes.get(code).map(|stock| QuoteRecord { details: Some(&stock.details), ..QuoteRecord::new(stock) });
+
+                (code.as_str(), record)
+            })
+            .collect();
+
+        Self { taken_at: Utc::now(), quotes }
+    }
+}
+
+/// A portfolio valuation as written by `--output json`, one per poll.
+#[derive(Debug, Serialize)]
+pub struct PortfolioRecord<'a> {
+    pub timestamp: DateTime<Utc>,
+    pub portfolio: &'a PortfolioSummary,
+}
+
+/// A headline as written by `--output json`, on a line of its own.
+#[derive(Debug, Serialize)]
+pub struct NewsRecord<'a> {
+    pub timestamp: DateTime<Utc>,
+    pub news: &'a NewsItem,
+}
+
+/// Somewhere emitted quotes go. An [`Output`] fans every quote out to all of
+/// its sinks; closures taking a [`QuoteRecord`] are sinks too.
+pub trait Sink: Send {
+    fn emit(&mut self, record: &QuoteRecord);
+
+    /// Writes the portfolio valuation at the end of a poll, returning whether
+    /// it did. It is logged when no sink writes it.
+    fn emit_portfolio(&mut self, _summary: &PortfolioSummary) -> bool {
+        false
+    }
+
+    /// Writes a headline that came with a quote, returning whether it did.
+    /// It is logged when no sink writes it.
+    fn emit_news(&mut self, _item: &NewsItem) -> bool {
+        false
+    }
+
+    /// Records a candle of `symbol` (e.g. `AAPL`) that the polls closed, for
+    /// sinks keeping candles.
+    fn emit_candle(&mut self, _symbol: &str, _interval: CandleInterval, _candle: &Candle) {}
+
+    /// Whether this sink prints to stdout, see [`Output::mute`].
+    fn is_stdout(&self) -> bool {
+        false
+    }
+
+    /// Whether this sink records every quote rather than showing the latest
+    /// ones. Stale quotes are only passed on again to sinks that don't, so
+    /// the history doesn't get them twice.
+    fn keeps_history(&self) -> bool {
+        false
+    }
+
+    /// The last quote of `symbol` (e.g. `AAPL`) this sink recorded and when,
+    /// for sinks keeping a history that [`Output::backfill`] can fill in.
+    fn last_recorded(&self, _symbol: &str) -> Option<(DateTime<Utc>, Stock)> {
+        None
+    }
+
+    /// Flushes anything still buffered; called once before exiting.
+    fn flush(&mut self) {}
+}
+
+impl<F: FnMut(&QuoteRecord) + Send> Sink for F {
+    fn emit(&mut self, record: &QuoteRecord) {
+        self(record)
+    }
+}
+
+/// Stands in for a sink during dry runs, logging each quote it would have
+/// been given.
+#[derive(Debug, Clone)]
+pub struct DryRunSink {
+    name: String,
+}
+
+impl DryRunSink {
+    /// `name` says where the quotes would have gone, e.g. `PostgreSQL`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+impl Sink for DryRunSink {
+    fn emit(&mut self, record: &QuoteRecord) {
+        info!(symbol = %record.stock.symbol, "Dry run, not written to {}: {} {} {}", self.name, record.stock.symbol, record.stock.price, record.stock.currency);
+    }
+}
+
+const CSV_HEADER: [&str; 9] = ["timestamp", "symbol", "company", "price", "currency", "status", "previous_price", "change_abs", "change_pct"];
+/// Appended to [`CSV_HEADER`] with `--extended`.
+const CSV_EXTENDED_HEADER: [&str; 8] = ["previous_close", "day_low", "day_high", "year_low", "year_high", "market_cap", "volume", "lot_size"];
+
+type Writer = Box<dyn Write + Send>;
+
+/// Human readable lines: log lines on stdout, plain lines in a file, shaped
+/// by a template when given.
+struct TextSink {
+    file: Option<Writer>,
+    template: Option<QuoteTemplate>,
+    colors: Option<Colors>,
+}
+
+/// The text line of `record`, from `template` when given.
+fn text_line(record: &QuoteRecord, template: Option<&QuoteTemplate>) -> String {
+    let stock = record.stock;
+
+    if let Some(template) = template {
+        return template.render(stock);
+    }
+
+    let mut line = stock.to_string();
+
+    // A sparkline only says something once there are two prices.
+    if stock.recent_prices.len() > 1 {
+        line = format!("{} {}", line, notify::sparkline(&stock.recent_prices));
+    }
+
+    match record.details {
+        Some(details) if !details.is_empty() => format!("{} | {}", line, details),
+        _ => line,
+    }
+}
+
+impl Sink for TextSink {
+    fn emit(&mut self, record: &QuoteRecord) {
+        let stock = record.stock;
+        let line = text_line(record, self.template.as_ref());
+
+        let Some(file) = self.file.as_mut() else {
+            let line = match self.colors {
+                Some(colors) => colors.paint(stock, &line),
+                None => line,
+            };
+
+            return info!(symbol = %stock.symbol, price = %stock.price, currency = %stock.currency, status = %stock.status, "{}", line);
+        };
+
+        if let Err(err) = writeln!(file, "{} {}", record.timestamp.to_rfc3339(), line) {
+            error!(symbol = %stock.symbol, "Failed to write {}: {}", stock.symbol, err);
+        }
+    }
+
+    fn is_stdout(&self) -> bool {
+        self.file.is_none()
+    }
+
+    fn flush(&mut self) {
+        if let Some(Err(err)) = self.file.as_mut().map(Write::flush) {
+            error!("Failed to flush text output: {}", err);
+        }
+    }
+}
+
+/// One JSON object per line, quotes and portfolio valuations alike.
+struct JsonSink {
+    writer: Writer,
+    stdout: bool,
+}
+
+impl JsonSink {
+    fn write_line(&mut self, value: &impl Serialize) -> Result<(), String> {
+        let line = serde_json::to_string(value).map_err(|err| err.to_string())?;
+
+        writeln!(self.writer, "{}", line)
+            .and_then(|_| self.writer.flush())
+            .map_err(|err| err.to_string())
+    }
+}
+
+impl Sink for JsonSink {
+    fn emit(&mut self, record: &QuoteRecord) {
+        if let Err(err) = self.write_line(record) {
+            error!(symbol = %record.stock.symbol, "Failed to write {}: {}", record.stock.symbol, err);
+        }
+    }
+
+    fn emit_portfolio(&mut self, summary: &PortfolioSummary) -> bool {
+        if let Err(err) = self.write_line(&PortfolioRecord { timestamp: Utc::now(), portfolio: summary }) {
+            error!("Failed to write the portfolio: {}", err);
+        }
+
+        true
+    }
+
+    fn emit_news(&mut self, item: &NewsItem) -> bool {
+        if let Err(err) = self.write_line(&NewsRecord { timestamp: Utc::now(), news: item }) {
+            error!(symbol = %item.symbol, "Failed to write news of {}: {}", item.symbol, err);
+        }
+
+        true
+    }
+
+    fn is_stdout(&self) -> bool {
+        self.stdout
+    }
+
+    fn flush(&mut self) {
+        if let Err(err) = self.writer.flush() {
+            error!("Failed to flush JSON output: {}", err);
+        }
+    }
+}
+
+struct CsvSink {
+    writer: csv::Writer<Writer>,
+    stdout: bool,
+    /// The file written to, read back for backfilling.
+    path: Option<PathBuf>,
+}
+
+impl CsvSink {
+    fn write(&mut self, record: &QuoteRecord) -> Result<(), csv::Error> {
+        let stock = record.stock;
+        let mut row = vec![
+            record.timestamp.to_rfc3339(),
+            stock.symbol.clone(),
+            stock.company_name.clone(),
+            stock.price.to_string(),
+            stock.currency.clone(),
+            stock.status.to_string(),
+            optional(stock.previous_price),
+            optional(stock.change_abs),
+            optional(stock.change_pct),
+        ];
+
+        if let Some(details) = record.details {
+            let low = |range: Option<PriceRange>| optional(range.map(|range| range.low));
+            let high = |range: Option<PriceRange>| optional(range.map(|range| range.high));
+
+            row.extend([
+                optional(details.previous_close),
+                low(details.day_range),
+                high(details.day_range),
+                low(details.year_range),
+                high(details.year_range),
+                optional(details.market_cap),
+                details.volume.map(|volume| volume.to_string()).unwrap_or_default(),
+                details.lot_size.map(|lot_size| lot_size.to_string()).unwrap_or_default(),
+            ]);
+        }
+
+        self.writer.write_record(row)?;
+
+        self.writer.flush()?;
+
+        Ok(())
+    }
+}
+
+impl Sink for CsvSink {
+    fn emit(&mut self, record: &QuoteRecord) {
+        if let Err(err) = self.write(record) {
+            error!(symbol = %record.stock.symbol, "Failed to write CSV row for {}: {}", record.stock.symbol, err);
+        }
+    }
+
+    fn is_stdout(&self) -> bool {
+        self.stdout
+    }
+
+    fn keeps_history(&self) -> bool {
+        true
+    }
+
+    /// Reads the file back, so only for files.
+    fn last_recorded(&self, symbol: &str) -> Option<(DateTime<Utc>, Stock)> {
+        // Rows with and without the --extended columns may be mixed.
+        let mut reader = csv::ReaderBuilder::new().flexible(true).from_path(self.path.as_ref()?).ok()?;
+
+        reader.records()
+            .map_while(Result::ok)
+            .filter(|row| row.get(1) == Some(symbol))
+            .last()
+            .and_then(|row| {
+                let timestamp = DateTime::parse_from_rfc3339(row.get(0)?).ok()?.with_timezone(&Utc);
+                let stock = Stock::new(symbol.to_string(), row.get(2)?.to_string(), row.get(3)?.parse().ok()?)
+                    .with_currency(row.get(4)?.to_string())
+                    .with_fetched_at(timestamp);
+
+                Some((timestamp, stock))
+            })
+    }
+
+    fn flush(&mut self) {
+        if let Err(err) = self.writer.flush() {
+            error!("Failed to flush CSV output: {}", err);
+        }
+    }
+}
+
+/// Opens a sink writing `format` to `file` when given, appending to it if it
+/// already exists, or to stdout. `options.extended` adds the key statistics
+/// columns to a CSV header.
+pub fn open_sink(format: OutputFormat, file: Option<&Path>, options: &SinkOptions) -> Result<Box<dyn Sink>, StockError> {
+    let (writer, is_new): (Option<Writer>, bool) = match file {
+        Some(path) => {
+            let is_new = std::fs::metadata(path).map(|meta| meta.len() == 0).unwrap_or(true);
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|err| {
+                    StockError::io(path, err)
+                })?;
+
+            (Some(Box::new(file)), is_new)
+        }
+        None => (None, true),
+    };
+    let stdout = writer.is_none();
+    let writer = || writer.unwrap_or_else(|| Box::new(io::stdout()));
+
+    match format {
+        OutputFormat::Text => Ok(Box::new(TextSink { file: (!stdout).then(writer), template: options.template.clone(), colors: options.colors })),
+        OutputFormat::Json => Ok(Box::new(JsonSink { writer: writer(), stdout })),
+        OutputFormat::Csv => {
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(writer());
+
+            if is_new {
+                let header = match options.extended {
+                    true => [CSV_HEADER.as_slice(), CSV_EXTENDED_HEADER.as_slice()].concat(),
+                    false => CSV_HEADER.to_vec(),
+                };
+
+                writer.write_record(header)
+                    .and_then(|_| writer.flush().map_err(csv::Error::from))
+                    .map_err(|err| {
+                        StockError::Output(err.into())
+                    })?;
+            }
+
+            Ok(Box::new(CsvSink { writer, stdout, path: file.map(Path::to_path_buf) }))
+        }
+    }
+}
+
+/// Fans every emitted quote out to its sinks: the console or files, the
+/// databases and the publishers.
+#[derive(Default)]
+pub struct Output {
+    sinks: Vec<Box<dyn Sink>>,
+    /// Include the key statistics of each quote.
+    extended: bool,
+}
+
+impl Output {
+    /// Prints `format` to stdout.
+    pub fn new(format: OutputFormat) -> Self {
+        Self::open(format, None, &SinkOptions::default()).unwrap_or_default()
+    }
+
+    /// Stops printing to stdout, e.g. while the TUI owns the terminal.
+    pub fn mute(mut self) -> Self {
+        self.sinks.retain(|sink| !sink.is_stdout());
+        self
+    }
+
+    /// Additionally sends every emitted quote to `sink`.
+    pub fn with_sink(mut self, sink: impl Sink + 'static) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+
+    /// Additionally calls `listener` with every emitted quote.
+    pub fn on_emit(self, listener: impl FnMut(&QuoteRecord) + Send + 'static) -> Self {
+        self.with_sink(listener)
+    }
+
+    pub fn is_extended(&self) -> bool {
+        self.extended
+    }
+
+    /// Creates an output writing `format` to `file` when given, see
+    /// [`open_sink`]. `options.extended` adds the key statistics of each
+    /// quote.
+    pub fn open(format: OutputFormat, file: Option<&Path>, options: &SinkOptions) -> Result<Self, StockError> {
+        Ok(Self { sinks: vec![open_sink(format, file, options)?], extended: options.extended })
+    }
+
+    /// Like [`Output::with_sink`], for sinks opened with [`open_sink`].
+    pub fn with_boxed_sink(mut self, sink: Box<dyn Sink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Stale quotes skip the sinks keeping a history, see [`Sink::keeps_history`].
+    pub fn emit(&mut self, stock: &Stock) {
+        let mut record = QuoteRecord::new(stock);
+
+        if self.extended {
+            record.details = Some(&stock.details);
+        }
+
+        for sink in self.sinks.iter_mut().filter(|sink| !(stock.stale && sink.keeps_history())) {
+            sink.emit(&record);
+        }
+    }
+
+    /// When `symbol` (e.g. `AAPL`) was last recorded by any sink keeping a
+    /// history.
+    pub fn last_recorded(&self, symbol: &str) -> Option<DateTime<Utc>> {
+        self.sinks.iter()
+            .filter_map(|sink| sink.last_recorded(symbol).map(|(timestamp, _)| timestamp))
+            .max()
+    }
+
+    /// Fills the gap in each history-keeping sink since it last recorded a
+    /// code with the daily closes `provider` has for the days in between,
+    /// up to yesterday. Codes it has no bars for are skipped with a warning.
+    pub fn backfill(&mut self, provider: &dyn HistoryProvider, codes: &[String]) {
+        let yesterday = Utc::now().date_naive() - Days::new(1);
+
+        for sink in self.sinks.iter_mut() {
+            for share_code in codes {
+                let symbol = share_code.to_uppercase().split(':').next().unwrap_or_default().to_string();
+
+                let Some((last_recorded, mut last)) = sink.last_recorded(&symbol) else {
+                    continue;
+                };
+
+                let from = last_recorded.date_naive() + Days::new(1);
+
+                if from > yesterday {
+                    continue;
+                }
+
+                let candles = match provider.fetch_history(share_code, from, yesterday) {
+                    Ok(candles) => candles,
+                    Err(err) => {
+                        warn!(symbol = %share_code, "Not backfilling {}: {}", share_code, err);
+                        continue;
+                    }
+                };
+
+                for candle in candles.iter() {
+                    let mut stock = Stock::new(last.symbol.clone(), last.company_name.clone(), candle.close)
+                        .with_currency(last.currency.clone())
+                        .with_fetched_at(candle.timestamp);
+                    stock.details = QuoteDetails {
+                        day_range: Some(PriceRange { low: candle.low, high: candle.high }),
+                        volume: candle.volume,
+                        ..QuoteDetails::default()
+                    };
+                    Valuation::between(last.price, stock.price).apply_to(&mut stock);
+
+                    sink.emit(&QuoteRecord { details: self.extended.then_some(&stock.details), ..QuoteRecord::new(&stock) });
+                    last = stock;
+                }
+
+                info!(symbol = %share_code, "Backfilled {} days of {} since {}", candles.len(), share_code, last_recorded.date_naive());
+            }
+        }
+    }
+
+    /// Writes the portfolio valuation at the end of a poll: as a JSON line
+    /// to JSON sinks, or to the log when there are none.
+    pub fn emit_portfolio(&mut self, summary: &PortfolioSummary) {
+        let mut written = false;
+
+        for sink in self.sinks.iter_mut() {
+            written |= sink.emit_portfolio(summary);
+        }
+
+        if written {
+            return;
+        }
+
+        for position in summary.positions.iter() {
+            info!(symbol = %position.symbol, "{}", position);
+        }
+
+        for total in summary.totals.iter() {
+            info!(currency = %total.currency, "{}", total);
+        }
+
+        if !summary.unpriced.is_empty() {
+            info!("No quote yet for {}", summary.unpriced.join(", "));
+        }
+    }
+
+    /// Writes a headline that came with a quote: as a JSON line to JSON
+    /// sinks, or to the log when there are none.
+    pub fn emit_news(&mut self, item: &NewsItem) {
+        let mut written = false;
+
+        for sink in self.sinks.iter_mut() {
+            written |= sink.emit_news(item);
+        }
+
+        if !written {
+            info!(symbol = %item.symbol, "{}", item);
+        }
+    }
+
+    /// Records the candles of `symbol` (e.g. `AAPL`) a quote closed.
+    pub fn emit_candles(&mut self, symbol: &str, closed: &[(CandleInterval, Candle)]) {
+        for sink in self.sinks.iter_mut() {
+            for (interval, candle) in closed {
+                sink.emit_candle(symbol, *interval, candle);
+            }
+        }
+    }
+
+    /// Flushes anything still buffered; called once before exiting.
+    pub fn flush(&mut self) {
+        for sink in self.sinks.iter_mut() {
+            sink.flush();
+        }
+
+        if let Err(err) = io::stdout().flush() {
+            error!("Failed to flush stdout: {}", err);
+        }
+    }
+}
+
+fn optional(value: Option<impl fmt::Display>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+/// Writes `stocks` once to `file` (appending) or stdout, see [`open_sink`].
+/// Text goes to stdout as plain lines rather than log lines, for scripts.
+pub fn write_quotes(format: OutputFormat, file: Option<&Path>, options: &SinkOptions, stocks: &[Stock]) -> Result<(), StockError> {
+    if format == OutputFormat::Text && file.is_none() {
+        let mut stdout = io::stdout().lock();
+
+        for stock in stocks {
+            let record = QuoteRecord { details: options.extended.then_some(&stock.details), ..QuoteRecord::new(stock) };
+
+            writeln!(stdout, "{}", text_line(&record, options.template.as_ref()))
+                .map_err(|err| {
+                    StockError::Output(err.into())
+                })?;
+        }
+
+        return Ok(());
+    }
+
+    let mut output = Output::open(format, file, options)?;
+
+    for stock in stocks {
+        output.emit(stock);
+    }
+
+    output.flush();
+
+    Ok(())
+}
+
+/// Writes `snapshot` as JSON to stdout, or to `file` by way of a temporary
+/// file next to it, so readers never see half of it.
+pub fn write_snapshot(file: Option<&Path>, snapshot: &SnapshotRecord) -> Result<(), StockError> {
+    let json = serde_json::to_string_pretty(snapshot).map_err(|err| StockError::Output(err.into()))?;
+
+    let Some(path) = file else {
+        return writeln!(io::stdout(), "{}", json).map_err(|err| StockError::Output(err.into()));
+    };
+
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+
+    std::fs::write(&partial, json + "\n")
+        .and_then(|_| std::fs::rename(&partial, path))
+        .map_err(|err| {
+            let _ = std::fs::remove_file(&partial);
+            StockError::io(path, err)
+        })
+}
+
+#[derive(Debug, Serialize)]
+struct CandleRecord<'a> {
+    symbol: &'a str,
+    #[serde(flatten)]
+    candle: &'a Candle,
+}
+
+/// Writes OHLC bars to `file` (replacing it) or stdout in the given format.
+/// Text lines show the day of daily bars, and the start time in UTC of
+/// candles of an `interval`.
+pub fn write_candles(format: OutputFormat, file: Option<&Path>, symbol: &str, interval: Option<CandleInterval>, candles: &[Candle]) -> Result<(), StockError> {
+    let mut sink: Box<dyn Write> = match file {
+        Some(path) => Box::new(std::fs::File::create(path)
+            .map_err(|err| {
+                StockError::io(path, err)
+            })?),
+        None => Box::new(io::stdout()),
+    };
+
+    match format {
+        OutputFormat::Text => {
+            for candle in candles {
+                let start = match interval {
+                    Some(_) => candle.timestamp.format("%Y-%m-%d %H:%M").to_string(),
+                    None => candle.timestamp.date_naive().to_string(),
+                };
+
+                writeln!(sink, "{} {} O {} H {} L {} C {} V {}",
+                    start, symbol, candle.open, candle.high, candle.low, candle.close,
+                    candle.volume.map(|volume| volume.to_string()).unwrap_or("-".to_string()))
+                    .map_err(|err| StockError::Output(err.into()))?;
+            }
+        }
+        OutputFormat::Json => {
+            for candle in candles {
+                let line = serde_json::to_string(&CandleRecord { symbol, candle }).map_err(|err| StockError::Output(err.into()))?;
+                writeln!(sink, "{}", line).map_err(|err| StockError::Output(err.into()))?;
+            }
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(sink);
+
+            writer.write_record(["date", "symbol", "open", "high", "low", "close", "volume"]).map_err(|err| StockError::Output(err.into()))?;
+
+            for candle in candles {
+                writer.write_record([
+                    candle.timestamp.date_naive().to_string(),
+                    symbol.to_string(),
+                    candle.open.to_string(),
+                    candle.high.to_string(),
+                    candle.low.to_string(),
+                    candle.close.to_string(),
+                    candle.volume.map(|volume| volume.to_string()).unwrap_or_default(),
+                ])
+                .map_err(|err| StockError::Output(err.into()))?;
+            }
+
+            return writer.flush().map_err(|err| StockError::Output(err.into()));
+        }
+    }
+
+    sink.flush().map_err(|err| StockError::Output(err.into()))
+}
+
+#[derive(Debug, Serialize)]
+struct FundamentalsRecord<'a> {
+    symbol: &'a str,
+    #[serde(flatten)]
+    fundamentals: &'a Fundamentals,
+}
+
+/// Writes the fundamentals of `symbol` to stdout in the given format.
+pub fn write_fundamentals(format: OutputFormat, symbol: &str, fundamentals: &Fundamentals) -> Result<(), StockError> {
+    let mut sink = io::stdout();
+
+    match format {
+        OutputFormat::Text if fundamentals.is_empty() => writeln!(sink, "{} has no fundamentals", symbol),
+        OutputFormat::Text => writeln!(sink, "{} {}", symbol, fundamentals),
+        OutputFormat::Json => {
+            let line = serde_json::to_string(&FundamentalsRecord { symbol, fundamentals }).map_err(|err| StockError::Output(err.into()))?;
+            writeln!(sink, "{}", line)
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(sink);
+
+            writer.write_record(["symbol", "pe_ratio", "eps", "dividend_yield", "market_cap"])
+                .and_then(|_| writer.write_record([
+                    symbol.to_string(),
+                    optional(fundamentals.pe_ratio),
+                    optional(fundamentals.eps),
+                    optional(fundamentals.dividend_yield),
+                    optional(fundamentals.market_cap),
+                ]))
+                .map_err(|err| StockError::Output(err.into()))?;
+
+            return writer.flush().map_err(|err| StockError::Output(err.into()));
+        }
+    }
+    .map_err(|err| StockError::Output(err.into()))
+}
+
+/// Writes a backtest to stdout: the trades and the summary as text, the
+/// whole report as a JSON line, or the trades alone as CSV.
+pub fn write_backtest(format: OutputFormat, report: &BacktestReport) -> Result<(), StockError> {
+    let mut sink = io::stdout();
+
+    match format {
+        OutputFormat::Text => {
+            for trade in report.session.trades.iter() {
+                writeln!(sink, "{} {}", trade.timestamp.format("%Y-%m-%d %H:%M"), trade).map_err(|err| StockError::Output(err.into()))?;
+            }
+            for position in report.session.positions.iter() {
+                writeln!(sink, "Open {}", position).map_err(|err| StockError::Output(err.into()))?;
+            }
+
+            writeln!(sink, "Backtest of {}", report)
+        }
+        OutputFormat::Json => {
+            let line = serde_json::to_string(report).map_err(|err| StockError::Output(err.into()))?;
+            writeln!(sink, "{}", line)
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(sink);
+
+            writer.write_record(["timestamp", "symbol", "side", "quantity", "price", "pnl"]).map_err(|err| StockError::Output(err.into()))?;
+
+            for trade in report.session.trades.iter() {
+                writer.write_record([
+                    trade.timestamp.to_rfc3339(),
+                    trade.symbol.clone(),
+                    trade.side.as_str().to_string(),
+                    trade.quantity.to_string(),
+                    trade.price.to_string(),
+                    optional(trade.pnl),
+                ])
+                .map_err(|err| StockError::Output(err.into()))?;
+            }
+
+            return writer.flush().map_err(|err| StockError::Output(err.into()));
+        }
+    }
+    .map_err(|err| StockError::Output(err.into()))
+}
+
+/// Writes fetch stats to stdout: a line per symbol and the total as text,
+/// the whole report as a JSON line, or the symbols alone as CSV.
+pub fn write_stats(format: OutputFormat, report: &StatsReport) -> Result<(), StockError> {
+    let mut sink = io::stdout();
+
+    match format {
+        OutputFormat::Text => {
+            for (code, stats) in report.symbols.iter() {
+                writeln!(sink, "{}: {}", code, stats).map_err(|err| StockError::Output(err.into()))?;
+            }
+
+            writeln!(sink, "Total: {}", report.total)
+        }
+        OutputFormat::Json => {
+            let line = serde_json::to_string(report).map_err(|err| StockError::Output(err.into()))?;
+            writeln!(sink, "{}", line)
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(sink);
+
+            writer.write_record(["symbol", "fetches", "failures", "parse_failures", "http_errors", "success_rate", "p50_ms", "p90_ms", "p99_ms"]).map_err(|err| StockError::Output(err.into()))?;
+
+            for (code, stats) in report.symbols.iter() {
+                let http_errors = stats.http_errors.iter()
+                    .map(|(status, count)| format!("{}:{}", status, count))
+                    .collect::<Vec<String>>();
+
+                writer.write_record([
+                    code.clone(),
+                    stats.fetches.to_string(),
+                    stats.failures.to_string(),
+                    stats.parse_failures.to_string(),
+                    http_errors.join(" "),
+                    optional(stats.success_rate),
+                    optional(stats.p50_ms),
+                    optional(stats.p90_ms),
+                    optional(stats.p99_ms),
+                ])
+                .map_err(|err| StockError::Output(err.into()))?;
+            }
+
+            return writer.flush().map_err(|err| StockError::Output(err.into()));
+        }
+    }
+    .map_err(|err| StockError::Output(err.into()))
+}
+
+/// Writes headlines to stdout in the given format, in the order given.
+pub fn write_news(format: OutputFormat, symbol: &str, items: &[NewsItem]) -> Result<(), StockError> {
+    let mut sink = io::stdout();
+
+    if items.is_empty() && format == OutputFormat::Text {
+        return writeln!(sink, "No news about {}", symbol).map_err(|err| StockError::Output(err.into()));
+    }
+
+    match format {
+        OutputFormat::Text => {
+            for item in items {
+                match &item.published {
+                    Some(published) => writeln!(sink, "{} ({}, {})\n  {}", item.headline, item.source, published, item.url),
+                    None => writeln!(sink, "{} ({})\n  {}", item.headline, item.source, item.url),
+                }
+                .map_err(|err| StockError::Output(err.into()))?;
+            }
+        }
+        OutputFormat::Json => {
+            for item in items {
+                let line = serde_json::to_string(item).map_err(|err| StockError::Output(err.into()))?;
+                writeln!(sink, "{}", line).map_err(|err| StockError::Output(err.into()))?;
+            }
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(sink);
+
+            writer.write_record(["symbol", "headline", "source", "url", "published"])
+                .map_err(|err| StockError::Output(err.into()))?;
+
+            for item in items {
+                writer.write_record([&item.symbol, &item.headline, &item.source, &item.url, item.published.as_deref().unwrap_or_default()])
+                    .map_err(|err| StockError::Output(err.into()))?;
+            }
+
+            return writer.flush().map_err(|err| StockError::Output(err.into()));
+        }
+    }
+
+    sink.flush().map_err(|err| StockError::Output(err.into()))
+}
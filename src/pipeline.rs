@@ -0,0 +1,383 @@
+use std::{collections::HashMap, mem, thread::{self, JoinHandle}, time::Duration};
+use chrono::Utc;
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
+
+use crate::{alert::{self, AlertEvent, PriceTarget}, candle::{Candle, CandleInterval, CandleSeries}, circuit::CircuitBreaker, health::Health, indicators::PriceHistory, notify::Dispatcher, output::Output, portfolio::{Portfolio, PortfolioSummary}, quotes::{QuoteDiff, QuoteStore}, rules::RuleSet, script::Script, simulate::{PaperTrader, SessionReport}, stock::Valuation, watch::WatchOptions, NewsItem, Stock, StockError};
+
+/// How far each stage may run ahead of the next before it has to wait.
+const QUEUE_CAPACITY: usize = 100;
+/// Recent prices attached to alerts for notifiers that chart them, and to
+/// quotes unless told otherwise.
+pub const DEFAULT_SPARKLINE_POINTS: usize = 20;
+
+
+/// What the fetch stage hands to the enrich stage.
+pub enum Fetched {
+    /// A quote, fetched and parsed, or why it couldn't be.
+    Quote(String, Box<Result<Stock, StockError>>),
+    /// How old the last quote of each code may get before it is stale,
+    /// after a poll.
+    StaleAfter(Vec<(String, Duration)>),
+    /// Every symbol due in a poll has been fetched.
+    CycleDone,
+}
+
+/// What the enrich stage hands to the sink stage.
+enum Enriched {
+    Quote(Box<Stock>),
+    /// The last quotes of symbols that just went stale, marked as such.
+    Stale(Vec<Stock>),
+    /// Headlines that came with a quote, passed on ahead of it.
+    News(Vec<NewsItem>),
+    /// Candles of a symbol (e.g. `AAPL`) that a quote closed.
+    Candles(String, Vec<(CandleInterval, Candle)>),
+    Portfolio(PortfolioSummary),
+}
+
+/// The stages a quote passes through while watching:
+///
+/// 1. fetch: the polling loops in [`crate::watch`] download and parse quotes,
+///    several at a time, and send them in as [`Fetched`]. Parsing stays with
+///    the download: batched responses are split per symbol and unreadable
+///    pages recorded while the response is at hand, and it costs little next
+///    to the request;
+/// 2. enrich: a thread of its own compares each quote with the last one of
+///    its symbol, adds indicators and fires alerts;
+/// 3. sink: another thread writes the quotes to the [`Output`].
+///
+/// The stages are connected by bounded channels, so a slow sink holds up
+/// enriching and then fetching instead of queueing quotes without limit.
+pub struct Pipeline {
+    sender: mpsc::Sender<Fetched>,
+    quotes: QuoteStore,
+    enricher: JoinHandle<Option<SessionReport>>,
+    sink: JoinHandle<Output>,
+}
+
+impl Pipeline {
+    pub fn start(options: &WatchOptions, output: Output, dispatcher: Dispatcher) -> Self {
+        let (sender, mut fetched) = mpsc::channel::<Fetched>(QUEUE_CAPACITY);
+        let (enriched_sender, mut enriched) = mpsc::channel::<Enriched>(QUEUE_CAPACITY);
+        let quotes = options.quotes.clone();
+        let mut enricher = Enricher::new(options, dispatcher, quotes.clone());
+
+        let enricher = thread::spawn(move || {
+            while let Some(fetched) = fetched.blocking_recv() {
+                for enriched in enricher.enrich(fetched) {
+                    if enriched_sender.blocking_send(enriched).is_err() {
+                        error!("Quote sink stopped, no longer enriching quotes");
+                        return enricher.report();
+                    }
+                }
+            }
+
+            enricher.report()
+        });
+
+        let sink = thread::spawn(move || {
+            let mut output = output;
+
+            while let Some(enriched) = enriched.blocking_recv() {
+                match enriched {
+                    Enriched::Quote(stock) => output.emit(&stock),
+                    Enriched::Stale(stocks) => stocks.iter().for_each(|stock| output.emit(stock)),
+                    Enriched::News(items) => items.iter().for_each(|item| output.emit_news(item)),
+                    Enriched::Candles(symbol, closed) => output.emit_candles(&symbol, &closed),
+                    Enriched::Portfolio(summary) => output.emit_portfolio(&summary),
+                }
+            }
+
+            output
+        });
+
+        Self { sender, quotes, enricher, sink }
+    }
+
+    /// Where the fetch stage sends its quotes.
+    pub fn sender(&self) -> mpsc::Sender<Fetched> {
+        self.sender.clone()
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.enricher.is_finished() && !self.sink.is_finished()
+    }
+
+    /// Waits for the quotes sent so far to pass through, flushes the output
+    /// and prints the last known price of every symbol, and the session
+    /// report when paper trading.
+    pub fn finish(self, codes: &[String]) {
+        drop(self.sender);
+
+        let report = match self.enricher.join() {
+            Ok(report) => report,
+            Err(_) => {
+                error!("Quote enricher panicked");
+                None
+            }
+        };
+
+        match self.sink.join() {
+            Ok(mut output) => output.flush(),
+            Err(_) => error!("Quote sink panicked"),
+        }
+
+        info!("Last known prices:");
+
+        for share_code in codes {
+            match self.quotes.get_last(share_code) {
+                Some(stock) => info!(symbol = share_code, "  {}", stock),
+                None => info!(symbol = share_code, "  {} - no data", share_code),
+            }
+        }
+
+        if let Some(report) = report {
+            info!("Simulated session: {}", report);
+
+            for position in report.positions.iter() {
+                info!(symbol = %position.symbol, "  {}", position);
+            }
+        }
+    }
+}
+
+/// Remembers the last quote per symbol, completing every new one with its
+/// status and indicators, and fires the alerts it triggers.
+struct Enricher {
+    quotes: QuoteStore,
+    history: PriceHistory,
+    candles: CandleSeries,
+    portfolio: Portfolio,
+    dispatcher: Dispatcher,
+    alert_threshold: Option<f64>,
+    thresholds: HashMap<String, f64>,
+    targets: Vec<PriceTarget>,
+    rules: RuleSet,
+    simulation: Option<PaperTrader>,
+    script: Option<Script>,
+    emit_unchanged: bool,
+    sparkline_points: usize,
+    health: Health,
+    circuit: CircuitBreaker,
+}
+
+impl Enricher {
+    fn new(options: &WatchOptions, dispatcher: Dispatcher, quotes: QuoteStore) -> Self {
+        Self {
+            quotes,
+            history: PriceHistory::new(options.indicators).keep_at_least(options.sparkline_points.max(DEFAULT_SPARKLINE_POINTS)),
+            candles: CandleSeries::new(),
+            portfolio: Portfolio::new(options.holdings.clone()),
+            dispatcher,
+            alert_threshold: options.alert_threshold,
+            thresholds: options.thresholds.clone(),
+            targets: options.targets.clone(),
+            rules: RuleSet::new(options.rules.clone(), options.rule_cooloff),
+            simulation: options.simulation.clone().map(PaperTrader::new),
+            script: options.script.clone(),
+            emit_unchanged: options.emit_unchanged,
+            sparkline_points: options.sparkline_points,
+            health: options.health.clone(),
+            circuit: options.circuit.clone(),
+        }
+    }
+
+    fn enrich(&mut self, fetched: Fetched) -> Vec<Enriched> {
+        match fetched {
+            Fetched::Quote(share_code, result) => match *result {
+                Ok(mut new_stock) => {
+                    self.health.succeeded(&share_code);
+
+                    if self.circuit.succeeded(&share_code) {
+                        info!(symbol = %share_code, "{} is back, polling it again", share_code);
+                    }
+
+                    let news = mem::take(&mut new_stock.news);
+                    let news = self.news(&share_code, &new_stock, news).map(Enriched::News);
+                    news.into_iter().chain(self.update(share_code, new_stock)).collect()
+                }
+                Err(err) => {
+                    self.failed(&share_code, &err);
+                    Vec::new()
+                }
+            },
+            Fetched::StaleAfter(stale_after) => self.check_stale(&stale_after).map(Enriched::Stale).into_iter().collect(),
+            Fetched::CycleDone => self.end_cycle().map(Enriched::Portfolio).into_iter().collect(),
+        }
+    }
+
+    /// Notifies the headlines that came with `stock`, unless it is the
+    /// first quote of its symbol: those were out before the watch started.
+    fn news(&self, share_code: &str, stock: &Stock, items: Vec<NewsItem>) -> Option<Vec<NewsItem>> {
+        if items.is_empty() {
+            return None;
+        }
+
+        if self.quotes.get_last(share_code).is_some() {
+            let fired = items.iter().map(|item| AlertEvent::news(stock, item.clone())).collect();
+            self.fire(share_code, fired);
+        }
+
+        Some(items)
+    }
+
+    /// Returns the candles the quote closed and the completed quote. The
+    /// quote is left out when its price hasn't changed since the last one and
+    /// unchanged quotes aren't emitted; nothing is returned when it was
+    /// observed before the last one, e.g. a retried fetch finishing late.
+    fn update(&mut self, share_code: String, mut new_stock: Stock) -> Vec<Enriched> {
+        if !self.transform(&share_code, &mut new_stock) {
+            debug!(symbol = %share_code, "The script dropped {}", share_code);
+            return Vec::new();
+        }
+
+        // Indicators and sparkline go in with the quote, so a snapshot never
+        // pairs the new price with the previous ones.
+        let mut closed = Vec::new();
+        let diff = self.quotes.update_with(&share_code, &mut new_stock, |stock| {
+            closed = self.candles.record(&share_code, stock.price, stock.fetched_at);
+            stock.indicators = Some(self.history.record_poll(&share_code, stock.price.to_f64(), &closed));
+            stock.recent_prices = self.history.recent(&share_code, self.sparkline_points);
+        });
+
+        let Some(QuoteDiff { valuation, previous }) = diff else {
+            debug!(symbol = %share_code, "{} from {} is older than the last one, skipping", share_code, new_stock.fetched_at);
+            return Vec::new();
+        };
+
+        let mut enriched = Vec::new();
+
+        if !closed.is_empty() {
+            enriched.push(Enriched::Candles(new_stock.symbol.clone(), closed));
+        }
+
+        if matches!(valuation, Valuation::Same { .. }) && !self.emit_unchanged {
+            debug!(symbol = %share_code, "{} is unchanged at {}, skipping", share_code, new_stock.price);
+            return enriched;
+        }
+
+        if let Some(past) = previous.as_ref() {
+            let moved = self.thresholds.get(new_stock.symbol.as_str()).copied()
+                .or(self.alert_threshold)
+                .and_then(|threshold| alert::price_move(past, &new_stock, threshold));
+            let crossed = self.targets.iter()
+                .filter_map(|target| alert::target_cross(past, &new_stock, target));
+
+            let fired = moved.into_iter().chain(crossed).collect::<Vec<AlertEvent>>();
+            self.fire(&share_code, fired);
+        }
+
+        let fired = self.rules.evaluate(&new_stock).into_iter()
+            .map(|rule| AlertEvent::for_rule(rule, &new_stock))
+            .collect();
+        self.fire(&share_code, fired);
+
+        let scripted = self.script.as_ref().and_then(|script| {
+            script.alert(&new_stock)
+                .inspect_err(|err| {
+                    warn!(symbol = %share_code, "Script alert failed on {}: {}", share_code, err);
+                })
+                .ok()
+                .flatten()
+        });
+        self.fire(&share_code, scripted.map(|message| AlertEvent::for_script(message, &new_stock)).into_iter().collect());
+
+        if let Some(trader) = self.simulation.as_mut() {
+            for trade in trader.record(&share_code, &new_stock) {
+                info!(symbol = %share_code, "Paper trade: {}", trade);
+            }
+        }
+
+        enriched.push(Enriched::Quote(Box::new(new_stock)));
+        enriched
+    }
+
+    /// Runs the script's transform over `stock`, picking up changes to the
+    /// script first; `false` when the script drops the quote. A failing
+    /// script leaves the quote as fetched.
+    fn transform(&mut self, share_code: &str, stock: &mut Stock) -> bool {
+        let Some(script) = self.script.as_mut() else {
+            return true;
+        };
+
+        script.reload_if_changed();
+
+        let mut transformed = stock.clone();
+
+        match script.transform(&mut transformed) {
+            Ok(keep) => {
+                *stock = transformed;
+                keep
+            }
+            Err(err) => {
+                warn!(symbol = %share_code, "Script transform failed on {}: {}", share_code, err);
+                true
+            }
+        }
+    }
+
+    fn fire(&self, share_code: &str, events: Vec<AlertEvent>) {
+        for event in events {
+            let history = self.history.recent(share_code, DEFAULT_SPARKLINE_POINTS);
+            self.dispatcher.dispatch(&event.with_history(history));
+        }
+    }
+
+    /// Logs a failed fetch; the last known quote for the symbol is kept as is.
+    /// Opens the symbol's circuit once it has failed too often in a row.
+    fn failed(&self, share_code: &str, err: &StockError) {
+        self.health.failed(share_code, err);
+
+        let past = self.quotes.get_last(share_code);
+
+        match past.as_ref() {
+            Some(past) => warn!(symbol = share_code, "Failed to fetch {}: {}, keeping last price {}", share_code, err, past.price),
+            None => warn!(symbol = share_code, "Failed to fetch {}: {}", share_code, err),
+        }
+
+        if let Some(cooldown) = self.circuit.failed(share_code) {
+            let failures = self.health.report(&[share_code.to_string()]).symbols.get(share_code).map(|health| health.consecutive_failures).unwrap_or_default();
+            let event = AlertEvent::circuit_opened(share_code, past.as_ref(), failures, cooldown);
+
+            self.dispatcher.dispatch(&event);
+        }
+    }
+
+    /// Marks the last quotes older than their code allows as stale, alerting
+    /// once per symbol until it gets a fresh quote, and returns those that
+    /// just went stale.
+    fn check_stale(&self, stale_after: &[(String, Duration)]) -> Option<Vec<Stock>> {
+        let now = Utc::now();
+        let mut stale = Vec::new();
+
+        for (share_code, after) in stale_after {
+            let went_stale = self.quotes.modify(share_code, |past| {
+                let age = (now - past.fetched_at).to_std().ok().filter(|age| !past.stale && age > after)?;
+                past.stale = true;
+
+                Some((past.clone(), age))
+            });
+
+            if let Some((past, age)) = went_stale.flatten() {
+                self.fire(share_code, vec![AlertEvent::stale(&past, age)]);
+                stale.push(past);
+            }
+        }
+
+        (!stale.is_empty()).then_some(stale)
+    }
+
+    /// Where the paper trading stands, when simulating.
+    fn report(&self) -> Option<SessionReport> {
+        self.simulation.as_ref().map(PaperTrader::report)
+    }
+
+    /// Values the portfolio once every symbol of a poll has been handled.
+    fn end_cycle(&self) -> Option<PortfolioSummary> {
+        if self.portfolio.holdings().is_empty() {
+            return None;
+        }
+
+        Some(self.portfolio.value(&self.quotes))
+    }
+}
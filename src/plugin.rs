@@ -0,0 +1,183 @@
+use std::{fmt, path::{Path, PathBuf}};
+use serde::Deserialize;
+
+use crate::{Stock, StockError};
+
+/// Name of the function a strategy plugin exports.
+pub const SIGNAL_EXPORT: &str = "signal";
+
+/// Fuel one call to a plugin may burn, roughly one unit per instruction, so
+/// that a plugin stuck in a loop fails instead of stalling the quotes.
+#[cfg(feature = "wasm")]
+const SIGNAL_FUEL: u64 = 10_000_000;
+
+/// Price, change %, SMA, EMA, RSI, MACD line, MACD signal, lower and upper
+/// Bollinger Band.
+#[cfg(feature = "wasm")]
+type SignalParams = (f64, f64, f64, f64, f64, f64, f64, f64, f64);
+
+
+/// What a plugin makes of a quote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginSignal {
+    Buy,
+    Sell,
+    Hold,
+}
+
+impl PluginSignal {
+    /// A positive result is a buy, a negative one a sell and zero a hold.
+    pub fn from_result(result: i32) -> Self {
+        match result.signum() {
+            1 => PluginSignal::Buy,
+            -1 => PluginSignal::Sell,
+            _ => PluginSignal::Hold,
+        }
+    }
+}
+
+/// A strategy compiled to WebAssembly and loaded at runtime, given as
+/// `plugin = "path.wasm"`. The module imports nothing and exports
+/// `signal(price, change_pct, sma, ema, rsi, macd, macd_signal, lower_band,
+/// upper_band: f64) -> i32`, called with every quote of its symbol; an
+/// indicator not known yet is NaN. The text format is read as well.
+#[derive(Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Plugin {
+    path: PathBuf,
+    #[cfg(feature = "wasm")]
+    engine: wasmi::Engine,
+    #[cfg(feature = "wasm")]
+    module: wasmi::Module,
+}
+
+impl Plugin {
+    /// Compiles the module at `path`, checking that it exports the signal.
+    #[cfg(feature = "wasm")]
+    pub fn load(path: &Path) -> Result<Self, StockError> {
+        let wasm = std::fs::read(path)
+            .map_err(|err| {
+                StockError::io(path, err)
+            })?;
+
+        let mut config = wasmi::Config::default();
+        config.consume_fuel(true);
+
+        let engine = wasmi::Engine::new(&config);
+        let module = wasmi::Module::new(&engine, wasm)
+            .map_err(|err| {
+                StockError::parse(path.display(), err)
+            })?;
+
+        let plugin = Self { path: path.to_path_buf(), engine, module };
+        plugin.instantiate()?;
+
+        Ok(plugin)
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    pub fn load(_path: &Path) -> Result<Self, StockError> {
+        Err(StockError::FeatureDisabled("strategy plugins require building with --features wasm".to_string()))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// A fresh instance of the module, with its own memory and globals.
+    #[cfg(feature = "wasm")]
+    pub fn instantiate(&self) -> Result<PluginInstance, StockError> {
+        let mut store = wasmi::Store::new(&self.engine, ());
+        store.set_fuel(SIGNAL_FUEL)
+            .map_err(|err| StockError::runtime("plugin", err))?;
+
+        let instance = wasmi::Linker::new(&self.engine).instantiate_and_start(&mut store, &self.module)
+            .map_err(|err| {
+                StockError::invalid("plugin", format!("{} can't be instantiated: {}", self.path.display(), err))
+            })?;
+        let signal = instance.get_typed_func::<SignalParams, i32>(&store, SIGNAL_EXPORT)
+            .map_err(|err| {
+                StockError::invalid("plugin", format!("{} doesn't export {}(f64 x 9) -> i32: {}", self.path.display(), SIGNAL_EXPORT, err))
+            })?;
+
+        Ok(PluginInstance { store, signal })
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    pub fn instantiate(&self) -> Result<PluginInstance, StockError> {
+        Err(StockError::FeatureDisabled("strategy plugins require building with --features wasm".to_string()))
+    }
+}
+
+impl TryFrom<String> for Plugin {
+    type Error = StockError;
+
+    fn try_from(path: String) -> Result<Self, Self::Error> {
+        Self::load(Path::new(&path))
+    }
+}
+
+impl fmt::Debug for Plugin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Plugin").field("path", &self.path).finish()
+    }
+}
+
+/// Plugins are the same when they were loaded from the same file.
+impl PartialEq for Plugin {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+/// A running plugin, keeping whatever state the module holds between quotes.
+#[cfg(feature = "wasm")]
+pub struct PluginInstance {
+    store: wasmi::Store<()>,
+    signal: wasmi::TypedFunc<SignalParams, i32>,
+}
+
+#[cfg(not(feature = "wasm"))]
+pub struct PluginInstance {
+    never: std::convert::Infallible,
+}
+
+impl PluginInstance {
+    /// Asks the plugin about `stock`; a trap, such as running out of fuel,
+    /// is an error.
+    #[cfg(feature = "wasm")]
+    pub fn signal(&mut self, stock: &Stock) -> Result<PluginSignal, StockError> {
+        let indicators = stock.indicators.as_ref();
+        let macd = indicators.and_then(|indicators| indicators.macd);
+        let bands = indicators.and_then(|indicators| indicators.bollinger);
+        let params = (
+            stock.price.to_f64(),
+            stock.change_pct.unwrap_or(f64::NAN),
+            indicators.and_then(|indicators| indicators.sma).unwrap_or(f64::NAN),
+            indicators.and_then(|indicators| indicators.ema).unwrap_or(f64::NAN),
+            indicators.and_then(|indicators| indicators.rsi).unwrap_or(f64::NAN),
+            macd.map_or(f64::NAN, |macd| macd.line),
+            macd.map_or(f64::NAN, |macd| macd.signal),
+            bands.map_or(f64::NAN, |bands| bands.lower),
+            bands.map_or(f64::NAN, |bands| bands.upper),
+        );
+
+        self.store.set_fuel(SIGNAL_FUEL)
+            .map_err(|err| StockError::runtime("plugin", err))?;
+
+        self.signal.call(&mut self.store, params)
+            .map(PluginSignal::from_result)
+            .map_err(|err| StockError::runtime("plugin", err))
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    pub fn signal(&mut self, _stock: &Stock) -> Result<PluginSignal, StockError> {
+        match self.never {}
+    }
+}
+
+impl fmt::Debug for PluginInstance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PluginInstance").finish_non_exhaustive()
+    }
+}
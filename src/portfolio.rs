@@ -0,0 +1,132 @@
+use std::{collections::BTreeMap, fmt};
+use serde::{Deserialize, Serialize};
+
+use crate::{quotes::QuoteStore, Decimal, Stock};
+
+
+/// A position declared in the config file. `cost_basis` is the average price
+/// paid per unit, in the currency the symbol is quoted in (the base currency
+/// when prices are converted).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Holding {
+    pub symbol: String,
+    pub quantity: Decimal,
+    pub cost_basis: Decimal,
+}
+
+/// A holding valued at its latest quote.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Position {
+    pub symbol: String,
+    pub quantity: Decimal,
+    pub cost_basis: Decimal,
+    pub price: Decimal,
+    pub currency: String,
+    pub market_value: Decimal,
+    pub unrealized_pnl: Decimal,
+    pub unrealized_pnl_pct: f64,
+}
+
+/// Sums over the positions quoted in one currency.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PortfolioTotal {
+    pub currency: String,
+    pub cost: Decimal,
+    pub market_value: Decimal,
+    pub unrealized_pnl: Decimal,
+    pub unrealized_pnl_pct: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PortfolioSummary {
+    pub positions: Vec<Position>,
+    /// One total per currency, as prices in different currencies can't be
+    /// added up; use `--base-currency` to get a single total.
+    pub totals: Vec<PortfolioTotal>,
+    /// Holdings without a quote yet.
+    pub unpriced: Vec<String>,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} x {:.2} {}: value {:.2}, P&L {:+.2} ({:+.2}%)", self.symbol, self.quantity, self.price, self.currency, self.market_value, self.unrealized_pnl, self.unrealized_pnl_pct)
+    }
+}
+
+impl fmt::Display for PortfolioTotal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Portfolio {}: value {:.2}, cost {:.2}, P&L {:+.2} ({:+.2}%)", self.currency, self.market_value, self.cost, self.unrealized_pnl, self.unrealized_pnl_pct)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Portfolio {
+    holdings: Vec<Holding>,
+}
+
+impl Portfolio {
+    pub fn new(holdings: Vec<Holding>) -> Self {
+        Self { holdings }
+    }
+
+    pub fn holdings(&self) -> &[Holding] {
+        &self.holdings
+    }
+
+    /// Values every holding at its last quote in `quotes`.
+    pub fn value(&self, quotes: &QuoteStore) -> PortfolioSummary {
+        let mut positions = Vec::new();
+        let mut unpriced = Vec::new();
+
+        for holding in self.holdings.iter() {
+            match quotes.get_last(&holding.symbol) {
+                Some(stock) => positions.push(position(holding, &stock)),
+                None => unpriced.push(holding.symbol.clone()),
+            }
+        }
+
+        let mut totals: BTreeMap<&str, (Decimal, Decimal)> = BTreeMap::new();
+
+        for position in positions.iter() {
+            let (cost, market_value) = totals.entry(position.currency.as_str()).or_default();
+            *cost += position.quantity * position.cost_basis;
+            *market_value += position.market_value;
+        }
+
+        let totals = totals.into_iter()
+            .map(|(currency, (cost, market_value))| PortfolioTotal {
+                currency: currency.to_string(),
+                cost,
+                market_value,
+                unrealized_pnl: market_value - cost,
+                unrealized_pnl_pct: percent(market_value - cost, cost),
+            })
+            .collect();
+
+        PortfolioSummary { positions, totals, unpriced }
+    }
+}
+
+fn position(holding: &Holding, stock: &Stock) -> Position {
+    let cost = holding.quantity * holding.cost_basis;
+    let market_value = holding.quantity * stock.price;
+
+    Position {
+        symbol: holding.symbol.clone(),
+        quantity: holding.quantity,
+        cost_basis: holding.cost_basis,
+        price: stock.price,
+        currency: stock.currency.clone(),
+        market_value,
+        unrealized_pnl: market_value - cost,
+        unrealized_pnl_pct: percent(market_value - cost, cost),
+    }
+}
+
+fn percent(change: Decimal, base: Decimal) -> f64 {
+    match base.is_zero() {
+        true => 0f64,
+        false => change.to_f64() / base.to_f64() * 100f64,
+    }
+}
@@ -0,0 +1,167 @@
+use crate::Decimal;
+
+
+/// A price as shown on a quote page, split into currency and amount.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Price {
+    /// ISO 4217 code, or the raw marker when it isn't recognised; empty when absent.
+    pub currency: String,
+    pub value: Decimal,
+}
+
+/// Currency markers in the order they are matched, longest first, so `HK$`
+/// wins over `$` and a `US$` suffix over `S$`.
+const CURRENCY_MARKERS: [(&str, &str); 20] = [
+    ("HK$", "HKD"),
+    ("CA$", "CAD"),
+    ("NZ$", "NZD"),
+    ("MX$", "MXN"),
+    ("US$", "USD"),
+    ("CHF", "CHF"),
+    ("A$", "AUD"),
+    ("S$", "SGD"),
+    ("R$", "BRL"),
+    ("Rp", "IDR"),
+    ("RM", "MYR"),
+    ("$", "USD"),
+    ("€", "EUR"),
+    ("£", "GBP"),
+    ("¥", "JPY"),
+    ("₹", "INR"),
+    ("₩", "KRW"),
+    ("₱", "PHP"),
+    ("฿", "THB"),
+    ("₫", "VND"),
+];
+
+/// Currencies written with `.` as the decimal separator, in which a lone
+/// `.` is never grouping, so `$1.234` is a dollar and change.
+const DOT_DECIMAL_CURRENCIES: [&str; 16] = [
+    "USD", "GBP", "JPY", "CNY", "HKD", "CAD", "AUD", "SGD",
+    "NZD", "MXN", "INR", "KRW", "PHP", "MYR", "THB", "CHF",
+];
+
+/// Parses texts like `$189.50`, `Rp 9,875`, `1.234,56 €` or `CHF 1'234.50`.
+///
+/// The decimal separator is whichever of `.` and `,` comes last when both
+/// appear. When only one appears it is a thousands separator if it repeats or
+/// is followed by exactly three digits (after a non-zero whole part),
+/// otherwise the decimal separator; except that a lone `.` is always the
+/// decimal separator in currencies written that way, like USD.
+pub fn parse_price(text: &str) -> Option<Price> {
+    let text = text.trim();
+    let (currency, amount) = split_currency(text);
+    let value = normalize_amount(amount, &currency).parse().ok()?;
+
+    Some(Price { currency, value })
+}
+
+/// Formats `value` for display in `currency`. Rupiah prices, which never
+/// have cents, are grouped in thousands, e.g. `9,675`; anything else is
+/// written as is.
+pub fn format_amount(value: Decimal, currency: &str) -> String {
+    if currency != "IDR" {
+        return value.to_string();
+    }
+
+    let digits = format!("{:.0}", value.abs());
+    let grouped = digits.as_bytes()
+        .rchunks(3)
+        .rev()
+        .map(|group| String::from_utf8_lossy(group).into_owned())
+        .collect::<Vec<String>>()
+        .join(",");
+
+    match value.is_sign_negative() {
+        true => format!("-{}", grouped),
+        false => grouped,
+    }
+}
+
+/// The smallest move a currency pair is quoted in, by its quote currency:
+/// `0.01` for yen, `1` for currencies quoted without decimals such as the
+/// rupiah, and `0.0001` otherwise.
+pub fn pip_size(quote_currency: &str) -> f64 {
+    match quote_currency {
+        "JPY" => 0.01,
+        "IDR" | "KRW" | "VND" => 1f64,
+        _ => 0.0001,
+    }
+}
+
+/// Parses abbreviated amounts like `2.87T USD`, `52.34M` or `850K`.
+pub fn parse_abbreviated(text: &str) -> Option<f64> {
+    let (currency, amount) = split_currency(text.trim());
+    let amount = amount.trim();
+
+    let (digits, scale) = match amount.chars().last()? {
+        'K' => (&amount[..amount.len() - 1], 1e3),
+        'M' => (&amount[..amount.len() - 1], 1e6),
+        'B' => (&amount[..amount.len() - 1], 1e9),
+        'T' => (&amount[..amount.len() - 1], 1e12),
+        _ => (amount, 1f64),
+    };
+
+    Some(normalize_amount(digits, &currency).parse::<f64>().ok()? * scale)
+}
+
+fn split_currency(text: &str) -> (String, &str) {
+    for (marker, code) in CURRENCY_MARKERS {
+        if let Some(rest) = text.strip_prefix(marker) {
+            return (code.to_string(), rest);
+        }
+
+        if let Some(rest) = text.strip_suffix(marker) {
+            return (code.to_string(), rest);
+        }
+    }
+
+    let is_code = |part: &str| part.len() == 3 && part.chars().all(|c| c.is_ascii_uppercase());
+
+    match text.split_once(char::is_whitespace) {
+        Some((code, rest)) if is_code(code) => (code.to_string(), rest),
+        _ => match text.rsplit_once(char::is_whitespace) {
+            Some((rest, code)) if is_code(code) => (code.to_string(), rest),
+            _ => (String::new(), text),
+        },
+    }
+}
+
+/// `amount` in `currency` with `.` as its decimal separator and no grouping,
+/// e.g. `1234.56` for `1.234,56`.
+fn normalize_amount(amount: &str, currency: &str) -> String {
+    let digits = amount.chars()
+        .filter(|c| !c.is_whitespace() && *c != '\'' && *c != '’')
+        .collect::<String>();
+    let digits = digits.trim_start_matches('+');
+
+    let last_dot = digits.rfind('.');
+    let last_comma = digits.rfind(',');
+
+    let decimal = match (last_dot, last_comma) {
+        (Some(dot), Some(comma)) => Some(if dot > comma { '.' } else { ',' }),
+        (Some(_), None) if DOT_DECIMAL_CURRENCIES.contains(&currency) && digits.matches('.').count() == 1 => Some('.'),
+        (Some(_), None) => single_separator(digits, '.'),
+        (None, Some(_)) => single_separator(digits, ','),
+        (None, None) => None,
+    };
+
+    digits.chars()
+        .filter_map(|c| match c {
+            '.' | ',' if Some(c) == decimal => Some('.'),
+            '.' | ',' => None,
+            c => Some(c),
+        })
+        .collect()
+}
+
+fn single_separator(digits: &str, separator: char) -> Option<char> {
+    let count = digits.matches(separator).count();
+    let whole = digits.split(separator).next().unwrap_or_default().trim_start_matches('-');
+    let fraction = digits.rsplit(separator).next().unwrap_or_default();
+
+    match count > 1 || (fraction.len() == 3 && !whole.is_empty() && whole != "0") {
+        true => None,
+        false => Some(separator),
+    }
+}
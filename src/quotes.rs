@@ -0,0 +1,122 @@
+use std::{collections::HashMap, hash::{BuildHasher, RandomState}, sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard}};
+
+use crate::{Stock, Valuation};
+
+/// Locks the codes are spread over, so updates of different symbols rarely
+/// wait for each other.
+const DEFAULT_SHARDS: usize = 16;
+
+
+/// What [`QuoteStore::update_and_diff`] found before storing a quote.
+#[derive(Debug, Clone)]
+pub struct QuoteDiff {
+    /// The quote against the last one, already applied to it.
+    pub valuation: Valuation,
+    /// The last quote, replaced by the new one.
+    pub previous: Option<Stock>,
+}
+
+/// The last quote of every symbol, keyed by code and shared between threads.
+/// Codes are spread over a few locks, so concurrent updates of different
+/// symbols don't contend, and each update of a symbol compares with and
+/// replaces its last quote in one go. Clones share the same quotes.
+#[derive(Debug, Clone)]
+pub struct QuoteStore {
+    shards: Arc<[RwLock<HashMap<String, Stock>>]>,
+    hasher: RandomState,
+}
+
+impl Default for QuoteStore {
+    fn default() -> Self {
+        Self::with_shards(DEFAULT_SHARDS)
+    }
+}
+
+impl QuoteStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spreads the codes over `shards` locks, at least one.
+    pub fn with_shards(shards: usize) -> Self {
+        Self {
+            shards: (0..shards.max(1)).map(|_| RwLock::default()).collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    fn shard(&self, share_code: &str) -> &RwLock<HashMap<String, Stock>> {
+        &self.shards[self.hasher.hash_one(share_code) as usize % self.shards.len()]
+    }
+
+    // A panic elsewhere leaves the quotes as they were, still usable.
+    fn read(&self, share_code: &str) -> RwLockReadGuard<'_, HashMap<String, Stock>> {
+        self.shard(share_code).read().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn write(&self, share_code: &str) -> RwLockWriteGuard<'_, HashMap<String, Stock>> {
+        self.shard(share_code).write().unwrap_or_else(|err| err.into_inner())
+    }
+
+    pub fn get_last(&self, share_code: &str) -> Option<Stock> {
+        self.read(share_code).get(share_code).cloned()
+    }
+
+    /// Compares `stock` with the last quote of `share_code`, completing it
+    /// with its status and change, and stores it as the new last quote.
+    /// Returns nothing, storing nothing, when the last quote was observed
+    /// after `stock`.
+    pub fn update_and_diff(&self, share_code: &str, stock: &mut Stock) -> Option<QuoteDiff> {
+        self.update_with(share_code, stock, |_| ())
+    }
+
+    /// Like [`QuoteStore::update_and_diff`], calling `enrich` on `stock` once
+    /// it is known to be newer and before it is stored, under the same lock,
+    /// so readers never see the new quote without what `enrich` adds.
+    pub fn update_with(&self, share_code: &str, stock: &mut Stock, enrich: impl FnOnce(&mut Stock)) -> Option<QuoteDiff> {
+        let mut quotes = self.write(share_code);
+        let previous = quotes.get(share_code);
+
+        if previous.is_some_and(|previous| stock.fetched_at < previous.fetched_at) {
+            return None;
+        }
+
+        let valuation = match previous {
+            Some(previous) => Valuation::between(previous.price, stock.price),
+            None => Valuation::First,
+        };
+        valuation.apply_to(stock);
+        enrich(stock);
+
+        let previous = quotes.insert(share_code.to_string(), stock.clone());
+
+        Some(QuoteDiff { valuation, previous })
+    }
+
+    /// Changes the last quote of `share_code` in place, if there is one.
+    pub fn modify<T>(&self, share_code: &str, change: impl FnOnce(&mut Stock) -> T) -> Option<T> {
+        self.write(share_code).get_mut(share_code).map(change)
+    }
+
+    /// A copy of every last quote, keyed by code. Every lock is held while
+    /// copying, so no update lands halfway through.
+    pub fn snapshot(&self) -> HashMap<String, Stock> {
+        let shards = self.shards.iter()
+            .map(|shard| shard.read().unwrap_or_else(|err| err.into_inner()))
+            .collect::<Vec<_>>();
+
+        shards.iter()
+            .flat_map(|quotes| quotes.iter().map(|(code, stock)| (code.clone(), stock.clone())))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter()
+            .map(|shard| shard.read().unwrap_or_else(|err| err.into_inner()).len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,185 @@
+use std::{collections::HashMap, str::FromStr, sync::{Arc, Mutex}, thread, time::{Duration, Instant}};
+
+use crate::{http::BoxFuture, scraping::{alphavantage, finnhub, QuoteProvider}, Stock, StockError};
+
+
+/// A token bucket refilled at a steady rate.
+///
+/// Callers that find the bucket empty reserve the next token and sleep until
+/// it is due, so requests over the cap are queued in arrival order rather
+/// than rejected.
+#[derive(Debug)]
+pub struct TokenBucket {
+    capacity: f64,
+    per_second: f64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(capacity: u32, per_second: f64) -> Self {
+        let capacity = capacity.max(1) as f64;
+
+        Self { capacity, per_second, state: Mutex::new(BucketState { tokens: capacity, updated: Instant::now() }) }
+    }
+
+    /// Allows `requests` per minute, with bursts of up to ten seconds' worth.
+    pub fn per_minute(requests: u32) -> Self {
+        Self::new(requests / 6, requests.max(1) as f64 / 60f64)
+    }
+
+    /// Blocks until a request may be made.
+    pub fn acquire(&self) {
+        let wait = self.reserve();
+
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+
+    /// Same as [`TokenBucket::acquire`], waiting on tokio's timer.
+    pub async fn async_acquire(&self) {
+        let wait = self.reserve();
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Takes a token and returns how long to wait before using it.
+    fn reserve(&self) -> Duration {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = Instant::now();
+
+        let refilled = now.duration_since(state.updated).as_secs_f64() * self.per_second;
+        state.tokens = (state.tokens + refilled).min(self.capacity) - 1f64;
+        state.updated = now;
+
+        match state.tokens {
+            tokens if tokens >= 0f64 => Duration::ZERO,
+            tokens => Duration::from_secs_f64(-tokens / self.per_second),
+        }
+    }
+}
+
+/// A `PROVIDER=REQUESTS_PER_MINUTE` cap as given on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimit {
+    pub provider: String,
+    pub per_minute: u32,
+}
+
+impl FromStr for RateLimit {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (provider, per_minute) = value.split_once('=')
+            .ok_or_else(|| {
+                StockError::invalid("rate limit", format!("expected PROVIDER=REQUESTS_PER_MINUTE, got {}", value))
+            })?;
+
+        let per_minute = per_minute.trim().parse::<u32>()
+            .map_err(|err| {
+                StockError::invalid("rate limit", format!("{}: {}", value, err))
+            })?;
+
+        if per_minute == 0 {
+            return Err(StockError::invalid("rate limit", format!("{}: must allow at least one request per minute", value)));
+        }
+
+        Ok(Self { provider: provider.trim().to_lowercase(), per_minute })
+    }
+}
+
+/// Requests-per-minute caps keyed by provider name (`google`, `yahoo`,
+/// `stooq`, `alphavantage`, `finnhub`, `idx`, `coingecko`). Providers without an entry are not
+/// limited, except those with a [`default_per_minute`].
+#[derive(Debug, Clone, Default)]
+pub struct RateLimits {
+    per_minute: HashMap<String, u32>,
+}
+
+impl RateLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(mut self, provider: &str, per_minute: u32) -> Self {
+        self.per_minute.insert(provider.to_lowercase(), per_minute);
+        self
+    }
+
+    /// The cap of `provider`, its own or else its default.
+    pub fn get(&self, provider: &str) -> Option<u32> {
+        self.per_minute.get(provider).copied().or(default_per_minute(provider))
+    }
+
+    /// Wraps `provider` in its limiter when it has a cap.
+    pub fn apply(&self, provider: Arc<dyn QuoteProvider>) -> Arc<dyn QuoteProvider> {
+        match self.get(provider.name()) {
+            Some(per_minute) => Arc::new(RateLimited::new(provider, TokenBucket::per_minute(per_minute))),
+            None => provider,
+        }
+    }
+}
+
+/// The cap of providers whose API enforces one anyway.
+pub fn default_per_minute(provider: &str) -> Option<u32> {
+    match provider {
+        "alphavantage" => Some(alphavantage::REQUESTS_PER_MINUTE),
+        "finnhub" => Some(finnhub::REQUESTS_PER_MINUTE),
+        _ => None,
+    }
+}
+
+/// A provider whose fetches, single or batched, wait for a token from a shared [`TokenBucket`].
+pub struct RateLimited {
+    inner: Arc<dyn QuoteProvider>,
+    bucket: TokenBucket,
+}
+
+impl RateLimited {
+    pub fn new(inner: Arc<dyn QuoteProvider>, bucket: TokenBucket) -> Self {
+        Self { inner, bucket }
+    }
+}
+
+impl QuoteProvider for RateLimited {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn fetch(&self, symbol: &str) -> Result<Stock, StockError> {
+        self.bucket.acquire();
+        self.inner.fetch(symbol)
+    }
+
+    fn async_fetch<'a>(self: Arc<Self>, symbol: &'a str) -> BoxFuture<'a, Result<Stock, StockError>> {
+        Box::pin(async move {
+            self.bucket.async_acquire().await;
+            Arc::clone(&self.inner).async_fetch(symbol).await
+        })
+    }
+
+    fn batches(&self, symbols: &[String]) -> Vec<Vec<String>> {
+        self.inner.batches(symbols)
+    }
+
+    /// A batch is one request, so it takes one token.
+    fn fetch_batch(&self, symbols: &[String]) -> Result<Vec<Result<Stock, StockError>>, StockError> {
+        self.bucket.acquire();
+        self.inner.fetch_batch(symbols)
+    }
+
+    fn async_fetch_batch<'a>(self: Arc<Self>, symbols: &'a [String]) -> BoxFuture<'a, Result<Vec<Result<Stock, StockError>>, StockError>> {
+        Box::pin(async move {
+            self.bucket.async_acquire().await;
+            Arc::clone(&self.inner).async_fetch_batch(symbols).await
+        })
+    }
+}
@@ -0,0 +1,110 @@
+use std::{collections::HashMap, future::Future, time::Duration};
+use rand::Rng;
+use tracing::warn;
+
+use crate::StockError;
+
+
+/// How often and how patiently a failed fetch is retried.
+///
+/// Only transient failures (network errors, HTTP 429 and 5xx) are retried;
+/// the delay doubles after every attempt up to `max_delay`, with random
+/// jitter so many symbols failing together don't retry in lockstep.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Per-symbol overrides of `max_attempts`, keyed by the bare symbol.
+    pub max_attempts_per_symbol: HashMap<String, u32>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            max_attempts_per_symbol: HashMap::new(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that gives up after the first failure.
+    pub fn none() -> Self {
+        Self { max_attempts: 1, ..Self::default() }
+    }
+
+    pub fn max_attempts_for(&self, share_code: &str) -> u32 {
+        let symbol = share_code.to_uppercase().split(':').next().unwrap_or_default().to_string();
+
+        self.max_attempts_per_symbol.get(&symbol)
+            .copied()
+            .unwrap_or(self.max_attempts)
+            .max(1)
+    }
+
+    /// The delay before retry number `attempt` (starting at 1).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+        let half = exponential / 2;
+        let jitter = rand::thread_rng().gen_range(0..=half.as_millis() as u64);
+
+        half + Duration::from_millis(jitter)
+    }
+
+    pub fn run<T>(&self, share_code: &str, mut operation: impl FnMut() -> Result<T, StockError>) -> Result<T, StockError> {
+        let mut attempt = 1;
+
+        loop {
+            match operation() {
+                Ok(value) => return Ok(value),
+                Err(err) => match self.retry_after(share_code, attempt, &err) {
+                    Some(delay) => std::thread::sleep(delay),
+                    None => return Err(err),
+                },
+            }
+
+            attempt += 1;
+        }
+    }
+
+    /// Same as [`RetryPolicy::run`] for an async `operation`, backing off on
+    /// tokio's timer.
+    pub async fn async_run<T, F>(&self, share_code: &str, mut operation: impl FnMut() -> F) -> Result<T, StockError>
+    where
+        F: Future<Output = Result<T, StockError>>,
+    {
+        let mut attempt = 1;
+
+        loop {
+            match operation().await {
+                Ok(value) => return Ok(value),
+                Err(err) => match self.retry_after(share_code, attempt, &err) {
+                    Some(delay) => tokio::time::sleep(delay).await,
+                    None => return Err(err),
+                },
+            }
+
+            attempt += 1;
+        }
+    }
+
+    /// How long to back off before retrying after attempt `attempt` failed
+    /// with `err`, or `None` when it shouldn't be retried.
+    fn retry_after(&self, share_code: &str, attempt: u32, err: &StockError) -> Option<Duration> {
+        let max_attempts = self.max_attempts_for(share_code);
+
+        if !err.is_transient() || attempt >= max_attempts {
+            return None;
+        }
+
+        let delay = self.backoff(attempt);
+
+        warn!(attempt, max_attempts, "Attempt {}/{} for {} failed: {}, retrying in {:?}", attempt, max_attempts, share_code, err, delay);
+        Some(delay)
+    }
+}
@@ -0,0 +1,213 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}, thread, time::{Duration, Instant}};
+use regex::Regex;
+use tracing::{debug, info};
+use url::Url;
+
+use crate::StockError;
+
+/// Name matched against `User-agent` lines in robots.txt.
+pub const PRODUCT_TOKEN: &str = "scraping-stock";
+/// Sent in polite mode unless other user agents are configured.
+pub const USER_AGENT: &str = concat!("scraping-stock/", env!("CARGO_PKG_VERSION"), " (+https://github.com/hexennacht/scraping-stock)");
+pub const DEFAULT_HOST_DELAY: Duration = Duration::from_secs(1);
+/// How long a host's robots.txt is trusted before it is fetched again.
+const ROBOTS_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Longest `Crawl-delay` honoured; a longer one would stall every poll.
+pub const MAX_CRAWL_DELAY: Duration = Duration::from_secs(60);
+
+
+/// The user agents of a robots.txt group and its `(field, value)` lines.
+type Group = (Vec<String>, Vec<(String, String)>);
+
+/// The rules of a robots.txt that apply to [`PRODUCT_TOKEN`].
+#[derive(Debug, Clone, Default)]
+pub struct Robots {
+    /// `(allow, pattern, length)`, where the longest matching pattern wins.
+    rules: Vec<(bool, Regex, usize)>,
+    pub crawl_delay: Option<Duration>,
+}
+
+impl Robots {
+    /// Allows everything, as when a site has no robots.txt.
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    /// Reads the group for [`PRODUCT_TOKEN`], or the `*` group when there is
+    /// none. Lines that can't be understood are skipped.
+    pub fn parse(content: &str) -> Self {
+        let mut groups: Vec<Group> = Vec::new();
+        let mut in_agents = false;
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let (key, value) = (key.trim().to_lowercase(), value.trim().to_string());
+
+            match key.as_str() {
+                "user-agent" => {
+                    if !in_agents || groups.is_empty() {
+                        groups.push((Vec::new(), Vec::new()));
+                    }
+
+                    if let Some((agents, _)) = groups.last_mut() {
+                        agents.push(value.to_lowercase());
+                    }
+                    in_agents = true;
+                }
+                "allow" | "disallow" | "crawl-delay" => {
+                    if let Some((_, lines)) = groups.last_mut() {
+                        lines.push((key, value));
+                    }
+                    in_agents = false;
+                }
+                _ => {}
+            }
+        }
+
+        let matching = |agent: &str| groups.iter()
+            .filter(|(agents, _)| agents.iter().any(|candidate| candidate == agent))
+            .flat_map(|(_, lines)| lines.iter())
+            .collect::<Vec<&(String, String)>>();
+
+        let lines = match matching(PRODUCT_TOKEN) {
+            lines if !lines.is_empty() => lines,
+            _ => matching("*"),
+        };
+
+        let mut robots = Self::default();
+
+        for (key, value) in lines {
+            match key.as_str() {
+                "crawl-delay" => robots.crawl_delay = value.parse::<f64>().ok().and_then(crawl_delay),
+                // An empty `Disallow:` allows everything.
+                _ if value.is_empty() => {}
+                key => {
+                    if let Some(pattern) = pattern(value) {
+                        robots.rules.push((key == "allow", pattern, value.len()));
+                    }
+                }
+            }
+        }
+
+        robots
+    }
+
+    /// Whether `path`, including its query, may be fetched: the longest
+    /// matching rule decides, `Allow` winning ties.
+    pub fn is_allowed(&self, path: &str) -> bool {
+        self.rules.iter()
+            .filter(|(_, pattern, _)| pattern.is_match(path))
+            .max_by_key(|(allow, _, length)| (*length, *allow))
+            .is_none_or(|(allow, _, _)| *allow)
+    }
+}
+
+/// A `Crawl-delay` of `secs`, capped at [`MAX_CRAWL_DELAY`]. Negative and
+/// NaN delays are ignored.
+fn crawl_delay(secs: f64) -> Option<Duration> {
+    match Duration::try_from_secs_f64(secs) {
+        Ok(delay) => Some(delay.min(MAX_CRAWL_DELAY)),
+        // Too long for a `Duration`, infinite included.
+        Err(_) if secs > 0f64 => Some(MAX_CRAWL_DELAY),
+        Err(_) => None,
+    }
+}
+
+/// Turns a robots.txt path pattern, with `*` for any characters and a
+/// trailing `$` anchoring the end, into a regex matching from the start.
+fn pattern(value: &str) -> Option<Regex> {
+    let (value, anchored) = match value.strip_suffix('$') {
+        Some(value) => (value, true),
+        None => (value, false),
+    };
+    let body = value.split('*').map(regex::escape).collect::<Vec<String>>().join(".*");
+
+    Regex::new(&format!("^{}{}", body, if anchored { "$" } else { "" })).ok()
+}
+
+struct Host {
+    robots: Option<(Robots, Instant)>,
+    /// When the next request to the host may be sent.
+    next_request: Instant,
+}
+
+/// Keeps requests within what each host's robots.txt allows and spaces them
+/// at least `delay` apart per host, or further when the host asks for a
+/// longer `Crawl-delay`.
+pub struct Politeness {
+    delay: Duration,
+    hosts: Mutex<HashMap<String, Arc<Mutex<Host>>>>,
+}
+
+impl Politeness {
+    pub fn new(delay: Duration) -> Self {
+        Self { delay, hosts: Mutex::new(HashMap::new()) }
+    }
+
+    /// Blocks until a request to `url` may be sent, or fails when robots.txt
+    /// forbids it. `fetch_robots` downloads a robots.txt, returning `None`
+    /// when the site has none.
+    pub fn admit(&self, url: &Url, fetch_robots: impl FnOnce(&Url) -> Result<Option<String>, StockError>) -> Result<(), StockError> {
+        let wait = self.reserve(url, fetch_robots)?;
+
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+
+        Ok(())
+    }
+
+    /// Takes the host's next turn for `url` and returns how long to wait for
+    /// it, so async callers can wait without blocking.
+    pub fn reserve(&self, url: &Url, fetch_robots: impl FnOnce(&Url) -> Result<Option<String>, StockError>) -> Result<Duration, StockError> {
+        let origin = url.origin().ascii_serialization();
+        let host = self.hosts.lock().unwrap_or_else(|err| err.into_inner())
+            .entry(origin.clone())
+            .or_insert_with(|| Arc::new(Mutex::new(Host { robots: None, next_request: Instant::now() })))
+            .clone();
+
+        // Held while robots.txt is fetched, so it is fetched once per host.
+        let mut host = host.lock().unwrap_or_else(|err| err.into_inner());
+
+        if host.robots.as_ref().is_none_or(|(_, fetched_at)| fetched_at.elapsed() >= ROBOTS_TTL) {
+            let robots_url = url.join("/robots.txt")
+                .map_err(|err| {
+                    StockError::Url(err)
+                })?;
+
+            let robots = match fetch_robots(&robots_url)? {
+                Some(content) => Robots::parse(&content),
+                None => Robots::allow_all(),
+            };
+
+            info!("Read {}{}", robots_url, robots.crawl_delay.map(|delay| format!(", crawl delay {:?}", delay)).unwrap_or_default());
+            host.robots = Some((robots, Instant::now()));
+        }
+
+        let robots = host.robots.as_ref().map(|(robots, _)| robots);
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+
+        if robots.is_some_and(|robots| !robots.is_allowed(&path)) {
+            return Err(StockError::Disallowed(format!("{}{}", origin, path)));
+        }
+
+        let delay = robots.and_then(|robots| robots.crawl_delay).unwrap_or_default().max(self.delay);
+        let now = Instant::now();
+        let wait = host.next_request.saturating_duration_since(now);
+        host.next_request = now.max(host.next_request) + delay;
+        drop(host);
+
+        if !wait.is_zero() {
+            debug!("Waiting {:?} before requesting {}", wait, origin);
+        }
+
+        Ok(wait)
+    }
+}
@@ -0,0 +1,512 @@
+use std::{collections::HashMap, fmt, str::FromStr, time::{Duration, Instant}};
+use serde::Deserialize;
+
+use crate::{notify::Channel, Stock, StockError};
+
+pub const DEFAULT_COOLOFF: Duration = Duration::from_secs(15 * 60);
+
+
+/// The quote figure a rule looks at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    Price,
+    /// Percent change since the previous poll.
+    ChangePct,
+    Rsi,
+}
+
+impl Metric {
+    fn of(&self, stock: &Stock) -> Option<f64> {
+        match self {
+            Metric::Price => Some(stock.price.to_f64()),
+            Metric::ChangePct => stock.change_pct,
+            Metric::Rsi => stock.indicators.and_then(|indicators| indicators.rsi),
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Metric::Price => write!(f, "price"),
+            Metric::ChangePct => write!(f, "change_pct"),
+            Metric::Rsi => write!(f, "rsi"),
+        }
+    }
+}
+
+impl FromStr for Metric {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "price" => Ok(Metric::Price),
+            "change_pct" => Ok(Metric::ChangePct),
+            "rsi" => Ok(Metric::Rsi),
+            _ => Err(invalid(value, "unknown metric, expected price, change_pct or rsi")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Above,
+    AtLeast,
+    Below,
+    AtMost,
+}
+
+impl Comparison {
+    fn holds(&self, value: f64, limit: f64) -> bool {
+        match self {
+            Comparison::Above => value > limit,
+            Comparison::AtLeast => value >= limit,
+            Comparison::Below => value < limit,
+            Comparison::AtMost => value <= limit,
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Comparison::Above => write!(f, ">"),
+            Comparison::AtLeast => write!(f, ">="),
+            Comparison::Below => write!(f, "<"),
+            Comparison::AtMost => write!(f, "<="),
+        }
+    }
+}
+
+impl FromStr for Comparison {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            ">" | "above" => Ok(Comparison::Above),
+            ">=" => Ok(Comparison::AtLeast),
+            "<" | "below" => Ok(Comparison::Below),
+            "<=" => Ok(Comparison::AtMost),
+            _ => Err(invalid(value, "unknown comparison, expected above, below, >, >=, < or <=")),
+        }
+    }
+}
+
+/// An indicator crossing a rule can wait for instead of a comparison.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    /// The MACD line crosses above its signal line.
+    MacdCrossUp,
+    /// The MACD line crosses below its signal line.
+    MacdCrossDown,
+    /// The price moves above the upper Bollinger Band.
+    PriceAboveUpperBand,
+    /// The price moves below the lower Bollinger Band.
+    PriceBelowLowerBand,
+}
+
+impl Signal {
+    /// Whether `stock` is on the side the signal crosses into; `None` until
+    /// the indicator is known.
+    fn state(&self, stock: &Stock) -> Option<bool> {
+        let indicators = stock.indicators?;
+        let price = stock.price.to_f64();
+
+        match self {
+            Signal::MacdCrossUp => indicators.macd.map(|macd| macd.line > macd.signal),
+            Signal::MacdCrossDown => indicators.macd.map(|macd| macd.line < macd.signal),
+            Signal::PriceAboveUpperBand => indicators.bollinger.map(|bands| price > bands.upper),
+            Signal::PriceBelowLowerBand => indicators.bollinger.map(|bands| price < bands.lower),
+        }
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Signal::MacdCrossUp => write!(f, "macd_cross_up"),
+            Signal::MacdCrossDown => write!(f, "macd_cross_down"),
+            Signal::PriceAboveUpperBand => write!(f, "price_above_upper_band"),
+            Signal::PriceBelowLowerBand => write!(f, "price_below_lower_band"),
+        }
+    }
+}
+
+impl FromStr for Signal {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "macd_cross_up" => Ok(Signal::MacdCrossUp),
+            "macd_cross_down" => Ok(Signal::MacdCrossDown),
+            "price_above_upper_band" => Ok(Signal::PriceAboveUpperBand),
+            "price_below_lower_band" => Ok(Signal::PriceBelowLowerBand),
+            _ => Err(invalid(value, "unknown signal, expected macd_cross_up, macd_cross_down, price_above_upper_band or price_below_lower_band")),
+        }
+    }
+}
+
+/// What a rule waits for: a comparison, a crossing, or conditions combined
+/// with `AND`, `OR` and `NOT`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Condition {
+    /// A metric compared with a value, e.g. `rsi < 30`.
+    Compare { metric: Metric, comparison: Comparison, value: f64 },
+    /// An indicator crossing, e.g. `macd_cross_up`.
+    Cross(Signal),
+    /// Every condition holds.
+    All(Vec<Condition>),
+    /// At least one condition holds.
+    Any(Vec<Condition>),
+    Not(Box<Condition>),
+}
+
+impl Condition {
+    /// Whether the condition holds for `stock`; `None` while a crossing's
+    /// indicator isn't known yet and the outcome depends on it.
+    fn state(&self, stock: &Stock) -> Option<bool> {
+        match self {
+            Condition::Compare { metric, comparison, value } => Some(metric.of(stock).is_some_and(|metric| comparison.holds(metric, *value))),
+            Condition::Cross(signal) => signal.state(stock),
+            Condition::All(conditions) => {
+                let states: Vec<Option<bool>> = conditions.iter().map(|condition| condition.state(stock)).collect();
+
+                match states.contains(&Some(false)) {
+                    true => Some(false),
+                    false => states.into_iter().collect::<Option<Vec<bool>>>().map(|_| true),
+                }
+            },
+            Condition::Any(conditions) => {
+                let states: Vec<Option<bool>> = conditions.iter().map(|condition| condition.state(stock)).collect();
+
+                match states.contains(&Some(true)) {
+                    true => Some(true),
+                    false => states.into_iter().collect::<Option<Vec<bool>>>().map(|_| false),
+                }
+            },
+            Condition::Not(condition) => condition.state(stock).map(|holds| !holds),
+        }
+    }
+
+    /// Whether the condition waits for a crossing anywhere in it.
+    fn has_crossing(&self) -> bool {
+        match self {
+            Condition::Compare { .. } => false,
+            Condition::Cross(_) => true,
+            Condition::All(conditions) | Condition::Any(conditions) => conditions.iter().any(Condition::has_crossing),
+            Condition::Not(condition) => condition.has_crossing(),
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Condition::Compare { metric, comparison, value } => write!(f, "{} {} {}", metric, comparison, value),
+            Condition::Cross(signal) => write!(f, "{}", signal),
+            Condition::All(conditions) => {
+                let operands: Vec<String> = conditions.iter()
+                    .map(|condition| match condition {
+                        Condition::Any(_) => format!("({})", condition),
+                        _ => condition.to_string(),
+                    })
+                    .collect();
+                write!(f, "{}", operands.join(" AND "))
+            },
+            Condition::Any(conditions) => {
+                let operands: Vec<String> = conditions.iter().map(Condition::to_string).collect();
+                write!(f, "{}", operands.join(" OR "))
+            },
+            Condition::Not(condition) => match **condition {
+                Condition::All(_) | Condition::Any(_) => write!(f, "NOT ({})", condition),
+                _ => write!(f, "NOT {}", condition),
+            },
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Parser::new(value, words(value)).parse()
+    }
+}
+
+impl TryFrom<String> for Condition {
+    type Error = StockError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// Recursive descent over the words of a condition, `NOT` binding tighter
+/// than `AND` and `AND` tighter than `OR`.
+struct Parser<'a> {
+    text: &'a str,
+    words: Vec<String>,
+    next: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str, words: Vec<String>) -> Self {
+        Self { text, words, next: 0 }
+    }
+
+    fn parse(mut self) -> Result<Condition, StockError> {
+        let condition = self.any()?;
+
+        match self.take() {
+            Some(word) => Err(invalid(self.text, &format!("unexpected {}", word))),
+            None => Ok(condition),
+        }
+    }
+
+    fn take(&mut self) -> Option<String> {
+        let word = self.words.get(self.next).cloned();
+        self.next += 1;
+        word
+    }
+
+    /// Takes the next word if it is `keyword`, in any case.
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = self.words.get(self.next).is_some_and(|word| word.eq_ignore_ascii_case(keyword));
+
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn any(&mut self) -> Result<Condition, StockError> {
+        let mut conditions = vec![self.all()?];
+
+        while self.keyword("or") {
+            conditions.push(self.all()?);
+        }
+
+        Ok(match conditions.len() {
+            1 => conditions.remove(0),
+            _ => Condition::Any(conditions),
+        })
+    }
+
+    fn all(&mut self) -> Result<Condition, StockError> {
+        let mut conditions = vec![self.unary()?];
+
+        while self.keyword("and") {
+            conditions.push(self.unary()?);
+        }
+
+        Ok(match conditions.len() {
+            1 => conditions.remove(0),
+            _ => Condition::All(conditions),
+        })
+    }
+
+    fn unary(&mut self) -> Result<Condition, StockError> {
+        if self.keyword("not") {
+            return Ok(Condition::Not(Box::new(self.unary()?)));
+        }
+
+        if self.keyword("(") {
+            let condition = self.any()?;
+
+            return match self.keyword(")") {
+                true => Ok(condition),
+                false => Err(invalid(self.text, "missing )")),
+            };
+        }
+
+        self.term()
+    }
+
+    /// `SIGNAL`, `above|below VALUE` for the price, or `METRIC OP VALUE`.
+    fn term(&mut self) -> Result<Condition, StockError> {
+        let word = self.take()
+            .ok_or_else(|| invalid(self.text, "expected a condition"))?;
+
+        if let Ok(signal) = word.parse::<Signal>() {
+            return Ok(Condition::Cross(signal));
+        }
+
+        let (metric, comparison) = match word.parse::<Comparison>() {
+            Ok(comparison) => (Metric::Price, comparison),
+            Err(_) => {
+                let metric = word.parse::<Metric>()
+                    .map_err(|_| invalid(&word, "expected a metric (price, change_pct, rsi), a signal, above or below"))?;
+                let comparison = self.take()
+                    .ok_or_else(|| invalid(self.text, &format!("expected a comparison after {}", word)))?;
+
+                (metric, comparison.parse()?)
+            },
+        };
+
+        let value = self.take()
+            .ok_or_else(|| invalid(self.text, &format!("expected a value after {}", comparison)))?;
+        let value = value.parse::<f64>()
+            .map_err(|err| invalid(&value, &err.to_string()))?;
+
+        Ok(Condition::Compare { metric, comparison, value })
+    }
+}
+
+/// A condition on one symbol's quotes, written as `AAPL above 200`,
+/// `TLKM below 3000`, `BBCA change_pct > 3`, `AAPL macd_cross_up`, or such
+/// conditions combined: `AAPL price > 200 AND (change_pct > 1.5 OR NOT rsi < 70)`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "RuleEntry")]
+pub struct Rule {
+    /// The bare symbol, e.g. `AAPL`.
+    pub symbol: String,
+    pub condition: Condition,
+    /// Channels to notify when the rule fires; all of them when empty.
+    pub notify: Vec<Channel>,
+}
+
+/// A rule in the config file: either just the condition or a table that
+/// also picks the channels, `{ when = "AAPL above 200", notify = ["discord"] }`.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RuleEntry {
+    Condition(String),
+    Table {
+        when: String,
+        #[serde(default)]
+        notify: Vec<String>,
+    },
+}
+
+impl Rule {
+    fn new(symbol: &str, condition: Condition) -> Self {
+        Self {
+            symbol: symbol.to_uppercase().split(':').next().unwrap_or_default().to_string(),
+            condition,
+            notify: Vec::new(),
+        }
+    }
+
+    /// Whether `stock` satisfies the rule; never for other symbols or when
+    /// the metric isn't known yet. A crossing is satisfied for as long as
+    /// the symbol stays on the side it crossed into.
+    pub fn matches(&self, stock: &Stock) -> bool {
+        stock.symbol == self.symbol && self.condition.state(stock) == Some(true)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.symbol, self.condition)
+    }
+}
+
+impl FromStr for Rule {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let words = words(value);
+
+        let Some((symbol, condition)) = words.split_first() else {
+            return Err(invalid(value, "expected SYMBOL CONDITION, e.g. AAPL above 200"));
+        };
+
+        Ok(Self::new(symbol, Parser::new(value, condition.to_vec()).parse()?))
+    }
+}
+
+impl TryFrom<RuleEntry> for Rule {
+    type Error = StockError;
+
+    fn try_from(entry: RuleEntry) -> Result<Self, Self::Error> {
+        match entry {
+            RuleEntry::Condition(when) => when.parse(),
+            RuleEntry::Table { when, notify } => {
+                let notify = notify.iter()
+                    .map(|channel| channel.parse())
+                    .collect::<Result<Vec<Channel>, StockError>>()?;
+
+                Ok(Self { notify, ..when.parse()? })
+            },
+        }
+    }
+}
+
+/// Evaluates rules against incoming quotes. A rule fires when its condition
+/// starts to hold and stays quiet until the condition has cleared again and
+/// the cooloff since it last fired has passed, so a price hovering around a
+/// limit doesn't flood the notifiers. A crossing only fires once it has been
+/// seen on the other side first, not because it already holds at start-up.
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+    cooloff: Duration,
+    /// Per rule, whether its condition held at its symbol's last quote;
+    /// `None` until known.
+    active: Vec<Option<bool>>,
+    fired_at: HashMap<usize, Instant>,
+}
+
+impl RuleSet {
+    pub fn new(rules: Vec<Rule>, cooloff: Duration) -> Self {
+        let active = vec![None; rules.len()];
+
+        Self { rules, cooloff, active, fired_at: HashMap::new() }
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Updates the rule states with `stock` and returns the rules that fire.
+    pub fn evaluate(&mut self, stock: &Stock) -> Vec<&Rule> {
+        let now = Instant::now();
+        let mut fired = Vec::new();
+
+        for (index, rule) in self.rules.iter().enumerate() {
+            if rule.symbol != stock.symbol {
+                continue;
+            }
+
+            let state = rule.condition.state(stock);
+            let was_active = match std::mem::replace(&mut self.active[index], state) {
+                Some(was_active) => was_active,
+                None => rule.condition.has_crossing(),
+            };
+
+            if state != Some(true) || was_active {
+                continue;
+            }
+
+            let cooling_off = self.fired_at.get(&index)
+                .is_some_and(|fired_at| now.duration_since(*fired_at) < self.cooloff);
+
+            if cooling_off {
+                // Stay inactive so the rule fires once the cooloff is over; a
+                // crossing during the cooloff is dropped.
+                if !rule.condition.has_crossing() {
+                    self.active[index] = Some(false);
+                }
+                continue;
+            }
+
+            self.fired_at.insert(index, now);
+            fired.push(rule);
+        }
+
+        fired
+    }
+}
+
+/// The words of a rule, parentheses counting as words of their own.
+fn words(value: &str) -> Vec<String> {
+    value.replace('(', " ( ").replace(')', " ) ")
+        .split_whitespace()
+        .map(String::from)
+        .collect()
+}
+
+fn invalid(value: &str, reason: &str) -> StockError {
+    StockError::invalid("rule", format!("{}: {}", value, reason))
+}
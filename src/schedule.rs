@@ -0,0 +1,421 @@
+use std::{collections::{HashMap, HashSet}, fmt, str::FromStr, time::Duration};
+use chrono::{DateTime, Datelike, Local, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::{circuit::CircuitBreaker, cron::CronSchedule, market::{self, MarketStatus}, scraping::coingecko, symbol, watch::WatchOptions, StockError};
+
+/// How often symbols outside their hours are checked again.
+const RECHECK: Duration = Duration::from_secs(60);
+/// Between polls of a fund whose NAV is published once a day, unless its
+/// exchange has a schedule of its own.
+pub const NAV_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Longest interval between polls accepted from the config.
+pub const MAX_INTERVAL: Duration = Duration::from_secs(366 * 24 * 60 * 60);
+
+
+/// A daily window such as `09:00-16:00`. A window whose end is before its
+/// start runs past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ActiveHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl ActiveHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        match self.start <= self.end {
+            true => self.start <= time && time < self.end,
+            false => time >= self.start || time < self.end,
+        }
+    }
+}
+
+impl FromStr for ActiveHours {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parse = |time: &str| NaiveTime::parse_from_str(time.trim(), "%H:%M").ok();
+
+        value.split_once('-')
+            .and_then(|(start, end)| Some(Self { start: parse(start)?, end: parse(end)? }))
+            .ok_or_else(|| {
+                StockError::invalid("schedule", format!("{}: expected hours as HH:MM-HH:MM", value))
+            })
+    }
+}
+
+impl TryFrom<String> for ActiveHours {
+    type Error = StockError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for ActiveHours {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.start.format("%H:%M"), self.end.format("%H:%M"))
+    }
+}
+
+/// A length of time such as `90s`, `30m`, `1h30m` or `2d`; a bare number is
+/// seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct HumanDuration(pub Duration);
+
+impl FromStr for HumanDuration {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || StockError::invalid("duration", format!("{}: expected e.g. 90s, 30m, 1h30m or 2d", value));
+        let value = value.trim();
+
+        if let Ok(secs) = value.parse::<u64>() {
+            return Ok(Self(Duration::from_secs(secs)));
+        }
+
+        let mut secs = 0u64;
+        let mut digits = String::new();
+
+        for c in value.chars() {
+            if c.is_ascii_digit() {
+                digits.push(c);
+                continue;
+            }
+
+            let unit = match c.to_ascii_lowercase() {
+                's' => 1,
+                'm' => 60,
+                'h' => 60 * 60,
+                'd' => 24 * 60 * 60,
+                _ => return Err(invalid()),
+            };
+            let count: u64 = std::mem::take(&mut digits).parse().map_err(|_| invalid())?;
+
+            secs = count.checked_mul(unit).and_then(|part| secs.checked_add(part)).ok_or_else(invalid)?;
+        }
+
+        match digits.is_empty() && !value.is_empty() {
+            true => Ok(Self(Duration::from_secs(secs))),
+            false => Err(invalid()),
+        }
+    }
+}
+
+impl TryFrom<String> for HumanDuration {
+    type Error = StockError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let secs = self.0.as_secs();
+        let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+
+        match (hours, minutes, secs) {
+            (0, 0, secs) => write!(f, "{}s", secs),
+            (0, minutes, 0) => write!(f, "{}m", minutes),
+            (0, minutes, secs) => write!(f, "{}m{}s", minutes, secs),
+            (hours, 0, 0) => write!(f, "{}h", hours),
+            (hours, minutes, 0) => write!(f, "{}h{}m", hours, minutes),
+            (hours, minutes, secs) => write!(f, "{}h{}m{}s", hours, minutes, secs),
+        }
+    }
+}
+
+/// When to poll the symbols of one exchange, as a `[schedule.EXCHANGE]` table.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExchangeSchedule {
+    /// Overrides the global interval and schedule.
+    pub interval_secs: Option<u64>,
+    /// Poll at the minutes this cron expression matches, in `timezone`;
+    /// takes precedence over `interval_secs`.
+    pub cron: Option<CronSchedule>,
+    /// Only poll within these hours, Monday to Friday unless `weekends`.
+    pub hours: Option<ActiveHours>,
+    /// Time zone of `hours` and `cron`; defaults to the exchange's own, or UTC.
+    pub timezone: Option<Tz>,
+    pub weekends: bool,
+}
+
+/// Fails on an interval longer than [`MAX_INTERVAL`], naming it `what`.
+pub fn check_interval(interval: Duration, what: &str) -> Result<Duration, StockError> {
+    match interval > MAX_INTERVAL {
+        true => Err(StockError::invalid("duration", format!("{}: {}s is longer than the longest interval, {}s", what, interval.as_secs(), MAX_INTERVAL.as_secs()))),
+        false => Ok(interval),
+    }
+}
+
+/// A watchlist entry, `CODE` or `CODE@INTERVAL` such as `AAPL:NASDAQ@5s`,
+/// with its code normalized.
+pub fn parse_entry(entry: &str) -> Result<(String, Option<HumanDuration>), StockError> {
+    let Some((code, interval)) = entry.rsplit_once('@') else {
+        return Ok((symbol::normalize(entry)?, None));
+    };
+
+    let interval: HumanDuration = interval.parse()?;
+
+    if interval.0.is_zero() {
+        return Err(StockError::invalid("duration", format!("{}: the interval must be longer than 0s", entry)));
+    }
+
+    check_interval(interval.0, entry)?;
+
+    Ok((symbol::normalize(code)?, Some(interval)))
+}
+
+/// The key a code is scheduled under: its exchange, e.g. `IDX` for
+/// `BBCA:IDX`, or `CRYPTO` for coins.
+pub fn exchange_of(share_code: &str) -> String {
+    match coingecko::is_crypto_symbol(share_code) {
+        true => "CRYPTO".to_string(),
+        false => share_code.split_once(':').map(|(_, exchange)| exchange.to_uppercase()).unwrap_or_default(),
+    }
+}
+
+/// `interval` after `now`, or never when that is past the last date.
+fn after(now: DateTime<Utc>, interval: Duration) -> DateTime<Utc> {
+    chrono::Duration::from_std(interval).ok()
+        .and_then(|interval| now.checked_add_signed(interval))
+        .unwrap_or(DateTime::<Utc>::MAX_UTC)
+}
+
+/// Decides which symbols each poll fetches: those whose interval has passed
+/// since they were last polled or whose cron schedule has come round, within
+/// their active hours and, with `pause_when_closed`, while their market is
+/// open. A symbol's own interval wins over its exchange's settings, which win
+/// over the global ones.
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    interval: Duration,
+    /// Per-symbol intervals, keyed by code.
+    symbols: HashMap<String, Duration>,
+    /// Evaluated in local time, like cron.
+    cron: Option<CronSchedule>,
+    pause_when_closed: bool,
+    exchanges: HashMap<String, ExchangeSchedule>,
+    next_due: HashMap<String, DateTime<Utc>>,
+    paused: HashSet<String>,
+    circuit: CircuitBreaker,
+    /// Polls missed before a symbol's last quote is stale, 0 for never.
+    stale_after_polls: u32,
+}
+
+impl Scheduler {
+    pub fn new(options: &WatchOptions) -> Self {
+        Self {
+            interval: options.interval,
+            symbols: options.intervals.clone(),
+            cron: options.cron.clone(),
+            pause_when_closed: options.pause_when_closed,
+            exchanges: options.schedules.iter().map(|(exchange, schedule)| (exchange.to_uppercase(), schedule.clone())).collect(),
+            next_due: HashMap::new(),
+            paused: HashSet::new(),
+            circuit: options.circuit.clone(),
+            stale_after_polls: options.stale_after_polls,
+        }
+    }
+
+    fn timezone(exchange: &str, schedule: &ExchangeSchedule) -> Tz {
+        schedule.timezone
+            .or(market::calendar(exchange).map(|calendar| calendar.timezone))
+            .unwrap_or(Tz::UTC)
+    }
+
+    /// The fixed interval `share_code` is polled at, or `None` when it runs
+    /// on a cron schedule.
+    fn interval_of(&self, share_code: &str) -> Option<Duration> {
+        if let Some(interval) = self.symbols.get(share_code) {
+            return Some(*interval);
+        }
+
+        match self.exchanges.get(&exchange_of(share_code)) {
+            Some(ExchangeSchedule { cron: Some(_), .. }) => None,
+            Some(ExchangeSchedule { interval_secs: Some(secs), .. }) => Some(Duration::from_secs(*secs)),
+            _ if symbol::kind_of(share_code).is_daily() => Some(NAV_INTERVAL),
+            _ => match &self.cron {
+                Some(_) => None,
+                None => Some(self.interval),
+            },
+        }
+    }
+
+    /// When `share_code` is next due after a poll at `now`.
+    fn next_after(&self, share_code: &str, now: DateTime<Utc>) -> DateTime<Utc> {
+        if let Some(interval) = self.interval_of(share_code) {
+            return after(now, interval);
+        }
+
+        let exchange = exchange_of(share_code);
+        // Expressions are checked to match at some point when parsed.
+        let never = DateTime::<Utc>::MAX_UTC;
+
+        match self.exchanges.get(&exchange) {
+            Some(schedule @ ExchangeSchedule { cron: Some(cron), .. }) => cron.next_after(now, &Self::timezone(&exchange, schedule)),
+            _ => self.cron.as_ref().and_then(|cron| cron.next_after(now, &Local)),
+        }
+        .unwrap_or(never)
+    }
+
+    /// The tick after `due` for a poll at `now` that was due then, keeping
+    /// to `due + n * interval` however long the poll took. Ticks missed
+    /// along the way are skipped rather than polled back to back.
+    fn next_tick(share_code: &str, due: DateTime<Utc>, interval: Duration, now: DateTime<Utc>) -> DateTime<Utc> {
+        let (Ok(interval), Ok(behind)) = (chrono::Duration::from_std(interval), (now - due).to_std()) else {
+            return after(now, interval);
+        };
+
+        let missed = match interval.num_milliseconds() {
+            0 => 0,
+            millis => (behind.as_millis() / millis as u128).min(i32::MAX as u128 - 1) as i32,
+        };
+
+        if missed > 0 {
+            warn!(symbol = share_code, "{} fell {} polls behind, skipping them", share_code, missed);
+        }
+
+        interval.checked_mul(missed + 1)
+            .and_then(|ahead| due.checked_add_signed(ahead))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+
+    /// When `share_code` is first due: right away, unless it runs on a cron
+    /// schedule.
+    fn first_due(&self, share_code: &str, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self.interval_of(share_code) {
+            Some(_) => now,
+            None => self.next_after(share_code, now),
+        }
+    }
+
+    /// Why `share_code` shouldn't be polled at `now`, if it shouldn't.
+    fn inactive(&self, share_code: &str, now: DateTime<Utc>) -> Option<String> {
+        let exchange = exchange_of(share_code);
+
+        if let Some(schedule) = self.exchanges.get(&exchange) {
+            if let Some(hours) = schedule.hours {
+                let timezone = Self::timezone(&exchange, schedule);
+                let local = now.with_timezone(&timezone);
+                let weekend = matches!(local.weekday(), Weekday::Sat | Weekday::Sun);
+
+                if (weekend && !schedule.weekends) || !hours.contains(local.time()) {
+                    return Some(format!("outside its {} hours {} {}", exchange, hours, timezone));
+                }
+            }
+        }
+
+        if self.pause_when_closed && market::status(share_code, now) == Some(MarketStatus::Closed) {
+            return Some("while its market is closed".to_string());
+        }
+
+        None
+    }
+
+    /// The codes to fetch now, logging when one pauses or resumes. Codes
+    /// whose circuit is open are left out until it closes.
+    pub fn due(&mut self, codes: &[String]) -> Vec<String> {
+        self.due_at(codes, Utc::now())
+    }
+
+    /// [`Scheduler::due`] as of `now`.
+    pub fn due_at(&mut self, codes: &[String], now: DateTime<Utc>) -> Vec<String> {
+        let mut due = Vec::new();
+
+        for share_code in codes {
+            match (self.inactive(share_code, now), self.paused.contains(share_code)) {
+                (Some(reason), false) => {
+                    info!(symbol = share_code.as_str(), "Pausing {} {}", share_code, reason);
+                    self.paused.insert(share_code.clone());
+                    continue;
+                }
+                (Some(_), true) => continue,
+                (None, true) => {
+                    info!(symbol = share_code.as_str(), "Resuming {}", share_code);
+                    self.paused.remove(share_code);
+                    // Start its ticks over rather than count the pause as missed.
+                    self.next_due.remove(share_code);
+                }
+                (None, false) => {}
+            }
+
+            if self.circuit.open_until(share_code).is_some() {
+                // Its ticks start over once the circuit closes.
+                self.next_due.remove(share_code);
+                continue;
+            }
+
+            if !self.next_due.contains_key(share_code) {
+                let first_due = self.first_due(share_code, now);
+                self.next_due.insert(share_code.clone(), first_due);
+            }
+
+            if self.next_due[share_code] <= now {
+                due.push(share_code.clone());
+            }
+        }
+
+        due
+    }
+
+    /// Records that `codes` were just polled. Codes on an interval stay on
+    /// the ticks they started on, so slow polls don't push the next ones back.
+    pub fn polled(&mut self, codes: &[String]) {
+        self.polled_at(codes, Utc::now());
+    }
+
+    /// [`Scheduler::polled`] for a poll that finished at `now`.
+    pub fn polled_at(&mut self, codes: &[String], now: DateTime<Utc>) {
+        for share_code in codes {
+            let next_due = match (self.interval_of(share_code), self.next_due.get(share_code)) {
+                (Some(interval), Some(due)) => Self::next_tick(share_code, *due, interval, now),
+                _ => self.next_after(share_code, now),
+            };
+
+            self.next_due.insert(share_code.clone(), next_due);
+        }
+    }
+
+    /// How old the last quote of each of `codes` may get before it is stale:
+    /// `stale_after_polls` of its intervals. Paused codes and those on a cron
+    /// schedule are left out, as they aren't expected to be refreshed.
+    pub fn stale_after(&self, codes: &[String]) -> Vec<(String, Duration)> {
+        if self.stale_after_polls == 0 {
+            return Vec::new();
+        }
+
+        codes.iter()
+            .filter(|share_code| !self.paused.contains(share_code.as_str()))
+            .filter_map(|share_code| {
+                let interval = self.interval_of(share_code)?;
+
+                Some((share_code.clone(), interval.saturating_mul(self.stale_after_polls)))
+            })
+            .collect()
+    }
+
+    /// How long to wait before the next poll.
+    pub fn until_next(&self, codes: &[String]) -> Duration {
+        let now = Utc::now();
+        let wait = codes.iter()
+            .filter(|share_code| !self.paused.contains(share_code.as_str()))
+            .map(|share_code| match self.circuit.open_until(share_code).or(self.next_due.get(share_code).copied()) {
+                Some(next_due) => (next_due - now).to_std().unwrap_or(Duration::ZERO),
+                None => Duration::ZERO,
+            })
+            .min()
+            .unwrap_or(RECHECK);
+
+        match self.paused.is_empty() {
+            true => wait,
+            false => wait.min(RECHECK),
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::BTreeMap;
+use chrono::{Duration, NaiveDate, NaiveTime, Utc};
+use serde::Deserialize;
+
+use crate::{candle::Candle, http::Fetcher, scraping::{replay::Recorder, HistoryProvider, QuoteProvider}, Decimal, PriceRange, QuoteDetails, Stock, StockError};
+
+const BASE_URL: &str = "https://www.alphavantage.co/query";
+/// What the free tier allows, and the default cap of the `alphavantage`
+/// rate limit.
+pub const REQUESTS_PER_MINUTE: u32 = 5;
+/// How far back a `compact` history download reaches: 100 trading days.
+const COMPACT_HISTORY_DAYS: i64 = 140;
+
+
+/// Quotes and daily bars from the Alpha Vantage API, which needs a (free)
+/// API key. Responses carry no company name, so quotes are named `N/A`.
+#[derive(Debug, Clone, Default)]
+pub struct AlphaVantage {
+    fetcher: Fetcher,
+    api_key: Option<String>,
+    recorder: Option<Recorder>,
+}
+
+impl AlphaVantage {
+    /// Without an `api_key` every fetch fails with `MISSING_API_KEY`.
+    pub fn new(fetcher: Fetcher, api_key: Option<String>) -> Self {
+        Self { fetcher, api_key, recorder: None }
+    }
+
+    /// Saves every fetched quote response to `recorder` before parsing it.
+    pub fn record_to(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    fn api_key(&self) -> Result<&str, StockError> {
+        self.api_key.as_deref()
+            .ok_or_else(|| {
+                StockError::MissingApiKey("Alpha Vantage needs --alphavantage-api-key or ALPHAVANTAGE_API_KEY".to_string())
+            })
+    }
+}
+
+impl QuoteProvider for AlphaVantage {
+    fn name(&self) -> &str {
+        "alphavantage"
+    }
+
+    fn fetch(&self, symbol: &str) -> Result<Stock, StockError> {
+        let json_content = fetch_from_alpha_vantage(&self.fetcher, self.api_key()?, symbol)?;
+
+        if let Some(recorder) = &self.recorder {
+            recorder.save(symbol, "json", &json_content);
+        }
+
+        parse_stock_value(json_content, symbol)
+    }
+}
+
+impl HistoryProvider for AlphaVantage {
+    fn name(&self) -> &str {
+        "alphavantage"
+    }
+
+    fn fetch_history(&self, symbol: &str, from: NaiveDate, to: NaiveDate) -> Result<Vec<Candle>, StockError> {
+        let json_content = fetch_history_from_alpha_vantage(&self.fetcher, self.api_key()?, symbol, from)?;
+
+        parse_history(json_content, symbol, from, to)
+    }
+}
+
+/// The fields every response may carry in place of its data.
+#[derive(Debug, Deserialize)]
+struct ApiMessages {
+    #[serde(rename = "Error Message")]
+    error: Option<String>,
+    /// Rate limit notices, older and newer wording.
+    #[serde(rename = "Note")]
+    note: Option<String>,
+    #[serde(rename = "Information")]
+    information: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct QuoteResponse {
+    #[serde(rename = "Global Quote")]
+    quote: Option<GlobalQuote>,
+    #[serde(flatten)]
+    messages: ApiMessages,
+}
+
+#[derive(Debug, Deserialize)]
+struct GlobalQuote {
+    #[serde(rename = "03. high")]
+    high: Option<String>,
+    #[serde(rename = "04. low")]
+    low: Option<String>,
+    /// Missing when the symbol is unknown.
+    #[serde(rename = "05. price")]
+    price: Option<String>,
+    #[serde(rename = "06. volume")]
+    volume: Option<String>,
+    #[serde(rename = "08. previous close")]
+    previous_close: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryResponse {
+    #[serde(rename = "Time Series (Daily)")]
+    series: Option<BTreeMap<NaiveDate, DailyBar>>,
+    #[serde(flatten)]
+    messages: ApiMessages,
+}
+
+#[derive(Debug, Deserialize)]
+struct DailyBar {
+    #[serde(rename = "1. open")]
+    open: String,
+    #[serde(rename = "2. high")]
+    high: String,
+    #[serde(rename = "3. low")]
+    low: String,
+    #[serde(rename = "4. close")]
+    close: String,
+    #[serde(rename = "5. volume")]
+    volume: Option<String>,
+}
+
+impl ApiMessages {
+    fn check(self, stock: &str) -> Result<(), StockError> {
+        if let Some(err) = self.error {
+            return Err(StockError::SymbolNotFound(format!("{}: {}", stock, err)));
+        }
+
+        // Over the limit the API still answers 200, so this is reported as
+        // the 429 it stands for to have it retried.
+        match self.note.or(self.information) {
+            Some(notice) => Err(StockError::HttpStatus { status: 429, message: format!("Too Many Requests: {}", notice) }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Maps a Google-style `SYMBOL:EXCHANGE` code to Alpha Vantage's ticker and
+/// the currency it quotes in, e.g. `SAP:ETR` becomes `SAP.DEX` in EUR.
+pub fn to_alpha_vantage_symbol(stock: &str) -> Result<(String, &'static str), StockError> {
+    let stock = stock.to_uppercase();
+    let (symbol, exchange) = stock.split_once(':').unwrap_or((stock.as_str(), ""));
+
+    let (suffix, currency) = match exchange {
+        "NASDAQ" | "NYSE" | "NYSEARCA" | "NYSEAMERICAN" | "BATS" => ("", "USD"),
+        // London prices are in pence.
+        "LON" => (".LON", "GBX"),
+        "TSE" => (".TRT", "CAD"),
+        "ETR" => (".DEX", "EUR"),
+        "BOM" => (".BSE", "INR"),
+        "SHA" => (".SHH", "CNY"),
+        "SHE" => (".SHZ", "CNY"),
+        _ => {
+            return Err(StockError::UnsupportedSymbol(format!("Alpha Vantage has no quotes for {}", stock)));
+        }
+    };
+
+    Ok((format!("{}{}", symbol, suffix), currency))
+}
+
+fn number(value: &str) -> Result<Decimal, StockError> {
+    value.trim().parse()
+        .map_err(|_| {
+            StockError::PriceParse(value.to_string())
+        })
+}
+
+fn parse_json<T: for<'de> Deserialize<'de>>(json_content: &str) -> Result<T, StockError> {
+    serde_json::from_str(json_content)
+        .map_err(|err| {
+            StockError::Response(err.into())
+        })
+}
+
+/// Parses a `GLOBAL_QUOTE` response. Unknown symbols come back as an empty
+/// quote.
+pub fn parse_stock_value(json_content: String, stock: &str) -> Result<Stock, StockError> {
+    let (_, currency) = to_alpha_vantage_symbol(stock)?;
+    let response: QuoteResponse = parse_json(&json_content)?;
+    response.messages.check(stock)?;
+
+    let quote = response.quote
+        .filter(|quote| quote.price.is_some())
+        .ok_or_else(|| {
+            StockError::SymbolNotFound(stock.to_string())
+        })?;
+
+    let stock_code = stock
+        .to_uppercase()
+        .split(":")
+        .next()
+        .unwrap_or(stock)
+        .to_string();
+
+    let optional = |value: &Option<String>| value.as_deref().and_then(|value| number(value).ok());
+    let details = QuoteDetails {
+        previous_close: optional(&quote.previous_close),
+        day_range: optional(&quote.low).zip(optional(&quote.high)).map(|(low, high)| PriceRange { low, high }),
+        volume: quote.volume.as_deref().and_then(|volume| volume.trim().parse().ok()),
+        ..QuoteDetails::default()
+    };
+
+    Ok(Stock::new(stock_code, "N/A".to_string(), number(quote.price.as_deref().unwrap_or_default())?)
+        .with_currency(currency.to_string())
+        .with_details(details))
+}
+
+pub fn fetch_from_alpha_vantage(fetcher: &Fetcher, api_key: &str, stock: &str) -> Result<String, StockError> {
+    let (symbol, _) = to_alpha_vantage_symbol(stock)?;
+
+    fetcher.fetch_page(&format!("{}?function=GLOBAL_QUOTE&symbol={}&apikey={}", BASE_URL, symbol, api_key))
+}
+
+/// Parses the daily bars of a `TIME_SERIES_DAILY` response between `from`
+/// and `to`, oldest first.
+pub fn parse_history(json_content: String, stock: &str, from: NaiveDate, to: NaiveDate) -> Result<Vec<Candle>, StockError> {
+    let response: HistoryResponse = parse_json(&json_content)?;
+    response.messages.check(stock)?;
+
+    let series = response.series
+        .ok_or_else(|| {
+            StockError::SymbolNotFound(stock.to_string())
+        })?;
+
+    series.range(from..=to)
+        .map(|(date, bar)| {
+            Ok(Candle {
+                timestamp: date.and_time(NaiveTime::MIN).and_utc(),
+                open: number(&bar.open)?,
+                high: number(&bar.high)?,
+                low: number(&bar.low)?,
+                close: number(&bar.close)?,
+                volume: bar.volume.as_deref().and_then(|volume| volume.trim().parse().ok()),
+            })
+        })
+        .collect()
+}
+
+/// Asks for the full series, a premium feature, only when `from` is older
+/// than the free compact one reaches.
+pub fn fetch_history_from_alpha_vantage(fetcher: &Fetcher, api_key: &str, stock: &str, from: NaiveDate) -> Result<String, StockError> {
+    let (symbol, _) = to_alpha_vantage_symbol(stock)?;
+    let output_size = match Utc::now().date_naive() - from {
+        age if age > Duration::days(COMPACT_HISTORY_DAYS) => "full",
+        _ => "compact",
+    };
+
+    fetcher.fetch_page(&format!("{}?function=TIME_SERIES_DAILY&symbol={}&outputsize={}&apikey={}", BASE_URL, symbol, output_size, api_key))
+}
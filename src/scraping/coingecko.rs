@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use serde::Deserialize;
+
+use crate::{http::Fetcher, scraping::QuoteProvider, Decimal, Stock, StockError};
+
+const BASE_URL: &str = "https://api.coingecko.com/api/v3";
+
+/// Tickers resolved without a search request, as `(ticker, coin id, name)`.
+const KNOWN_COINS: [(&str, &str, &str); 16] = [
+    ("BTC", "bitcoin", "Bitcoin"),
+    ("ETH", "ethereum", "Ethereum"),
+    ("USDT", "tether", "Tether"),
+    ("BNB", "binancecoin", "BNB"),
+    ("SOL", "solana", "Solana"),
+    ("USDC", "usd-coin", "USDC"),
+    ("XRP", "ripple", "XRP"),
+    ("DOGE", "dogecoin", "Dogecoin"),
+    ("ADA", "cardano", "Cardano"),
+    ("TRX", "tron", "TRON"),
+    ("AVAX", "avalanche-2", "Avalanche"),
+    ("DOT", "polkadot", "Polkadot"),
+    ("LINK", "chainlink", "Chainlink"),
+    ("MATIC", "matic-network", "Polygon"),
+    ("LTC", "litecoin", "Litecoin"),
+    ("XLM", "stellar", "Stellar"),
+];
+
+
+/// Cryptocurrency prices from the free CoinGecko API.
+///
+/// Accepts `TICKER-CURRENCY` codes such as `BTC-USD` and `crypto:<coin id>`
+/// codes such as `crypto:bitcoin` (priced in USD).
+#[derive(Debug, Clone, Default)]
+pub struct CoinGecko {
+    fetcher: Fetcher,
+}
+
+impl CoinGecko {
+    pub fn new(fetcher: Fetcher) -> Self {
+        Self { fetcher }
+    }
+}
+
+impl QuoteProvider for CoinGecko {
+    fn name(&self) -> &str {
+        "coingecko"
+    }
+
+    fn fetch(&self, symbol: &str) -> Result<Stock, StockError> {
+        let coin = resolve(&self.fetcher, symbol)?;
+        let json_content = self.fetcher.fetch_page(&format!("{}/simple/price?ids={}&vs_currencies={}", BASE_URL, coin.id, coin.vs_currency.to_lowercase()))?;
+
+        parse_stock_value(json_content, &coin)
+    }
+}
+
+/// Whether `code` should be priced by CoinGecko rather than an equity source.
+pub fn is_crypto_symbol(code: &str) -> bool {
+    if code.to_lowercase().starts_with("crypto:") {
+        return true;
+    }
+
+    match code.split_once('-') {
+        Some((ticker, currency)) => {
+            (2..=10).contains(&ticker.len())
+                && ticker.chars().all(|c| c.is_ascii_alphanumeric())
+                && currency.len() == 3
+                && currency.chars().all(|c| c.is_ascii_alphabetic())
+        }
+        None => false,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coin {
+    pub symbol: String,
+    pub id: String,
+    pub name: String,
+    pub vs_currency: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    coins: Vec<SearchCoin>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchCoin {
+    id: String,
+    name: String,
+    symbol: String,
+}
+
+/// Maps a watchlist code to a CoinGecko coin id, searching the API for
+/// tickers that aren't in the bundled table.
+pub fn resolve(fetcher: &Fetcher, code: &str) -> Result<Coin, StockError> {
+    if let Some(id) = code.strip_prefix("crypto:").or_else(|| code.strip_prefix("CRYPTO:")) {
+        let id = id.to_lowercase();
+
+        return Ok(Coin { symbol: id.to_uppercase(), name: id.clone(), id, vs_currency: "USD".to_string() });
+    }
+
+    let (ticker, vs_currency) = code.split_once('-')
+        .ok_or_else(|| {
+            StockError::invalid("symbol", format!("expected TICKER-CURRENCY or crypto:<id>, got {}", code))
+        })?;
+    let ticker = ticker.to_uppercase();
+    let vs_currency = vs_currency.to_uppercase();
+    let symbol = format!("{}-{}", ticker, vs_currency);
+
+    if let Some((_, id, name)) = KNOWN_COINS.iter().find(|(known, _, _)| *known == ticker) {
+        return Ok(Coin { symbol, id: id.to_string(), name: name.to_string(), vs_currency });
+    }
+
+    let json_content = fetcher.fetch_page(&format!("{}/search?query={}", BASE_URL, ticker.to_lowercase()))?;
+    let response: SearchResponse = serde_json::from_str(&json_content)
+        .map_err(|err| {
+            StockError::Response(err.into())
+        })?;
+
+    response.coins.into_iter()
+        .find(|coin| coin.symbol.to_uppercase() == ticker)
+        .map(|coin| Coin { symbol, id: coin.id, name: coin.name, vs_currency })
+        .ok_or_else(|| {
+            StockError::SymbolNotFound(code.to_string())
+        })
+}
+
+pub fn parse_stock_value(json_content: String, coin: &Coin) -> Result<Stock, StockError> {
+    let response: HashMap<String, HashMap<String, Decimal>> = serde_json::from_str(&json_content)
+        .map_err(|err| {
+            StockError::Response(err.into())
+        })?;
+
+    let price = response.get(&coin.id)
+        .and_then(|prices| prices.get(&coin.vs_currency.to_lowercase()))
+        .copied()
+        .ok_or_else(|| {
+            StockError::SymbolNotFound(format!("{} in {}", coin.id, coin.vs_currency))
+        })?;
+
+    Ok(Stock::new(coin.symbol.clone(), coin.name.clone(), price).with_currency(coin.vs_currency.clone()))
+}
@@ -0,0 +1,248 @@
+#[cfg(feature = "streaming")]
+use std::{collections::HashMap, io::ErrorKind, time::Duration};
+#[cfg(feature = "streaming")]
+use chrono::DateTime;
+use serde::Deserialize;
+#[cfg(feature = "streaming")]
+use tracing::{info, warn};
+#[cfg(feature = "streaming")]
+use tungstenite::{stream::MaybeTlsStream, Message};
+
+use crate::{http::Fetcher, scraping::{replay::Recorder, QuoteProvider, StreamingProvider}, shutdown::Shutdown, Decimal, PriceRange, QuoteDetails, Stock, StockError};
+
+const BASE_URL: &str = "https://finnhub.io/api/v1";
+#[cfg(feature = "streaming")]
+const STREAM_URL: &str = "wss://ws.finnhub.io";
+/// What the free tier allows, and the default cap of the `finnhub` rate
+/// limit.
+pub const REQUESTS_PER_MINUTE: u32 = 60;
+
+
+/// Quotes from the Finnhub API, polled over REST or pushed trade by trade
+/// over its WebSocket, with a (free) API key. The free tier covers US
+/// listings only and responses carry no company name.
+#[derive(Debug, Clone, Default)]
+pub struct Finnhub {
+    fetcher: Fetcher,
+    api_key: Option<String>,
+    recorder: Option<Recorder>,
+}
+
+impl Finnhub {
+    /// Without an `api_key` every fetch fails with `MISSING_API_KEY`.
+    pub fn new(fetcher: Fetcher, api_key: Option<String>) -> Self {
+        Self { fetcher, api_key, recorder: None }
+    }
+
+    /// Saves every fetched quote response to `recorder` before parsing it.
+    pub fn record_to(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    fn api_key(&self) -> Result<&str, StockError> {
+        self.api_key.as_deref()
+            .ok_or_else(|| {
+                StockError::MissingApiKey("Finnhub needs --finnhub-api-key or FINNHUB_API_KEY".to_string())
+            })
+    }
+}
+
+impl QuoteProvider for Finnhub {
+    fn name(&self) -> &str {
+        "finnhub"
+    }
+
+    fn fetch(&self, symbol: &str) -> Result<Stock, StockError> {
+        let json_content = fetch_from_finnhub(&self.fetcher, self.api_key()?, symbol)?;
+
+        if let Some(recorder) = &self.recorder {
+            recorder.save(symbol, "json", &json_content);
+        }
+
+        parse_stock_value(json_content, symbol)
+    }
+}
+
+impl StreamingProvider for Finnhub {
+    fn name(&self) -> &str {
+        "finnhub"
+    }
+
+    fn stream(&self, symbols: &[String], shutdown: &Shutdown, on_quote: &mut dyn FnMut(&str, Stock) -> bool) -> Result<(), StockError> {
+        stream_trades(self.api_key()?, symbols, shutdown, on_quote)
+    }
+}
+
+/// A `/quote` response; unknown symbols come back with every field 0.
+#[derive(Debug, Deserialize)]
+struct QuoteResponse {
+    #[serde(rename = "c")]
+    price: Decimal,
+    #[serde(rename = "h")]
+    high: Decimal,
+    #[serde(rename = "l")]
+    low: Decimal,
+    #[serde(rename = "pc")]
+    previous_close: Decimal,
+    #[serde(rename = "t")]
+    timestamp: i64,
+}
+
+/// Maps a Google-style `SYMBOL:EXCHANGE` code to Finnhub's ticker, e.g.
+/// `AAPL:NASDAQ` becomes `AAPL`.
+pub fn to_finnhub_symbol(stock: &str) -> Result<String, StockError> {
+    let stock = stock.to_uppercase();
+    let (symbol, exchange) = stock.split_once(':').unwrap_or((stock.as_str(), ""));
+
+    match exchange {
+        "NASDAQ" | "NYSE" | "NYSEARCA" | "NYSEAMERICAN" | "BATS" => Ok(symbol.to_string()),
+        _ => Err(StockError::UnsupportedSymbol(format!("Finnhub has no quotes for {}", stock))),
+    }
+}
+
+fn stock_code(stock: &str) -> String {
+    stock
+        .to_uppercase()
+        .split(":")
+        .next()
+        .unwrap_or(stock)
+        .to_string()
+}
+
+pub fn parse_stock_value(json_content: String, stock: &str) -> Result<Stock, StockError> {
+    let quote: QuoteResponse = serde_json::from_str(&json_content)
+        .map_err(|err| {
+            StockError::Response(err.into())
+        })?;
+
+    if quote.timestamp == 0 {
+        return Err(StockError::SymbolNotFound(stock.to_string()));
+    }
+
+    let details = QuoteDetails {
+        previous_close: Some(quote.previous_close),
+        day_range: Some(PriceRange { low: quote.low, high: quote.high }),
+        ..QuoteDetails::default()
+    };
+
+    Ok(Stock::new(stock_code(stock), "N/A".to_string(), quote.price)
+        .with_currency("USD".to_string())
+        .with_details(details))
+}
+
+pub fn fetch_from_finnhub(fetcher: &Fetcher, api_key: &str, stock: &str) -> Result<String, StockError> {
+    fetcher.fetch_page(&format!("{}/quote?symbol={}&token={}", BASE_URL, to_finnhub_symbol(stock)?, api_key))
+}
+
+/// A message on the trade stream.
+#[cfg(feature = "streaming")]
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum StreamMessage {
+    Trade { data: Vec<Trade> },
+    Ping,
+    Error { msg: String },
+}
+
+#[cfg(feature = "streaming")]
+#[derive(Debug, Deserialize)]
+struct Trade {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "p")]
+    price: Decimal,
+    /// Milliseconds since the epoch.
+    #[serde(rename = "t")]
+    timestamp: i64,
+}
+
+/// Passes on the last trade of every symbol in each message, until
+/// `on_quote` refuses one, `shutdown` is requested or the connection drops.
+#[cfg(feature = "streaming")]
+fn stream_trades(api_key: &str, codes: &[String], shutdown: &Shutdown, on_quote: &mut dyn FnMut(&str, Stock) -> bool) -> Result<(), StockError> {
+    let stream_error = |err: tungstenite::Error| StockError::Stream(err.into());
+
+    let mut symbols = HashMap::new();
+    for code in codes {
+        match to_finnhub_symbol(code) {
+            Ok(symbol) => {
+                symbols.insert(symbol, code.as_str());
+            }
+            Err(err) => warn!(symbol = %code, "Not streaming {}: {}", code, err),
+        }
+    }
+
+    let (mut socket, _) = tungstenite::connect(format!("{}/?token={}", STREAM_URL, api_key)).map_err(stream_error)?;
+
+    // Wake up every second to notice a shutdown between trades.
+    let timeout = Some(Duration::from_secs(1));
+    let timeout_set = match socket.get_mut() {
+        MaybeTlsStream::Plain(stream) => stream.set_read_timeout(timeout),
+        MaybeTlsStream::NativeTls(stream) => stream.get_mut().set_read_timeout(timeout),
+        _ => Ok(()),
+    };
+    timeout_set.map_err(|err| StockError::Stream(err.into()))?;
+
+    for symbol in symbols.keys() {
+        let subscribe = serde_json::json!({"type": "subscribe", "symbol": symbol}).to_string();
+        socket.send(Message::text(subscribe)).map_err(stream_error)?;
+    }
+
+    info!("Streaming {} symbols from Finnhub", symbols.len());
+
+    while !shutdown.is_requested() {
+        let text = match socket.read() {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) => return Err(StockError::Stream("closed by Finnhub".into())),
+            Ok(_) => continue,
+            Err(tungstenite::Error::Io(err)) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+            Err(err) => return Err(stream_error(err)),
+        };
+
+        let message: StreamMessage = match serde_json::from_str(text.as_str()) {
+            Ok(message) => message,
+            Err(err) => {
+                warn!("Skipping unreadable Finnhub message: {}", err);
+                continue;
+            }
+        };
+
+        let trades = match message {
+            StreamMessage::Trade { data } => data,
+            StreamMessage::Ping => continue,
+            StreamMessage::Error { msg } => return Err(StockError::Stream(msg.into())),
+        };
+
+        // Trades arrive oldest first; only the latest price matters.
+        let mut latest = HashMap::new();
+        for trade in trades {
+            latest.insert(trade.symbol.clone(), trade);
+        }
+
+        for (symbol, trade) in latest {
+            let Some(code) = symbols.get(symbol.as_str()) else {
+                continue;
+            };
+
+            let stock = Stock::new(stock_code(code), "N/A".to_string(), trade.price).with_currency("USD".to_string());
+            let stock = match DateTime::from_timestamp_millis(trade.timestamp) {
+                Some(traded_at) => stock.with_fetched_at(traded_at),
+                None => stock,
+            };
+
+            if !on_quote(code, stock) {
+                return Ok(());
+            }
+        }
+    }
+
+    let _ = socket.close(None);
+
+    Ok(())
+}
+
+#[cfg(not(feature = "streaming"))]
+fn stream_trades(_api_key: &str, _codes: &[String], _shutdown: &Shutdown, _on_quote: &mut dyn FnMut(&str, Stock) -> bool) -> Result<(), StockError> {
+    Err(StockError::FeatureDisabled("--stream requires building with --features streaming".to_string()))
+}
@@ -1,207 +1,557 @@
-use std::{collections::HashMap, error::Error, fmt::{self, Debug}, sync::{Arc, RwLock}};
-use structopt::StructOpt;
+use std::{fmt, sync::{atomic::{AtomicBool, Ordering}, Arc, LazyLock}};
+use regex::Regex;
+use scraper::{Html, Selector};
+use serde::Serialize;
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::{http::{BoxFuture, Fetcher, HttpFetcher}, market::MarketStatus, price::{self, Price}, scraping::{self, replay::Recorder, selectors::{PageSelectors, Selectors}, FundamentalsProvider, NewsProvider, QuoteProvider}, symbol, Decimal, Fundamentals, NewsItem, PriceRange, QuoteDetails, QuoteKind, Stock, StockError};
+
+const BASE_URL: &str = "https://www.google.com/finance/quote/";
+const SEARCH_URL: &str = "https://www.google.com/finance?q=";
+/// What links on its pages, such as `./quote/AAPL:NASDAQ`, are relative to.
+const PAGE_BASE_URL: &str = "https://www.google.com/finance/";
+
+/// Set once a quote needed a fallback, so the layout change is only
+/// reported once.
+static FALLBACK_REPORTED: AtomicBool = AtomicBool::new(false);
+
+/// The marker Google puts before the price outside regular hours.
+static MARKET_STATUS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r">\s*(Pre-market|After Hours|Closed)\s*:").expect("valid market status pattern"));
+static LAST_PRICE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"data-last-price="(-?[\d.]+)""#).expect("valid price pattern"));
+static CURRENCY_CODE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"data-currency-code="([A-Z]{3})""#).expect("valid currency pattern"));
+static JSON_PRICE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#""price"\s*:\s*"?(-?[\d.,]+)"#).expect("valid price pattern"));
 
+#[derive(Debug, Clone, Default)]
+pub struct GoogleFinance<F = Fetcher> {
+    fetcher: F,
+    selectors: Selectors,
+    recorder: Option<Recorder>,
+}
+
+impl<F: HttpFetcher> GoogleFinance<F> {
+    pub fn new(fetcher: F) -> Self {
+        Self { fetcher, selectors: Selectors::default(), recorder: None }
+    }
+
+    /// Reads pages with `selectors`, picking up any reload.
+    pub fn with_selectors(mut self, selectors: Selectors) -> Self {
+        self.selectors = selectors;
+        self
+    }
+
+    /// Saves every fetched page to `recorder` before parsing it.
+    pub fn record_to(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Reads the quote of `symbol` from its page, falling back to rendering
+    /// the page in a browser, when the fetcher has one, if the price isn't in
+    /// the page as served.
+    fn read_quote(&self, symbol: &str, html_content: String) -> Result<Stock, StockError> {
+        if let Some(recorder) = &self.recorder {
+            recorder.save(symbol, "html", &html_content);
+        }
+
+        let selectors = self.selectors.google();
 
-#[derive(StructOpt, Debug, Clone)]
-struct CLI {
-    #[structopt(short, long, default_value = "AAPL:NASDAQ,BBCA:IDX,TLKM:IDX")]
-    codes: String,
+        match parse_stock_value(html_content, symbol, &selectors) {
+            Err(err @ StockError::Selector { .. }) => {
+                let Some(rendered) = self.fetcher.render_page(&quote_url(symbol)) else {
+                    return Err(err);
+                };
 
-    #[structopt(short, long, default_value = "10")]
-    interval: u64,
+                debug!(symbol, "No price in the page for {}, rendering it", symbol);
 
-    #[structopt(short, long)]
-    use_async: bool,
+                let html_content = rendered?;
+
+                if let Some(recorder) = &self.recorder {
+                    recorder.save(symbol, "html", &html_content);
+                }
+
+                parse_stock_value(html_content, symbol, &selectors)
+            }
+            result => result,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Default)]
-struct Stock {
-    symbol: String,
-    company_name: String,
-    price: f64,
-    status: String,
+impl<F: HttpFetcher> QuoteProvider for GoogleFinance<F> {
+    fn name(&self) -> &str {
+        "google"
+    }
+
+    fn fetch(&self, symbol: &str) -> Result<Stock, StockError> {
+        let html_content = fetch_from_google_finance(&self.fetcher, symbol)?;
+
+        self.read_quote(symbol, html_content)
+    }
+
+    /// Downloads the page without blocking, then parses it, and renders it
+    /// when that is needed, on tokio's blocking pool.
+    fn async_fetch<'a>(self: Arc<Self>, symbol: &'a str) -> BoxFuture<'a, Result<Stock, StockError>>
+    where
+        Self: 'static,
+    {
+        let symbol = symbol.to_string();
+
+        Box::pin(async move {
+            let html_content = self.fetcher.async_fetch_page(&quote_url(&symbol)).await?;
+
+            scraping::blocking(move || self.read_quote(&symbol, html_content)).await?
+        })
+    }
 }
 
-impl Stock {
-    fn new(symbol: String, company_name: String, price: f64, status: String) -> Self {
-        Self { symbol, company_name, price, status }
+/// From the same quote page as the price, only equities have them.
+impl<F: HttpFetcher> FundamentalsProvider for GoogleFinance<F> {
+    fn name(&self) -> &str {
+        "google"
+    }
+
+    fn fetch_fundamentals(&self, symbol: &str) -> Result<Fundamentals, StockError> {
+        let html_content = fetch_from_google_finance(&self.fetcher, symbol)?;
+
+        Ok(parse_fundamentals(&html_content, &self.selectors.google()))
     }
 }
 
-impl fmt::Display for Stock {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} - {}: ${} ({})", self.symbol, self.company_name, self.price, self.status)
+impl<F: HttpFetcher> NewsProvider for GoogleFinance<F> {
+    fn name(&self) -> &str {
+        "google"
+    }
+
+    fn fetch_news(&self, symbol: &str) -> Result<Vec<NewsItem>, StockError> {
+        let html_content = fetch_from_google_finance(&self.fetcher, symbol)?;
+
+        Ok(parse_news(&html_content, symbol, &self.selectors.google()))
     }
 }
 
-#[derive(Debug)]
-struct StockError {
-    code: String,
-    message: String,
+/// A way of reading a value off the quote page. Each value has its own
+/// chain, tried in order until one matches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Strategy {
+    /// The text of the first element matching a CSS selector.
+    Selector(String),
+    /// The `data-last-price` / `data-currency-code` attributes on the page.
+    EmbeddedData,
+    /// A schema.org JSON-LD block.
+    JsonLd,
+    /// The page `<title>`.
+    Title,
+    /// A pattern match over the raw HTML, the last resort.
+    Regex,
 }
 
-impl fmt::Display for StockError {
+impl fmt::Display for Strategy {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}: {}", self.code, self.message)
+        match self {
+            Strategy::Selector(selector) => write!(f, "selector {}", selector),
+            Strategy::EmbeddedData => write!(f, "embedded data"),
+            Strategy::JsonLd => write!(f, "JSON-LD"),
+            Strategy::Title => write!(f, "page title"),
+            Strategy::Regex => write!(f, "regex"),
+        }
     }
 }
 
-impl StockError {
-    fn new(code: String, message: String) -> Self {
-        Self { code, message }
+/// Which strategies found the price and company name of a quote page.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ParseDiagnostics {
+    /// `None` when no strategy found a price.
+    pub price: Option<Strategy>,
+    /// `None` when the name fell back to `N/A`.
+    pub company_name: Option<Strategy>,
+    /// Price strategies that were tried without a match, in order.
+    pub failed: Vec<Strategy>,
+}
+
+impl ParseDiagnostics {
+    /// Whether the preferred price selector no longer matches, which usually
+    /// means Google changed the page.
+    pub fn is_degraded(&self) -> bool {
+        !self.failed.is_empty()
     }
 }
 
-impl Error for StockError {}
+impl fmt::Display for ParseDiagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.price {
+            Some(strategy) => write!(f, "price via {}", strategy)?,
+            None => write!(f, "no price found")?,
+        }
 
-pub fn fetch_stock_price() {
-    let args = CLI::from_args();
+        if !self.failed.is_empty() {
+            let failed = self.failed.iter().map(Strategy::to_string).collect::<Vec<String>>();
+            write!(f, " (no match: {})", failed.join(", "))?;
+        }
 
-    match args.use_async {
-        true => async_determine_stock_status(&args),
-        false => determine_stock_status(args),
+        match &self.company_name {
+            Some(strategy) => write!(f, ", company name via {}", strategy),
+            None => write!(f, ", no company name found"),
+        }
     }
 }
 
-fn async_determine_stock_status(args: &CLI) {
-    let data: Arc<RwLock<HashMap<String, Stock>>> = Arc::new(RwLock::new(HashMap::new()));
+pub fn parse_stock_value(html_content: String, stock: &str, selectors: &PageSelectors) -> Result<Stock, StockError> {
+    let (result, diagnostics) = parse_with_diagnostics(&html_content, stock, selectors);
 
-    loop {
-        let cloned_args: CLI = args.clone();
-        let codes = cloned_args.codes.split(",").collect::<Vec<&str>>().into_iter().map(|code| {
-            code.to_string()
-        }).collect::<Vec<String>>();
+    debug!(symbol = stock, "Parsed {}: {}", stock, diagnostics);
 
-        for share_code in codes {
-            let local_data = Arc::clone(&data);
+    if result.is_ok() && diagnostics.is_degraded() && !FALLBACK_REPORTED.swap(true, Ordering::Relaxed) {
+        warn!("Google Finance layout changed, {} for {}", diagnostics, stock);
+    }
+
+    result
+}
 
-            std::thread::spawn(move || {
-                let html_content = fetch_from_google_finance(share_code.as_str()).unwrap();
-                let mut new_stock = parse_stock_value(html_content, share_code.as_str()).unwrap();
-                let default = &Stock::new("".to_string(), "".to_string(), 0f64, "".to_string());
+/// Parses a quote page, trying selectors first, then the data Google embeds
+/// in the page and finally a regex over the raw HTML, and reports which one
+/// worked.
+pub fn parse_with_diagnostics(html_content: &str, stock: &str, selectors: &PageSelectors) -> (Result<Stock, StockError>, ParseDiagnostics) {
+    let document = Html::parse_document(html_content);
+    let json_ld = json_ld(&document);
+    let mut diagnostics = ParseDiagnostics::default();
+
+    let price_strategies = selectors.price.iter()
+        .map(|selector| Strategy::Selector(selector.clone()))
+        .chain([Strategy::EmbeddedData, Strategy::JsonLd, Strategy::Regex]);
+
+    let mut stock_value = None;
+
+    for strategy in price_strategies {
+        let found = match &strategy {
+            Strategy::Selector(selector) => select_text(&document, selector).and_then(|text| price::parse_price(&text)),
+            Strategy::EmbeddedData => embedded_price(&document),
+            Strategy::JsonLd => json_ld.iter().find_map(json_ld_price),
+            Strategy::Regex => regex_price(html_content),
+            _ => None,
+        };
+
+        match found {
+            Some(found) => {
+                diagnostics.price = Some(strategy);
+                stock_value = Some(found);
+                break;
+            }
+            None => diagnostics.failed.push(strategy),
+        }
+    }
 
-                let past_stock = local_data.clone()
-                    .read()
-                    .unwrap()
-                    .get(share_code.as_str())
-                    .unwrap_or(default)
-                    .clone();
+    let company_strategies = selectors.company.iter()
+        .map(|selector| Strategy::Selector(selector.clone()))
+        .chain([Strategy::JsonLd, Strategy::Title]);
 
-                new_stock.status = get_stock_valuation_status(&new_stock.clone(), &past_stock);
-                
-                local_data.write().unwrap().insert(share_code, new_stock.clone());
+    let mut company_name = None;
 
-                println!("New status = {:?}", new_stock.clone());
+    for strategy in company_strategies {
+        let found = match &strategy {
+            Strategy::Selector(selector) => select_text(&document, selector),
+            Strategy::JsonLd => json_ld.iter().find_map(|value| find_key(value, "name")?.as_str().map(str::to_string)),
+            Strategy::Title => title_company(&document),
+            _ => None,
+        };
 
-                new_stock
-            });
+        if found.is_some() {
+            diagnostics.company_name = Some(strategy);
+            company_name = found;
+            break;
         }
-
-        std::thread::sleep(std::time::Duration::from_secs(args.interval));
     }
 
-}
+    let Some(Price { currency, value }) = stock_value else {
+        let err = StockError::Selector { symbol: stock.to_string(), message: diagnostics.to_string() };
+        return (Err(err), diagnostics);
+    };
 
-fn determine_stock_status(args: CLI) {
-    let mut past_data: HashMap<String, Stock> = HashMap::new();
+    let stock_code = stock
+        .to_uppercase()
+        .split(":")
+        .next()
+        .unwrap_or(stock)
+        .to_string();
 
-    loop {
-        args.clone().codes.split(",").for_each(|share_code| {
-            let html_content = fetch_from_google_finance(share_code).unwrap();
-            let mut new_stock = parse_stock_value(html_content, share_code).unwrap();
-    
-            new_stock.status = match past_data.get(share_code) {
-                Some(past) => get_stock_valuation_status(&new_stock, past),
-                None => "up".to_string(),
-            };
-    
-            println!("New Status = {:?}", new_stock);
-            past_data.insert(share_code.to_string(), new_stock);    
-        });
+    // Currency pages show the rate without a currency; it is in the pair's
+    // quote currency.
+    if let Some((base, quote)) = fx_pair(stock) {
+        let stock = Stock::new(stock_code, company_name.unwrap_or(format!("{} to {}", base, quote)), value)
+            .with_currency(quote.to_string())
+            .with_kind(QuoteKind::Fx);
 
-        std::thread::sleep(std::time::Duration::from_secs(args.interval));
+        return (Ok(stock), diagnostics);
     }
+
+    // Index pages show points, though some embed the currency of their
+    // constituents.
+    let (currency, kind) = match symbol::is_index(stock) {
+        true => (String::new(), QuoteKind::Index),
+        false => (currency, QuoteKind::Equity),
+    };
+
+    let company_name = company_name.unwrap_or("N/A".to_string());
+    let stock = Stock::new(stock_code, company_name, value)
+        .with_currency(currency)
+        .with_kind(kind)
+        .with_details(key_stats(&document, selectors))
+        .with_market_status(market_status(html_content));
+
+    (Ok(stock), diagnostics)
 }
 
-fn get_stock_valuation_status(nstock: &Stock, past_stock: &Stock) -> String {
-    match nstock.price.partial_cmp(&past_stock.price) {
-        Some(std::cmp::Ordering::Greater) => "up".to_string(),
-        Some(std::cmp::Ordering::Less) => "down".to_string(),
-        _ => "same".to_string(),
+/// Google prefixes the price with `Pre-market:`, `After Hours:` or `Closed:`
+/// outside regular hours and shows no marker while the market is open.
+fn market_status(html_content: &str) -> Option<MarketStatus> {
+    match &MARKET_STATUS.captures(html_content)?[1] {
+        "Pre-market" => Some(MarketStatus::PreMarket),
+        "After Hours" => Some(MarketStatus::AfterHours),
+        _ => Some(MarketStatus::Closed),
     }
 }
 
-fn parse_stock_value(html_content: String, stock: &str) -> Result<Stock, StockError> {
-    let html_selector = scraper::Html::parse_document(&html_content);
+/// Reads the key statistics rows, e.g. `Previous close` / `$190.12`, and
+/// `Market cap` / `2.87T USD`. Unknown or unparsable rows are skipped.
+fn key_stats(document: &Html, selectors: &PageSelectors) -> QuoteDetails {
+    let mut details = QuoteDetails::default();
+
+    for (label, value) in stat_rows(document, selectors) {
+        match label.as_str() {
+            "previous close" => details.previous_close = price::parse_price(&value).map(|price| price.value),
+            "day range" => details.day_range = parse_range(&value),
+            "year range" => details.year_range = parse_range(&value),
+            "market cap" => details.market_cap = price::parse_abbreviated(&value),
+            "volume" => details.volume = price::parse_abbreviated(&value).map(|volume| volume.round() as u64),
+            _ => {}
+        }
+    }
 
-    let company_selector = scraper::Selector::parse(".zzDege")
-        .map_err(|err| {
-            StockError::new("SELECTOR_FAILED".to_string(), err.to_string())
-        })?;
+    details
+}
 
-    let stock_value_selector = scraper::Selector::parse(".YMlKec.fxKbKc")
-        .map_err(|err| {
-            StockError::new("SELECTOR_FAILED".to_string(), err.to_string())
-        })?;
+/// The key statistics rows as `(label, value)`, labels in lower case.
+fn stat_rows(document: &Html, selectors: &PageSelectors) -> Vec<(String, String)> {
+    let (Ok(row), Ok(label), Ok(value)) = (
+        Selector::parse(&selectors.stat_row),
+        Selector::parse(&selectors.stat_label),
+        Selector::parse(&selectors.stat_value),
+    ) else {
+        return Vec::new();
+    };
+
+    document.select(&row)
+        .filter_map(|element| {
+            let text = |selector: &Selector| element.select(selector)
+                .next()
+                .map(|element| element.text().collect::<String>().trim().to_string());
+
+            Some((text(&label)?.to_lowercase(), text(&value)?))
+        })
+        .collect()
+}
 
-    let company_name = html_selector.select(&company_selector)
-        .next()
-        .map(|value| {
-            value.text().next().unwrap_or("N/A").to_string()
+/// Reads the P/E ratio, dividend yield and market cap off the key
+/// statistics, e.g. `29.59` and `0.51%`, and the latest quarter's earnings
+/// per share off the income statement. Google shows `-` for figures a
+/// company doesn't have, such as the yield of one paying no dividend.
+pub fn parse_fundamentals(html_content: &str, selectors: &PageSelectors) -> Fundamentals {
+    let document = Html::parse_document(html_content);
+    let mut fundamentals = Fundamentals::default();
+
+    for (label, value) in stat_rows(&document, selectors) {
+        match label.as_str() {
+            "p/e ratio" => fundamentals.pe_ratio = value.replace(',', "").parse().ok(),
+            "dividend yield" => fundamentals.dividend_yield = value.trim_end_matches('%').parse().ok(),
+            "market cap" => fundamentals.market_cap = price::parse_abbreviated(&value),
+            _ => {}
+        }
+    }
+
+    fundamentals.eps = statement_row(&document, "earnings per share").and_then(|value| value.parse().ok());
+
+    fundamentals
+}
+
+/// Reads the related news off a quote page, in the order listed. Articles
+/// without a headline or a link are skipped; links relative to the page are
+/// made absolute.
+pub fn parse_news(html_content: &str, share_code: &str, selectors: &PageSelectors) -> Vec<NewsItem> {
+    let (Ok(item), Ok(headline), Ok(source), Ok(published), Ok(link)) = (
+        Selector::parse(&selectors.news_item),
+        Selector::parse(&selectors.news_headline),
+        Selector::parse(&selectors.news_source),
+        Selector::parse(&selectors.news_published),
+        Selector::parse("a[href]"),
+    ) else {
+        return Vec::new();
+    };
+
+    let document = Html::parse_document(html_content);
+    let symbol = share_code.to_uppercase().split(':').next().unwrap_or_default().to_string();
+    let base = url::Url::parse(PAGE_BASE_URL).expect("Google Finance URL");
+
+    document.select(&item)
+        .filter_map(|element| {
+            let text = |selector: &Selector| element.select(selector)
+                .next()
+                .map(|element| element.text().collect::<String>().trim().to_string())
+                .filter(|text| !text.is_empty());
+
+            let href = match element.value().attr("href") {
+                Some(href) => href,
+                None => element.select(&link).next()?.value().attr("href")?,
+            };
+
+            Some(NewsItem {
+                symbol: symbol.clone(),
+                headline: text(&headline)?,
+                source: text(&source).unwrap_or_default(),
+                url: base.join(href).ok()?.to_string(),
+                published: text(&published),
+            })
         })
-        .unwrap_or("N/A".to_string());
+        .collect()
+}
+
+/// The latest figure of a financial statement row, the cell after its
+/// label.
+fn statement_row(document: &Html, label: &str) -> Option<String> {
+    let (Ok(row), Ok(cell)) = (Selector::parse("tr"), Selector::parse("td")) else {
+        return None;
+    };
+
+    document.select(&row).find_map(|row| {
+        let mut cells = row.select(&cell).map(|cell| cell.text().collect::<String>().trim().to_string());
+
+        match cells.next()?.to_lowercase().starts_with(label) {
+            true => cells.next(),
+            false => None,
+        }
+    })
+}
+
+/// Ranges read like `$189.10 - $191.50`.
+fn parse_range(text: &str) -> Option<PriceRange> {
+    let (low, high) = text.split_once(" - ")?;
 
-    let stock_value = html_selector.select(&stock_value_selector)
+    Some(PriceRange { low: price::parse_price(low)?.value, high: price::parse_price(high)?.value })
+}
+
+fn select_text(document: &Html, selector: &str) -> Option<String> {
+    let selector = Selector::parse(selector).ok()?;
+
+    document.select(&selector)
         .next()
-        .map(|value| {
-            let v = value.text().next()
-                .unwrap_or("0,0")
-                .replace("$", "")
-                .replace("Rp\u{a0}", "")
-                .replace(",","");
-            
-            v.parse::<f64>().unwrap_or(0f64)
-        })
-        .unwrap_or(0f64);
+        .and_then(|element| element.text().next())
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
 
+fn embedded_price(document: &Html) -> Option<Price> {
+    let selector = Selector::parse("[data-last-price]").ok()?;
+    let element = document.select(&selector).next()?.value();
 
-    let stock_code = stock
-        .to_uppercase()
-        .split(":")
-        .nth(0)
-        .unwrap_or(stock)
-        .to_string();
-    
-    Ok(Stock::new(stock_code, company_name, stock_value, "up".to_string()))
+    Some(Price {
+        currency: element.attr("data-currency-code").unwrap_or_default().to_string(),
+        value: element.attr("data-last-price")?.parse().ok()?,
+    })
 }
 
-fn fetch_from_google_finance(stock: &str) -> Result<String, StockError> {
-    let base_url = "https://www.google.com/finance/quote/";
+fn json_ld(document: &Html) -> Vec<Value> {
+    let Ok(selector) = Selector::parse("script[type='application/ld+json']") else {
+        return Vec::new();
+    };
 
-    let url = url::Url::parse(&format!("{}{}", base_url, stock))
-        .map_err(move |err| {
-            println!("{:?}", err.clone());
-            StockError::new("PARSE_URL_FAILED".to_string(), err.to_string())
-        })?;
+    document.select(&selector)
+        .filter_map(|element| serde_json::from_str(&element.text().collect::<String>()).ok())
+        .collect()
+}
 
-    
-    let client = reqwest::blocking::Client::new();
-    
-    let res = client.get(url)
-        .header("User-Agent", "Mozilla/5.0")
-        .send()
-        .map_err(|err| {
-            StockError::new("REQUEST_FAILED".to_string(), err.to_string())
-        })?;
+fn json_ld_price(value: &Value) -> Option<Price> {
+    let amount = match find_key(value, "price")? {
+        Value::Number(number) => number.to_string().parse().ok()?,
+        Value::String(text) => price::parse_price(text)?.value,
+        _ => return None,
+    };
+    let currency = find_key(value, "priceCurrency").and_then(Value::as_str).unwrap_or_default();
+
+    Some(Price { currency: currency.to_string(), value: amount })
+}
 
-    if !res.status().is_success() {
-        return Err(StockError::new("RESPONSE_FAILED".to_string(), res.status().to_string()));
+/// Depth-first search for the first `key` in nested JSON-LD objects.
+fn find_key<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    match value {
+        Value::Object(map) => map.get(key).or_else(|| map.values().find_map(|value| find_key(value, key))),
+        Value::Array(values) => values.iter().find_map(|value| find_key(value, key)),
+        _ => None,
     }
+}
 
-    let html_content = res.text()
-        .map_err(|err| {
-            StockError::new("RESPONSE_BODY_FAILED".to_string(), err.to_string())
+/// Titles read like `Apple Inc (AAPL) Stock Price & News - Google Finance`.
+fn title_company(document: &Html) -> Option<String> {
+    let title = select_text(document, "title")?;
+    let (company, _) = title.split_once(" (")?;
+
+    Some(company.trim().to_string()).filter(|company| !company.is_empty())
+}
+
+fn regex_price(html_content: &str) -> Option<Price> {
+    let value = LAST_PRICE.captures(html_content)
+        .and_then(|captures| captures[1].parse::<Decimal>().ok())
+        .or_else(|| JSON_PRICE.captures(html_content).and_then(|captures| price::parse_price(&captures[1])).map(|price| price.value))?;
+    let currency = CURRENCY_CODE.captures(html_content)
+        .map(|captures| captures[1].to_string())
+        .unwrap_or_default();
+
+    Some(Price { currency, value })
+}
+
+/// The base and quote currency of an `FX` code such as `USD-IDR:FX`.
+fn fx_pair(stock: &str) -> Option<(String, String)> {
+    let stock = stock.to_uppercase();
+    let (pair, "FX") = stock.split_once(':')? else {
+        return None;
+    };
+    let (base, quote) = symbol::fx_pair(pair)?;
+
+    Some((base.to_string(), quote.to_string()))
+}
+
+/// Currency pages go without an exchange, e.g. `USD-IDR`.
+fn quote_url(stock: &str) -> String {
+    match fx_pair(stock) {
+        Some((base, quote)) => format!("{}{}-{}", BASE_URL, base, quote),
+        None => format!("{}{}", BASE_URL, stock),
+    }
+}
+
+pub fn fetch_from_google_finance(fetcher: &impl HttpFetcher, stock: &str) -> Result<String, StockError> {
+    fetcher.fetch_page(&quote_url(stock))
+}
+
+/// Finds the `SYMBOL:EXCHANGE` code Google Finance lists a bare ticker
+/// under, such as `AAPL:NASDAQ` for `AAPL`.
+pub fn search_code(fetcher: &impl HttpFetcher, ticker: &str) -> Result<String, StockError> {
+    let query = url::form_urlencoded::byte_serialize(ticker.as_bytes()).collect::<String>();
+    let html_content = fetcher.fetch_page(&format!("{}{}", SEARCH_URL, query))?;
+
+    parse_search_results(&html_content, ticker)
+}
+
+/// The first listing of `ticker` on a known exchange among the quote links
+/// of a search results page (or the quote page it redirected to).
+pub fn parse_search_results(html_content: &str, ticker: &str) -> Result<String, StockError> {
+    let ticker = ticker.to_uppercase();
+    let pattern = Regex::new(&format!(r"quote/{}:([A-Z]+)", regex::escape(&ticker))).expect("valid search pattern");
+
+    let exchange = pattern.captures_iter(html_content)
+        .filter_map(|captures| captures.get(1))
+        .map(|exchange| exchange.as_str())
+        .find(|exchange| symbol::is_exchange(exchange))
+        .ok_or_else(|| {
+            StockError::SymbolNotFound(format!("{} on Google Finance", ticker))
         })?;
 
-    Ok(html_content)
-}
\ No newline at end of file
+    Ok(format!("{}:{}", ticker, exchange))
+}
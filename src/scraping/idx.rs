@@ -0,0 +1,113 @@
+use serde::Deserialize;
+
+use crate::{http::Fetcher, scraping::{replay::Recorder, QuoteProvider}, symbol, Decimal, PriceRange, QuoteDetails, Stock, StockError};
+
+const BASE_URL: &str = "https://www.idx.co.id/primary/ListedCompany/GetTradingInfoSS";
+/// Shares per lot, the unit orders on the exchange are placed in.
+pub const LOT_SIZE: u32 = 100;
+
+
+/// End-of-day trading summaries from the Indonesia Stock Exchange's own
+/// site, for `SYMBOL:IDX` codes.
+#[derive(Debug, Clone, Default)]
+pub struct Idx {
+    fetcher: Fetcher,
+    recorder: Option<Recorder>,
+}
+
+impl Idx {
+    pub fn new(fetcher: Fetcher) -> Self {
+        Self { fetcher, recorder: None }
+    }
+
+    /// Saves every fetched quote response to `recorder` before parsing it.
+    pub fn record_to(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+}
+
+impl QuoteProvider for Idx {
+    fn name(&self) -> &str {
+        "idx"
+    }
+
+    fn fetch(&self, symbol: &str) -> Result<Stock, StockError> {
+        let json_content = fetch_from_idx(&self.fetcher, symbol)?;
+
+        if let Some(recorder) = &self.recorder {
+            recorder.save(symbol, "json", &json_content);
+        }
+
+        parse_stock_value(json_content, symbol)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TradingInfoResponse {
+    #[serde(default)]
+    replies: Vec<TradingDay>,
+}
+
+/// A trading day's summary, the latest first.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TradingDay {
+    stock_name: String,
+    previous: Option<Decimal>,
+    high: Option<Decimal>,
+    low: Option<Decimal>,
+    close: Decimal,
+    /// In shares, not lots.
+    volume: Option<f64>,
+    listed_shares: Option<f64>,
+}
+
+/// Whether `code` is a stock listed on the Indonesia Stock Exchange, e.g.
+/// `BBCA:IDX`. Its indices, such as `COMPOSITE:IDX`, have no trading info.
+pub fn is_idx_symbol(code: &str) -> bool {
+    code.to_uppercase().ends_with(":IDX") && !symbol::is_index(code)
+}
+
+pub fn parse_stock_value(json_content: String, stock: &str) -> Result<Stock, StockError> {
+    let response: TradingInfoResponse = serde_json::from_str(&json_content)
+        .map_err(|err| {
+            StockError::Response(err.into())
+        })?;
+
+    let day = response.replies.into_iter()
+        .next()
+        .ok_or_else(|| {
+            StockError::SymbolNotFound(stock.to_string())
+        })?;
+
+    let stock_code = stock
+        .to_uppercase()
+        .split(":")
+        .next()
+        .unwrap_or(stock)
+        .to_string();
+
+    let details = QuoteDetails {
+        previous_close: day.previous,
+        day_range: day.low.zip(day.high).map(|(low, high)| PriceRange { low, high }),
+        market_cap: day.listed_shares.map(|shares| shares * day.close.to_f64()),
+        volume: day.volume.map(|volume| volume as u64),
+        lot_size: Some(LOT_SIZE),
+        ..QuoteDetails::default()
+    };
+
+    Ok(Stock::new(stock_code, day.stock_name.trim().to_string(), day.close)
+        .with_currency("IDR".to_string())
+        .with_details(details))
+}
+
+pub fn fetch_from_idx(fetcher: &Fetcher, stock: &str) -> Result<String, StockError> {
+    if !is_idx_symbol(stock) {
+        return Err(StockError::UnsupportedSymbol(format!("{} is not listed on IDX", stock)));
+    }
+
+    let symbol = stock.to_uppercase().split(':').next().unwrap_or_default().to_string();
+
+    fetcher.fetch_page(&format!("{}?code={}&start=0&length=1", BASE_URL, symbol))
+}
@@ -1 +1,331 @@
-pub mod googlefinance;
\ No newline at end of file
+use std::{fmt, str::FromStr, sync::Arc};
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use crate::{calendar::CorporateEvent, candle::Candle, http::{BoxFuture, Fetcher}, ratelimit::RateLimits, scraping::{replay::Recorder, selectors::Selectors}, shutdown::Shutdown, Fundamentals, NewsItem, Stock, StockError};
+
+pub mod alphavantage;
+pub mod coingecko;
+pub mod finnhub;
+pub mod googlefinance;
+pub mod idx;
+pub mod replay;
+pub mod selectors;
+pub mod stooq;
+pub mod yahoofinance;
+
+
+/// A data source able to turn a `SYMBOL:EXCHANGE` code into a [`Stock`].
+///
+/// Implement this to plug exchange APIs, local files or test doubles into
+/// [`crate::StockClient`] and the polling loops.
+pub trait QuoteProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn fetch(&self, symbol: &str) -> Result<Stock, StockError>;
+
+    /// Same as [`QuoteProvider::fetch`] without tying up the thread it is
+    /// awaited on while the request is in flight. By default the blocking
+    /// `fetch` runs on tokio's blocking pool.
+    fn async_fetch<'a>(self: Arc<Self>, symbol: &'a str) -> BoxFuture<'a, Result<Stock, StockError>>
+    where
+        Self: 'static,
+    {
+        let symbol = symbol.to_string();
+
+        Box::pin(async move { blocking(move || self.fetch(&symbol)).await? })
+    }
+
+    /// Groups `symbols` into the batches [`QuoteProvider::fetch_batch`]
+    /// takes. Sources that can quote several symbols in one request put them
+    /// together; by default every symbol is a batch of its own.
+    fn batches(&self, symbols: &[String]) -> Vec<Vec<String>> {
+        symbols.iter().map(|symbol| vec![symbol.clone()]).collect()
+    }
+
+    /// Fetches one of the batches made by [`QuoteProvider::batches`] in a
+    /// single request, returning a result per symbol in the same order.
+    /// Fails as a whole only when the request itself does.
+    fn fetch_batch(&self, symbols: &[String]) -> Result<Vec<Result<Stock, StockError>>, StockError> {
+        Ok(symbols.iter().map(|symbol| self.fetch(symbol)).collect())
+    }
+
+    /// Same as [`QuoteProvider::fetch_batch`] for async code, running the
+    /// blocking one on tokio's blocking pool by default like
+    /// [`QuoteProvider::async_fetch`].
+    fn async_fetch_batch<'a>(self: Arc<Self>, symbols: &'a [String]) -> BoxFuture<'a, Result<Vec<Result<Stock, StockError>>, StockError>>
+    where
+        Self: 'static,
+    {
+        let symbols = symbols.to_vec();
+
+        Box::pin(async move { blocking(move || self.fetch_batch(&symbols)).await? })
+    }
+}
+
+/// Runs blocking `work` on tokio's blocking pool, in the caller's span.
+pub async fn blocking<T: Send + 'static>(work: impl FnOnce() -> T + Send + 'static) -> Result<T, StockError> {
+    let span = tracing::Span::current();
+
+    tokio::task::spawn_blocking(move || span.in_scope(work))
+        .await
+        .map_err(|err| {
+            StockError::runtime("task", err)
+        })
+}
+
+/// `provider`, saving its responses to `recorder` with `record_to` when
+/// there is one.
+fn recorded<P: QuoteProvider + 'static>(provider: P, recorder: Option<&Recorder>, record_to: fn(P, Recorder) -> P) -> Arc<dyn QuoteProvider> {
+    match recorder {
+        Some(recorder) => Arc::new(record_to(provider, recorder.clone())),
+        None => Arc::new(provider),
+    }
+}
+
+/// Splits `symbols` into batches of at most `size`, in order.
+pub fn chunked(symbols: &[String], size: usize) -> Vec<Vec<String>> {
+    symbols.chunks(size.max(1)).map(<[String]>::to_vec).collect()
+}
+
+/// Decides whether a route handles a `SYMBOL:EXCHANGE` code.
+pub type RouteMatcher = fn(&str) -> bool;
+
+/// Sends each symbol to the first provider whose route matches it, falling
+/// back to a default provider, so one watchlist can mix asset classes.
+pub struct RoutedProvider {
+    routes: Vec<(RouteMatcher, Arc<dyn QuoteProvider>)>,
+    fallback: Arc<dyn QuoteProvider>,
+}
+
+impl RoutedProvider {
+    pub fn new(fallback: Arc<dyn QuoteProvider>) -> Self {
+        Self { routes: Vec::new(), fallback }
+    }
+
+    pub fn route(mut self, matches: RouteMatcher, provider: Arc<dyn QuoteProvider>) -> Self {
+        self.routes.push((matches, provider));
+        self
+    }
+
+    pub fn provider_for(&self, symbol: &str) -> &dyn QuoteProvider {
+        self.provider(self.route_of(symbol)).as_ref()
+    }
+
+    /// The index of the route `symbol` takes, `routes.len()` for the fallback.
+    fn route_of(&self, symbol: &str) -> usize {
+        self.routes.iter()
+            .position(|(matches, _)| matches(symbol))
+            .unwrap_or(self.routes.len())
+    }
+
+    fn provider(&self, route: usize) -> &Arc<dyn QuoteProvider> {
+        self.routes.get(route)
+            .map(|(_, provider)| provider)
+            .unwrap_or(&self.fallback)
+    }
+}
+
+impl QuoteProvider for RoutedProvider {
+    fn name(&self) -> &str {
+        self.fallback.name()
+    }
+
+    fn fetch(&self, symbol: &str) -> Result<Stock, StockError> {
+        self.provider_for(symbol).fetch(symbol)
+    }
+
+    fn async_fetch<'a>(self: Arc<Self>, symbol: &'a str) -> BoxFuture<'a, Result<Stock, StockError>> {
+        Arc::clone(self.provider(self.route_of(symbol))).async_fetch(symbol)
+    }
+
+    /// Batches never mix providers: each provider groups its own symbols.
+    fn batches(&self, symbols: &[String]) -> Vec<Vec<String>> {
+        let mut routed = vec![Vec::new(); self.routes.len() + 1];
+
+        for symbol in symbols {
+            routed[self.route_of(symbol)].push(symbol.clone());
+        }
+
+        routed.iter()
+            .enumerate()
+            .filter(|(_, symbols)| !symbols.is_empty())
+            .flat_map(|(route, symbols)| self.provider(route).batches(symbols))
+            .collect()
+    }
+
+    fn fetch_batch(&self, symbols: &[String]) -> Result<Vec<Result<Stock, StockError>>, StockError> {
+        match symbols.first() {
+            Some(symbol) => self.provider_for(symbol).fetch_batch(symbols),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn async_fetch_batch<'a>(self: Arc<Self>, symbols: &'a [String]) -> BoxFuture<'a, Result<Vec<Result<Stock, StockError>>, StockError>> {
+        match symbols.first() {
+            Some(symbol) => Arc::clone(self.provider(self.route_of(symbol))).async_fetch_batch(symbols),
+            None => Box::pin(async { Ok(Vec::new()) }),
+        }
+    }
+}
+
+/// Credentials for the data sources that need them.
+#[derive(Debug, Clone, Default)]
+pub struct ApiKeys {
+    pub alpha_vantage: Option<String>,
+    pub finnhub: Option<String>,
+}
+
+/// A data source that pushes quotes as trades happen instead of being polled.
+pub trait StreamingProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Subscribes to `symbols` and hands every update, with the code it is
+    /// for, to `on_quote` until that returns `false` or `shutdown` is
+    /// requested. Fails once the connection is lost.
+    fn stream(&self, symbols: &[String], shutdown: &Shutdown, on_quote: &mut dyn FnMut(&str, Stock) -> bool) -> Result<(), StockError>;
+}
+
+/// A data source able to download daily OHLC bars for a date range.
+pub trait HistoryProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Daily bars from `from` to `to`, both inclusive, oldest first.
+    fn fetch_history(&self, symbol: &str, from: NaiveDate, to: NaiveDate) -> Result<Vec<Candle>, StockError>;
+}
+
+/// A data source able to report what a company earns and pays out.
+pub trait FundamentalsProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn fetch_fundamentals(&self, symbol: &str) -> Result<Fundamentals, StockError>;
+}
+
+/// A data source able to tell when a company reports earnings and goes
+/// ex-dividend next.
+pub trait CalendarProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// The upcoming events the source knows of, in no particular order.
+    fn fetch_calendar(&self, symbol: &str) -> Result<Vec<CorporateEvent>, StockError>;
+}
+
+/// A data source able to list recent headlines about a company.
+pub trait NewsProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// The latest headlines first, as the source lists them.
+    fn fetch_news(&self, symbol: &str) -> Result<Vec<NewsItem>, StockError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    #[default]
+    Google,
+    Yahoo,
+    Stooq,
+    AlphaVantage,
+    Finnhub,
+    /// IDX's own site for `SYMBOL:IDX` codes, Google Finance for the rest.
+    Idx,
+}
+
+impl Source {
+    pub const ALL: [Source; 6] = [Source::Google, Source::Yahoo, Source::Stooq, Source::AlphaVantage, Source::Finnhub, Source::Idx];
+
+    /// The provider for this source, with crypto codes (`BTC-USD`,
+    /// `crypto:bitcoin`) routed to CoinGecko.
+    pub fn provider(&self) -> Arc<dyn QuoteProvider> {
+        self.provider_with(&Fetcher::default(), &RateLimits::default(), &Selectors::default(), &ApiKeys::default(), None)
+    }
+
+    /// Same as [`Source::provider`], sending requests through `fetcher`, with
+    /// each underlying provider capped by its entry in `limits`, pages read
+    /// with `selectors`, APIs called with `keys` and, given a `recorder`,
+    /// responses saved before parsing.
+    pub fn provider_with(&self, fetcher: &Fetcher, limits: &RateLimits, selectors: &Selectors, keys: &ApiKeys, recorder: Option<&Recorder>) -> Arc<dyn QuoteProvider> {
+        let fallback = match self {
+            Source::Google | Source::Idx => recorded(googlefinance::GoogleFinance::new(fetcher.clone()).with_selectors(selectors.clone()), recorder, googlefinance::GoogleFinance::record_to),
+            Source::Yahoo => recorded(yahoofinance::YahooFinance::new(fetcher.clone()), recorder, yahoofinance::YahooFinance::record_to),
+            Source::Stooq => recorded(stooq::Stooq::new(fetcher.clone()), recorder, stooq::Stooq::record_to),
+            Source::AlphaVantage => recorded(alphavantage::AlphaVantage::new(fetcher.clone(), keys.alpha_vantage.clone()), recorder, alphavantage::AlphaVantage::record_to),
+            Source::Finnhub => recorded(finnhub::Finnhub::new(fetcher.clone(), keys.finnhub.clone()), recorder, finnhub::Finnhub::record_to),
+        };
+
+        let crypto = limits.apply(Arc::new(coingecko::CoinGecko::new(fetcher.clone())));
+        let routed = RoutedProvider::new(limits.apply(fallback)).route(coingecko::is_crypto_symbol, crypto);
+
+        match self {
+            Source::Idx => {
+                let provider = recorded(idx::Idx::new(fetcher.clone()), recorder, idx::Idx::record_to);
+
+                Arc::new(routed.route(idx::is_idx_symbol, limits.apply(provider)))
+            }
+            _ => Arc::new(routed),
+        }
+    }
+
+    /// The push-based provider of this source, for sources that offer one.
+    pub fn streaming_provider(&self, fetcher: &Fetcher, keys: &ApiKeys) -> Result<Arc<dyn StreamingProvider>, StockError> {
+        match self {
+            Source::Finnhub => Ok(Arc::new(finnhub::Finnhub::new(fetcher.clone(), keys.finnhub.clone()))),
+            _ => Err(StockError::Unsupported(format!("{} can't stream quotes, only finnhub can", self))),
+        }
+    }
+
+    /// Only Google Finance's quote page has them for free, whatever the
+    /// source of the quotes.
+    pub fn fundamentals_provider(&self, fetcher: &Fetcher, selectors: &Selectors) -> Arc<dyn FundamentalsProvider> {
+        Arc::new(googlefinance::GoogleFinance::new(fetcher.clone()).with_selectors(selectors.clone()))
+    }
+
+    /// Google Finance's quote page, like [`Source::fundamentals_provider`].
+    pub fn news_provider(&self, fetcher: &Fetcher, selectors: &Selectors) -> Arc<dyn NewsProvider> {
+        Arc::new(googlefinance::GoogleFinance::new(fetcher.clone()).with_selectors(selectors.clone()))
+    }
+
+    /// Yahoo's, whatever the source of the quotes.
+    pub fn calendar_provider(&self, fetcher: &Fetcher) -> Arc<dyn CalendarProvider> {
+        Arc::new(yahoofinance::YahooFinance::new(fetcher.clone()))
+    }
+
+    /// Google Finance, Finnhub and IDX have no (free) history endpoint, so
+    /// they fall back to Yahoo.
+    pub fn history_provider(&self, fetcher: &Fetcher, keys: &ApiKeys) -> Arc<dyn HistoryProvider> {
+        match self {
+            Source::Google | Source::Yahoo | Source::Finnhub | Source::Idx => Arc::new(yahoofinance::YahooFinance::new(fetcher.clone())),
+            Source::Stooq => Arc::new(stooq::Stooq::new(fetcher.clone())),
+            Source::AlphaVantage => Arc::new(alphavantage::AlphaVantage::new(fetcher.clone(), keys.alpha_vantage.clone())),
+        }
+    }
+}
+
+impl FromStr for Source {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "google" => Ok(Source::Google),
+            "yahoo" => Ok(Source::Yahoo),
+            "stooq" => Ok(Source::Stooq),
+            "alphavantage" => Ok(Source::AlphaVantage),
+            "finnhub" => Ok(Source::Finnhub),
+            "idx" => Ok(Source::Idx),
+            _ => Err(StockError::invalid("source", value.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Google => write!(f, "google"),
+            Source::Yahoo => write!(f, "yahoo"),
+            Source::Stooq => write!(f, "stooq"),
+            Source::AlphaVantage => write!(f, "alphavantage"),
+            Source::Finnhub => write!(f, "finnhub"),
+            Source::Idx => write!(f, "idx"),
+        }
+    }
+}
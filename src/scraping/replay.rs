@@ -0,0 +1,290 @@
+use std::{collections::{HashMap, VecDeque}, fs, path::{Path, PathBuf}, sync::{Arc, Mutex}};
+use chrono::Utc;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::{scraping::{alphavantage, finnhub, googlefinance, idx, selectors::Selectors, stooq, yahoofinance, QuoteProvider, Source}, shutdown::Shutdown, Decimal, QuoteKind, Stock, StockError};
+
+
+/// A quote line as written by `--output json`.
+#[derive(Debug, Deserialize)]
+struct RecordedQuote {
+    symbol: String,
+    company_name: String,
+    price: Decimal,
+    #[serde(default)]
+    currency: String,
+    #[serde(default)]
+    kind: QuoteKind,
+    #[serde(default)]
+    stale: bool,
+}
+
+#[derive(Debug)]
+enum Recording {
+    /// A saved quote page, parsed like a fetched one.
+    Page(PathBuf),
+    Quote(Box<Stock>),
+}
+
+/// The directory a code's pages are kept in: `AAPL:NASDAQ` in `AAPL_NASDAQ`.
+pub fn directory_name(share_code: &str) -> String {
+    share_code.replace(':', "_")
+}
+
+/// The extension and content of a page by code.
+type PendingPages = Mutex<HashMap<String, (String, String)>>;
+
+/// Saves every fetched quote page, before it is parsed, as
+/// `DIR/AAPL_NASDAQ/20240102T150405.123Z.html`, ready for [`ReplayProvider`].
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    dir: PathBuf,
+    /// With [`Recorder::failures`], the last page of each code, until it is
+    /// known whether it parsed.
+    pending: Option<Arc<PendingPages>>,
+}
+
+impl Recorder {
+    pub fn new(dir: &Path) -> Result<Self, StockError> {
+        fs::create_dir_all(dir)
+            .map_err(|err| {
+                StockError::io(dir, err)
+            })?;
+
+        Ok(Self { dir: dir.to_path_buf(), pending: None })
+    }
+
+    /// Like [`Recorder::new`], only keeping the pages that fail to parse,
+    /// once [`Recorder::settle`] says so.
+    pub fn failures(dir: &Path) -> Result<Self, StockError> {
+        Ok(Self { pending: Some(Arc::default()), ..Self::new(dir)? })
+    }
+
+    /// Saves `content` with the given file extension. Failures are only
+    /// logged so recording never costs a quote.
+    pub fn save(&self, share_code: &str, extension: &str, content: &str) {
+        match &self.pending {
+            Some(pending) => {
+                pending.lock().unwrap_or_else(|err| err.into_inner()).insert(share_code.to_string(), (extension.to_string(), content.to_string()));
+            }
+            None => {
+                self.write(share_code, extension, content);
+            }
+        }
+    }
+
+    /// Tells the recorder how parsing the last page of `share_code` went.
+    /// With [`Recorder::failures`], the page is written when it was
+    /// unreadable and dropped otherwise.
+    pub fn settle<T>(&self, share_code: &str, result: &Result<T, StockError>) {
+        let Some(pending) = &self.pending else {
+            return;
+        };
+
+        let page = pending.lock().unwrap_or_else(|err| err.into_inner()).remove(share_code);
+
+        if let (Some((extension, content)), Err(err)) = (page, result) {
+            if !err.is_unreadable() {
+                return;
+            }
+
+            if let Some(path) = self.write(share_code, &extension, &content) {
+                warn!(symbol = share_code, "Saved the page of {} that failed to parse to {}", share_code, path.display());
+            }
+        }
+    }
+
+    fn write(&self, share_code: &str, extension: &str, content: &str) -> Option<PathBuf> {
+        let dir = self.dir.join(directory_name(share_code));
+        let path = dir.join(format!("{}.{}", Utc::now().format("%Y%m%dT%H%M%S%.3fZ"), extension));
+
+        match fs::create_dir_all(&dir).and_then(|_| fs::write(&path, content)) {
+            Ok(()) => Some(path),
+            Err(err) => {
+                warn!(symbol = share_code, "Failed to record {}: {}", path.display(), err);
+                None
+            }
+        }
+    }
+}
+
+/// Serves recordings instead of fetching: either a directory of saved quote
+/// pages, `DIR/AAPL_NASDAQ/*` replayed in file name order and parsed as
+/// `source` would, or a `--output json` file. Each fetch of a code returns
+/// its next recording; once all of them have been served a shutdown is
+/// requested.
+pub struct ReplayProvider {
+    source: Source,
+    selectors: Selectors,
+    codes: Vec<String>,
+    recordings: Mutex<HashMap<String, VecDeque<Recording>>>,
+    shutdown: Shutdown,
+}
+
+impl ReplayProvider {
+    pub fn open(path: &Path, source: Source, selectors: Selectors, shutdown: Shutdown) -> Result<Self, StockError> {
+        let (codes, recordings) = match path.is_dir() {
+            true => read_pages(path)?,
+            false => read_quotes(path)?,
+        };
+
+        if codes.is_empty() {
+            return Err(StockError::invalid("replay", format!("{}: nothing to replay", path.display())));
+        }
+
+        Ok(Self { source, selectors, codes, recordings: Mutex::new(recordings), shutdown })
+    }
+
+    /// The recorded codes, in the order they first appear.
+    pub fn codes(&self) -> &[String] {
+        &self.codes
+    }
+
+    /// Drops the recordings of codes that won't be polled, so the replay
+    /// ends once the polled ones are done.
+    pub fn retain(&mut self, codes: &[String]) {
+        let recordings = self.recordings.get_mut().unwrap_or_else(|err| err.into_inner());
+        let keys = codes.iter().map(|code| key(recordings, code)).collect::<Vec<String>>();
+
+        recordings.retain(|code, _| keys.contains(code));
+        self.codes.retain(|code| keys.contains(code));
+    }
+
+    fn next(&self, symbol: &str) -> Result<Recording, StockError> {
+        let mut recordings = self.recordings.lock().unwrap_or_else(|err| err.into_inner());
+        let key = key(&recordings, symbol);
+
+        let recording = recordings.get_mut(&key)
+            .and_then(VecDeque::pop_front)
+            .ok_or_else(|| StockError::ReplayExhausted(symbol.to_string()))?;
+
+        if recordings.values().all(VecDeque::is_empty) {
+            info!("Replay finished");
+            self.shutdown.request();
+        }
+
+        Ok(recording)
+    }
+}
+
+impl QuoteProvider for ReplayProvider {
+    fn name(&self) -> &str {
+        "replay"
+    }
+
+    fn fetch(&self, symbol: &str) -> Result<Stock, StockError> {
+        let path = match self.next(symbol)? {
+            Recording::Quote(stock) => return Ok(stock.with_fetched_at(Utc::now())),
+            Recording::Page(path) => path,
+        };
+
+        let content = fs::read_to_string(&path)
+            .map_err(|err| {
+                StockError::io(path, err)
+            })?;
+
+        match self.source {
+            Source::Google => googlefinance::parse_stock_value(content, symbol, &self.selectors.google()),
+            Source::Yahoo => yahoofinance::parse_stock_value(content, symbol),
+            Source::Stooq => stooq::parse_stock_value(content, symbol),
+            Source::AlphaVantage => alphavantage::parse_stock_value(content, symbol),
+            Source::Finnhub => finnhub::parse_stock_value(content, symbol),
+            Source::Idx if idx::is_idx_symbol(symbol) => idx::parse_stock_value(content, symbol),
+            Source::Idx => googlefinance::parse_stock_value(content, symbol, &self.selectors.google()),
+        }
+    }
+}
+
+/// The recordings key of a polled code: the code itself, or its bare symbol
+/// for quote streams.
+fn key(recordings: &HashMap<String, VecDeque<Recording>>, symbol: &str) -> String {
+    match recordings.contains_key(symbol) {
+        true => symbol.to_string(),
+        false => symbol.to_uppercase().split(':').next().unwrap_or_default().to_string(),
+    }
+}
+
+type Recordings = (Vec<String>, HashMap<String, VecDeque<Recording>>);
+
+fn read_pages(dir: &Path) -> Result<Recordings, StockError> {
+    let read_error = |path: &Path, err: std::io::Error| {
+        StockError::io(path, err)
+    };
+
+    let mut directories = fs::read_dir(dir)
+        .map_err(|err| read_error(dir, err))?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect::<Vec<PathBuf>>();
+    directories.sort();
+
+    let mut codes = Vec::new();
+    let mut recordings = HashMap::new();
+
+    for directory in directories {
+        let Some(name) = directory.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let share_code = name.replacen('_', ":", 1);
+
+        let mut pages = fs::read_dir(&directory)
+            .map_err(|err| read_error(&directory, err))?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect::<Vec<PathBuf>>();
+        pages.sort();
+
+        if pages.is_empty() {
+            continue;
+        }
+
+        codes.push(share_code.clone());
+        recordings.insert(share_code, pages.into_iter().map(Recording::Page).collect());
+    }
+
+    Ok((codes, recordings))
+}
+
+fn read_quotes(path: &Path) -> Result<Recordings, StockError> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| {
+            StockError::io(path, err)
+        })?;
+
+    let mut codes = Vec::new();
+    let mut recordings: HashMap<String, VecDeque<Recording>> = HashMap::new();
+
+    for (index, line) in content.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let value: serde_json::Value = serde_json::from_str(line)
+            .map_err(|err| {
+                StockError::parse(format!("{}:{}", path.display(), index + 1), err)
+            })?;
+
+        // Portfolio valuations are interleaved with the quotes.
+        if value.get("symbol").is_none() {
+            continue;
+        }
+
+        let quote: RecordedQuote = serde_json::from_value(value)
+            .map_err(|err| {
+                StockError::parse(format!("{}:{}", path.display(), index + 1), err)
+            })?;
+
+        // A stale quote repeats an earlier one.
+        if quote.stale {
+            continue;
+        }
+
+        if !recordings.contains_key(&quote.symbol) {
+            codes.push(quote.symbol.clone());
+        }
+
+        let stock = Stock::new(quote.symbol.clone(), quote.company_name, quote.price).with_currency(quote.currency).with_kind(quote.kind);
+        recordings.entry(quote.symbol).or_default().push_back(Recording::Quote(Box::new(stock)));
+    }
+
+    Ok((codes, recordings))
+}
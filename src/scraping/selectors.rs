@@ -0,0 +1,148 @@
+use std::{fs, path::{Path, PathBuf}, sync::{Arc, RwLock}, thread, time::{Duration, SystemTime}};
+use serde::Deserialize;
+use tracing::{error, info};
+
+use crate::{shutdown::Shutdown, StockError};
+
+pub const DEFAULT_SELECTORS_FILE: &str = "selectors.toml";
+/// How often the selectors file is checked for changes.
+const RELOAD_CHECK: Duration = Duration::from_secs(2);
+
+
+/// CSS selectors for the values read off a quote page, each list tried in
+/// order before the provider's other strategies.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PageSelectors {
+    pub price: Vec<String>,
+    pub company: Vec<String>,
+    /// A row of the key statistics (previous close, day range, ...), holding
+    /// a `stat_label` and a `stat_value` element.
+    pub stat_row: String,
+    pub stat_label: String,
+    pub stat_value: String,
+    /// A related news article, linking to it and holding a `news_headline`,
+    /// a `news_source` and a `news_published` element.
+    pub news_item: String,
+    pub news_headline: String,
+    pub news_source: String,
+    pub news_published: String,
+}
+
+impl PageSelectors {
+    /// Google Finance's selectors, current layout first.
+    pub fn google() -> Self {
+        Self {
+            price: vec![".YMlKec.fxKbKc".to_string(), "[data-last-price] .YMlKec".to_string(), ".IsqQVc.NprOob".to_string()],
+            company: vec![".zzDege".to_string(), "[role='heading'][aria-level='1']".to_string()],
+            stat_row: ".gyFHrc".to_string(),
+            stat_label: ".mfs7Fc".to_string(),
+            stat_value: ".P6K39c".to_string(),
+            news_item: ".yY3Lee".to_string(),
+            news_headline: ".Yfwt5".to_string(),
+            news_source: ".sfyJob".to_string(),
+            news_published: ".Adak".to_string(),
+        }
+    }
+
+    fn validate(&self, path: &Path) -> Result<(), StockError> {
+        let stats = [&self.stat_row, &self.stat_label, &self.stat_value];
+        let news = [&self.news_item, &self.news_headline, &self.news_source, &self.news_published];
+
+        for selector in self.price.iter().chain(self.company.iter()).chain(stats).chain(news) {
+            scraper::Selector::parse(selector)
+                .map_err(|err| {
+                    StockError::invalid("selector", format!("{}: {}: {}", path.display(), selector, err))
+                })?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for PageSelectors {
+    fn default() -> Self {
+        Self::google()
+    }
+}
+
+/// The contents of `selectors.toml`; a provider or list left out keeps its
+/// built-in selectors.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SelectorsFile {
+    google: PageSelectors,
+}
+
+/// Shared, reloadable selectors. Clones see every reload.
+#[derive(Debug, Clone, Default)]
+pub struct Selectors {
+    google: Arc<RwLock<PageSelectors>>,
+}
+
+impl Selectors {
+    pub fn load(path: &Path) -> Result<Self, StockError> {
+        let selectors = Self::default();
+        selectors.reload(path)?;
+
+        Ok(selectors)
+    }
+
+    /// Re-reads `path`, keeping the current selectors when it is invalid.
+    pub fn reload(&self, path: &Path) -> Result<(), StockError> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| {
+                StockError::io(path, err)
+            })?;
+
+        let file: SelectorsFile = toml::from_str(&content)
+            .map_err(|err| {
+                StockError::parse(path.display(), err)
+            })?;
+
+        file.google.validate(path)?;
+        *self.google.write().unwrap_or_else(|err| err.into_inner()) = file.google;
+
+        Ok(())
+    }
+
+    pub fn google(&self) -> PageSelectors {
+        self.google.read().unwrap_or_else(|err| err.into_inner()).clone()
+    }
+
+    /// Reloads `path` whenever it changes (or on SIGHUP on Unix) until
+    /// `shutdown` is requested.
+    pub fn watch(&self, path: PathBuf, shutdown: Shutdown) {
+        let selectors = self.clone();
+        let mut modified = modified_at(&path);
+
+        #[cfg(unix)]
+        crate::daemon::catch_hangup();
+
+        thread::spawn(move || {
+            while !shutdown.wait_timeout(RELOAD_CHECK) {
+                let now_modified = modified_at(&path);
+
+                #[cfg(unix)]
+                let hangup = crate::daemon::take_hangup();
+                #[cfg(not(unix))]
+                let hangup = false;
+
+                if now_modified == modified && !hangup {
+                    continue;
+                }
+
+                modified = now_modified;
+
+                match selectors.reload(&path) {
+                    Ok(()) => info!("Reloaded selectors from {}", path.display()),
+                    Err(err) => error!("Keeping the previous selectors: {}", err),
+                }
+            }
+        });
+    }
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
@@ -0,0 +1,257 @@
+use std::{collections::HashMap, str::FromStr};
+use chrono::{NaiveDate, NaiveTime};
+use serde::Deserialize;
+
+use crate::{candle::Candle, http::Fetcher, scraping::{self, replay::Recorder, HistoryProvider, QuoteProvider}, Decimal, PriceRange, QuoteDetails, Stock, StockError};
+
+const QUOTE_URL: &str = "https://stooq.com/q/l/";
+const HISTORY_URL: &str = "https://stooq.com/q/d/l/";
+/// What stooq writes in place of values it doesn't have.
+const NO_DATA: &str = "N/D";
+/// Symbols quoted per request; stooq takes more, but long URLs get cut off.
+const BATCH_SIZE: usize = 20;
+
+
+/// Quotes and daily bars from stooq.com's CSV downloads: no HTML to break,
+/// but no key statistics beyond the day's range and volume either.
+#[derive(Debug, Clone, Default)]
+pub struct Stooq {
+    fetcher: Fetcher,
+    recorder: Option<Recorder>,
+}
+
+impl Stooq {
+    pub fn new(fetcher: Fetcher) -> Self {
+        Self { fetcher, recorder: None }
+    }
+
+    /// Saves every fetched quote response to `recorder` before parsing it.
+    pub fn record_to(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+}
+
+impl QuoteProvider for Stooq {
+    fn name(&self) -> &str {
+        "stooq"
+    }
+
+    fn fetch(&self, symbol: &str) -> Result<Stock, StockError> {
+        let csv_content = fetch_from_stooq(&self.fetcher, symbol)?;
+
+        if let Some(recorder) = &self.recorder {
+            recorder.save(symbol, "csv", &csv_content);
+        }
+
+        parse_stock_value(csv_content, symbol)
+    }
+
+    /// Symbols stooq knows go in batches; the rest fail on their own.
+    fn batches(&self, symbols: &[String]) -> Vec<Vec<String>> {
+        let (supported, unsupported): (Vec<String>, Vec<String>) = symbols.iter()
+            .cloned()
+            .partition(|symbol| to_stooq_symbol(symbol).is_ok());
+
+        scraping::chunked(&supported, BATCH_SIZE).into_iter()
+            .chain(unsupported.into_iter().map(|symbol| vec![symbol]))
+            .collect()
+    }
+
+    fn fetch_batch(&self, symbols: &[String]) -> Result<Vec<Result<Stock, StockError>>, StockError> {
+        if let [symbol] = symbols {
+            return Ok(vec![self.fetch(symbol)]);
+        }
+
+        let mut rows = split_rows(&fetch_batch_from_stooq(&self.fetcher, symbols)?);
+
+        let stocks = symbols.iter()
+            .map(|symbol| {
+                let (ticker, _) = to_stooq_symbol(symbol)?;
+                let csv_content = rows.remove(&ticker.to_uppercase())
+                    .ok_or_else(|| {
+                        StockError::SymbolNotFound(symbol.to_string())
+                    })?;
+
+                // Saved as if fetched on its own, so it replays the same.
+                if let Some(recorder) = &self.recorder {
+                    recorder.save(symbol, "csv", &csv_content);
+                }
+
+                parse_stock_value(csv_content, symbol)
+            })
+            .collect();
+
+        Ok(stocks)
+    }
+}
+
+impl HistoryProvider for Stooq {
+    fn name(&self) -> &str {
+        "stooq"
+    }
+
+    fn fetch_history(&self, symbol: &str, from: NaiveDate, to: NaiveDate) -> Result<Vec<Candle>, StockError> {
+        let csv_content = fetch_history_from_stooq(&self.fetcher, symbol, from, to)?;
+
+        parse_history(csv_content, symbol)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct QuoteRow {
+    high: String,
+    low: String,
+    close: String,
+    volume: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct HistoryRow {
+    date: String,
+    open: String,
+    high: String,
+    low: String,
+    close: String,
+    /// Missing altogether for indices.
+    volume: Option<String>,
+}
+
+/// Maps a Google-style `SYMBOL:EXCHANGE` code to stooq's ticker and the
+/// currency it quotes in, e.g. `AAPL:NASDAQ` becomes `aapl.us` in USD.
+pub fn to_stooq_symbol(stock: &str) -> Result<(String, &'static str), StockError> {
+    let stock = stock.to_lowercase();
+    let (symbol, exchange) = stock.split_once(':').unwrap_or((stock.as_str(), ""));
+
+    let (suffix, currency) = match exchange {
+        "nasdaq" | "nyse" | "nysearca" | "nyseamerican" | "bats" => ("us", "USD"),
+        // London prices are in pence.
+        "lon" => ("uk", "GBX"),
+        "etr" | "fra" => ("de", "EUR"),
+        "tyo" => ("jp", "JPY"),
+        "hkg" => ("hk", "HKD"),
+        _ => {
+            return Err(StockError::UnsupportedSymbol(format!("stooq has no quotes for {}", stock.to_uppercase())));
+        }
+    };
+
+    Ok((format!("{}.{}", symbol, suffix), currency))
+}
+
+fn number<T: FromStr>(value: &str) -> Option<T> {
+    match value.trim() {
+        NO_DATA => None,
+        value => value.parse().ok(),
+    }
+}
+
+fn parse_error(err: csv::Error) -> StockError {
+    StockError::Response(err.into())
+}
+
+/// Splits a quote download of several symbols into one per symbol, the
+/// header and its row, keyed by stooq's ticker in upper case.
+pub fn split_rows(csv_content: &str) -> HashMap<String, String> {
+    let mut lines = csv_content.lines();
+    let header = lines.next().unwrap_or_default();
+
+    lines.filter_map(|row| {
+            let (ticker, _) = row.split_once(',')?;
+
+            Some((ticker.trim().to_uppercase(), format!("{}\n{}\n", header, row)))
+        })
+        .collect()
+}
+
+/// Parses the single row of a quote download. Symbols stooq doesn't know
+/// come back as a row of `N/D`.
+pub fn parse_stock_value(csv_content: String, stock: &str) -> Result<Stock, StockError> {
+    let (_, currency) = to_stooq_symbol(stock)?;
+
+    let row: QuoteRow = csv::Reader::from_reader(csv_content.as_bytes())
+        .deserialize()
+        .next()
+        .ok_or_else(|| {
+            StockError::SymbolNotFound(stock.to_string())
+        })?
+        .map_err(parse_error)?;
+
+    let price = number::<Decimal>(&row.close)
+        .ok_or_else(|| {
+            StockError::SymbolNotFound(stock.to_string())
+        })?;
+
+    let stock_code = stock
+        .to_uppercase()
+        .split(":")
+        .next()
+        .unwrap_or(stock)
+        .to_string();
+
+    let details = QuoteDetails {
+        day_range: number(&row.low).zip(number(&row.high)).map(|(low, high)| PriceRange { low, high }),
+        volume: number::<f64>(&row.volume).map(|volume| volume as u64),
+        ..QuoteDetails::default()
+    };
+
+    Ok(Stock::new(stock_code, row.name, price)
+        .with_currency(currency.to_string())
+        .with_details(details))
+}
+
+pub fn fetch_from_stooq(fetcher: &Fetcher, stock: &str) -> Result<String, StockError> {
+    let (symbol, _) = to_stooq_symbol(stock)?;
+
+    fetcher.fetch_page(&format!("{}?s={}&f=sd2t2ohlcvn&h&e=csv", QUOTE_URL, symbol))
+}
+
+/// Downloads the quotes of several symbols at once, a row each.
+pub fn fetch_batch_from_stooq(fetcher: &Fetcher, stocks: &[String]) -> Result<String, StockError> {
+    let symbols = stocks.iter()
+        .map(|stock| to_stooq_symbol(stock).map(|(symbol, _)| symbol))
+        .collect::<Result<Vec<String>, StockError>>()?;
+
+    fetcher.fetch_page(&format!("{}?s={}&f=sd2t2ohlcvn&h&e=csv", QUOTE_URL, symbols.join("+")))
+}
+
+/// Parses the daily bars of a history download, oldest first. A range
+/// without trading days comes back as `No data`.
+pub fn parse_history(csv_content: String, stock: &str) -> Result<Vec<Candle>, StockError> {
+    if csv_content.trim() == "No data" {
+        return Ok(Vec::new());
+    }
+
+    let mut candles = Vec::new();
+
+    for row in csv::Reader::from_reader(csv_content.as_bytes()).deserialize::<HistoryRow>() {
+        let row = row.map_err(parse_error)?;
+        let date = NaiveDate::parse_from_str(&row.date, "%Y-%m-%d")
+            .map_err(|err| {
+                StockError::Response(format!("{}: {}: {}", stock, row.date, err).into())
+            })?;
+
+        let (Some(open), Some(high), Some(low), Some(close)) = (number(&row.open), number(&row.high), number(&row.low), number(&row.close)) else {
+            continue;
+        };
+
+        candles.push(Candle {
+            timestamp: date.and_time(NaiveTime::MIN).and_utc(),
+            open,
+            high,
+            low,
+            close,
+            volume: row.volume.as_deref().and_then(number::<f64>).map(|volume| volume as u64),
+        });
+    }
+
+    Ok(candles)
+}
+
+pub fn fetch_history_from_stooq(fetcher: &Fetcher, stock: &str, from: NaiveDate, to: NaiveDate) -> Result<String, StockError> {
+    let (symbol, _) = to_stooq_symbol(stock)?;
+
+    fetcher.fetch_page(&format!("{}?s={}&d1={}&d2={}&i=d", HISTORY_URL, symbol, from.format("%Y%m%d"), to.format("%Y%m%d")))
+}
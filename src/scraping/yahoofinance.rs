@@ -0,0 +1,501 @@
+use std::{collections::HashMap, sync::Arc};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+
+use crate::{calendar::{CorporateEvent, EventKind}, candle::Candle, http::{BoxFuture, Fetcher}, market::MarketStatus, scraping::{self, replay::Recorder, CalendarProvider, HistoryProvider, QuoteProvider}, symbol, Decimal, PriceRange, QuoteDetails, QuoteKind, Stock, StockError};
+
+const BASE_URL: &str = "https://query1.finance.yahoo.com/v8/finance/chart/";
+/// Charts of several symbols in one response.
+const SPARK_URL: &str = "https://query1.finance.yahoo.com/v7/finance/spark";
+/// Company data such as the earnings calendar, one module at a time.
+const SUMMARY_URL: &str = "https://query2.finance.yahoo.com/v10/finance/quoteSummary/";
+/// Symbols quoted per request, as many as the spark endpoint takes.
+const BATCH_SIZE: usize = 20;
+
+
+#[derive(Debug, Clone, Default)]
+pub struct YahooFinance {
+    fetcher: Fetcher,
+    recorder: Option<Recorder>,
+}
+
+impl YahooFinance {
+    pub fn new(fetcher: Fetcher) -> Self {
+        Self { fetcher, recorder: None }
+    }
+
+    /// Saves every fetched quote response to `recorder` before parsing it.
+    pub fn record_to(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    fn read_quote(&self, symbol: &str, json_content: String) -> Result<Stock, StockError> {
+        if let Some(recorder) = &self.recorder {
+            recorder.save(symbol, "json", &json_content);
+        }
+
+        parse_stock_value(json_content, symbol)
+    }
+
+    /// Reads the quote of every symbol of a batch from a spark response.
+    fn read_batch(&self, symbols: &[String], json_content: &str) -> Result<Vec<Result<Stock, StockError>>, StockError> {
+        let mut charts = split_charts(json_content)?;
+
+        let stocks = symbols.iter()
+            .map(|symbol| {
+                let json_content = charts.remove(&to_yahoo_symbol(symbol))
+                    .ok_or_else(|| {
+                        StockError::SymbolNotFound(symbol.to_string())
+                    })?;
+
+                // Saved as if fetched on its own, so it replays the same.
+                self.read_quote(symbol, json_content)
+            })
+            .collect();
+
+        Ok(stocks)
+    }
+}
+
+impl QuoteProvider for YahooFinance {
+    fn name(&self) -> &str {
+        "yahoo"
+    }
+
+    fn fetch(&self, symbol: &str) -> Result<Stock, StockError> {
+        let json_content = fetch_from_yahoo_finance(&self.fetcher, symbol)?;
+
+        self.read_quote(symbol, json_content)
+    }
+
+    fn async_fetch<'a>(self: Arc<Self>, symbol: &'a str) -> BoxFuture<'a, Result<Stock, StockError>> {
+        Box::pin(async move {
+            let json_content = self.fetcher.async_fetch_page(&chart_url(symbol)).await?;
+
+            self.read_quote(symbol, json_content)
+        })
+    }
+
+    fn batches(&self, symbols: &[String]) -> Vec<Vec<String>> {
+        scraping::chunked(symbols, BATCH_SIZE)
+    }
+
+    fn fetch_batch(&self, symbols: &[String]) -> Result<Vec<Result<Stock, StockError>>, StockError> {
+        if let [symbol] = symbols {
+            return Ok(vec![self.fetch(symbol)]);
+        }
+
+        self.read_batch(symbols, &fetch_batch_from_yahoo_finance(&self.fetcher, symbols)?)
+    }
+
+    fn async_fetch_batch<'a>(self: Arc<Self>, symbols: &'a [String]) -> BoxFuture<'a, Result<Vec<Result<Stock, StockError>>, StockError>> {
+        Box::pin(async move {
+            if let [symbol] = symbols {
+                return Ok(vec![self.async_fetch(symbol).await]);
+            }
+
+            let json_content = self.fetcher.async_fetch_page(&spark_url(symbols)).await?;
+
+            self.read_batch(symbols, &json_content)
+        })
+    }
+}
+
+impl HistoryProvider for YahooFinance {
+    fn name(&self) -> &str {
+        "yahoo"
+    }
+
+    fn fetch_history(&self, symbol: &str, from: NaiveDate, to: NaiveDate) -> Result<Vec<Candle>, StockError> {
+        let json_content = fetch_history_from_yahoo_finance(&self.fetcher, symbol, from, to)?;
+
+        parse_history(json_content, symbol)
+    }
+}
+
+impl CalendarProvider for YahooFinance {
+    fn name(&self) -> &str {
+        "yahoo"
+    }
+
+    fn fetch_calendar(&self, symbol: &str) -> Result<Vec<CorporateEvent>, StockError> {
+        let json_content = fetch_calendar_from_yahoo_finance(&self.fetcher, symbol)?;
+
+        parse_calendar(&json_content, symbol)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChartResponse {
+    chart: Chart,
+}
+
+#[derive(Debug, Deserialize)]
+struct Chart {
+    result: Option<Vec<ChartResult>>,
+    error: Option<ChartError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChartResult {
+    meta: ChartMeta,
+    #[serde(default)]
+    timestamp: Vec<i64>,
+    indicators: Option<Indicators>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Indicators {
+    #[serde(default)]
+    quote: Vec<QuoteSeries>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct QuoteSeries {
+    open: Vec<Option<Decimal>>,
+    high: Vec<Option<Decimal>>,
+    low: Vec<Option<Decimal>>,
+    close: Vec<Option<Decimal>>,
+    volume: Vec<Option<u64>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChartMeta {
+    regular_market_price: Option<Decimal>,
+    chart_previous_close: Option<Decimal>,
+    regular_market_day_low: Option<Decimal>,
+    regular_market_day_high: Option<Decimal>,
+    fifty_two_week_low: Option<Decimal>,
+    fifty_two_week_high: Option<Decimal>,
+    regular_market_volume: Option<u64>,
+    current_trading_period: Option<TradingPeriods>,
+    currency: Option<String>,
+    /// `EQUITY`, `ETF`, `MUTUALFUND`, ...
+    instrument_type: Option<String>,
+    long_name: Option<String>,
+    short_name: Option<String>,
+}
+
+/// The sessions of the current (or last) trading day.
+#[derive(Debug, Deserialize)]
+struct TradingPeriods {
+    pre: TradingPeriod,
+    regular: TradingPeriod,
+    post: TradingPeriod,
+}
+
+#[derive(Debug, Deserialize)]
+struct TradingPeriod {
+    start: i64,
+    end: i64,
+}
+
+impl TradingPeriods {
+    fn status_at(&self, timestamp: i64) -> MarketStatus {
+        let within = |period: &TradingPeriod| (period.start..period.end).contains(&timestamp);
+
+        if within(&self.regular) {
+            MarketStatus::Open
+        } else if within(&self.pre) {
+            MarketStatus::PreMarket
+        } else if within(&self.post) {
+            MarketStatus::AfterHours
+        } else {
+            MarketStatus::Closed
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChartError {
+    code: String,
+    description: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SparkResponse {
+    spark: Spark,
+}
+
+#[derive(Debug, Deserialize)]
+struct Spark {
+    result: Option<Vec<SparkResult>>,
+    error: Option<ChartError>,
+}
+
+/// The chart of one symbol, kept as is to be read like a chart response.
+#[derive(Debug, Deserialize)]
+struct SparkResult {
+    symbol: String,
+    #[serde(default)]
+    response: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SummaryResponse {
+    #[serde(rename = "quoteSummary")]
+    quote_summary: Summary,
+}
+
+#[derive(Debug, Deserialize)]
+struct Summary {
+    result: Option<Vec<SummaryResult>>,
+    error: Option<ChartError>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SummaryResult {
+    calendar_events: Option<CalendarEvents>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct CalendarEvents {
+    earnings: EarningsEvents,
+    ex_dividend_date: SummaryDate,
+}
+
+/// Lists the day of the next report, or the first and last day it may be
+/// on while the company hasn't said.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct EarningsEvents {
+    earnings_date: Vec<SummaryDate>,
+}
+
+/// Yahoo sends `{}` for dates it doesn't have.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SummaryDate {
+    raw: Option<i64>,
+}
+
+impl SummaryDate {
+    fn date(&self) -> Option<NaiveDate> {
+        Some(DateTime::<Utc>::from_timestamp(self.raw?, 0)?.date_naive())
+    }
+}
+
+/// Maps a Google-style `SYMBOL:EXCHANGE` code to the ticker Yahoo expects,
+/// e.g. `BBCA:IDX` becomes `BBCA.JK` and `.INX:INDEXSP` becomes `^GSPC`.
+pub fn to_yahoo_symbol(stock: &str) -> String {
+    let stock = stock.to_uppercase();
+
+    match stock.as_str() {
+        ".INX:INDEXSP" => return "^GSPC".to_string(),
+        "COMPOSITE:IDX" => return "^JKSE".to_string(),
+        "LQ45:IDX" => return "^JKLQ45".to_string(),
+        // Yahoo's other index tickers are Google's with a caret for the dot.
+        code if symbol::is_index(code) => return format!("^{}", code.split(':').next().unwrap_or_default().trim_start_matches('.')),
+        _ => {}
+    }
+    let mut parts = stock.split(':');
+
+    let symbol = parts.next().unwrap_or_default();
+
+    let suffix = match parts.next() {
+        // Pairs read like `USDIDR=X`.
+        Some("FX") => return format!("{}=X", symbol.replace('-', "")),
+        Some("IDX") => ".JK",
+        Some("LON") => ".L",
+        Some("TYO") => ".T",
+        Some("HKG") => ".HK",
+        Some("ASX") => ".AX",
+        Some("TSE") => ".TO",
+        Some("FRA") => ".F",
+        Some("ETR") => ".DE",
+        Some("EPA") => ".PA",
+        Some("SGX") => ".SI",
+        Some("KRX") => ".KS",
+        _ => "",
+    };
+
+    format!("{}{}", symbol, suffix)
+}
+
+fn parse_chart(json_content: &str, stock: &str) -> Result<ChartResult, StockError> {
+    let response: ChartResponse = serde_json::from_str(json_content)
+        .map_err(|err| {
+            StockError::Response(err.into())
+        })?;
+
+    if let Some(err) = response.chart.error {
+        return Err(match err.code.as_str() {
+            "Not Found" => StockError::SymbolNotFound(format!("{}: {}", stock, err.description)),
+            code => StockError::Response(format!("{}: {}", code, err.description).into()),
+        });
+    }
+
+    response.chart.result
+        .and_then(|results| results.into_iter().next())
+        .ok_or_else(|| {
+            StockError::SymbolNotFound(stock.to_string())
+        })
+}
+
+pub fn parse_stock_value(json_content: String, stock: &str) -> Result<Stock, StockError> {
+    let meta = parse_chart(&json_content, stock)?.meta;
+    // A chart without a price is no quote at all, rather than one at 0.
+    let price = meta.regular_market_price
+        .ok_or_else(|| {
+            StockError::PriceParse(format!("no regularMarketPrice for {} on Yahoo Finance", stock))
+        })?;
+
+    let company_name = meta.long_name
+        .or(meta.short_name)
+        .unwrap_or("N/A".to_string());
+
+    let stock_code = stock
+        .to_uppercase()
+        .split(":")
+        .next()
+        .unwrap_or(stock)
+        .to_string();
+
+    let range = |low: Option<Decimal>, high: Option<Decimal>| Some(PriceRange { low: low?, high: high? });
+    let details = QuoteDetails {
+        previous_close: meta.chart_previous_close,
+        day_range: range(meta.regular_market_day_low, meta.regular_market_day_high),
+        year_range: range(meta.fifty_two_week_low, meta.fifty_two_week_high),
+        market_cap: None,
+        volume: meta.regular_market_volume,
+        lot_size: None,
+    };
+
+    let kind = match meta.instrument_type.as_deref() {
+        Some("ETF") => QuoteKind::Etf,
+        Some("MUTUALFUND") => QuoteKind::Fund,
+        Some("INDEX") => QuoteKind::Index,
+        _ => QuoteKind::Equity,
+    };
+
+    // Indices are in points, whatever currency Yahoo reports.
+    let currency = match kind {
+        QuoteKind::Index => String::new(),
+        _ => meta.currency.unwrap_or_default().to_uppercase(),
+    };
+
+    Ok(Stock::new(stock_code, company_name, price)
+        .with_currency(currency)
+        .with_kind(kind)
+        .with_details(details)
+        .with_market_status(meta.current_trading_period.map(|periods| periods.status_at(Utc::now().timestamp()))))
+}
+
+fn chart_url(stock: &str) -> String {
+    format!("{}{}", BASE_URL, to_yahoo_symbol(stock))
+}
+
+pub fn fetch_from_yahoo_finance(fetcher: &Fetcher, stock: &str) -> Result<String, StockError> {
+    fetcher.fetch_page(&chart_url(stock))
+}
+
+/// Splits a spark response into a chart response per symbol, keyed by
+/// Yahoo's ticker in upper case. Symbols Yahoo doesn't know are left out.
+pub fn split_charts(json_content: &str) -> Result<HashMap<String, String>, StockError> {
+    let response: SparkResponse = serde_json::from_str(json_content)
+        .map_err(|err| {
+            StockError::Response(err.into())
+        })?;
+
+    if let Some(err) = response.spark.error {
+        return Err(StockError::Response(format!("{}: {}", err.code, err.description).into()));
+    }
+
+    let charts = response.spark.result.unwrap_or_default().into_iter()
+        .filter_map(|result| {
+            let chart = serde_json::json!({ "chart": { "result": [result.response.into_iter().next()?], "error": null } });
+
+            Some((result.symbol.to_uppercase(), chart.to_string()))
+        })
+        .collect();
+
+    Ok(charts)
+}
+
+/// Downloads the charts of several symbols at once.
+pub fn fetch_batch_from_yahoo_finance(fetcher: &Fetcher, stocks: &[String]) -> Result<String, StockError> {
+    fetcher.fetch_page(&spark_url(stocks))
+}
+
+fn spark_url(stocks: &[String]) -> String {
+    let symbols = stocks.iter().map(|stock| to_yahoo_symbol(stock)).collect::<Vec<String>>();
+
+    format!("{}?symbols={}&range=1d&interval=1d", SPARK_URL, symbols.join(","))
+}
+
+/// Parses the daily bars of a chart response, skipping days Yahoo reports
+/// without a complete set of prices.
+pub fn parse_history(json_content: String, stock: &str) -> Result<Vec<Candle>, StockError> {
+    let result = parse_chart(&json_content, stock)?;
+    let series = result.indicators
+        .and_then(|indicators| indicators.quote.into_iter().next())
+        .unwrap_or_default();
+
+    let candles = result.timestamp.iter()
+        .enumerate()
+        .filter_map(|(index, timestamp)| {
+            let price = |values: &Vec<Option<Decimal>>| values.get(index).copied().flatten();
+
+            Some(Candle {
+                timestamp: DateTime::<Utc>::from_timestamp(*timestamp, 0)?,
+                open: price(&series.open)?,
+                high: price(&series.high)?,
+                low: price(&series.low)?,
+                close: price(&series.close)?,
+                volume: series.volume.get(index).copied().flatten(),
+            })
+        })
+        .collect();
+
+    Ok(candles)
+}
+
+pub fn fetch_history_from_yahoo_finance(fetcher: &Fetcher, stock: &str, from: NaiveDate, to: NaiveDate) -> Result<String, StockError> {
+    let period1 = from.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc().timestamp();
+    let period2 = to.succ_opt().unwrap_or(to).and_hms_opt(0, 0, 0).unwrap_or_default().and_utc().timestamp();
+
+    fetcher.fetch_page(&format!("{}{}?period1={}&period2={}&interval=1d", BASE_URL, to_yahoo_symbol(stock), period1, period2))
+}
+
+/// Parses the next earnings date, the earliest when Yahoo gives a range,
+/// and the next ex-dividend date of a calendar events summary.
+pub fn parse_calendar(json_content: &str, stock: &str) -> Result<Vec<CorporateEvent>, StockError> {
+    let response: SummaryResponse = serde_json::from_str(json_content)
+        .map_err(|err| {
+            StockError::Response(err.into())
+        })?;
+
+    if let Some(err) = response.quote_summary.error {
+        return Err(match err.code.as_str() {
+            "Not Found" => StockError::SymbolNotFound(format!("{}: {}", stock, err.description)),
+            code => StockError::Response(format!("{}: {}", code, err.description).into()),
+        });
+    }
+
+    let calendar = response.quote_summary.result
+        .and_then(|results| results.into_iter().next())
+        .ok_or_else(|| {
+            StockError::SymbolNotFound(stock.to_string())
+        })?
+        .calendar_events
+        .unwrap_or_default();
+
+    let symbol = stock.to_uppercase().split(':').next().unwrap_or(stock).to_string();
+    let earnings = calendar.earnings.earnings_date.iter().filter_map(SummaryDate::date).min();
+    let ex_dividend = calendar.ex_dividend_date.date();
+
+    let events = [(EventKind::Earnings, earnings), (EventKind::ExDividend, ex_dividend)].into_iter()
+        .filter_map(|(kind, date)| Some(CorporateEvent { symbol: symbol.clone(), kind, date: date? }))
+        .collect();
+
+    Ok(events)
+}
+
+pub fn fetch_calendar_from_yahoo_finance(fetcher: &Fetcher, stock: &str) -> Result<String, StockError> {
+    fetcher.fetch_page(&format!("{}{}?modules=calendarEvents", SUMMARY_URL, to_yahoo_symbol(stock)))
+}
@@ -0,0 +1,258 @@
+use std::path::Path;
+#[cfg(feature = "lua")]
+use std::{collections::BTreeMap, fmt, fs, path::PathBuf, sync::{atomic::{AtomicU32, Ordering}, Arc}, time::{Duration, Instant, SystemTime}};
+#[cfg(feature = "lua")]
+use mlua::{Function, HookTriggers, Lua, LuaSerdeExt, Table, Value, VmState};
+#[cfg(feature = "lua")]
+use tracing::{error, info};
+
+#[cfg(feature = "lua")]
+use crate::Decimal;
+use crate::{Stock, StockError};
+
+/// How often the script file is checked for changes.
+#[cfg(feature = "lua")]
+const RELOAD_CHECK: Duration = Duration::from_secs(2);
+
+/// Instructions between two checks of the budget.
+#[cfg(feature = "lua")]
+const BUDGET_STEP: u32 = 1_000;
+
+/// Checks one call may take, so that a script stuck in a loop fails instead
+/// of stalling the quotes: about ten million instructions.
+#[cfg(feature = "lua")]
+const BUDGET_STEPS: u32 = 10_000;
+
+
+/// A Lua script given with `--script`, reloaded when its file changes. It
+/// may define `transform(quote)`, called with every fetched quote before
+/// anything else sees it, and `alert(quote)`, called once the quote has its
+/// indicators.
+///
+/// `quote` is a table with `symbol`, `company`, `price`, `currency`,
+/// `previous_price`, `change_pct`, `timestamp` (Unix seconds), the
+/// indicators (`sma`, `ema`, `rsi`, `macd`, `macd_signal`, `lower_band`,
+/// `upper_band`, nil until known) and `fields`, the custom fields. From
+/// `transform`, the changes to `company`, `price`, `currency` and `fields`
+/// are kept, whether made in place or on a table returned instead; `false`
+/// drops the quote. `alert` returns a message, or `true`, to fire an alert.
+#[cfg(feature = "lua")]
+#[derive(Clone)]
+pub struct Script {
+    path: PathBuf,
+    lua: Lua,
+    modified: Option<SystemTime>,
+    checked: Instant,
+    /// Budget steps taken by the current call.
+    steps: Arc<AtomicU32>,
+}
+
+#[cfg(not(feature = "lua"))]
+#[derive(Debug, Clone)]
+pub struct Script {
+    never: std::convert::Infallible,
+}
+
+#[cfg(feature = "lua")]
+impl Script {
+    pub fn load(path: &Path) -> Result<Self, StockError> {
+        let steps = Arc::new(AtomicU32::new(0));
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            lua: run(path, &steps)?,
+            modified: modified_at(path),
+            checked: Instant::now(),
+            steps,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reruns the file when it changed since it was last run, keeping the
+    /// current script when it fails.
+    pub fn reload_if_changed(&mut self) {
+        if self.checked.elapsed() < RELOAD_CHECK {
+            return;
+        }
+
+        self.checked = Instant::now();
+        let modified = modified_at(&self.path);
+
+        if modified == self.modified {
+            return;
+        }
+
+        self.modified = modified;
+
+        match run(&self.path, &self.steps) {
+            Ok(lua) => {
+                self.lua = lua;
+                info!("Reloaded script from {}", self.path.display());
+            }
+            Err(err) => error!("Keeping the previous script: {}", err),
+        }
+    }
+
+    /// Applies the script's `transform` to `stock`; `false` when the script
+    /// drops it.
+    pub fn transform(&self, stock: &mut Stock) -> Result<bool, StockError> {
+        let Some(transform) = self.function("transform")? else {
+            return Ok(true);
+        };
+
+        let quote = self.quote(stock).map_err(failed)?;
+
+        match self.call(&transform, quote.clone())? {
+            Value::Boolean(false) => Ok(false),
+            Value::Nil | Value::Boolean(true) => self.apply(&quote, stock).map(|()| true).map_err(failed),
+            Value::Table(changed) => self.apply(&changed, stock).map(|()| true).map_err(failed),
+            other => Err(StockError::invalid("script", format!("transform returned a {}, expected a table, nil or false", other.type_name()))),
+        }
+    }
+
+    /// The message of the alert the script's `alert` fires for `stock`.
+    pub fn alert(&self, stock: &Stock) -> Result<Option<String>, StockError> {
+        let Some(alert) = self.function("alert")? else {
+            return Ok(None);
+        };
+
+        let quote = self.quote(stock).map_err(failed)?;
+
+        match self.call(&alert, quote)? {
+            Value::Nil | Value::Boolean(false) => Ok(None),
+            Value::Boolean(true) => Ok(Some("matched the script".to_string())),
+            Value::String(message) => Ok(Some(message.to_string_lossy())),
+            other => Err(StockError::invalid("script", format!("alert returned a {}, expected a string, a boolean or nil", other.type_name()))),
+        }
+    }
+
+    fn function(&self, name: &str) -> Result<Option<Function>, StockError> {
+        self.lua.globals().get::<Option<Function>>(name).map_err(failed)
+    }
+
+    fn call(&self, function: &Function, quote: Table) -> Result<Value, StockError> {
+        self.steps.store(0, Ordering::Relaxed);
+        function.call::<Value>(quote).map_err(failed)
+    }
+
+    fn quote(&self, stock: &Stock) -> mlua::Result<Table> {
+        let quote = self.lua.create_table()?;
+        let indicators = stock.indicators.as_ref();
+        let macd = indicators.and_then(|indicators| indicators.macd);
+        let bands = indicators.and_then(|indicators| indicators.bollinger);
+
+        quote.set("symbol", stock.symbol.as_str())?;
+        quote.set("company", stock.company_name.as_str())?;
+        quote.set("price", stock.price.to_f64())?;
+        quote.set("currency", stock.currency.as_str())?;
+        quote.set("previous_price", stock.previous_price.map(Decimal::to_f64))?;
+        quote.set("change_pct", stock.change_pct)?;
+        quote.set("timestamp", stock.fetched_at.timestamp())?;
+        quote.set("sma", indicators.and_then(|indicators| indicators.sma))?;
+        quote.set("ema", indicators.and_then(|indicators| indicators.ema))?;
+        quote.set("rsi", indicators.and_then(|indicators| indicators.rsi))?;
+        quote.set("macd", macd.map(|macd| macd.line))?;
+        quote.set("macd_signal", macd.map(|macd| macd.signal))?;
+        quote.set("lower_band", bands.map(|bands| bands.lower))?;
+        quote.set("upper_band", bands.map(|bands| bands.upper))?;
+        quote.set("fields", self.lua.to_value(&stock.fields)?)?;
+
+        Ok(quote)
+    }
+
+    fn apply(&self, quote: &Table, stock: &mut Stock) -> mlua::Result<()> {
+        if let Some(company) = quote.get::<Option<String>>("company")? {
+            stock.company_name = company;
+        }
+
+        if let Some(currency) = quote.get::<Option<String>>("currency")? {
+            stock.currency = currency;
+        }
+
+        // Only a changed price goes through floating point.
+        if let Some(price) = quote.get::<Option<f64>>("price")?.filter(|price| *price != stock.price.to_f64()) {
+            stock.price = Decimal::from_f64(price).ok_or_else(|| mlua::Error::runtime(format!("price {} is not a number", price)))?;
+        }
+
+        stock.fields = match quote.get::<Value>("fields")? {
+            Value::Nil => BTreeMap::new(),
+            fields => self.lua.from_value(fields)?,
+        };
+
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "lua"))]
+impl Script {
+    pub fn load(_path: &Path) -> Result<Self, StockError> {
+        Err(StockError::FeatureDisabled("--script requires building with --features lua".to_string()))
+    }
+
+    pub fn path(&self) -> &Path {
+        match self.never {}
+    }
+
+    pub fn reload_if_changed(&mut self) {
+        match self.never {}
+    }
+
+    pub fn transform(&self, _stock: &mut Stock) -> Result<bool, StockError> {
+        match self.never {}
+    }
+
+    pub fn alert(&self, _stock: &Stock) -> Result<Option<String>, StockError> {
+        match self.never {}
+    }
+}
+
+#[cfg(feature = "lua")]
+impl fmt::Debug for Script {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Script").field("path", &self.path).finish_non_exhaustive()
+    }
+}
+
+/// Runs the file at `path` in a fresh Lua state, each call of which may
+/// take [`BUDGET_STEPS`] of `steps`.
+#[cfg(feature = "lua")]
+fn run(path: &Path, steps: &Arc<AtomicU32>) -> Result<Lua, StockError> {
+    let source = fs::read_to_string(path)
+        .map_err(|err| {
+            StockError::io(path, err)
+        })?;
+
+    let lua = Lua::new();
+    let counted = steps.clone();
+
+    lua.set_hook(HookTriggers::new().every_nth_instruction(BUDGET_STEP), move |_, _| {
+        match counted.fetch_add(1, Ordering::Relaxed) < BUDGET_STEPS {
+            true => Ok(VmState::Continue),
+            false => Err(mlua::Error::runtime("the script ran for too long")),
+        }
+    })
+    .map_err(failed)?;
+
+    steps.store(0, Ordering::Relaxed);
+    lua.load(source)
+        .set_name(format!("@{}", path.display()))
+        .exec()
+        .map_err(|err| {
+            StockError::parse(path.display(), err)
+        })?;
+
+    Ok(lua)
+}
+
+#[cfg(feature = "lua")]
+fn failed(err: mlua::Error) -> StockError {
+    StockError::runtime("script", err)
+}
+
+#[cfg(feature = "lua")]
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
@@ -0,0 +1,142 @@
+use std::{fmt, path::Path};
+use serde::{de, Deserializer};
+
+use crate::StockError;
+
+/// Service the credentials are filed under in the OS keychain.
+pub const KEYCHAIN_SERVICE: &str = "scraping-stock";
+
+/// Variables the command line takes credentials from. Each can instead be
+/// given as `NAME_FILE`, the path of a file holding the value, the way Docker
+/// mounts secrets.
+pub const SECRET_VARS: &[&str] = &[
+    "ALPHAVANTAGE_API_KEY",
+    "FINNHUB_API_KEY",
+    "TELEGRAM_BOT_TOKEN",
+    "DISCORD_WEBHOOK_URL",
+    "SLACK_WEBHOOK_URL",
+    "SMTP_PASSWORD",
+    "INFLUXDB_TOKEN",
+    "POSTGRES_URL",
+    "REDIS_URL",
+];
+
+
+/// The secret in the file at `path`, without its trailing newline.
+pub fn read_file(path: &Path) -> Result<String, StockError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|err| {
+            StockError::io(path, err)
+        })?;
+
+    Ok(content.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Sets each of `names` from the file its `NAME_FILE` variable points at.
+/// Setting both is an error. Call it before any thread exists.
+pub fn load_env_files(names: &[&str]) -> Result<(), StockError> {
+    for name in names {
+        let Some(path) = std::env::var_os(format!("{}_FILE", name)) else {
+            continue;
+        };
+
+        if std::env::var_os(name).is_some() {
+            return Err(StockError::invalid("secret", format!("both {} and {}_FILE are set", name, name)));
+        }
+
+        std::env::set_var(name, read_file(Path::new(&path))?);
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "keychain")]
+fn entry(name: &str) -> Result<keyring::Entry, StockError> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, name)
+        .map_err(|err| {
+            StockError::runtime("keychain", err)
+        })
+}
+
+/// The secret stored as `name` in the OS keychain.
+#[cfg(feature = "keychain")]
+pub fn keychain(name: &str) -> Result<String, StockError> {
+    entry(name)?.get_password()
+        .map_err(|err| match err {
+            keyring::Error::NoEntry => StockError::invalid("secret", format!("no {} in the keychain, store it with `secret set {}`", name, name)),
+            err => StockError::runtime("keychain", err),
+        })
+}
+
+#[cfg(not(feature = "keychain"))]
+pub fn keychain(_name: &str) -> Result<String, StockError> {
+    Err(StockError::FeatureDisabled("the keychain requires building with --features keychain".to_string()))
+}
+
+/// Stores `value` as `name` in the OS keychain, replacing any secret stored
+/// there before.
+#[cfg(feature = "keychain")]
+pub fn store(name: &str, value: &str) -> Result<(), StockError> {
+    entry(name)?.set_password(value)
+        .map_err(|err| {
+            StockError::runtime("keychain", err)
+        })
+}
+
+#[cfg(not(feature = "keychain"))]
+pub fn store(name: &str, _value: &str) -> Result<(), StockError> {
+    keychain(name).map(|_| ())
+}
+
+/// Removes the secret stored as `name` from the OS keychain.
+#[cfg(feature = "keychain")]
+pub fn delete(name: &str) -> Result<(), StockError> {
+    entry(name)?.delete_credential()
+        .map_err(|err| match err {
+            keyring::Error::NoEntry => StockError::invalid("secret", format!("no {} in the keychain", name)),
+            err => StockError::runtime("keychain", err),
+        })
+}
+
+#[cfg(not(feature = "keychain"))]
+pub fn delete(name: &str) -> Result<(), StockError> {
+    keychain(name).map(|_| ())
+}
+
+/// Deserializes a credential given as text, as `{ file = "PATH" }` or as
+/// `{ keychain = "NAME" }`, reading the latter two right away. For use with
+/// `#[serde(deserialize_with)]` on an `Option<String>`.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    deserializer.deserialize_any(SecretVisitor).map(Some)
+}
+
+struct SecretVisitor;
+
+impl<'de> de::Visitor<'de> for SecretVisitor {
+    type Value = String;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("text, { file = PATH } or { keychain = NAME }")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(value.to_string())
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let Some((source, name)) = map.next_entry::<String, String>()? else {
+            return Err(de::Error::invalid_length(0, &self));
+        };
+
+        if map.next_key::<de::IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(2, &self));
+        }
+
+        match source.as_str() {
+            "file" => read_file(Path::new(&name)),
+            "keychain" => keychain(&name),
+            _ => return Err(de::Error::unknown_field(&source, &["file", "keychain"])),
+        }
+            .map_err(de::Error::custom)
+    }
+}
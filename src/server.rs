@@ -0,0 +1,297 @@
+use std::{collections::{BTreeMap, HashMap, VecDeque}, net::SocketAddr, sync::{Arc, Mutex}, thread};
+use axum::{
+    extract::{ws::{Message, WebSocket, WebSocketUpgrade}, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+use crate::{health::{Health, HealthStatus}, notify::Dispatcher, output::{Output, QuoteRecord, SnapshotRecord}, quotes::QuoteStore, shutdown::Shutdown, stats::{Stats, StatsReport}, watch::{self, WatchOptions}, Stock, StockClient, StockError};
+
+/// Updates buffered per subscriber before a slow client starts missing some.
+const STREAM_BUFFER: usize = 256;
+/// Updates kept in memory per symbol for `GET /history/{symbol}`.
+const RECENT_HISTORY: usize = 500;
+const DEFAULT_HISTORY_LIMIT: usize = 20;
+
+
+pub struct ServerOptions {
+    pub address: SocketAddr,
+    /// Serve `GET /history/{symbol}` from this database instead of the
+    /// updates seen since the server started.
+    #[cfg(feature = "sqlite")]
+    pub database: Option<std::path::PathBuf>,
+}
+
+/// A quote as last seen by the server.
+#[derive(Debug, Clone)]
+struct Quote {
+    timestamp: DateTime<Utc>,
+    stock: Stock,
+}
+
+impl Quote {
+    fn record(&self) -> QuoteRecord<'_> {
+        QuoteRecord { timestamp: self.timestamp, stock: &self.stock, details: None }
+    }
+}
+
+#[derive(Debug, Default)]
+struct QuoteBook {
+    latest: BTreeMap<String, Quote>,
+    recent: HashMap<String, VecDeque<Quote>>,
+}
+
+impl QuoteBook {
+    fn insert(&mut self, quote: Quote) {
+        let recent = self.recent.entry(quote.stock.symbol.clone()).or_default();
+
+        if recent.len() == RECENT_HISTORY {
+            recent.pop_front();
+        }
+
+        recent.push_back(quote.clone());
+        self.latest.insert(quote.stock.symbol.clone(), quote);
+    }
+}
+
+#[derive(Clone)]
+struct ServerState {
+    updates: broadcast::Sender<String>,
+    quotes: Arc<Mutex<QuoteBook>>,
+    health: Health,
+    stats: Stats,
+    latest: QuoteStore,
+    codes: Arc<Vec<String>>,
+    #[cfg(feature = "sqlite")]
+    store: Option<Arc<Mutex<crate::storage::sqlite::SqliteStore>>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    limit: Option<usize>,
+}
+
+struct ApiError(StatusCode, StockError);
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    code: &'a str,
+    message: &'a str,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let ApiError(status, err) = self;
+
+        (status, Json(ErrorBody { code: err.code(), message: &err.to_string() })).into_response()
+    }
+}
+
+/// Runs the polling loop in the background and serves its updates until a
+/// shutdown is requested.
+///
+/// - `GET /stream` upgrades to a WebSocket that receives every new quote as a
+///   JSON message.
+/// - `GET /quotes` and `GET /quotes/{symbol}` return the latest quotes.
+/// - `GET /history/{symbol}?limit=N` returns the most recent quotes of a
+///   symbol, oldest first.
+/// - `GET /healthz` reports when each symbol was last fetched, with a 503
+///   while every one of them is failing.
+/// - `GET /stats` reports the fetch latency percentiles, success rate, parse
+///   failures and HTTP errors of each symbol and of all of them.
+/// - `GET /snapshot` returns the last quote of every symbol at once, with its
+///   status, indicators and key statistics.
+pub fn run(client: StockClient, options: WatchOptions, output: Output, dispatcher: Dispatcher, shutdown: Shutdown, use_async: bool, server: ServerOptions) -> Result<(), StockError> {
+    let (updates, _) = broadcast::channel::<String>(STREAM_BUFFER);
+    let quotes = Arc::new(Mutex::new(QuoteBook::default()));
+
+    let state = ServerState {
+        updates: updates.clone(),
+        quotes: quotes.clone(),
+        health: options.health.clone(),
+        stats: options.stats.clone(),
+        latest: options.quotes.clone(),
+        codes: Arc::new(options.codes.clone()),
+        #[cfg(feature = "sqlite")]
+        store: match &server.database {
+            Some(path) => Some(Arc::new(Mutex::new(crate::storage::sqlite::SqliteStore::open(path)?))),
+            None => None,
+        },
+    };
+
+    let output = output.on_emit(move |record| {
+        if let Ok(message) = serde_json::to_string(record) {
+            let _ = updates.send(message);
+        }
+
+        if let Ok(mut quotes) = quotes.lock() {
+            quotes.insert(Quote { timestamp: record.timestamp, stock: record.stock.clone() });
+        }
+    });
+
+    let poller_shutdown = shutdown.clone();
+    let poller = thread::spawn(move || match use_async {
+        true => watch::async_determine_stock_status(client, options, output, dispatcher, poller_shutdown),
+        false => watch::determine_stock_status(client, options, output, dispatcher, poller_shutdown),
+    });
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(server_error)?;
+
+    let result = runtime.block_on(serve(server.address, state, shutdown.clone()));
+
+    shutdown.request();
+
+    if poller.join().is_err() {
+        error!("Polling thread panicked");
+    }
+
+    result
+}
+
+async fn serve(addr: SocketAddr, state: ServerState, shutdown: Shutdown) -> Result<(), StockError> {
+    let router = Router::new()
+        .route("/stream", get(stream))
+        .route("/quotes", get(list_quotes))
+        .route("/quotes/{symbol}", get(get_quote))
+        .route("/history/{symbol}", get(get_history))
+        .route("/healthz", get(healthz))
+        .route("/stats", get(get_stats))
+        .route("/snapshot", get(get_snapshot))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(addr).await.map_err(server_error)?;
+
+    info!("Serving quotes on http://{} (stream at ws://{}/stream)", addr, addr);
+
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async move { shutdown.async_wait().await })
+        .await
+        .map_err(server_error)
+}
+
+async fn stream(ws: WebSocketUpgrade, State(state): State<ServerState>) -> impl IntoResponse {
+    let updates = state.updates.subscribe();
+
+    ws.on_upgrade(move |socket| forward_updates(socket, updates))
+}
+
+async fn forward_updates(mut socket: WebSocket, mut updates: broadcast::Receiver<String>) {
+    loop {
+        match updates.recv().await {
+            Ok(message) => {
+                if socket.send(Message::Text(message.into())).await.is_err() {
+                    return;
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("WebSocket client lagging, skipped {} updates", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => {
+                let _ = socket.send(Message::Close(None)).await;
+                return;
+            }
+        }
+    }
+}
+
+async fn list_quotes(State(state): State<ServerState>) -> Result<Response, ApiError> {
+    let quotes = lock_quotes(&state)?;
+    let records: Vec<QuoteRecord> = quotes.latest.values().map(Quote::record).collect();
+
+    Ok(Json(records).into_response())
+}
+
+async fn get_quote(State(state): State<ServerState>, Path(symbol): Path<String>) -> Result<Response, ApiError> {
+    let symbol = bare_symbol(&symbol);
+    let quotes = lock_quotes(&state)?;
+
+    match quotes.latest.get(&symbol) {
+        Some(quote) => Ok(Json(quote.record()).into_response()),
+        None => Err(not_found(&symbol)),
+    }
+}
+
+async fn get_history(State(state): State<ServerState>, Path(symbol): Path<String>, Query(query): Query<HistoryQuery>) -> Result<Response, ApiError> {
+    let symbol = bare_symbol(&symbol);
+    let limit = query.limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+
+    #[cfg(feature = "sqlite")]
+    if let Some(store) = &state.store {
+        let entries = store.lock()
+            .map_err(|_| internal_error("history store lock poisoned"))?
+            .history(&symbol, limit)
+            .map_err(|err| ApiError(StatusCode::INTERNAL_SERVER_ERROR, err))?;
+
+        if entries.is_empty() {
+            return Err(not_found(&symbol));
+        }
+
+        let records: Vec<QuoteRecord> = entries.iter()
+            .map(|entry| QuoteRecord { timestamp: entry.fetched_at, stock: &entry.stock, details: None })
+            .collect();
+
+        return Ok(Json(records).into_response());
+    }
+
+    let quotes = lock_quotes(&state)?;
+    let Some(recent) = quotes.recent.get(&symbol) else {
+        return Err(not_found(&symbol));
+    };
+
+    let records: Vec<QuoteRecord> = recent.iter()
+        .skip(recent.len().saturating_sub(limit))
+        .map(Quote::record)
+        .collect();
+
+    Ok(Json(records).into_response())
+}
+
+async fn healthz(State(state): State<ServerState>) -> Response {
+    let report = state.health.report(&state.codes);
+    let status = match report.status {
+        HealthStatus::Failing => StatusCode::SERVICE_UNAVAILABLE,
+        HealthStatus::Ok | HealthStatus::Degraded => StatusCode::OK,
+    };
+
+    (status, Json(report)).into_response()
+}
+
+async fn get_stats(State(state): State<ServerState>) -> Json<StatsReport> {
+    Json(state.stats.report(&state.codes))
+}
+
+async fn get_snapshot(State(state): State<ServerState>) -> Response {
+    let quotes = state.latest.snapshot();
+
+    Json(SnapshotRecord::new(&state.codes, &quotes)).into_response()
+}
+
+fn lock_quotes(state: &ServerState) -> Result<std::sync::MutexGuard<'_, QuoteBook>, ApiError> {
+    state.quotes.lock().map_err(|_| internal_error("quote book lock poisoned"))
+}
+
+/// Quotes are keyed by the bare symbol, so `BBCA:IDX` and `bbca` both match `BBCA`.
+fn bare_symbol(symbol: &str) -> String {
+    symbol.to_uppercase().split(':').next().unwrap_or_default().to_string()
+}
+
+fn not_found(symbol: &str) -> ApiError {
+    ApiError(StatusCode::NOT_FOUND, StockError::SymbolNotFound(format!("no quotes for {}", symbol)))
+}
+
+fn internal_error(message: &str) -> ApiError {
+    ApiError(StatusCode::INTERNAL_SERVER_ERROR, StockError::runtime("server", message.to_string()))
+}
+
+fn server_error(err: std::io::Error) -> StockError {
+    StockError::runtime("server", err.to_string())
+}
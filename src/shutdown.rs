@@ -0,0 +1,101 @@
+use std::{sync::{Arc, Condvar, Mutex}, thread, time::Duration};
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+use crate::StockError;
+
+
+/// Shared stop flag set by Ctrl-C / SIGTERM.
+///
+/// Both polling loops sleep through [`Shutdown::wait_timeout`] (or its async
+/// twin) so a request to stop interrupts the pause between polls right away.
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    requested: Mutex<bool>,
+    condvar: Condvar,
+    notify: Notify,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Installs a Ctrl-C / SIGTERM handler that requests a shutdown. A second
+    /// signal while shutting down exits immediately.
+    pub fn install_handler(&self) -> Result<(), StockError> {
+        let shutdown = self.clone();
+
+        ctrlc::set_handler(move || {
+            if shutdown.is_requested() {
+                warn!("Forced exit");
+                std::process::exit(130);
+            }
+
+            info!("Shutting down, waiting for in-flight fetches (press Ctrl-C again to force)");
+            shutdown.request();
+        })
+        .map_err(|err| {
+            StockError::runtime("signal handler", err)
+        })
+    }
+
+    pub fn request(&self) {
+        *self.inner.requested.lock().unwrap_or_else(|err| err.into_inner()) = true;
+        self.inner.condvar.notify_all();
+        self.inner.notify.notify_waiters();
+    }
+
+    /// Requests a shutdown once `timeout` has passed, unless one was
+    /// requested before.
+    pub fn request_after(&self, timeout: Duration) {
+        let shutdown = self.clone();
+
+        thread::spawn(move || {
+            if !shutdown.wait_timeout(timeout) {
+                info!("Ran for {:?}, shutting down", timeout);
+                shutdown.request();
+            }
+        });
+    }
+
+    pub fn is_requested(&self) -> bool {
+        *self.inner.requested.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Blocks for up to `timeout`, returning `true` if a shutdown was requested.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let requested = self.inner.requested.lock().unwrap_or_else(|err| err.into_inner());
+        let (requested, _) = self.inner.condvar
+            .wait_timeout_while(requested, timeout, |requested| !*requested)
+            .unwrap_or_else(|err| err.into_inner());
+
+        *requested
+    }
+
+    /// Resolves once a shutdown has been requested.
+    pub async fn async_wait(&self) {
+        while !self.async_wait_timeout(Duration::from_secs(1)).await {}
+    }
+
+    /// Async version of [`Shutdown::wait_timeout`].
+    pub async fn async_wait_timeout(&self, timeout: Duration) -> bool {
+        let notified = self.inner.notify.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        if self.is_requested() {
+            return true;
+        }
+
+        tokio::select! {
+            _ = notified => true,
+            _ = tokio::time::sleep(timeout) => self.is_requested(),
+        }
+    }
+}
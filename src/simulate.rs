@@ -0,0 +1,327 @@
+use std::{collections::BTreeMap, fmt, time::Duration};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{plugin::{Plugin, PluginInstance, PluginSignal}, rules::{Condition, Rule, RuleSet}, Decimal, Stock};
+
+/// Cash a simulated session starts with unless `--cash` says otherwise.
+pub const DEFAULT_CASH: i64 = 10_000;
+
+
+/// A strategy in the config file, as `[[simulate.strategies]]`: buys
+/// `quantity` units of `symbol` each time `buy` starts to hold and sells the
+/// whole position when `sell` does, e.g. `buy = "rsi < 30"`. A `plugin`
+/// decides instead of the two conditions.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Strategy {
+    /// The code polled, e.g. `AAPL:NASDAQ`.
+    pub symbol: String,
+    pub buy: Option<Condition>,
+    pub sell: Option<Condition>,
+    pub plugin: Option<Plugin>,
+    pub quantity: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationOptions {
+    /// In the currency the symbols are quoted in, or the base currency when
+    /// prices are converted.
+    pub cash: Decimal,
+    pub strategies: Vec<Strategy>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    /// `buy` or `sell`, as serialized.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        }
+    }
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Side::Buy => write!(f, "Bought"),
+            Side::Sell => write!(f, "Sold"),
+        }
+    }
+}
+
+/// A virtual trade, filled at the price of the quote that triggered it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Trade {
+    pub timestamp: DateTime<Utc>,
+    pub symbol: String,
+    pub side: Side,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    /// Realized by a sale, against what the position cost.
+    pub pnl: Option<Decimal>,
+}
+
+impl fmt::Display for Trade {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {} x {:.2}", self.side, self.symbol, self.quantity, self.price)?;
+
+        match self.pnl {
+            Some(pnl) => write!(f, ", P&L {:+.2}", pnl),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A simulated position still open, valued at its symbol's last price.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OpenPosition {
+    pub symbol: String,
+    pub quantity: Decimal,
+    /// What buying it cost in total.
+    pub cost: Decimal,
+    pub price: Decimal,
+    pub market_value: Decimal,
+    pub unrealized_pnl: Decimal,
+}
+
+impl fmt::Display for OpenPosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} x {:.2}: value {:.2}, cost {:.2}, P&L {:+.2}", self.symbol, self.quantity, self.price, self.market_value, self.cost, self.unrealized_pnl)
+    }
+}
+
+/// Where a simulated session stands, as reported when it ends.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionReport {
+    pub starting_cash: Decimal,
+    pub cash: Decimal,
+    pub positions: Vec<OpenPosition>,
+    pub trades: Vec<Trade>,
+    pub realized_pnl: Decimal,
+    pub unrealized_pnl: Decimal,
+    /// Cash plus the market value of the open positions.
+    pub equity: Decimal,
+    pub return_pct: f64,
+}
+
+impl fmt::Display for SessionReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "equity {:.2} from {:.2} ({:+.2}%), cash {:.2}, realized P&L {:+.2}, unrealized P&L {:+.2}, trades: {}",
+            self.equity, self.starting_cash, self.return_pct, self.cash, self.realized_pnl, self.unrealized_pnl, self.trades.len())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Holding {
+    quantity: Decimal,
+    cost: Decimal,
+    price: Decimal,
+}
+
+/// How a strategy decides to buy or sell.
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+enum Signals {
+    /// Its buy and its sell condition, as rules.
+    Conditions(Option<RuleSet>, Option<RuleSet>),
+    /// `None` when the plugin couldn't be instantiated; its last signal.
+    Plugin(Option<PluginInstance>, PluginSignal),
+}
+
+impl Signals {
+    fn new(strategy: &Strategy) -> Self {
+        let Some(plugin) = &strategy.plugin else {
+            return Signals::Conditions(strategy.buy.as_ref().map(|buy| signal(strategy, buy)), strategy.sell.as_ref().map(|sell| signal(strategy, sell)));
+        };
+
+        let instance = plugin.instantiate()
+            .inspect_err(|err| {
+                warn!(symbol = strategy.symbol, "Not trading {}: {}", strategy.symbol, err);
+            })
+            .ok();
+
+        Signals::Plugin(instance, PluginSignal::Hold)
+    }
+
+    /// Whether to buy and whether to sell on `stock`. A plugin's signal
+    /// fires when it changes, as a condition does when it starts to hold.
+    fn evaluate(&mut self, share_code: &str, stock: &Stock) -> (bool, bool) {
+        match self {
+            Signals::Conditions(buy, sell) => {
+                let fires = |rules: &mut Option<RuleSet>| rules.as_mut().is_some_and(|rules| !rules.evaluate(stock).is_empty());
+
+                (fires(buy), fires(sell))
+            }
+            Signals::Plugin(None, _) => (false, false),
+            Signals::Plugin(Some(instance), last) => {
+                let signal = instance.signal(stock)
+                    .unwrap_or_else(|err| {
+                        warn!(symbol = share_code, "Plugin failed on {}: {}", share_code, err);
+                        PluginSignal::Hold
+                    });
+                let changed = signal != *last;
+                *last = signal;
+
+                (changed && signal == PluginSignal::Buy, changed && signal == PluginSignal::Sell)
+            }
+        }
+    }
+}
+
+/// Executes the strategies' virtual trades against incoming quotes, keeping
+/// track of cash, positions and P&L. A signal fires like an alert rule, once
+/// when its condition starts to hold; buys that would take more cash than
+/// is left are skipped.
+#[derive(Debug)]
+pub struct PaperTrader {
+    strategies: Vec<Strategy>,
+    signals: Vec<Signals>,
+    starting_cash: Decimal,
+    cash: Decimal,
+    holdings: BTreeMap<String, Holding>,
+    trades: Vec<Trade>,
+    realized_pnl: Decimal,
+}
+
+impl PaperTrader {
+    pub fn new(options: SimulationOptions) -> Self {
+        let signals = options.strategies.iter().map(Signals::new).collect();
+
+        Self {
+            strategies: options.strategies,
+            signals,
+            starting_cash: options.cash,
+            cash: options.cash,
+            holdings: BTreeMap::new(),
+            trades: Vec::new(),
+            realized_pnl: Decimal::ZERO,
+        }
+    }
+
+    /// Evaluates the strategies on `share_code` against `stock`, selling
+    /// before buying, and returns the trades made.
+    pub fn record(&mut self, share_code: &str, stock: &Stock) -> Vec<Trade> {
+        if let Some(holding) = self.holdings.get_mut(share_code) {
+            holding.price = stock.price;
+        }
+
+        let mut sells = false;
+        let mut buys = Vec::new();
+
+        for (strategy, signals) in self.strategies.iter().zip(self.signals.iter_mut()) {
+            if strategy.symbol != share_code {
+                continue;
+            }
+
+            let (buy, sell) = signals.evaluate(share_code, stock);
+            sells |= sell;
+
+            if buy {
+                buys.push(strategy.quantity);
+            }
+        }
+
+        let sold = sells.then(|| self.sell(share_code, stock)).flatten();
+
+        sold.into_iter()
+            .chain(buys.into_iter().filter_map(|quantity| self.buy(share_code, stock, quantity)))
+            .collect()
+    }
+
+    fn buy(&mut self, share_code: &str, stock: &Stock, quantity: Decimal) -> Option<Trade> {
+        let cost = quantity * stock.price;
+
+        if cost > self.cash {
+            warn!(symbol = share_code, "Not buying {} {} at {:.2}, only {:.2} cash left", quantity, share_code, stock.price, self.cash);
+            return None;
+        }
+
+        let holding = self.holdings.entry(share_code.to_string())
+            .or_insert(Holding { quantity: Decimal::ZERO, cost: Decimal::ZERO, price: stock.price });
+        holding.quantity += quantity;
+        holding.cost += cost;
+        self.cash -= cost;
+
+        Some(self.trade(share_code, stock, Side::Buy, quantity, None))
+    }
+
+    fn sell(&mut self, share_code: &str, stock: &Stock) -> Option<Trade> {
+        let holding = self.holdings.remove(share_code)?;
+        let proceeds = holding.quantity * stock.price;
+        let pnl = proceeds - holding.cost;
+
+        self.cash += proceeds;
+        self.realized_pnl += pnl;
+
+        Some(self.trade(share_code, stock, Side::Sell, holding.quantity, Some(pnl)))
+    }
+
+    fn trade(&mut self, share_code: &str, stock: &Stock, side: Side, quantity: Decimal, pnl: Option<Decimal>) -> Trade {
+        let trade = Trade { timestamp: stock.fetched_at, symbol: share_code.to_string(), side, quantity, price: stock.price, pnl };
+
+        self.trades.push(trade.clone());
+        trade
+    }
+
+    /// Cash plus the open positions valued at their last price.
+    pub fn equity(&self) -> Decimal {
+        self.cash + self.holdings.values().map(|holding| holding.quantity * holding.price).sum::<Decimal>()
+    }
+
+    /// The session so far, open positions valued at their last price.
+    pub fn report(&self) -> SessionReport {
+        let positions: Vec<OpenPosition> = self.holdings.iter()
+            .map(|(symbol, holding)| {
+                let market_value = holding.quantity * holding.price;
+
+                OpenPosition {
+                    symbol: symbol.clone(),
+                    quantity: holding.quantity,
+                    cost: holding.cost,
+                    price: holding.price,
+                    market_value,
+                    unrealized_pnl: market_value - holding.cost,
+                }
+            })
+            .collect();
+
+        let unrealized_pnl = positions.iter().map(|position| position.unrealized_pnl).sum();
+        let equity = self.equity();
+        let return_pct = match self.starting_cash.is_zero() {
+            true => 0f64,
+            false => (equity - self.starting_cash).to_f64() / self.starting_cash.to_f64() * 100f64,
+        };
+
+        SessionReport {
+            starting_cash: self.starting_cash,
+            cash: self.cash,
+            positions,
+            trades: self.trades.clone(),
+            realized_pnl: self.realized_pnl,
+            unrealized_pnl,
+            equity,
+            return_pct,
+        }
+    }
+}
+
+/// A signal of `strategy` as a rule, which fires again as soon as its
+/// condition holds anew.
+fn signal(strategy: &Strategy, condition: &Condition) -> RuleSet {
+    let rule = Rule {
+        symbol: strategy.symbol.to_uppercase().split(':').next().unwrap_or_default().to_string(),
+        condition: condition.clone(),
+        notify: Vec::new(),
+    };
+
+    RuleSet::new(vec![rule], Duration::ZERO)
+}
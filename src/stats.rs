@@ -0,0 +1,182 @@
+use std::{collections::{BTreeMap, HashMap, VecDeque}, fmt, sync::{Arc, Mutex}, time::Duration};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::StockError;
+
+/// How often the watch loops log a summary of the stats by default.
+pub const DEFAULT_SUMMARY_EVERY: Duration = Duration::from_secs(15 * 60);
+
+/// Latencies kept per symbol for the percentiles, the most recent ones.
+const LATENCY_WINDOW: usize = 1000;
+
+
+/// What the fetches of one symbol, or all of them, added up to.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SymbolStats {
+    pub fetches: u64,
+    pub failures: u64,
+    /// Fetches whose response came back but couldn't be read.
+    pub parse_failures: u64,
+    /// Error responses, keyed by HTTP status.
+    pub http_errors: BTreeMap<u16, u64>,
+    /// Share of the fetches that succeeded, from 0 to 1.
+    pub success_rate: Option<f64>,
+    /// Percentiles of the recent fetch latencies, in milliseconds.
+    pub p50_ms: Option<u64>,
+    pub p90_ms: Option<u64>,
+    pub p99_ms: Option<u64>,
+}
+
+impl fmt::Display for SymbolStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} fetches", self.fetches)?;
+
+        if let Some(rate) = self.success_rate {
+            write!(f, ", {:.1}% ok", rate * 100.0)?;
+        }
+        if self.parse_failures > 0 {
+            write!(f, ", {} unreadable", self.parse_failures)?;
+        }
+        for (status, count) in self.http_errors.iter() {
+            write!(f, ", {} HTTP {}", count, status)?;
+        }
+        if let (Some(p50), Some(p90), Some(p99)) = (self.p50_ms, self.p90_ms, self.p99_ms) {
+            write!(f, ", p50 {} ms, p90 {} ms, p99 {} ms", p50, p90, p99)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// What `GET /stats` and the `stats` command answer with.
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsReport {
+    pub started_at: DateTime<Utc>,
+    /// Every symbol's fetches together.
+    pub total: SymbolStats,
+    /// Keyed by code, including codes not fetched yet.
+    pub symbols: BTreeMap<String, SymbolStats>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    fetches: u64,
+    failures: u64,
+    parse_failures: u64,
+    http_errors: BTreeMap<u16, u64>,
+    latencies: VecDeque<Duration>,
+}
+
+impl Counters {
+    fn add(&mut self, other: &Counters) {
+        self.fetches += other.fetches;
+        self.failures += other.failures;
+        self.parse_failures += other.parse_failures;
+        for (status, count) in other.http_errors.iter() {
+            *self.http_errors.entry(*status).or_default() += count;
+        }
+        self.latencies.extend(other.latencies.iter());
+    }
+
+    fn stats(&self) -> SymbolStats {
+        let mut latencies = self.latencies.iter().copied().collect::<Vec<Duration>>();
+        latencies.sort_unstable();
+
+        SymbolStats {
+            fetches: self.fetches,
+            failures: self.failures,
+            parse_failures: self.parse_failures,
+            http_errors: self.http_errors.clone(),
+            success_rate: (self.fetches > 0).then(|| (self.fetches - self.failures) as f64 / self.fetches as f64),
+            p50_ms: percentile(&latencies, 50),
+            p90_ms: percentile(&latencies, 90),
+            p99_ms: percentile(&latencies, 99),
+        }
+    }
+}
+
+/// The nearest-rank `pct`th percentile of sorted `latencies`, in
+/// milliseconds.
+fn percentile(latencies: &[Duration], pct: usize) -> Option<u64> {
+    let rank = (latencies.len() * pct).div_ceil(100).max(1);
+
+    latencies.get(rank - 1).map(|latency| latency.as_millis() as u64)
+}
+
+/// Fetch latencies and failures of every symbol, shared between the polling
+/// loops recording them and whatever reports on them.
+#[derive(Debug, Clone)]
+pub struct Stats {
+    started_at: DateTime<Utc>,
+    symbols: Arc<Mutex<HashMap<String, Counters>>>,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self { started_at: Utc::now(), symbols: Arc::default() }
+    }
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a fetch of `share_code` that took `elapsed` and failed with
+    /// `err`, if it did.
+    pub fn record(&self, share_code: &str, elapsed: Duration, err: Option<&StockError>) {
+        let mut symbols = self.symbols.lock().unwrap_or_else(|err| err.into_inner());
+        let counters = symbols.entry(share_code.to_string()).or_default();
+
+        counters.fetches += 1;
+        if counters.latencies.len() == LATENCY_WINDOW {
+            counters.latencies.pop_front();
+        }
+        counters.latencies.push_back(elapsed);
+
+        let Some(mut err) = err else {
+            return;
+        };
+
+        // A failed batch failed every symbol in it the same way.
+        while let StockError::Batch(inner) = err {
+            err = inner;
+        }
+
+        counters.failures += 1;
+        if err.is_unreadable() {
+            counters.parse_failures += 1;
+        }
+        if let StockError::HttpStatus { status, .. } = err {
+            *counters.http_errors.entry(*status).or_default() += 1;
+        }
+    }
+
+    pub fn report(&self, codes: &[String]) -> StatsReport {
+        let symbols = self.symbols.lock().unwrap_or_else(|err| err.into_inner());
+        let mut total = Counters::default();
+
+        for counters in symbols.values() {
+            total.add(counters);
+        }
+
+        let symbols = codes.iter()
+            .map(|code| (code.clone(), symbols.get(code).map(Counters::stats).unwrap_or_default()))
+            .collect::<BTreeMap<String, SymbolStats>>();
+
+        StatsReport { started_at: self.started_at, total: total.stats(), symbols }
+    }
+
+    /// One line on the fetches so far, naming the symbol failing most.
+    pub fn summary(&self, codes: &[String]) -> String {
+        let report = self.report(codes);
+        let mut summary = format!("Since {}: {}", report.started_at.format("%Y-%m-%d %H:%M:%S UTC"), report.total);
+
+        if let Some((code, stats)) = report.symbols.iter().filter(|(_, stats)| stats.failures > 0).max_by_key(|(_, stats)| stats.failures) {
+            summary.push_str(&format!("; most failures: {} ({} of {})", code, stats.failures, stats.fetches));
+        }
+
+        summary
+    }
+}
@@ -0,0 +1,628 @@
+use std::{collections::BTreeMap, error::Error, fmt, io, path::PathBuf, str::FromStr, sync::Arc};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{decimal::Decimal, fx::Conversion, indicators::Indicators, market::MarketStatus, notify, price, template::{Fields, Value}};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    #[default]
+    Up,
+    Down,
+    Same,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Status::Up => write!(f, "up"),
+            Status::Down => write!(f, "down"),
+            Status::Same => write!(f, "same"),
+        }
+    }
+}
+
+impl FromStr for Status {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "up" => Ok(Status::Up),
+            "down" => Ok(Status::Down),
+            "same" => Ok(Status::Same),
+            _ => Err(StockError::invalid("status", value.to_string())),
+        }
+    }
+}
+
+/// What kind of instrument a quote is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuoteKind {
+    #[default]
+    Equity,
+    Etf,
+    /// A mutual fund, priced at its net asset value once a day.
+    Fund,
+    /// A currency pair, priced in its quote currency.
+    Fx,
+    /// A market index such as the S&P 500, quoted in points.
+    Index,
+}
+
+impl QuoteKind {
+    /// Whether the price only changes once a day, when the NAV is struck.
+    pub fn is_daily(&self) -> bool {
+        matches!(self, QuoteKind::Fund)
+    }
+}
+
+impl fmt::Display for QuoteKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QuoteKind::Equity => write!(f, "equity"),
+            QuoteKind::Etf => write!(f, "etf"),
+            QuoteKind::Fund => write!(f, "fund"),
+            QuoteKind::Fx => write!(f, "fx"),
+            QuoteKind::Index => write!(f, "index"),
+        }
+    }
+}
+
+impl FromStr for QuoteKind {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "equity" => Ok(QuoteKind::Equity),
+            "etf" => Ok(QuoteKind::Etf),
+            "fund" => Ok(QuoteKind::Fund),
+            "fx" => Ok(QuoteKind::Fx),
+            "index" => Ok(QuoteKind::Index),
+            _ => Err(StockError::invalid("quote kind", value.to_string())),
+        }
+    }
+}
+
+/// How a quote compares to the previous one of the same symbol.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Valuation {
+    /// No earlier quote to compare against.
+    First,
+    Up { previous_price: Decimal, change_abs: Decimal, change_pct: f64 },
+    Down { previous_price: Decimal, change_abs: Decimal, change_pct: f64 },
+    Same { previous_price: Decimal },
+}
+
+impl Valuation {
+    pub fn between(previous_price: Decimal, price: Decimal) -> Self {
+        let change_abs = price - previous_price;
+        let change_pct = match previous_price.is_zero() {
+            true => 0f64,
+            false => change_abs.to_f64() / previous_price.to_f64() * 100f64,
+        };
+
+        match price.cmp(&previous_price) {
+            std::cmp::Ordering::Greater => Valuation::Up { previous_price, change_abs, change_pct },
+            std::cmp::Ordering::Less => Valuation::Down { previous_price, change_abs, change_pct },
+            std::cmp::Ordering::Equal => Valuation::Same { previous_price },
+        }
+    }
+
+    /// Quotes without history count as going up, as they always have.
+    pub fn status(&self) -> Status {
+        match self {
+            Valuation::First | Valuation::Up { .. } => Status::Up,
+            Valuation::Down { .. } => Status::Down,
+            Valuation::Same { .. } => Status::Same,
+        }
+    }
+
+    /// Copies the status and change figures onto `stock`, with the change in
+    /// pips for currency pairs.
+    pub fn apply_to(&self, stock: &mut Stock) {
+        stock.status = self.status();
+
+        (stock.previous_price, stock.change_abs, stock.change_pct) = match *self {
+            Valuation::First => (None, None, None),
+            Valuation::Up { previous_price, change_abs, change_pct }
+            | Valuation::Down { previous_price, change_abs, change_pct } => (Some(previous_price), Some(change_abs), Some(change_pct)),
+            Valuation::Same { previous_price } => (Some(previous_price), Some(Decimal::ZERO), Some(0f64)),
+        };
+
+        stock.change_pips = match stock.kind {
+            QuoteKind::Fx => stock.change_abs.map(|change_abs| change_abs.to_f64() / price::pip_size(&stock.currency)),
+            _ => None,
+        };
+    }
+}
+
+/// A low/high pair, such as a day or 52-week range.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PriceRange {
+    pub low: Decimal,
+    pub high: Decimal,
+}
+
+impl fmt::Display for PriceRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.low, self.high)
+    }
+}
+
+/// Key statistics shown next to the price on quote pages, each `None` when
+/// the source doesn't have it. Prices are in the quote's currency.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct QuoteDetails {
+    pub previous_close: Option<Decimal>,
+    pub day_range: Option<PriceRange>,
+    pub year_range: Option<PriceRange>,
+    /// Approximate, as quote pages abbreviate it, e.g. `2.87T`.
+    pub market_cap: Option<f64>,
+    pub volume: Option<u64>,
+    /// Shares per lot on exchanges that trade in lots.
+    pub lot_size: Option<u32>,
+}
+
+impl QuoteDetails {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Multiplies every monetary value by `rate`, for currency conversion.
+    pub fn scale(&mut self, rate: Decimal) {
+        let scale_range = |range: PriceRange| PriceRange { low: range.low * rate, high: range.high * rate };
+
+        self.previous_close = self.previous_close.map(|value| value * rate);
+        self.day_range = self.day_range.map(scale_range);
+        self.year_range = self.year_range.map(scale_range);
+        self.market_cap = self.market_cap.map(|value| value * rate.to_f64());
+    }
+}
+
+impl fmt::Display for QuoteDetails {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = Vec::new();
+
+        if let Some(previous_close) = self.previous_close {
+            parts.push(format!("prev close {}", previous_close));
+        }
+        if let Some(day_range) = self.day_range {
+            parts.push(format!("day {}", day_range));
+        }
+        if let Some(year_range) = self.year_range {
+            parts.push(format!("52w {}", year_range));
+        }
+        if let Some(market_cap) = self.market_cap {
+            parts.push(format!("cap {}", abbreviate(market_cap)));
+        }
+        if let Some(volume) = self.volume {
+            parts.push(format!("vol {}", abbreviate(volume as f64)));
+        }
+        if let Some(lot_size) = self.lot_size {
+            parts.push(format!("lot {}", lot_size));
+        }
+
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// What a company earns and pays out against its price, each `None` when
+/// the source doesn't have it. Amounts are in the quote's currency.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Fundamentals {
+    /// Price over the last twelve months' earnings per share.
+    pub pe_ratio: Option<f64>,
+    /// Earnings per share of the last reported quarter.
+    pub eps: Option<Decimal>,
+    /// Dividends of the last twelve months as a percentage of the price.
+    pub dividend_yield: Option<f64>,
+    /// Approximate, as quote pages abbreviate it, e.g. `2.87T`.
+    pub market_cap: Option<f64>,
+}
+
+impl Fundamentals {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Multiplies every monetary value by `rate`, for currency conversion.
+    pub fn scale(&mut self, rate: Decimal) {
+        self.eps = self.eps.map(|value| value * rate);
+        self.market_cap = self.market_cap.map(|value| value * rate.to_f64());
+    }
+}
+
+impl fmt::Display for Fundamentals {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = Vec::new();
+
+        if let Some(pe_ratio) = self.pe_ratio {
+            parts.push(format!("P/E {:.2}", pe_ratio));
+        }
+        if let Some(eps) = self.eps {
+            parts.push(format!("EPS {:.2}", eps));
+        }
+        if let Some(dividend_yield) = self.dividend_yield {
+            parts.push(format!("yield {:.2}%", dividend_yield));
+        }
+        if let Some(market_cap) = self.market_cap {
+            parts.push(format!("cap {}", abbreviate(market_cap)));
+        }
+
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// A headline about a company, as linked from its quote page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NewsItem {
+    /// Bare, e.g. `AAPL`.
+    pub symbol: String,
+    pub headline: String,
+    /// The publisher, e.g. `Reuters`.
+    pub source: String,
+    pub url: String,
+    /// As the page words it, e.g. `3 hours ago`.
+    pub published: Option<String>,
+}
+
+impl fmt::Display for NewsItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.published {
+            Some(published) => write!(f, "{}: {} ({}, {}) {}", self.symbol, self.headline, self.source, published, self.url),
+            None => write!(f, "{}: {} ({}) {}", self.symbol, self.headline, self.source, self.url),
+        }
+    }
+}
+
+/// Formats large numbers the way quote pages do, e.g. `2.87T` or `52.34M`.
+pub fn abbreviate(value: f64) -> String {
+    const SUFFIXES: [(f64, &str); 4] = [(1e12, "T"), (1e9, "B"), (1e6, "M"), (1e3, "K")];
+
+    SUFFIXES.iter()
+        .find(|(scale, _)| value.abs() >= *scale)
+        .map(|(scale, suffix)| format!("{:.2}{}", value / scale, suffix))
+        .unwrap_or_else(|| value.to_string())
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Stock {
+    pub symbol: String,
+    pub company_name: String,
+    pub price: Decimal,
+    /// ISO 4217 code of `price`, empty when the source doesn't say.
+    pub currency: String,
+    pub kind: QuoteKind,
+    /// Set when `price` was converted from the currency the source quoted in.
+    pub conversion: Option<Conversion>,
+    pub status: Status,
+    pub previous_price: Option<Decimal>,
+    pub change_abs: Option<Decimal>,
+    pub change_pct: Option<f64>,
+    /// `change_abs` in pips, for currency pairs.
+    pub change_pips: Option<f64>,
+    /// Set by the polling loops from the quotes seen so far.
+    pub indicators: Option<Indicators>,
+    /// Set by the polling loops: the latest prices of the symbol, oldest
+    /// first and this one last, for drawing sparklines.
+    #[serde(skip)]
+    pub recent_prices: Vec<f64>,
+    /// As reported by the source, or else from the exchange's calendar.
+    pub market_status: Option<MarketStatus>,
+    /// Set on the last quote of a symbol that hasn't been refreshed for a
+    /// few polls, when it is passed on again to say so.
+    pub stale: bool,
+    /// Only written with `--extended`, see [`crate::output::QuoteRecord`].
+    #[serde(skip)]
+    pub details: QuoteDetails,
+    /// Set by the polling loops when asked to, refreshed now and then.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fundamentals: Option<Fundamentals>,
+    /// Set by the polling loops when asked to: headlines not passed on
+    /// before, which the pipeline splits off into events of their own.
+    #[serde(skip)]
+    pub news: Vec<NewsItem>,
+    /// Custom fields computed by the `--script`, see [`crate::script::Script`].
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, serde_json::Value>,
+    /// When the price was observed: when it was fetched, or the time of the
+    /// trade for streamed quotes. Written as the record's `timestamp`.
+    #[serde(skip)]
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl Stock {
+    /// Observed now, see [`Stock::with_fetched_at`] otherwise.
+    pub fn new(symbol: String, company_name: String, price: Decimal) -> Self {
+        Self { symbol, company_name, price, fetched_at: Utc::now(), ..Self::default() }
+    }
+
+    pub fn with_currency(mut self, currency: String) -> Self {
+        self.currency = currency;
+        self
+    }
+
+    pub fn with_kind(mut self, kind: QuoteKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn with_market_status(mut self, market_status: Option<MarketStatus>) -> Self {
+        self.market_status = market_status;
+        self
+    }
+
+    pub fn with_details(mut self, details: QuoteDetails) -> Self {
+        self.details = details;
+        self
+    }
+
+    pub fn with_fetched_at(mut self, fetched_at: DateTime<Utc>) -> Self {
+        self.fetched_at = fetched_at;
+        self
+    }
+}
+
+/// For `--format` lines; `line` is the line as printed without one.
+impl Fields for Stock {
+    const NAMES: &'static [&'static str] = &[
+        "symbol", "company", "price", "currency", "kind", "status", "previous_price", "change_abs", "change_pct", "change_pips", "market_status", "fetched_at", "pe_ratio", "eps", "dividend_yield", "sparkline", "line",
+    ];
+
+    fn value(&self, name: &str) -> Value {
+        match name {
+            "symbol" => Value::Text(self.symbol.clone()),
+            "company" => Value::Text(self.company_name.clone()),
+            "price" => Value::Number(self.price),
+            "currency" => Value::Text(self.currency.clone()),
+            "kind" => Value::Text(self.kind.to_string()),
+            "status" => Value::Text(self.status.to_string()),
+            "previous_price" => self.previous_price.into(),
+            "change_abs" => self.change_abs.map(Value::Change).unwrap_or(Value::Missing),
+            "change_pct" => self.change_pct.and_then(Decimal::from_f64).map(Value::Change).unwrap_or(Value::Missing),
+            "change_pips" => self.change_pips.and_then(Decimal::from_f64).map(Value::Change).unwrap_or(Value::Missing),
+            "market_status" => self.market_status.map(|status| Value::Text(status.to_string())).unwrap_or(Value::Missing),
+            "fetched_at" => Value::Text(self.fetched_at.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            "pe_ratio" => self.fundamentals.as_ref().and_then(|fundamentals| fundamentals.pe_ratio).into(),
+            "eps" => self.fundamentals.as_ref().and_then(|fundamentals| fundamentals.eps).into(),
+            "dividend_yield" => self.fundamentals.as_ref().and_then(|fundamentals| fundamentals.dividend_yield).into(),
+            "sparkline" => Value::Text(notify::sparkline(&self.recent_prices)),
+            "line" => Value::Text(self.to_string()),
+            _ => Value::Missing,
+        }
+    }
+}
+
+impl fmt::Display for Stock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} - {}: {}", self.symbol, self.company_name, price::format_amount(self.price, &self.currency))?;
+
+        if !self.currency.is_empty() {
+            write!(f, " {}", self.currency)?;
+        } else if self.kind == QuoteKind::Index {
+            write!(f, " pts")?;
+        }
+
+        // A NAV moves once a day, so its change is since the last NAV
+        // rather than within a session, and it has no session to be out of.
+        if self.kind.is_daily() {
+            write!(f, " NAV")?;
+        }
+
+        match (self.change_abs, self.change_pct) {
+            (Some(_), Some(change_pct)) if self.kind == QuoteKind::Fx => write!(f, " ({} {:+.1} pips / {:+.2}%)", self.status, self.change_pips.unwrap_or_default(), change_pct)?,
+            (Some(change_abs), Some(change_pct)) if self.kind.is_daily() => write!(f, " ({} {:+.2} / {:+.2}% since last NAV)", self.status, change_abs, change_pct)?,
+            (Some(change_abs), Some(change_pct)) => write!(f, " ({} {:+.2} / {:+.2}%)", self.status, change_abs, change_pct)?,
+            _ => write!(f, " ({})", self.status)?,
+        }
+
+        if let Some(market_status) = self.market_status.filter(|status| *status != MarketStatus::Open && !self.kind.is_daily()) {
+            write!(f, " [{}]", market_status)?;
+        }
+
+        if self.stale {
+            write!(f, " [stale]")?;
+        }
+
+        if let Some(fundamentals) = self.fundamentals.as_ref().filter(|fundamentals| !fundamentals.is_empty()) {
+            write!(f, " [{}]", fundamentals)?;
+        }
+
+        if let Some(indicators) = &self.indicators {
+            if let Some(sma) = indicators.sma {
+                write!(f, " SMA{} {:.2}", indicators.ma_window, sma)?;
+            }
+            if let Some(ema) = indicators.ema {
+                write!(f, " EMA{} {:.2}", indicators.ma_window, ema)?;
+            }
+            if let Some(rsi) = indicators.rsi {
+                write!(f, " RSI{} {:.1}", indicators.rsi_window, rsi)?;
+            }
+            if let Some(macd) = indicators.macd {
+                write!(f, " MACD {:.2}/{:.2}", macd.line, macd.signal)?;
+            }
+            if let Some(bands) = indicators.bollinger {
+                write!(f, " BB {:.2}-{:.2}", bands.lower, bands.upper)?;
+            }
+        }
+
+        for (name, value) in self.fields.iter() {
+            match value {
+                serde_json::Value::String(text) => write!(f, " {}={}", name, text)?,
+                value => write!(f, " {}={}", name, value)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An error from any other library or service, kept as the source of a
+/// [`StockError`].
+pub type BoxError = Box<dyn Error + Send + Sync>;
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum StockError {
+    /// The request failed before an answer came back, or the answer couldn't
+    /// be read.
+    #[error("request failed: {0}")]
+    Network(#[source] BoxError),
+    /// The server answered with an error status; `message` is its reason or
+    /// whatever it explained the status with.
+    #[error("HTTP {status} {message}")]
+    HttpStatus { status: u16, message: String },
+    #[error("{0} is disallowed by its robots.txt")]
+    Disallowed(String),
+    #[error("invalid URL: {0}")]
+    Url(#[from] url::ParseError),
+    #[error("can't set up the HTTP client: {0}")]
+    HttpClient(#[source] BoxError),
+    #[error("rendering failed: {0}")]
+    Render(#[source] BoxError),
+    /// Neither the selectors nor the fallbacks found the price on the page;
+    /// `message` says what was tried.
+    #[error("{symbol}: {message}")]
+    Selector { symbol: String, message: String },
+    /// What should be a price isn't a number.
+    #[error("not a price: {0}")]
+    PriceParse(String),
+    /// The response isn't in the shape the source answers in.
+    #[error("unreadable response: {0}")]
+    Response(#[source] BoxError),
+    /// The response body grew past the `limit` in bytes.
+    #[error("response from {host} is larger than {limit} bytes")]
+    ResponseTooLarge { host: String, limit: u64 },
+    #[error("symbol not found: {0}")]
+    SymbolNotFound(String),
+    /// The source can't quote the symbol at all.
+    #[error("{0}")]
+    UnsupportedSymbol(String),
+    #[error("{0}")]
+    MissingApiKey(String),
+    /// No rate for the pair, e.g. `USDIDR=X`.
+    #[error("no exchange rate for {0}")]
+    FxRateMissing(String),
+    #[error("stream failed: {0}")]
+    Stream(#[source] BoxError),
+    #[error("nothing left to replay for {0}")]
+    ReplayExhausted(String),
+    /// The request for a batch of symbols failed, and with it every symbol
+    /// in the batch.
+    #[error(transparent)]
+    Batch(Arc<StockError>),
+    #[error("{}: {source}", path.display())]
+    Io { path: PathBuf, #[source] source: io::Error },
+    /// A file that isn't in its format; `location` is the path, and the
+    /// line when known.
+    #[error("{location}: {source}")]
+    Parse { location: String, #[source] source: BoxError },
+    #[error("writing output failed: {0}")]
+    Output(#[source] BoxError),
+    #[error("storage failed: {0}")]
+    Storage(#[source] BoxError),
+    #[error("notification failed: {0}")]
+    Notification(#[source] BoxError),
+    /// A value from the command line or config that can't be used, e.g.
+    /// `what` "duration" for `--interval 5x`.
+    #[error("invalid {what}: {message}")]
+    Invalid { what: &'static str, message: String },
+    /// Options or platforms that can't work together.
+    #[error("{0}")]
+    Unsupported(String),
+    /// Needs a cargo feature this build doesn't have.
+    #[error("{0}")]
+    FeatureDisabled(String),
+    #[error("{} names running process {pid}", path.display())]
+    AlreadyRunning { path: PathBuf, pid: i32 },
+    #[error("{0} is not in the watchlist")]
+    NotInWatchlist(String),
+    /// Part of the program itself, such as the `tui` or `server`, failed.
+    #[error("{what} failed: {source}")]
+    Runtime { what: &'static str, #[source] source: BoxError },
+    #[error("{} of {total} quotes failed: {}", failed.len(), failed.join(", "))]
+    QuotesFailed { failed: Vec<String>, total: usize },
+    #[error("{0} checks failed")]
+    ChecksFailed(usize),
+}
+
+impl StockError {
+    pub fn invalid(what: &'static str, message: impl Into<String>) -> Self {
+        StockError::Invalid { what, message: message.into() }
+    }
+
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        StockError::Io { path: path.into(), source }
+    }
+
+    pub fn parse(location: impl fmt::Display, source: impl Into<BoxError>) -> Self {
+        StockError::Parse { location: location.to_string(), source: source.into() }
+    }
+
+    pub fn runtime(what: &'static str, source: impl Into<BoxError>) -> Self {
+        StockError::Runtime { what, source: source.into() }
+    }
+
+    /// An error status, e.g. `404 Not Found`.
+    pub fn status(status: reqwest::StatusCode) -> Self {
+        StockError::HttpStatus { status: status.as_u16(), message: status.canonical_reason().unwrap_or_default().to_string() }
+    }
+
+    /// A stable name for the kind of error, as reported by the server's
+    /// error responses.
+    pub fn code(&self) -> &'static str {
+        match self {
+            StockError::Network(_) => "REQUEST_FAILED",
+            StockError::HttpStatus { .. } => "RESPONSE_FAILED",
+            StockError::Disallowed(_) => "DISALLOWED_BY_ROBOTS",
+            StockError::Url(_) => "PARSE_URL_FAILED",
+            StockError::HttpClient(_) => "HTTP_CLIENT_FAILED",
+            StockError::Render(_) => "RENDER_FAILED",
+            StockError::Selector { .. } => "PRICE_NOT_FOUND",
+            StockError::PriceParse(_) => "PRICE_PARSE_FAILED",
+            StockError::Response(_) => "RESPONSE_PARSE_FAILED",
+            StockError::ResponseTooLarge { .. } => "RESPONSE_TOO_LARGE",
+            StockError::SymbolNotFound(_) => "SYMBOL_NOT_FOUND",
+            StockError::UnsupportedSymbol(_) => "UNSUPPORTED_SYMBOL",
+            StockError::MissingApiKey(_) => "MISSING_API_KEY",
+            StockError::FxRateMissing(_) => "FX_RATE_MISSING",
+            StockError::Stream(_) => "STREAM_FAILED",
+            StockError::ReplayExhausted(_) => "REPLAY_EXHAUSTED",
+            StockError::Batch(err) => err.code(),
+            StockError::Io { .. } => "IO_FAILED",
+            StockError::Parse { .. } => "PARSE_FAILED",
+            StockError::Output(_) => "OUTPUT_WRITE_FAILED",
+            StockError::Storage(_) => "STORAGE_FAILED",
+            StockError::Notification(_) => "NOTIFICATION_FAILED",
+            StockError::Invalid { .. } => "INVALID_INPUT",
+            StockError::Unsupported(_) => "UNSUPPORTED",
+            StockError::FeatureDisabled(_) => "FEATURE_DISABLED",
+            StockError::AlreadyRunning { .. } => "ALREADY_RUNNING",
+            StockError::NotInWatchlist(_) => "NOT_IN_WATCHLIST",
+            StockError::Runtime { .. } => "RUNTIME_FAILED",
+            StockError::QuotesFailed { .. } => "QUOTE_FAILED",
+            StockError::ChecksFailed(_) => "DOCTOR_FAILED",
+        }
+    }
+
+    /// Whether retrying the same request may succeed: network failures and
+    /// HTTP 429 / 5xx responses.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            StockError::Network(_) => true,
+            StockError::HttpStatus { status, .. } => *status == 429 || (500..600).contains(status),
+            StockError::Batch(err) => err.is_transient(),
+            _ => false,
+        }
+    }
+
+    /// Whether the response came back but couldn't be read: no price where
+    /// the selectors look, a price that isn't a number, or a response in a
+    /// shape the source doesn't answer in.
+    pub fn is_unreadable(&self) -> bool {
+        matches!(self, StockError::Selector { .. } | StockError::PriceParse(_) | StockError::Response(_))
+    }
+}
@@ -0,0 +1,252 @@
+use std::{sync::mpsc::{self, RecvTimeoutError}, thread, time::{Duration, Instant}};
+use chrono::{DateTime, Utc};
+use tracing::{debug, error, warn};
+
+use crate::{output::{QuoteRecord, Sink}, retry::RetryPolicy, Stock, StockError};
+
+pub const DEFAULT_MEASUREMENT: &str = "quote";
+pub const DEFAULT_BATCH_SIZE: usize = 100;
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+/// How long exiting waits for the last batch to be written.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(60);
+
+
+/// Where and how quotes are written to InfluxDB.
+#[derive(Debug, Clone)]
+pub struct InfluxOptions {
+    /// Base URL of the server, e.g. `http://localhost:8086`.
+    pub url: String,
+    pub org: String,
+    pub bucket: String,
+    /// API token with write access to `bucket`.
+    pub token: Option<String>,
+    pub measurement: String,
+    /// Points are written once this many are waiting...
+    pub batch_size: usize,
+    /// ...or this long after the first of them arrived.
+    pub flush_interval: Duration,
+    /// Applied to transient failures (network errors, HTTP 429 / 5xx).
+    pub retry: RetryPolicy,
+}
+
+enum Message {
+    Point(String),
+    /// Write whatever is waiting, then acknowledge.
+    Flush(mpsc::Sender<()>),
+}
+
+/// Writes every quote as a line protocol point through the InfluxDB v2 HTTP
+/// API.
+///
+/// Points are batched and written from a thread of its own, so a slow or
+/// unreachable server never delays polling; a batch that still fails after
+/// its retries is dropped.
+pub struct InfluxExporter {
+    sender: mpsc::Sender<Message>,
+    measurement: String,
+}
+
+impl InfluxExporter {
+    pub fn new(options: InfluxOptions) -> Self {
+        let (sender, receiver) = mpsc::channel::<Message>();
+        let measurement = options.measurement.clone();
+        let writer = Writer::new(options);
+
+        thread::spawn(move || writer.run(receiver));
+
+        Self { sender, measurement }
+    }
+}
+
+impl Sink for InfluxExporter {
+    fn emit(&mut self, record: &QuoteRecord) {
+        let stock = record.stock;
+
+        if self.sender.send(Message::Point(line(&self.measurement, stock, record.timestamp))).is_err() {
+            warn!(symbol = %stock.symbol, "InfluxDB exporter is not running, dropping {}", stock.symbol);
+        }
+    }
+
+    fn keeps_history(&self) -> bool {
+        true
+    }
+
+    /// Writes the points still waiting, blocking until they are handed over.
+    fn flush(&mut self) {
+        let (ack, done) = mpsc::channel();
+
+        if self.sender.send(Message::Flush(ack)).is_ok() && done.recv_timeout(FLUSH_TIMEOUT).is_err() {
+            warn!("Timed out writing the last points to InfluxDB");
+        }
+    }
+}
+
+struct Writer {
+    client: reqwest::blocking::Client,
+    options: InfluxOptions,
+    batch: Vec<String>,
+    /// When the oldest waiting point has to be written by.
+    deadline: Option<Instant>,
+}
+
+impl Writer {
+    fn new(options: InfluxOptions) -> Self {
+        Self { client: reqwest::blocking::Client::new(), options, batch: Vec::new(), deadline: None }
+    }
+
+    fn run(mut self, receiver: mpsc::Receiver<Message>) {
+        loop {
+            let message = match self.deadline {
+                Some(deadline) => receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+                None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+
+            match message {
+                Ok(Message::Point(point)) => {
+                    self.deadline.get_or_insert_with(|| Instant::now() + self.options.flush_interval);
+                    self.batch.push(point);
+
+                    if self.batch.len() >= self.options.batch_size {
+                        self.write_batch();
+                    }
+                }
+                Ok(Message::Flush(ack)) => {
+                    self.write_batch();
+                    let _ = ack.send(());
+                }
+                Err(RecvTimeoutError::Timeout) => self.write_batch(),
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.write_batch();
+                    return;
+                }
+            }
+        }
+    }
+
+    fn write_batch(&mut self) {
+        self.deadline = None;
+
+        if self.batch.is_empty() {
+            return;
+        }
+
+        let body = std::mem::take(&mut self.batch).join("\n");
+        let points = body.lines().count();
+
+        match self.options.retry.run("influxdb", || self.write(&body)) {
+            Ok(()) => debug!("Wrote {} points to InfluxDB", points),
+            Err(err) => error!("Failed to write {} points to InfluxDB, dropping them: {}", points, err),
+        }
+    }
+
+    fn write(&self, body: &str) -> Result<(), StockError> {
+        let url = format!("{}/api/v2/write", self.options.url.trim_end_matches('/'));
+        let request = self.client.post(url)
+            .query(&[("org", self.options.org.as_str()), ("bucket", self.options.bucket.as_str()), ("precision", "ns")])
+            .header("Content-Type", "text/plain; charset=utf-8")
+            .body(body.to_string());
+
+        let request = match &self.options.token {
+            Some(token) => request.header("Authorization", format!("Token {}", token)),
+            None => request,
+        };
+
+        let res = request.send()
+            .map_err(|err| {
+                StockError::Network(err.without_url().into())
+            })?;
+
+        if !res.status().is_success() {
+            let status = res.status();
+            let reason = status.canonical_reason().unwrap_or_default();
+            // InfluxDB explains rejected points in the body.
+            let message = match res.text().unwrap_or_default().trim() {
+                "" => reason.to_string(),
+                body => format!("{}: {}", reason, body),
+            };
+
+            return Err(StockError::HttpStatus { status: status.as_u16(), message });
+        }
+
+        Ok(())
+    }
+}
+
+/// Formats `stock` as a line protocol point, e.g.
+/// `quote,symbol=AAPL,currency=USD price=189.84,company="Apple Inc",status="up" 1704207845000000000`.
+/// Values the quote doesn't have are left out.
+pub fn line(measurement: &str, stock: &Stock, timestamp: DateTime<Utc>) -> String {
+    let mut tags = vec![("symbol", stock.symbol.as_str())];
+
+    if !stock.currency.is_empty() {
+        tags.push(("currency", stock.currency.as_str()));
+    }
+
+    let mut fields = vec![
+        ("price", stock.price.to_string()),
+        ("company", string(&stock.company_name)),
+        ("status", string(&stock.status.to_string())),
+    ];
+
+    let details = &stock.details;
+    let numbers = [
+        ("previous_price", stock.previous_price.map(|value| value.to_string())),
+        ("change_abs", stock.change_abs.map(|value| value.to_string())),
+        ("change_pct", stock.change_pct.map(float)),
+        ("previous_close", details.previous_close.map(|value| value.to_string())),
+        ("day_low", details.day_range.map(|range| range.low.to_string())),
+        ("day_high", details.day_range.map(|range| range.high.to_string())),
+        ("year_low", details.year_range.map(|range| range.low.to_string())),
+        ("year_high", details.year_range.map(|range| range.high.to_string())),
+        ("market_cap", details.market_cap.map(float)),
+    ];
+
+    for (key, value) in numbers {
+        if let Some(value) = value {
+            fields.push((key, value));
+        }
+    }
+
+    if let Some(volume) = details.volume {
+        fields.push(("volume", format!("{}i", volume)));
+    }
+
+    if let Some(market_status) = stock.market_status {
+        fields.push(("market_status", string(&market_status.to_string())));
+    }
+
+    let tags = tags.iter()
+        .map(|(key, value)| format!(",{}={}", key, escape(value, &[',', '=', ' '])))
+        .collect::<String>();
+    let fields = fields.iter()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<String>>()
+        .join(",");
+    let nanos = timestamp.timestamp_nanos_opt().unwrap_or_default();
+
+    format!("{}{} {} {}", escape(measurement, &[',', ' ']), tags, fields, nanos)
+}
+
+/// Line protocol has no NaN or infinity, so those are left out.
+fn float(value: f64) -> String {
+    match value.is_finite() {
+        true => value.to_string(),
+        false => String::new(),
+    }
+}
+
+fn string(value: &str) -> String {
+    format!("\"{}\"", escape(value, &['"', '\\']))
+}
+
+fn escape(value: &str, special: &[char]) -> String {
+    value.chars()
+        .fold(String::with_capacity(value.len()), |mut escaped, c| {
+            if special.contains(&c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+            escaped
+        })
+}
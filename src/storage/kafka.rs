@@ -0,0 +1,120 @@
+use std::{collections::HashMap, sync::atomic::{AtomicBool, Ordering}, time::Duration};
+use rdkafka::{
+    config::ClientConfig,
+    error::{KafkaError, RDKafkaErrorCode},
+    message::Message,
+    producer::{BaseRecord, DeliveryResult, Producer, ProducerContext, ThreadedProducer},
+    ClientContext,
+};
+use tracing::{debug, error, info, warn};
+
+use crate::{output::{QuoteRecord, Sink}, StockError};
+
+pub const DEFAULT_TOPIC: &str = "stocks";
+/// How long exiting waits for queued events to be delivered.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(30);
+
+
+/// Where quote events are produced.
+#[derive(Debug, Clone)]
+pub struct KafkaOptions {
+    /// Comma separated `host:port` bootstrap servers.
+    pub brokers: String,
+    pub topic: String,
+    /// Further librdkafka producer settings, e.g. `security.protocol`.
+    pub properties: HashMap<String, String>,
+}
+
+/// Logs events the broker never acknowledged, once librdkafka has given up
+/// retrying them, and when the brokers go down or come back.
+#[derive(Default)]
+struct DeliveryReport {
+    brokers_down: AtomicBool,
+}
+
+impl ClientContext for DeliveryReport {
+    /// librdkafka reports unreachable brokers over and over while it
+    /// reconnects, so only the first report is logged.
+    fn error(&self, error: KafkaError, reason: &str) {
+        match error.rdkafka_error_code() {
+            Some(RDKafkaErrorCode::AllBrokersDown) if !self.brokers_down.swap(true, Ordering::Relaxed) => {
+                warn!("Kafka brokers are unreachable, queueing events: {}", reason);
+            }
+            Some(RDKafkaErrorCode::AllBrokersDown | RDKafkaErrorCode::BrokerTransportFailure) => debug!("Kafka: {}", reason),
+            _ => error!("Kafka: {}: {}", error, reason),
+        }
+    }
+}
+
+impl ProducerContext for DeliveryReport {
+    type DeliveryOpaque = ();
+
+    fn delivery(&self, result: &DeliveryResult<'_>, _: Self::DeliveryOpaque) {
+        match result {
+            Ok(_) if self.brokers_down.swap(false, Ordering::Relaxed) => info!("Kafka brokers are reachable again"),
+            Ok(_) => {}
+            Err((err, message)) => {
+                let symbol = message.key().map(String::from_utf8_lossy).unwrap_or_default();
+
+                error!(symbol = %symbol, "Failed to deliver {} to Kafka topic {}: {}", symbol, message.topic(), err);
+            }
+        }
+    }
+}
+
+/// Produces every quote as a JSON event, as written by `--output json`, keyed
+/// by symbol so each symbol's events stay ordered within a partition.
+///
+/// Events are queued and sent by librdkafka's own thread; dropping the
+/// producer waits for the queue to drain.
+pub struct KafkaProducer {
+    producer: ThreadedProducer<DeliveryReport>,
+    topic: String,
+}
+
+impl KafkaProducer {
+    pub fn open(options: KafkaOptions) -> Result<Self, StockError> {
+        let mut config = ClientConfig::new();
+        config.set("bootstrap.servers", &options.brokers);
+
+        for (key, value) in options.properties.iter() {
+            config.set(key, value);
+        }
+
+        let producer = config.create_with_context(DeliveryReport::default())
+            .map_err(|err| {
+                StockError::invalid("publisher", format!("Kafka: {}", err))
+            })?;
+
+        Ok(Self { producer, topic: options.topic })
+    }
+}
+
+impl Sink for KafkaProducer {
+    fn emit(&mut self, record: &QuoteRecord) {
+        let symbol = &record.stock.symbol;
+        let payload = match serde_json::to_string(record) {
+            Ok(payload) => payload,
+            Err(err) => {
+                error!(symbol = %symbol, "Failed to serialize {}: {}", symbol, err);
+                return;
+            }
+        };
+
+        match self.producer.send(BaseRecord::to(&self.topic).key(symbol).payload(&payload)) {
+            Ok(()) => {}
+            Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), _)) => {
+                warn!(symbol = %symbol, "Kafka queue is full, dropping {}", symbol);
+            }
+            Err((err, _)) => error!(symbol = %symbol, "Failed to produce {} to Kafka: {}", symbol, err),
+        }
+    }
+}
+
+impl Drop for KafkaProducer {
+    fn drop(&mut self) {
+        if let Err(err) = self.producer.flush(FLUSH_TIMEOUT) {
+            warn!("{} Kafka events were not delivered before exiting: {}", self.producer.in_flight_count(), err);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::{sync::{atomic::{AtomicBool, Ordering}, Arc}, thread::{self, JoinHandle}, time::Duration};
+use rumqttc::{Client, ClientError, Event, Outgoing, Packet, QoS};
+use tracing::{error, info, warn};
+
+use crate::{output::{QuoteRecord, Sink}, StockError};
+
+pub const DEFAULT_PORT: u16 = 1883;
+pub const DEFAULT_TOPIC: &str = "stocks/{symbol}";
+/// Publishes waiting while the broker is unreachable before more are dropped.
+const QUEUE_CAPACITY: usize = 1000;
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+
+/// Where and how quote updates are published.
+#[derive(Debug, Clone)]
+pub struct MqttOptions {
+    /// `host`, `host:port` or `mqtt://host:port`.
+    pub broker: String,
+    /// Topic per quote, with `{symbol}` replaced by the symbol.
+    pub topic: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// 0, 1 or 2.
+    pub qos: u8,
+    /// Keep the last quote of each topic on the broker for new subscribers.
+    pub retain: bool,
+}
+
+/// Publishes every quote as JSON, as written by `--output json`, to an MQTT
+/// topic per symbol, e.g. for Home Assistant sensors.
+///
+/// The connection is kept up by a thread of its own that reconnects as
+/// needed; publishes wait in a bounded queue meanwhile. Dropping the publisher
+/// disconnects once the queue has been sent.
+pub struct MqttPublisher {
+    client: Client,
+    topic: String,
+    qos: QoS,
+    retain: bool,
+    stopping: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl MqttPublisher {
+    pub fn open(options: MqttOptions) -> Result<Self, StockError> {
+        let (host, port) = broker_address(&options.broker)?;
+        let qos = match options.qos {
+            0 => QoS::AtMostOnce,
+            1 => QoS::AtLeastOnce,
+            2 => QoS::ExactlyOnce,
+            qos => return Err(invalid(&format!("QoS must be 0, 1 or 2, got {}", qos))),
+        };
+
+        let mut mqtt_options = rumqttc::MqttOptions::new(format!("scraping-stock-{}", std::process::id()), host, port);
+        mqtt_options.set_keep_alive(Duration::from_secs(30));
+
+        if let Some(username) = &options.username {
+            mqtt_options.set_credentials(username, options.password.clone().unwrap_or_default());
+        }
+
+        let (client, mut connection) = Client::new(mqtt_options, QUEUE_CAPACITY);
+        let stopping = Arc::new(AtomicBool::new(false));
+        let broker = options.broker.clone();
+        let stopped = stopping.clone();
+
+        let worker = thread::spawn(move || {
+            let mut failing = false;
+
+            for notification in connection.iter() {
+                match notification {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) if failing => {
+                        info!("Connected to MQTT broker {} again", broker);
+                        failing = false;
+                    }
+                    Ok(Event::Outgoing(Outgoing::Disconnect)) => return,
+                    Ok(_) => {}
+                    Err(_) if stopped.load(Ordering::Relaxed) => return,
+                    Err(err) => {
+                        if !failing {
+                            warn!("MQTT broker {} is unreachable, retrying: {}", broker, err);
+                            failing = true;
+                        }
+
+                        thread::sleep(RECONNECT_DELAY);
+                    }
+                }
+            }
+        });
+
+        Ok(Self { client, topic: options.topic, qos, retain: options.retain, stopping, worker: Some(worker) })
+    }
+}
+
+impl Sink for MqttPublisher {
+    fn emit(&mut self, record: &QuoteRecord) {
+        let symbol = &record.stock.symbol;
+        let payload = match serde_json::to_vec(record) {
+            Ok(payload) => payload,
+            Err(err) => {
+                error!(symbol = %symbol, "Failed to serialize {}: {}", symbol, err);
+                return;
+            }
+        };
+
+        match self.client.try_publish(self.topic.replace("{symbol}", symbol), self.qos, self.retain, payload) {
+            Ok(()) => {}
+            Err(ClientError::TryRequest(_)) => warn!(symbol = %symbol, "MQTT queue is full, dropping {}", symbol),
+            Err(err) => error!(symbol = %symbol, "Failed to publish {} over MQTT: {}", symbol, err),
+        }
+    }
+}
+
+impl Drop for MqttPublisher {
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::Relaxed);
+
+        // With the queue full the disconnect can't be queued, so nothing
+        // would stop the worker.
+        if self.client.try_disconnect().is_err() {
+            return;
+        }
+
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn broker_address(broker: &str) -> Result<(String, u16), StockError> {
+    let address = match broker.split_once("://") {
+        Some(("mqtt" | "tcp", address)) => address,
+        Some((scheme, _)) => return Err(invalid(&format!("unsupported scheme {}", scheme))),
+        None => broker,
+    };
+
+    match address.trim_end_matches('/').rsplit_once(':') {
+        Some((host, port)) => {
+            let port = port.parse().map_err(|_| invalid(&format!("bad port in {}", broker)))?;
+
+            Ok((host.to_string(), port))
+        }
+        None => Ok((address.trim_end_matches('/').to_string(), DEFAULT_PORT)),
+    }
+}
+
+fn invalid(reason: &str) -> StockError {
+    StockError::invalid("publisher", format!("MQTT: {}", reason))
+}
@@ -0,0 +1,130 @@
+use std::{sync::mpsc, thread::{self, JoinHandle}, time::Duration};
+use tracing::{error, info, warn};
+
+use crate::{output::{QuoteRecord, Sink}, StockError};
+
+pub const DEFAULT_CHANNEL_PREFIX: &str = "stocks";
+/// Limit on connecting and on every command.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+
+/// Where quote updates are published.
+#[derive(Debug, Clone)]
+pub struct RedisOptions {
+    /// e.g. `redis://localhost:6379/0`.
+    pub url: String,
+    /// Quotes go to the `PREFIX:SYMBOL` channel.
+    pub channel_prefix: String,
+    /// Also keep the latest quote of every symbol in this hash, keyed by
+    /// symbol.
+    pub hash: Option<String>,
+}
+
+/// A quote as published: its channel, hash field and JSON.
+struct Update {
+    channel: String,
+    symbol: String,
+    payload: String,
+}
+
+/// Publishes every quote as JSON, as written by `--output json`, to a Redis
+/// channel per symbol.
+///
+/// Updates are sent from a thread of its own that reconnects as needed; while
+/// Redis is unreachable they are dropped. Dropping the publisher waits for the
+/// updates already queued.
+pub struct RedisPublisher {
+    sender: Option<mpsc::Sender<Update>>,
+    channel_prefix: String,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl RedisPublisher {
+    pub fn open(options: RedisOptions) -> Result<Self, StockError> {
+        let client = redis::Client::open(options.url.as_str())
+            .map_err(|err| {
+                StockError::invalid("publisher", format!("{}: {}", options.url, err))
+            })?;
+        let (sender, receiver) = mpsc::channel::<Update>();
+        let hash = options.hash.clone();
+
+        let worker = thread::spawn(move || {
+            let mut connection = None;
+            let mut failing = false;
+
+            for update in receiver {
+                match send(&client, &mut connection, &update, hash.as_deref()) {
+                    Ok(()) if failing => {
+                        info!("Publishing to Redis again");
+                        failing = false;
+                    }
+                    Ok(()) => {}
+                    Err(err) => {
+                        // Reconnect for the next update.
+                        connection = None;
+
+                        if !failing {
+                            error!(symbol = %update.symbol, "Failed to publish {} to Redis, dropping updates until it is back: {}", update.symbol, err);
+                            failing = true;
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Self { sender: Some(sender), channel_prefix: options.channel_prefix, worker: Some(worker) })
+    }
+}
+
+impl Sink for RedisPublisher {
+    fn emit(&mut self, record: &QuoteRecord) {
+        let payload = match serde_json::to_string(record) {
+            Ok(payload) => payload,
+            Err(err) => {
+                error!(symbol = %record.stock.symbol, "Failed to serialize {}: {}", record.stock.symbol, err);
+                return;
+            }
+        };
+        let update = Update {
+            channel: format!("{}:{}", self.channel_prefix, record.stock.symbol),
+            symbol: record.stock.symbol.clone(),
+            payload,
+        };
+
+        if self.sender.as_ref().is_none_or(|sender| sender.send(update).is_err()) {
+            warn!(symbol = %record.stock.symbol, "Redis publisher is not running, dropping {}", record.stock.symbol);
+        }
+    }
+}
+
+impl Drop for RedisPublisher {
+    fn drop(&mut self) {
+        self.sender.take();
+
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn send(client: &redis::Client, connection: &mut Option<redis::Connection>, update: &Update, hash: Option<&str>) -> Result<(), redis::RedisError> {
+    let connection = match connection {
+        Some(connection) => connection,
+        None => {
+            let opened = client.get_connection_with_timeout(TIMEOUT)?;
+            opened.set_read_timeout(Some(TIMEOUT))?;
+            opened.set_write_timeout(Some(TIMEOUT))?;
+
+            connection.insert(opened)
+        }
+    };
+
+    let mut pipeline = redis::pipe();
+    pipeline.cmd("PUBLISH").arg(&update.channel).arg(&update.payload).ignore();
+
+    if let Some(hash) = hash {
+        pipeline.cmd("HSET").arg(hash).arg(&update.symbol).arg(&update.payload).ignore();
+    }
+
+    pipeline.query::<()>(connection)
+}
@@ -0,0 +1,217 @@
+use std::path::Path;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, ToSql};
+
+use tracing::error;
+
+use crate::{candle::{Candle, CandleInterval}, output::{QuoteRecord, Sink}, Decimal, Stock, StockError};
+
+
+/// A quote as stored in the history table.
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub fetched_at: DateTime<Utc>,
+    pub stock: Stock,
+}
+
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+impl SqliteStore {
+    pub fn open(path: &Path) -> Result<Self, StockError> {
+        let connection = Connection::open(path)
+            .map_err(|err| {
+                StockError::Storage(format!("{}: {}", path.display(), err).into())
+            })?;
+
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS quotes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                symbol TEXT NOT NULL,
+                company_name TEXT NOT NULL,
+                price REAL NOT NULL,
+                currency TEXT NOT NULL DEFAULT '',
+                status TEXT NOT NULL,
+                fetched_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS quotes_symbol_fetched_at ON quotes (symbol, fetched_at);
+            CREATE TABLE IF NOT EXISTS candles (
+                symbol TEXT NOT NULL,
+                interval TEXT NOT NULL,
+                started_at TEXT NOT NULL,
+                open REAL NOT NULL,
+                high REAL NOT NULL,
+                low REAL NOT NULL,
+                close REAL NOT NULL,
+                PRIMARY KEY (symbol, interval, started_at)
+            );",
+        )
+        .map_err(storage_error)?;
+
+        let store = Self { connection };
+        store.migrate()?;
+
+        Ok(store)
+    }
+
+    /// Adds columns introduced after a database file was created.
+    fn migrate(&self) -> Result<(), StockError> {
+        let has_currency = self.connection
+            .prepare("SELECT 1 FROM pragma_table_info('quotes') WHERE name = 'currency'")
+            .and_then(|mut statement| statement.exists([]))
+            .map_err(storage_error)?;
+
+        if !has_currency {
+            self.connection
+                .execute_batch("ALTER TABLE quotes ADD COLUMN currency TEXT NOT NULL DEFAULT ''")
+                .map_err(storage_error)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn record(&self, stock: &Stock, fetched_at: DateTime<Utc>) -> Result<(), StockError> {
+        self.connection.execute(
+            "INSERT INTO quotes (symbol, company_name, price, currency, status, fetched_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![stock.symbol, stock.company_name, stock.price.to_f64(), stock.currency, stock.status.to_string(), fetched_at.to_rfc3339()],
+        )
+        .map_err(storage_error)?;
+
+        Ok(())
+    }
+
+    /// Returns the most recent `limit` quotes for `symbol`, oldest first.
+    pub fn history(&self, symbol: &str, limit: usize) -> Result<Vec<HistoryEntry>, StockError> {
+        let mut entries = self.entries(
+            "SELECT symbol, company_name, price, currency, status, fetched_at FROM quotes
+             WHERE symbol = ?1 ORDER BY fetched_at DESC LIMIT ?2",
+            params![symbol, limit as i64],
+        )?;
+
+        entries.reverse();
+
+        Ok(entries)
+    }
+
+    /// Returns the quotes for `symbol` fetched from `from` up to `to`, `to`
+    /// excluded, oldest first.
+    pub fn history_between(&self, symbol: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<HistoryEntry>, StockError> {
+        self.entries(
+            "SELECT symbol, company_name, price, currency, status, fetched_at FROM quotes
+             WHERE symbol = ?1 AND fetched_at >= ?2 AND fetched_at < ?3 ORDER BY fetched_at",
+            params![symbol, from.to_rfc3339(), to.to_rfc3339()],
+        )
+    }
+
+    fn entries(&self, query: &str, params: &[&dyn ToSql]) -> Result<Vec<HistoryEntry>, StockError> {
+        let mut statement = self.connection.prepare(query)
+            .map_err(storage_error)?;
+
+        let rows = statement.query_map(params, |row| {
+            let status: String = row.get(4)?;
+            let fetched_at: String = row.get(5)?;
+            let price = Decimal::from_f64(row.get(2)?).unwrap_or_default();
+            let mut stock = Stock::new(row.get(0)?, row.get(1)?, price).with_currency(row.get(3)?);
+            stock.status = status.parse().unwrap_or_default();
+
+            Ok((stock, fetched_at))
+        })
+        .map_err(storage_error)?;
+
+        let mut entries = Vec::new();
+
+        for row in rows {
+            let (stock, fetched_at) = row.map_err(storage_error)?;
+            let fetched_at = DateTime::parse_from_rfc3339(&fetched_at)
+                .map(|time| time.with_timezone(&Utc))
+                .map_err(|err| {
+                    StockError::Storage(err.into())
+                })?;
+
+            entries.push(HistoryEntry { fetched_at, stock: stock.with_fetched_at(fetched_at) });
+        }
+
+        Ok(entries)
+    }
+
+    /// Stores a closed candle, replacing one recorded for the same start.
+    pub fn record_candle(&self, symbol: &str, interval: CandleInterval, candle: &Candle) -> Result<(), StockError> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO candles (symbol, interval, started_at, open, high, low, close) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                symbol,
+                interval.to_string(),
+                candle.timestamp.to_rfc3339(),
+                candle.open.to_f64(),
+                candle.high.to_f64(),
+                candle.low.to_f64(),
+                candle.close.to_f64(),
+            ],
+        )
+        .map_err(storage_error)?;
+
+        Ok(())
+    }
+
+    /// Returns the most recent `limit` candles of `symbol`, oldest first.
+    pub fn candles(&self, symbol: &str, interval: CandleInterval, limit: usize) -> Result<Vec<Candle>, StockError> {
+        let mut statement = self.connection.prepare(
+            "SELECT started_at, open, high, low, close FROM candles
+             WHERE symbol = ?1 AND interval = ?2 ORDER BY started_at DESC LIMIT ?3",
+        )
+        .map_err(storage_error)?;
+
+        let rows = statement.query_map(params![symbol, interval.to_string(), limit as i64], |row| {
+            let price = |index| row.get(index).map(|value| Decimal::from_f64(value).unwrap_or_default());
+
+            Ok((row.get::<_, String>(0)?, price(1)?, price(2)?, price(3)?, price(4)?))
+        })
+        .map_err(storage_error)?;
+
+        let mut candles = Vec::new();
+
+        for row in rows {
+            let (started_at, open, high, low, close) = row.map_err(storage_error)?;
+            let timestamp = DateTime::parse_from_rfc3339(&started_at)
+                .map(|time| time.with_timezone(&Utc))
+                .map_err(|err| {
+                    StockError::Storage(err.into())
+                })?;
+
+            candles.push(Candle { timestamp, open, high, low, close, volume: None });
+        }
+
+        candles.reverse();
+
+        Ok(candles)
+    }
+}
+
+impl Sink for SqliteStore {
+    fn emit(&mut self, record: &QuoteRecord) {
+        if let Err(err) = self.record(record.stock, record.timestamp) {
+            error!(symbol = %record.stock.symbol, "Failed to record {}: {}", record.stock.symbol, err);
+        }
+    }
+
+    fn emit_candle(&mut self, symbol: &str, interval: CandleInterval, candle: &Candle) {
+        if let Err(err) = self.record_candle(symbol, interval, candle) {
+            error!(symbol, "Failed to record the {} candle of {}: {}", interval, symbol, err);
+        }
+    }
+
+    fn keeps_history(&self) -> bool {
+        true
+    }
+
+    fn last_recorded(&self, symbol: &str) -> Option<(DateTime<Utc>, Stock)> {
+        let entry = self.history(symbol, 1).ok()?.pop()?;
+
+        Some((entry.fetched_at, entry.stock))
+    }
+}
+
+fn storage_error(err: rusqlite::Error) -> StockError {
+    StockError::Storage(err.into())
+}
@@ -0,0 +1,212 @@
+use std::collections::BTreeMap;
+use tracing::info;
+
+use crate::{scraping::coingecko, QuoteKind, StockError};
+
+/// Exchange codes as Google Finance spells them.
+const EXCHANGES: [&str; 45] = [
+    "NASDAQ", "NYSE", "NYSEARCA", "NYSEAMERICAN", "OTCMKTS", "BATS", "INDEXSP", "INDEXDJX", "INDEXNASDAQ", "MUTF", "FX",
+    "IDX", "LON", "TYO", "HKG", "ASX", "TSE", "CVE", "FRA", "ETR", "EPA", "AMS", "EBR", "ELI", "BIT", "BME",
+    "SWX", "STO", "CPH", "HEL", "OSL", "SGX", "KRX", "KOSDAQ", "NSE", "BOM", "SHA", "SHE", "TPE", "KLSE",
+    "SET", "BVMF", "BMV", "JSE", "TADAWUL",
+];
+
+/// Other names people use for an exchange, as `(alias, exchange)`.
+const EXCHANGE_ALIASES: [(&str, &str); 16] = [
+    ("JKT", "IDX"),
+    ("JK", "IDX"),
+    ("JSX", "IDX"),
+    ("NMS", "NASDAQ"),
+    ("NAS", "NASDAQ"),
+    ("NYQ", "NYSE"),
+    ("ARCA", "NYSEARCA"),
+    ("AMEX", "NYSEAMERICAN"),
+    ("LSE", "LON"),
+    ("JPX", "TYO"),
+    ("HKEX", "HKG"),
+    ("TSX", "TSE"),
+    ("XETRA", "ETR"),
+    ("PAR", "EPA"),
+    ("SES", "SGX"),
+    ("KOSPI", "KRX"),
+];
+
+/// Index codes on the exchanges their constituents trade on, rather than an
+/// `INDEX...` one.
+const LISTED_INDICES: [&str; 2] = ["COMPOSITE:IDX", "LQ45:IDX"];
+
+/// Other names people use for an index, as `(alias, code)`: Yahoo's tickers
+/// and the local name of the Jakarta Composite.
+const INDEX_ALIASES: [(&str, &str); 5] = [
+    ("^GSPC", ".INX:INDEXSP"),
+    ("^DJI", ".DJI:INDEXDJX"),
+    ("^IXIC", ".IXIC:INDEXNASDAQ"),
+    ("^JKSE", "COMPOSITE:IDX"),
+    ("IHSG:IDX", "COMPOSITE:IDX"),
+];
+
+/// Bare tickers of widely held stocks and funds with the exchange they are
+/// listed on, so `AAPL` alone reads as `AAPL:NASDAQ` without a lookup.
+const LISTINGS: [(&str, &str); 41] = [
+    ("AAPL", "NASDAQ"), ("MSFT", "NASDAQ"), ("GOOGL", "NASDAQ"), ("GOOG", "NASDAQ"), ("AMZN", "NASDAQ"), ("NVDA", "NASDAQ"),
+    ("META", "NASDAQ"), ("TSLA", "NASDAQ"), ("NFLX", "NASDAQ"), ("AMD", "NASDAQ"), ("INTC", "NASDAQ"), ("AVGO", "NASDAQ"),
+    ("ADBE", "NASDAQ"), ("CSCO", "NASDAQ"), ("PEP", "NASDAQ"), ("COST", "NASDAQ"), ("QQQ", "NASDAQ"), ("IBM", "NYSE"),
+    ("JPM", "NYSE"), ("V", "NYSE"), ("MA", "NYSE"), ("WMT", "NYSE"), ("KO", "NYSE"), ("DIS", "NYSE"),
+    ("BAC", "NYSE"), ("XOM", "NYSE"), ("JNJ", "NYSE"), ("PG", "NYSE"), ("ORCL", "NYSE"), ("BRK.B", "NYSE"),
+    ("SPY", "NYSEARCA"), ("VOO", "NYSEARCA"), ("IVV", "NYSEARCA"), ("BBCA", "IDX"), ("BBRI", "IDX"), ("BMRI", "IDX"),
+    ("BBNI", "IDX"), ("TLKM", "IDX"), ("ASII", "IDX"), ("UNVR", "IDX"), ("GOTO", "IDX"),
+];
+
+
+/// Checks a watchlist code and returns it in canonical form: crypto codes as
+/// CoinGecko expects them, anything else as upper-case `SYMBOL:EXCHANGE`
+/// with exchange aliases such as `JKT` mapped to their Google Finance name
+/// (`IDX`). Index aliases such as `^GSPC` become Google's code, and symbols
+/// on `INDEX...` exchanges get the leading dot Google gives them, e.g.
+/// `INX:INDEXSP` becomes `.INX:INDEXSP`. A well-known bare ticker gets the
+/// exchange it is listed on, e.g. `AAPL:NASDAQ`; others have to be looked up
+/// with [`resolve_tickers`] first.
+pub fn normalize(code: &str) -> Result<String, StockError> {
+    let code = code.trim();
+
+    if let Some((_, index)) = INDEX_ALIASES.iter().find(|(alias, _)| alias.eq_ignore_ascii_case(code)) {
+        return Ok(index.to_string());
+    }
+
+    if coingecko::is_crypto_symbol(code) {
+        return match code.split_once(':') {
+            Some((_, id)) if !id.is_empty() => Ok(format!("crypto:{}", id.to_lowercase())),
+            Some(_) => Err(invalid(code, "missing coin id after crypto:")),
+            None => Ok(code.to_uppercase()),
+        };
+    }
+
+    let Some((symbol, exchange)) = code.split_once(':') else {
+        return match listing_of(code) {
+            Some(listing) => normalize(&listing),
+            None => Err(invalid(code, "unknown exchange, expected SYMBOL:EXCHANGE, e.g. AAPL:NASDAQ")),
+        };
+    };
+    let symbol = symbol.trim().to_uppercase();
+    let exchange = exchange.trim().to_uppercase();
+
+    if symbol.is_empty() || !symbol.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '&')) {
+        return Err(invalid(code, "symbol may only contain letters, digits, '.', '-' and '&'"));
+    }
+
+    let exchange = EXCHANGE_ALIASES.iter()
+        .find(|(alias, _)| *alias == exchange)
+        .map(|(_, exchange)| exchange.to_string())
+        .unwrap_or(exchange);
+
+    if !EXCHANGES.contains(&exchange.as_str()) {
+        return Err(invalid(code, &format!("unknown exchange {}", exchange)));
+    }
+
+    if exchange == "FX" && fx_pair(&symbol).is_none() {
+        return Err(invalid(code, "expected a currency pair such as USD-IDR:FX"));
+    }
+
+    if exchange.starts_with("INDEX") && !symbol.starts_with('.') {
+        return Ok(format!(".{}:{}", symbol, exchange));
+    }
+
+    Ok(format!("{}:{}", symbol, exchange))
+}
+
+/// Looks up the exchange of every bare ticker among `codes` that isn't a
+/// well-known listing, `search` giving its `SYMBOL:EXCHANGE` code, each
+/// ticker once. Returns the normalized code of each, keyed by the ticker in
+/// upper case, for [`resolved`] to put in place of the tickers. Codes with
+/// an exchange, crypto codes and index aliases are left alone.
+pub fn resolve_tickers<'a>(codes: impl IntoIterator<Item = &'a str>, search: impl Fn(&str) -> Result<String, StockError>) -> Result<BTreeMap<String, String>, StockError> {
+    let mut resolved = BTreeMap::new();
+
+    for code in codes {
+        let ticker = code.trim().to_uppercase();
+
+        if !is_ticker(&ticker) || listing_of(&ticker).is_some() || resolved.contains_key(&ticker) {
+            continue;
+        }
+
+        let found = normalize(&search(&ticker)?)?;
+        info!(symbol = %ticker, "Resolved {} to {}", ticker, found);
+
+        resolved.insert(ticker, found);
+    }
+
+    Ok(resolved)
+}
+
+/// `code` with a bare ticker found by [`resolve_tickers`] replaced by its
+/// `SYMBOL:EXCHANGE` code. Any other code is returned as given.
+pub fn resolved(code: &str, tickers: &BTreeMap<String, String>) -> String {
+    tickers.get(&code.trim().to_uppercase()).cloned().unwrap_or_else(|| code.to_string())
+}
+
+/// The `SYMBOL:EXCHANGE` code of a well-known bare ticker.
+fn listing_of(ticker: &str) -> Option<String> {
+    let ticker = ticker.to_uppercase();
+
+    LISTINGS.iter()
+        .find(|(symbol, _)| *symbol == ticker)
+        .map(|(symbol, exchange)| format!("{}:{}", symbol, exchange))
+}
+
+/// Whether `code` is a bare ticker such as `AAPL`, without an exchange and
+/// neither a crypto code nor an index alias.
+fn is_ticker(code: &str) -> bool {
+    !code.is_empty()
+        && !code.contains(':')
+        && !coingecko::is_crypto_symbol(code)
+        && !INDEX_ALIASES.iter().any(|(alias, _)| alias.eq_ignore_ascii_case(code))
+        && code.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '&'))
+}
+
+/// Whether `exchange` is a Google Finance exchange code such as `IDX`.
+pub fn is_exchange(exchange: &str) -> bool {
+    EXCHANGES.contains(&exchange)
+}
+
+/// The kind of instrument a code names, as far as the code tells: mutual
+/// funds are listed under `MUTF`, e.g. `VFIAX:MUTF`, currency pairs under
+/// `FX`, e.g. `USD-IDR:FX`, and indices as [`is_index`] tells.
+pub fn kind_of(code: &str) -> QuoteKind {
+    if is_index(code) {
+        return QuoteKind::Index;
+    }
+
+    match code.to_uppercase().split_once(':') {
+        Some((_, "MUTF")) => QuoteKind::Fund,
+        Some((_, "FX")) => QuoteKind::Fx,
+        _ => QuoteKind::Equity,
+    }
+}
+
+/// Whether `code` names a market index: anything on an `INDEX...` exchange,
+/// e.g. `.DJI:INDEXDJX`, and a few listed under a stock exchange such as
+/// `COMPOSITE:IDX`.
+pub fn is_index(code: &str) -> bool {
+    let code = code.to_uppercase();
+
+    match code.split_once(':') {
+        Some((_, exchange)) if exchange.starts_with("INDEX") => true,
+        _ => LISTED_INDICES.contains(&code.as_str()),
+    }
+}
+
+/// The base and quote currency of a pair such as `USD-IDR`.
+pub fn fx_pair(symbol: &str) -> Option<(&str, &str)> {
+    let is_currency = |part: &str| part.len() == 3 && part.chars().all(|c| c.is_ascii_alphabetic());
+
+    symbol.split_once('-').filter(|(base, quote)| is_currency(base) && is_currency(quote))
+}
+
+/// [`normalize`] for a whole watchlist, failing on the first bad code.
+pub fn normalize_all(codes: &[String]) -> Result<Vec<String>, StockError> {
+    codes.iter().map(|code| normalize(code)).collect()
+}
+
+fn invalid(code: &str, reason: &str) -> StockError {
+    StockError::invalid("symbol", format!("{}: {}", code, reason))
+}
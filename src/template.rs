@@ -0,0 +1,289 @@
+use std::{fmt, str::FromStr};
+use serde::Deserialize;
+
+use crate::{Decimal, StockError};
+
+/// Widest width and precision a placeholder may ask for; more would only
+/// fill memory with padding.
+pub const MAX_WIDTH: usize = 256;
+
+/// What a placeholder is filled in with.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    Number(Decimal),
+    /// A change, shown with its sign and two decimals unless the
+    /// placeholder says otherwise.
+    Change(Decimal),
+    /// Not known (yet), shown as `-`.
+    Missing,
+}
+
+impl From<Option<Decimal>> for Value {
+    fn from(value: Option<Decimal>) -> Self {
+        value.map(Value::Number).unwrap_or(Value::Missing)
+    }
+}
+
+impl From<Option<f64>> for Value {
+    fn from(value: Option<f64>) -> Self {
+        value.and_then(Decimal::from_f64).into()
+    }
+}
+
+/// Something templates can be rendered from, by placeholder name.
+pub trait Fields {
+    /// Every placeholder name `value` knows, for checking templates.
+    const NAMES: &'static [&'static str];
+
+    fn value(&self, name: &str) -> Value;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// The part of a placeholder after the `:`, as in Rust's `format!`:
+/// `[[fill]align][+][0][width][.precision]`, e.g. `>10.2` or `+.2`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Spec {
+    fill: Option<char>,
+    align: Option<Align>,
+    sign: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Field { name: String, spec: Spec },
+}
+
+/// A line with `{placeholder}`s, each optionally formatted, e.g.
+/// `{symbol} {price:>10.2} {change_pct:+.2}%`. `{{` and `}}` stand for
+/// literal braces.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Template {
+    source: String,
+    parts: Vec<Part>,
+}
+
+impl FromStr for Template {
+    type Err = StockError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = value.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    let mut closed = false;
+
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            closed = true;
+                            break;
+                        }
+
+                        placeholder.push(c);
+                    }
+
+                    let (name, spec) = placeholder.split_once(':').unwrap_or((placeholder.as_str(), ""));
+
+                    if !closed || name.trim().is_empty() {
+                        return Err(invalid(value, "empty or unclosed placeholder"));
+                    }
+
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+
+                    let spec = parse_spec(spec).ok_or_else(|| invalid(value, &format!("bad format {:?} for {{{}}}", spec, name)))?;
+                    parts.push(Part::Field { name: name.trim().to_string(), spec });
+                }
+                '}' => return Err(invalid(value, "unmatched }, write }} for a literal one")),
+                c => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+
+        Ok(Self { source: value.to_string(), parts })
+    }
+}
+
+impl TryFrom<String> for Template {
+    type Error = StockError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl Template {
+    /// Fails on the first placeholder that isn't one of `names`.
+    pub fn check(&self, names: &[&str]) -> Result<(), StockError> {
+        for part in self.parts.iter() {
+            let Part::Field { name, .. } = part else {
+                continue;
+            };
+
+            if !names.contains(&name.as_str()) {
+                let reason = format!("unknown placeholder {{{}}}, expected one of {}", name, names.join(", "));
+                return Err(invalid(&self.source, &reason));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// [`Template::check`] against the placeholders of `F`.
+    pub fn check_for<F: Fields>(&self) -> Result<(), StockError> {
+        self.check(F::NAMES)
+    }
+
+    pub fn render(&self, fields: &impl Fields) -> String {
+        self.parts.iter()
+            .map(|part| match part {
+                Part::Text(text) => text.clone(),
+                Part::Field { name, spec } => spec.apply(fields.value(name)),
+            })
+            .collect()
+    }
+}
+
+fn parse_spec(spec: &str) -> Option<Spec> {
+    let align_of = |c: char| match c {
+        '<' => Some(Align::Left),
+        '^' => Some(Align::Center),
+        '>' => Some(Align::Right),
+        _ => None,
+    };
+
+    let mut parsed = Spec::default();
+    let chars: Vec<char> = spec.chars().collect();
+    let mut rest = chars.as_slice();
+
+    match rest {
+        [fill, align, ..] if align_of(*align).is_some() => {
+            parsed.fill = Some(*fill);
+            parsed.align = align_of(*align);
+            rest = &rest[2..];
+        }
+        [align, ..] if align_of(*align).is_some() => {
+            parsed.align = align_of(*align);
+            rest = &rest[1..];
+        }
+        _ => {}
+    }
+
+    if let ['+', tail @ ..] = rest {
+        parsed.sign = true;
+        rest = tail;
+    }
+
+    if let ['0', tail @ ..] = rest {
+        parsed.zero = true;
+        rest = tail;
+    }
+
+    let rest: String = rest.iter().collect();
+    let (width, precision) = match rest.split_once('.') {
+        Some((width, precision)) => (width, Some(precision.parse().ok().filter(|precision| *precision <= MAX_WIDTH)?)),
+        None => (rest.as_str(), None),
+    };
+
+    parsed.width = match width {
+        "" => 0,
+        width => width.parse().ok().filter(|width| *width <= MAX_WIDTH)?,
+    };
+    parsed.precision = precision;
+
+    Some(parsed)
+}
+
+impl Spec {
+    fn apply(&self, value: Value) -> String {
+        let (text, numeric) = match value {
+            Value::Text(text) => (self.precision.map(|precision| text.chars().take(precision).collect()).unwrap_or(text), false),
+            Value::Missing => ("-".to_string(), false),
+            Value::Number(number) => (self.number(number, false), true),
+            Value::Change(change) => (self.number(change, true), true),
+        };
+
+        let padding = self.width.saturating_sub(text.chars().count());
+
+        if padding == 0 {
+            return text;
+        }
+
+        // Zero padding goes between the sign and the digits.
+        if numeric && self.zero && self.align.is_none() {
+            let (sign, digits) = match text.strip_prefix(['+', '-']) {
+                Some(digits) => text.split_at(text.len() - digits.len()),
+                None => ("", text.as_str()),
+            };
+
+            return format!("{}{}{}", sign, "0".repeat(padding), digits);
+        }
+
+        let fill = self.fill.unwrap_or(' ').to_string();
+        let align = self.align.unwrap_or(match numeric {
+            true => Align::Right,
+            false => Align::Left,
+        });
+
+        match align {
+            Align::Left => format!("{}{}", text, fill.repeat(padding)),
+            Align::Right => format!("{}{}", fill.repeat(padding), text),
+            Align::Center => format!("{}{}{}", fill.repeat(padding / 2), text, fill.repeat(padding - padding / 2)),
+        }
+    }
+
+    fn number(&self, number: Decimal, is_change: bool) -> String {
+        let (sign, precision) = match (is_change, self.precision) {
+            // A bare change placeholder reads like `+1.25`.
+            (true, None) if !self.sign => (true, Some(2)),
+            (_, precision) => (self.sign, precision),
+        };
+
+        let text = match precision {
+            Some(precision) => format!("{:.*}", precision, number),
+            None => number.to_string(),
+        };
+
+        match sign && !text.starts_with('-') {
+            true => format!("+{}", text),
+            false => text,
+        }
+    }
+}
+
+fn invalid(template: &str, reason: &str) -> StockError {
+    StockError::invalid("template", format!("{:?}: {}", template, reason))
+}
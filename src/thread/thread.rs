@@ -1,17 +1,18 @@
 use std::thread;
+use tracing::info;
 
 pub fn run_simple_thread() {
 
 
     thread::spawn(move || {
-        println!("Hello from a thread!");
+        info!("Hello from a thread!");
     });
 
     thread::spawn(move || {
-        println!("Hello from another thread!");
+        info!("Hello from another thread!");
     });
 
     std::thread::sleep(std::time::Duration::from_secs(1));
 
-    println!("Hello from main thread!")
+    info!("Hello from main thread!")
 }
\ No newline at end of file
@@ -0,0 +1,245 @@
+use std::{collections::HashMap, fs::OpenOptions, io, sync::mpsc, thread, time::Duration};
+use ratatui::{
+    backend::CrosstermBackend,
+    crossterm::{
+        event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+        execute,
+        terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    },
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Paragraph, Row, Table},
+    Frame, Terminal,
+};
+
+use crate::{notify::{self, Dispatcher}, output::Output, shutdown::Shutdown, stock::{self, PriceRange, Status}, watch::{self, WatchOptions}, Stock, StockClient, StockError};
+
+/// While the dashboard owns the terminal, anything written to stderr goes here.
+pub const LOG_FILE: &str = "scraping-stock.log";
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortBy {
+    Symbol,
+    Company,
+    Price,
+    Change,
+}
+
+impl SortBy {
+    fn next(self) -> Self {
+        match self {
+            SortBy::Symbol => SortBy::Company,
+            SortBy::Company => SortBy::Price,
+            SortBy::Price => SortBy::Change,
+            SortBy::Change => SortBy::Symbol,
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            SortBy::Symbol => "symbol",
+            SortBy::Company => "company",
+            SortBy::Price => "price",
+            SortBy::Change => "change %",
+        }
+    }
+}
+
+struct Dashboard {
+    tickers: HashMap<String, Stock>,
+    sort_by: SortBy,
+    descending: bool,
+    interval: Duration,
+    /// Show the key statistics columns.
+    extended: bool,
+    /// Width of the sparklines, in prices.
+    sparkline_points: usize,
+}
+
+impl Dashboard {
+    fn new(interval: Duration, extended: bool, sparkline_points: usize) -> Self {
+        Self { tickers: HashMap::new(), sort_by: SortBy::Symbol, descending: false, interval, extended, sparkline_points }
+    }
+
+    /// Each quote carries its recent prices, so the latest is all there is
+    /// to keep.
+    fn update(&mut self, stock: Stock) {
+        self.tickers.insert(stock.symbol.clone(), stock);
+    }
+
+    fn sorted(&self) -> Vec<&Stock> {
+        let mut tickers = self.tickers.values().collect::<Vec<&Stock>>();
+
+        tickers.sort_by(|a, b| {
+            let ordering = match self.sort_by {
+                SortBy::Symbol => a.symbol.cmp(&b.symbol),
+                SortBy::Company => a.company_name.cmp(&b.company_name),
+                SortBy::Price => a.price.cmp(&b.price),
+                SortBy::Change => a.change_pct.unwrap_or(0f64).total_cmp(&b.change_pct.unwrap_or(0f64)),
+            };
+
+            match self.descending {
+                true => ordering.reverse(),
+                false => ordering,
+            }
+        });
+
+        tickers
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [table_area, help_area] = Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+
+        let mut header = vec!["Symbol", "Company", "Price", "Change %", "Trend"];
+
+        if self.extended {
+            header.extend(["Day range", "52w range", "Mkt cap", "Volume"]);
+        }
+
+        let header = Row::new(header).style(Style::default().add_modifier(Modifier::BOLD));
+
+        let rows = self.sorted().into_iter().map(|stock| {
+            let color = match stock.status {
+                _ if stock.stale => Color::DarkGray,
+                Status::Up => Color::Green,
+                Status::Down => Color::Red,
+                Status::Same => Color::Gray,
+            };
+
+            let mut cells = vec![
+                stock.symbol.clone(),
+                stock.company_name.clone(),
+                format!("{:.2}", stock.price),
+                match stock.stale {
+                    true => "stale".to_string(),
+                    false => stock.change_pct.map(|pct| format!("{:+.2}%", pct)).unwrap_or("-".to_string()),
+                },
+                notify::sparkline(&stock.recent_prices),
+            ];
+
+            if self.extended {
+                let details = &stock.details;
+                let range = |range: Option<PriceRange>| range.map(|range| format!("{:.2}-{:.2}", range.low, range.high));
+
+                cells.extend([
+                    range(details.day_range),
+                    range(details.year_range),
+                    details.market_cap.map(stock::abbreviate),
+                    details.volume.map(|volume| stock::abbreviate(volume as f64)),
+                ].map(|cell| cell.unwrap_or("-".to_string())));
+            }
+
+            Row::new(cells).style(Style::default().fg(color))
+        });
+
+        let mut widths = vec![
+            Constraint::Length(10),
+            Constraint::Min(20),
+            Constraint::Length(14),
+            Constraint::Length(10),
+            Constraint::Length(self.sparkline_points as u16 + 2),
+        ];
+
+        if self.extended {
+            widths.extend([Constraint::Length(20), Constraint::Length(20), Constraint::Length(10), Constraint::Length(10)]);
+        }
+
+        let title = format!(" scraping-stock - every {}s - sorted by {}{} ",
+            self.interval.as_secs(),
+            self.sort_by.title(),
+            if self.descending { " (desc)" } else { "" });
+
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(Block::default().borders(Borders::ALL).title(title));
+
+        frame.render_widget(table, table_area);
+        frame.render_widget(Paragraph::new(format!(" q quit  s sort column  r reverse order  (log: {})", LOG_FILE)), help_area);
+    }
+}
+
+/// Runs the polling loop in the background and shows a live table until the
+/// user quits or a shutdown is requested.
+pub fn run(client: StockClient, options: WatchOptions, output: Output, dispatcher: Dispatcher, shutdown: Shutdown, use_async: bool) -> Result<(), StockError> {
+    let (sender, receiver) = mpsc::channel::<Stock>();
+    let dashboard = Dashboard::new(options.interval, output.is_extended(), options.sparkline_points);
+    let output = output.mute().on_emit(move |record| {
+        let _ = sender.send(record.stock.clone());
+    });
+    let poller_shutdown = shutdown.clone();
+    let poller = thread::spawn(move || match use_async {
+        true => watch::async_determine_stock_status(client, options, output, dispatcher, poller_shutdown),
+        false => watch::determine_stock_status(client, options, output, dispatcher, poller_shutdown),
+    });
+
+    let result = show_dashboard(receiver, &shutdown, dashboard);
+
+    shutdown.request();
+
+    if poller.join().is_err() {
+        tracing::error!("Polling thread panicked");
+    }
+
+    result
+}
+
+fn show_dashboard(receiver: mpsc::Receiver<Stock>, shutdown: &Shutdown, dashboard: Dashboard) -> Result<(), StockError> {
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(LOG_FILE)
+        .map_err(|err| {
+            StockError::io(LOG_FILE, err)
+        })?;
+    let _stderr = gag::Redirect::stderr(log)
+        .map_err(|err| {
+            StockError::runtime("dashboard", err)
+        })?;
+
+    enable_raw_mode().map_err(tui_error)?;
+    execute!(io::stdout(), EnterAlternateScreen).map_err(tui_error)?;
+
+    let result = Terminal::new(CrosstermBackend::new(io::stdout()))
+        .map_err(tui_error)
+        .and_then(|mut terminal| event_loop(&mut terminal, receiver, shutdown, dashboard));
+
+    disable_raw_mode().map_err(tui_error)?;
+    execute!(io::stdout(), LeaveAlternateScreen).map_err(tui_error)?;
+
+    result
+}
+
+fn event_loop(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, receiver: mpsc::Receiver<Stock>, shutdown: &Shutdown, mut dashboard: Dashboard) -> Result<(), StockError> {
+    while !shutdown.is_requested() {
+        while let Ok(stock) = receiver.try_recv() {
+            dashboard.update(stock);
+        }
+
+        terminal.draw(|frame| dashboard.draw(frame)).map_err(tui_error)?;
+
+        if !event::poll(Duration::from_millis(250)).map_err(tui_error)? {
+            continue;
+        }
+
+        if let Event::Key(key) = event::read().map_err(tui_error)? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => break,
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break,
+                KeyCode::Char('s') => dashboard.sort_by = dashboard.sort_by.next(),
+                KeyCode::Char('r') => dashboard.descending = !dashboard.descending,
+                _ => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn tui_error(err: io::Error) -> StockError {
+    StockError::runtime("dashboard", err)
+}
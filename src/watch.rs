@@ -0,0 +1,283 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}, thread, time::{Duration, Instant}};
+use tokio::{sync::Semaphore, task::JoinSet};
+use tracing::{debug, error, info, info_span, warn, Instrument, Span};
+
+use crate::{alert::PriceTarget, circuit::CircuitBreaker, health::Health, indicators::IndicatorOptions, notify::Dispatcher, pipeline::{Fetched, Pipeline}, portfolio::Holding, output::Output, quotes::QuoteStore, rules::Rule, cron::CronSchedule, schedule::{ExchangeSchedule, Scheduler}, script::Script, scraping::StreamingProvider, shutdown::Shutdown, simulate::SimulationOptions, stats::Stats, stock::Valuation, Stock, StockClient, StockError};
+/// Wait before reconnecting a quote stream that dropped.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+
+#[derive(Debug, Clone, Default)]
+pub struct WatchOptions {
+    pub codes: Vec<String>,
+    /// Between polls of a symbol, unless its exchange's schedule says otherwise.
+    pub interval: Duration,
+    /// Per-symbol intervals keyed by code, overriding `interval` and the
+    /// exchange's schedule.
+    pub intervals: HashMap<String, Duration>,
+    /// Poll when this matches instead of every `interval`.
+    pub cron: Option<CronSchedule>,
+    /// Leave symbols out of a poll while their exchange is closed.
+    pub pause_when_closed: bool,
+    /// Pass on quotes whose price hasn't changed since the last one.
+    pub emit_unchanged: bool,
+    /// Intervals and active hours per exchange, keyed like `IDX` or `CRYPTO`.
+    pub schedules: HashMap<String, ExchangeSchedule>,
+    pub max_concurrency: usize,
+    pub alert_threshold: Option<f64>,
+    /// Per-symbol alert thresholds, keyed by the bare symbol (e.g. `AAPL`).
+    pub thresholds: HashMap<String, f64>,
+    pub targets: Vec<PriceTarget>,
+    pub rules: Vec<Rule>,
+    /// How long a rule that fired stays quiet.
+    pub rule_cooloff: Duration,
+    pub indicators: IndicatorOptions,
+    /// Recent prices attached to each quote for its sparkline; 0 for none.
+    pub sparkline_points: usize,
+    /// Positions valued after every poll; their symbols are among `codes`.
+    pub holdings: Vec<Holding>,
+    /// Paper-trade these strategies, reporting the session at the end.
+    pub simulation: Option<SimulationOptions>,
+    /// Transforms quotes and fires alerts of its own.
+    pub script: Option<Script>,
+    /// Request a shutdown after this many polls.
+    pub iterations: Option<u64>,
+    /// Where the outcome of every fetch is recorded.
+    pub health: Health,
+    /// Where the latency and errors of every fetch are counted.
+    pub stats: Stats,
+    /// Where the pipeline keeps the last quote of every symbol.
+    pub quotes: QuoteStore,
+    /// Between the summaries of `stats` logged, zero for none.
+    pub stats_every: Duration,
+    /// Leaves symbols that keep failing out of polls for a while.
+    pub circuit: CircuitBreaker,
+    /// Polls a symbol may go without a fresh quote before its last one is
+    /// marked stale, 0 for never.
+    pub stale_after_polls: u32,
+}
+
+impl WatchOptions {
+    /// Requests a shutdown once `cycle` is the last poll wanted.
+    fn finish_after(&self, cycle: u64, shutdown: &Shutdown) {
+        if self.iterations.is_some_and(|iterations| cycle >= iterations) {
+            info!("Finished {} polls, shutting down", cycle);
+            shutdown.request();
+        }
+    }
+
+    /// Logs a summary of the stats if the last one, logged at `logged`, was
+    /// `stats_every` ago.
+    fn log_stats(&self, logged: &mut Instant) {
+        if !self.stats_every.is_zero() && logged.elapsed() >= self.stats_every {
+            *logged = Instant::now();
+            info!("{}", self.stats.summary(&self.codes));
+        }
+    }
+}
+
+pub fn async_determine_stock_status(client: StockClient, options: WatchOptions, output: Output, dispatcher: Dispatcher, shutdown: Shutdown) {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("failed to start the tokio runtime");
+
+    runtime.block_on(async_poll_stock_status(client, options, output, dispatcher, shutdown));
+}
+
+async fn async_poll_stock_status(client: StockClient, options: WatchOptions, output: Output, dispatcher: Dispatcher, shutdown: Shutdown) {
+    let limit = Arc::new(Semaphore::new(options.max_concurrency.max(1)));
+    let pipeline = Pipeline::start(&options, output, dispatcher);
+    let sender = pipeline.sender();
+    let mut scheduler = Scheduler::new(&options);
+    let mut logged = Instant::now();
+
+    for cycle in 1u64.. {
+        let poll = info_span!("poll", cycle);
+        let started = Instant::now();
+        let mut tasks = JoinSet::new();
+        let codes = scheduler.due(&options.codes);
+
+        for batch in client.batches(&codes) {
+            let client = client.clone();
+            let limit = Arc::clone(&limit);
+            let sender = sender.clone();
+            let stats = options.stats.clone();
+            let fetch = info_span!(parent: &poll, "fetch", symbol = %batch.join(","));
+
+            tasks.spawn(async move {
+                let _permit = limit.acquire_owned().await;
+                let fetched = Instant::now();
+                let results = client.async_fetch_batch(&batch).await;
+                let elapsed = fetched.elapsed();
+
+                for (share_code, result) in batch.into_iter().zip(results) {
+                    stats.record(&share_code, elapsed, result.as_ref().err());
+                    let _ = sender.send(Fetched::Quote(share_code, Box::new(result))).await;
+                }
+            }.instrument(fetch));
+        }
+
+        while let Some(joined) = tasks.join_next().instrument(poll.clone()).await {
+            if let Err(err) = joined {
+                error!(parent: &poll, "Fetch task failed: {}", err);
+            }
+        }
+
+        log_latency(&poll, &codes, started);
+        options.log_stats(&mut logged);
+        scheduler.polled(&codes);
+
+        let stale_after = scheduler.stale_after(&options.codes);
+        if !stale_after.is_empty() {
+            let _ = sender.send(Fetched::StaleAfter(stale_after)).await;
+        }
+
+        if !pipeline.is_running() || (!codes.is_empty() && sender.send(Fetched::CycleDone).await.is_err()) {
+            error!("Quote pipeline stopped, exiting");
+            break;
+        }
+
+        options.finish_after(cycle, &shutdown);
+
+        if shutdown.async_wait_timeout(scheduler.until_next(&options.codes)).await {
+            break;
+        }
+    }
+
+    drop(sender);
+
+    // Joining the stages blocks until the last quotes have been written.
+    let codes = options.codes.clone();
+    if let Err(err) = tokio::task::spawn_blocking(move || pipeline.finish(&codes)).await {
+        error!("Quote pipeline failed: {}", err);
+    }
+}
+
+pub fn determine_stock_status(client: StockClient, options: WatchOptions, output: Output, dispatcher: Dispatcher, shutdown: Shutdown) {
+    let pipeline = Pipeline::start(&options, output, dispatcher);
+    let mut scheduler = Scheduler::new(&options);
+    let mut logged = Instant::now();
+
+    let workers = options.max_concurrency.clamp(1, options.codes.len().max(1));
+
+    for cycle in 1u64.. {
+        let poll = info_span!("poll", cycle);
+        let started = Instant::now();
+        let codes = scheduler.due(&options.codes);
+        let batches = client.batches(&codes);
+        let queue = Mutex::new(batches.into_iter());
+
+        // Workers share one queue of batches so a slow request only holds up
+        // its own worker.
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                let sender = pipeline.sender();
+                let (queue, poll, client, shutdown, stats) = (&queue, &poll, &client, &shutdown, &options.stats);
+
+                scope.spawn(move || loop {
+                    let next = queue.lock().unwrap_or_else(|err| err.into_inner()).next();
+
+                    let Some(batch) = next.filter(|_| !shutdown.is_requested()) else {
+                        break;
+                    };
+
+                    let _fetch = info_span!(parent: poll, "fetch", symbol = %batch.join(",")).entered();
+                    let fetched = Instant::now();
+                    let results = client.fetch_batch(&batch);
+                    let elapsed = fetched.elapsed();
+
+                    for (share_code, result) in batch.into_iter().zip(results) {
+                        stats.record(&share_code, elapsed, result.as_ref().err());
+                        if sender.blocking_send(Fetched::Quote(share_code, Box::new(result))).is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        log_latency(&poll, &codes, started);
+        options.log_stats(&mut logged);
+        scheduler.polled(&codes);
+
+        let stale_after = scheduler.stale_after(&options.codes);
+        if !stale_after.is_empty() {
+            let _ = pipeline.sender().blocking_send(Fetched::StaleAfter(stale_after));
+        }
+
+        if !shutdown.is_requested() && !codes.is_empty() && pipeline.sender().blocking_send(Fetched::CycleDone).is_err() {
+            error!("Quote pipeline stopped, exiting");
+            break;
+        }
+
+        options.finish_after(cycle, &shutdown);
+
+        if shutdown.wait_timeout(scheduler.until_next(&options.codes)) {
+            break;
+        }
+    }
+
+    pipeline.finish(&options.codes);
+}
+
+/// Passes the quotes `provider` pushes through the pipeline as they come,
+/// reconnecting whenever the stream drops. Holdings are valued at most once
+/// per `interval`.
+pub fn stream_stock_status(provider: Arc<dyn StreamingProvider>, options: WatchOptions, output: Output, dispatcher: Dispatcher, shutdown: Shutdown) {
+    let pipeline = Pipeline::start(&options, output, dispatcher);
+    let sender = pipeline.sender();
+    let mut valued = Instant::now();
+
+    while !shutdown.is_requested() {
+        let mut on_quote = |share_code: &str, stock: Stock| {
+            if sender.blocking_send(Fetched::Quote(share_code.to_string(), Box::new(Ok(stock)))).is_err() {
+                return false;
+            }
+
+            if valued.elapsed() >= options.interval {
+                valued = Instant::now();
+                return sender.blocking_send(Fetched::CycleDone).is_ok();
+            }
+
+            true
+        };
+
+        let result = provider.stream(&options.codes, &shutdown, &mut on_quote);
+
+        if !pipeline.is_running() {
+            error!("Quote pipeline stopped, exiting");
+            break;
+        }
+
+        match result {
+            Ok(()) => break,
+            // Only a lost connection is worth another try.
+            Err(err @ StockError::Stream(_)) => warn!("{} stream failed: {}, reconnecting in {:?}", provider.name(), err, RECONNECT_DELAY),
+            Err(err) => {
+                error!("{}", err);
+                break;
+            }
+        }
+
+        if shutdown.wait_timeout(RECONNECT_DELAY) {
+            break;
+        }
+    }
+
+    drop(sender);
+    pipeline.finish(&options.codes);
+}
+
+/// Logs how long fetching `codes` took, since `started`.
+fn log_latency(poll: &Span, codes: &[String], started: Instant) {
+    if !codes.is_empty() {
+        let latency = started.elapsed();
+
+        debug!(parent: poll, latency_ms = latency.as_millis() as u64, "Polled {} symbols in {:?}", codes.len(), latency);
+    }
+}
+
+pub fn get_stock_valuation_status(nstock: &Stock, past_stock: &Stock) -> Valuation {
+    Valuation::between(past_stock.price, nstock.price)
+}
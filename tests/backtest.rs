@@ -0,0 +1,110 @@
+use chrono::{DateTime, Utc};
+use scraping_stock::{
+    backtest,
+    indicators::IndicatorOptions,
+    simulate::{SimulationOptions, Strategy},
+    Decimal, Stock,
+};
+
+
+fn at(day: u32) -> DateTime<Utc> {
+    format!("2024-01-{:02}T00:00:00Z", day).parse().unwrap()
+}
+
+fn quote(symbol: &str, day: u32, price: i64) -> (String, Stock) {
+    let stock = Stock::new(symbol.to_string(), symbol.to_string(), Decimal::from(price)).with_fetched_at(at(day));
+
+    (format!("{}:NASDAQ", symbol), stock)
+}
+
+fn simulation(buy: &str, sell: &str) -> SimulationOptions {
+    SimulationOptions {
+        cash: Decimal::from(2000),
+        strategies: vec![Strategy {
+            symbol: "AAPL:NASDAQ".to_string(),
+            buy: Some(buy.parse().unwrap()),
+            sell: Some(sell.parse().unwrap()),
+            plugin: None,
+            quantity: Decimal::from(10),
+        }],
+    }
+}
+
+#[test]
+fn reports_win_rate_and_drawdown() {
+    let quotes = [(3, 98), (4, 95), (5, 112), (8, 104), (9, 97), (10, 90), (11, 92)]
+        .into_iter()
+        .map(|(day, price)| quote("AAPL", day, price))
+        .collect();
+
+    let report = backtest::run(quotes, IndicatorOptions::default(), simulation("price < 100", "price > 110 OR price < 91"));
+
+    // Bought at 98 and sold at 112, bought at 97 and sold at 90.
+    assert_eq!(report.session.trades.len(), 4);
+    assert_eq!(report.session.realized_pnl, Decimal::from(140 - 70));
+    assert_eq!(report.win_rate, Some(50.0));
+    // The equity fell from 2140 to 2070.
+    assert!((report.max_drawdown_pct - 70.0 / 2140.0 * 100.0).abs() < 1e-9);
+    assert_eq!((report.quotes, report.first, report.last), (7, Some(at(3)), Some(at(11))));
+}
+
+#[test]
+fn replays_quotes_in_time_order() {
+    let quotes = vec![quote("AAPL", 4, 110), quote("MSFT", 2, 400), quote("AAPL", 3, 100), quote("AAPL", 2, 90)];
+
+    let report = backtest::run(quotes, IndicatorOptions::default(), simulation("price > 0", "price > 1000"));
+
+    // Only bought on the first AAPL quote, which is the oldest.
+    assert_eq!(report.session.trades.len(), 1);
+    assert_eq!(report.session.trades[0].price, Decimal::from(90));
+    assert_eq!(report.win_rate, None);
+    assert_eq!(report.session.unrealized_pnl, Decimal::from(200));
+}
+
+#[test]
+fn computes_indicators_over_the_replayed_prices() {
+    let quotes = [(2, 100), (3, 104), (4, 90), (5, 120)]
+        .into_iter()
+        .map(|(day, price)| quote("AAPL", day, price))
+        .collect();
+    let indicators = IndicatorOptions { rsi_window: 2, ..IndicatorOptions::default() };
+
+    // The RSI is 22.2 at 90, and 82.1 at 120.
+    let report = backtest::run(quotes, indicators, simulation("rsi < 30", "rsi > 70"));
+
+    assert_eq!(report.session.trades.iter().map(|trade| trade.price).collect::<Vec<Decimal>>(), vec![Decimal::from(90), Decimal::from(120)]);
+}
+
+#[test]
+fn reads_history_csv() {
+    let path = std::env::temp_dir().join(format!("scraping-stock-backtest-{}.csv", std::process::id()));
+    std::fs::write(&path, "date,symbol,open,high,low,close,volume\n2024-01-02,AAPL,187.15,188.44,183.89,185.64,82488700\n2024-01-03,AAPL,184.22,185.88,183.43,184.25,\n").unwrap();
+
+    let stocks = backtest::read_csv(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(stocks.len(), 2);
+    assert_eq!(stocks[0].symbol, "AAPL");
+    assert_eq!(stocks[0].price, "185.64".parse().unwrap());
+    assert_eq!(stocks[1].fetched_at, at(3));
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn reads_stored_quotes_between_dates() {
+    use scraping_stock::storage::sqlite::SqliteStore;
+
+    let path = std::env::temp_dir().join(format!("scraping-stock-backtest-{}.db", std::process::id()));
+    let store = SqliteStore::open(&path).unwrap();
+
+    for day in [2, 3, 4] {
+        let (_, stock) = quote("AAPL", day, 100 + day as i64);
+        store.record(&stock, at(day)).unwrap();
+    }
+
+    let entries = store.history_between("AAPL", at(3), at(4)).unwrap();
+    drop(store);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(entries.iter().map(|entry| entry.fetched_at).collect::<Vec<DateTime<Utc>>>(), vec![at(3)]);
+}
@@ -0,0 +1,145 @@
+use std::sync::{Arc, Mutex};
+
+use scraping_stock::{
+    retry::RetryPolicy,
+    scraping::{stooq, yahoofinance, QuoteProvider, RoutedProvider},
+    Decimal, Stock, StockClient, StockError,
+};
+
+
+/// Quotes every symbol at 100 in batches of `size`, remembering each
+/// request, and fails every request once told to.
+struct BatchingProvider {
+    size: usize,
+    failing: bool,
+    requests: Mutex<Vec<Vec<String>>>,
+}
+
+impl BatchingProvider {
+    fn new(size: usize) -> Self {
+        Self { size, failing: false, requests: Mutex::default() }
+    }
+
+    fn failing(mut self) -> Self {
+        self.failing = true;
+        self
+    }
+}
+
+impl QuoteProvider for BatchingProvider {
+    fn name(&self) -> &str {
+        "batching"
+    }
+
+    fn fetch(&self, symbol: &str) -> Result<Stock, StockError> {
+        self.fetch_batch(&[symbol.to_string()])?.remove(0)
+    }
+
+    fn batches(&self, symbols: &[String]) -> Vec<Vec<String>> {
+        symbols.chunks(self.size).map(<[String]>::to_vec).collect()
+    }
+
+    fn fetch_batch(&self, symbols: &[String]) -> Result<Vec<Result<Stock, StockError>>, StockError> {
+        self.requests.lock().unwrap().push(symbols.to_vec());
+
+        if self.failing {
+            return Err(StockError::HttpStatus { status: 404, message: "Not Found".to_string() });
+        }
+
+        Ok(symbols.iter().map(|symbol| Ok(Stock::new(symbol.clone(), "Test".to_string(), Decimal::from(100)))).collect())
+    }
+}
+
+fn codes(codes: &[&str]) -> Vec<String> {
+    codes.iter().map(|code| code.to_string()).collect()
+}
+
+#[test]
+fn splits_stooq_download_by_symbol() {
+    let csv_content = "Symbol,Date,Time,Open,High,Low,Close,Volume,Name\n\
+        AAPL.US,2024-05-10,22:00:09,184.9,185.09,182.13,183.05,50759496,APPLE\n\
+        XYZ.US,N/D,N/D,N/D,N/D,N/D,N/D,N/D,XYZ.US\n";
+
+    let mut rows = stooq::split_rows(csv_content);
+    let stock = stooq::parse_stock_value(rows.remove("AAPL.US").unwrap(), "AAPL:NASDAQ").unwrap();
+
+    assert_eq!(stock.symbol, "AAPL");
+    assert_eq!(stock.price, "183.05".parse::<Decimal>().unwrap());
+    assert_eq!(stock.currency, "USD");
+    assert!(matches!(stooq::parse_stock_value(rows.remove("XYZ.US").unwrap(), "XYZ:NASDAQ"), Err(StockError::SymbolNotFound(_))));
+    assert!(rows.is_empty());
+}
+
+#[test]
+fn splits_yahoo_spark_by_symbol() {
+    let json_content = r#"{"spark":{"result":[
+        {"symbol":"AAPL","response":[{"meta":{"regularMarketPrice":183.05,"currency":"USD","instrumentType":"EQUITY","longName":"Apple Inc."}}]},
+        {"symbol":"BBCA.JK","response":[{"meta":{"regularMarketPrice":9875,"currency":"IDR","instrumentType":"EQUITY","shortName":"Bank Central Asia"}}]}
+    ],"error":null}}"#;
+
+    let mut charts = yahoofinance::split_charts(json_content).unwrap();
+    let apple = yahoofinance::parse_stock_value(charts.remove("AAPL").unwrap(), "AAPL:NASDAQ").unwrap();
+    let bca = yahoofinance::parse_stock_value(charts.remove("BBCA.JK").unwrap(), "BBCA:IDX").unwrap();
+
+    assert_eq!((apple.symbol.as_str(), apple.company_name.as_str(), apple.price), ("AAPL", "Apple Inc.", "183.05".parse().unwrap()));
+    assert_eq!((bca.symbol.as_str(), bca.currency.as_str(), bca.price), ("BBCA", "IDR", Decimal::from(9875)));
+}
+
+#[test]
+fn batches_never_mix_providers() {
+    let crypto = Arc::new(BatchingProvider::new(10));
+    let stocks = Arc::new(BatchingProvider::new(2));
+    let provider = RoutedProvider::new(stocks.clone()).route(|code| code.ends_with("-USD"), crypto.clone());
+
+    let batches = provider.batches(&codes(&["AAPL:NASDAQ", "BTC-USD", "MSFT:NASDAQ", "ETH-USD", "GOOG:NASDAQ"]));
+
+    assert_eq!(batches, vec![
+        codes(&["BTC-USD", "ETH-USD"]),
+        codes(&["AAPL:NASDAQ", "MSFT:NASDAQ"]),
+        codes(&["GOOG:NASDAQ"]),
+    ]);
+
+    let results = provider.fetch_batch(&batches[1]).unwrap();
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(*stocks.requests.lock().unwrap(), vec![codes(&["AAPL:NASDAQ", "MSFT:NASDAQ"])]);
+    assert!(crypto.requests.lock().unwrap().is_empty());
+}
+
+#[test]
+fn failed_batch_fails_every_symbol() {
+    let client = StockClient::with_provider(BatchingProvider::new(5).failing()).retry(RetryPolicy::none());
+    let batch = codes(&["AAPL:NASDAQ", "MSFT:NASDAQ"]);
+
+    let results = client.fetch_batch(&batch);
+
+    assert_eq!(results.len(), 2);
+    for result in results {
+        let err = result.unwrap_err();
+
+        assert!(matches!(err, StockError::Batch(_)));
+        assert_eq!(err.code(), "RESPONSE_FAILED");
+        assert_eq!(err.to_string(), "HTTP 404 Not Found");
+    }
+}
+
+#[tokio::test]
+async fn async_batches_fall_back_to_the_blocking_fetch() {
+    let client = StockClient::with_provider(BatchingProvider::new(5)).retry(RetryPolicy::none());
+    let batch = codes(&["AAPL:NASDAQ", "MSFT:NASDAQ"]);
+
+    let results = client.async_fetch_batch(&batch).await;
+
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(Result::is_ok));
+    assert!(client.async_fetch_quote("AAPL:NASDAQ").await.is_ok());
+}
+
+#[tokio::test]
+async fn failed_async_batch_fails_every_symbol() {
+    let client = StockClient::with_provider(BatchingProvider::new(5).failing()).retry(RetryPolicy::none());
+
+    let results = client.async_fetch_batch(&codes(&["AAPL:NASDAQ", "MSFT:NASDAQ"])).await;
+
+    assert!(results.iter().all(|result| matches!(result, Err(StockError::Batch(_)))));
+}
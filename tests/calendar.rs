@@ -0,0 +1,73 @@
+use chrono::NaiveDate;
+use scraping_stock::{
+    alert::AlertEvent,
+    calendar::{self, CorporateEvent, EventKind, Reminders},
+    scraping::yahoofinance,
+    StockError,
+};
+
+
+fn date(value: &str) -> NaiveDate {
+    value.parse().unwrap()
+}
+
+fn event(kind: EventKind, day: &str) -> CorporateEvent {
+    CorporateEvent { symbol: "AAPL".to_string(), kind, date: date(day) }
+}
+
+#[test]
+fn parses_yahoo_calendar_events() {
+    let json_content = r#"{"quoteSummary":{"result":[{"calendarEvents":{"maxAge":1,
+        "earnings":{"earningsDate":[{"raw":1722470400,"fmt":"2024-08-01"},{"raw":1722902400,"fmt":"2024-08-06"}],"earningsAverage":{"raw":1.34}},
+        "exDividendDate":{"raw":1715299200,"fmt":"2024-05-10"},
+        "dividendDate":{"raw":1715817600,"fmt":"2024-05-16"}}}],"error":null}}"#;
+
+    let events = yahoofinance::parse_calendar(json_content, "aapl:nasdaq").unwrap();
+
+    assert_eq!(events, vec![event(EventKind::Earnings, "2024-08-01"), event(EventKind::ExDividend, "2024-05-10")]);
+}
+
+#[test]
+fn skips_dates_yahoo_does_not_have() {
+    let json_content = r#"{"quoteSummary":{"result":[{"calendarEvents":{"earnings":{"earningsDate":[]},"exDividendDate":{}}}],"error":null}}"#;
+
+    assert!(yahoofinance::parse_calendar(json_content, "GOTO:IDX").unwrap().is_empty());
+}
+
+#[test]
+fn reports_unknown_symbol() {
+    let json_content = r#"{"quoteSummary":{"result":null,"error":{"code":"Not Found","description":"Quote not found for symbol: XYZ"}}}"#;
+
+    assert!(matches!(yahoofinance::parse_calendar(json_content, "XYZ:NASDAQ"), Err(StockError::SymbolNotFound(_))));
+}
+
+#[test]
+fn reminds_once_within_days_ahead() {
+    let mut reminders = Reminders::new(3);
+    let events = [
+        event(EventKind::ExDividend, "2024-05-10"),
+        event(EventKind::Earnings, "2024-08-01"),
+        event(EventKind::Earnings, "2024-05-01"),
+    ];
+
+    assert_eq!(reminders.due(&events, date("2024-05-06")), vec![]);
+    assert_eq!(reminders.due(&events, date("2024-05-07")), vec![(events[0].clone(), 3)]);
+    assert_eq!(reminders.due(&events, date("2024-05-09")), vec![]);
+}
+
+#[test]
+fn words_reminders() {
+    let reminder = |days| AlertEvent::upcoming(event(EventKind::ExDividend, "2024-05-10"), days).to_string();
+
+    assert_eq!(reminder(3), "AAPL goes ex-dividend in 3 days, on 2024-05-10");
+    assert_eq!(reminder(1), "AAPL goes ex-dividend tomorrow, on 2024-05-10");
+    assert_eq!(AlertEvent::upcoming(event(EventKind::Earnings, "2024-05-10"), 0).to_string(), "AAPL reports earnings today");
+}
+
+#[test]
+fn follows_equities_only() {
+    assert!(calendar::has_calendar("AAPL:NASDAQ"));
+    assert!(!calendar::has_calendar("USD-IDR:FX"));
+    assert!(!calendar::has_calendar(".INX:INDEXSP"));
+    assert!(!calendar::has_calendar("BTC-USD"));
+}
@@ -0,0 +1,102 @@
+use chrono::{DateTime, Utc};
+use scraping_stock::{
+    candle::{Candle, CandleInterval, CandleSeries},
+    indicators::{IndicatorOptions, PriceHistory},
+    Decimal, StockError,
+};
+
+
+fn at(time: &str) -> DateTime<Utc> {
+    format!("2024-05-10T{}Z", time).parse().unwrap()
+}
+
+fn candle(start: &str, open: i32, high: i32, low: i32, close: i32) -> Candle {
+    Candle { timestamp: at(start), open: open.into(), high: high.into(), low: low.into(), close: close.into(), volume: None }
+}
+
+#[test]
+fn parses_candle_intervals() {
+    assert_eq!("5m".parse::<CandleInterval>().unwrap(), CandleInterval::FiveMinutes);
+    assert_eq!(CandleInterval::OneHour.to_string(), "1h");
+    assert!(matches!("15m".parse::<CandleInterval>(), Err(StockError::Invalid { .. })));
+}
+
+#[test]
+fn aligns_candles_to_the_hour() {
+    assert_eq!(CandleInterval::FiveMinutes.start_of(at("14:37:21")), at("14:35:00"));
+    assert_eq!(CandleInterval::OneHour.start_of(at("14:37:21")), at("14:00:00"));
+}
+
+#[test]
+fn aggregates_polls_into_candles() {
+    let mut series = CandleSeries::new();
+
+    for (time, price) in [("14:31:05", 190), ("14:31:35", 193), ("14:31:50", 189), ("14:32:10", 191)] {
+        series.record("AAPL:NASDAQ", Decimal::from(price), at(time));
+    }
+
+    assert_eq!(series.candles("AAPL:NASDAQ", CandleInterval::OneMinute, 10), vec![
+        candle("14:31:00", 190, 193, 189, 189),
+        candle("14:32:00", 191, 191, 191, 191),
+    ]);
+    assert_eq!(series.candles("AAPL:NASDAQ", CandleInterval::FiveMinutes, 10), vec![candle("14:30:00", 190, 193, 189, 191)]);
+    assert_eq!(series.candles("AAPL:NASDAQ", CandleInterval::OneMinute, 1), vec![candle("14:32:00", 191, 191, 191, 191)]);
+}
+
+#[test]
+fn returns_candles_a_poll_closed() {
+    let mut series = CandleSeries::new();
+
+    assert!(series.record("AAPL:NASDAQ", Decimal::from(190), at("14:34:30")).is_empty());
+    assert!(series.record("AAPL:NASDAQ", Decimal::from(192), at("14:34:50")).is_empty());
+
+    let closed = series.record("AAPL:NASDAQ", Decimal::from(191), at("14:35:10"));
+
+    assert_eq!(closed, vec![
+        (CandleInterval::OneMinute, candle("14:34:00", 190, 192, 190, 192)),
+        (CandleInterval::FiveMinutes, candle("14:30:00", 190, 192, 190, 192)),
+    ]);
+}
+
+#[test]
+fn leaves_out_polls_older_than_the_forming_candle() {
+    let mut series = CandleSeries::new();
+
+    series.record("AAPL:NASDAQ", Decimal::from(190), at("14:35:10"));
+    series.record("AAPL:NASDAQ", Decimal::from(150), at("14:34:50"));
+
+    assert_eq!(series.candles("AAPL:NASDAQ", CandleInterval::OneMinute, 10), vec![candle("14:35:00", 190, 190, 190, 190)]);
+}
+
+#[test]
+fn computes_indicators_without_recording() {
+    let mut history = PriceHistory::new(IndicatorOptions { ma_window: 2, ..IndicatorOptions::default() });
+
+    history.record("AAPL:NASDAQ", 190.0);
+    let recorded = history.record("AAPL:NASDAQ", 194.0);
+
+    assert_eq!(history.indicators("AAPL:NASDAQ"), recorded);
+    assert_eq!(recorded.sma, Some(192.0));
+    assert_eq!(history.indicators("MSFT:NASDAQ").sma, None);
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn persists_candles_in_sqlite() {
+    use scraping_stock::storage::sqlite::SqliteStore;
+
+    let path = std::env::temp_dir().join(format!("scraping-stock-candles-{}.db", std::process::id()));
+    let store = SqliteStore::open(&path).unwrap();
+
+    store.record_candle("AAPL", CandleInterval::FiveMinutes, &candle("14:30:00", 190, 193, 189, 191)).unwrap();
+    store.record_candle("AAPL", CandleInterval::FiveMinutes, &candle("14:35:00", 191, 191, 188, 188)).unwrap();
+    // Recording a candle again replaces it.
+    store.record_candle("AAPL", CandleInterval::FiveMinutes, &candle("14:35:00", 191, 192, 188, 192)).unwrap();
+    store.record_candle("AAPL", CandleInterval::OneMinute, &candle("14:35:00", 191, 191, 191, 191)).unwrap();
+
+    let candles = store.candles("AAPL", CandleInterval::FiveMinutes, 10).unwrap();
+    drop(store);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(candles, vec![candle("14:30:00", 190, 193, 189, 191), candle("14:35:00", 191, 192, 188, 192)]);
+}
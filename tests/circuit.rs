@@ -0,0 +1,76 @@
+use std::{thread, time::Duration};
+use scraping_stock::circuit::CircuitBreaker;
+
+
+const CODE: &str = "AAPL:NASDAQ";
+
+#[test]
+fn opens_after_failures_in_a_row() {
+    let circuit = CircuitBreaker::new(3, Duration::from_secs(60));
+
+    assert_eq!(circuit.failed(CODE), None);
+    assert_eq!(circuit.failed(CODE), None);
+    assert!(circuit.open_until(CODE).is_none());
+
+    assert_eq!(circuit.failed(CODE), Some(Duration::from_secs(60)));
+    assert!(circuit.open_until(CODE).is_some());
+    assert!(circuit.open_until("MSFT:NASDAQ").is_none());
+}
+
+#[test]
+fn a_success_resets_the_failures() {
+    let circuit = CircuitBreaker::new(2, Duration::from_secs(60));
+
+    circuit.failed(CODE);
+    // It never opened, so there is nothing to close.
+    assert!(!circuit.succeeded(CODE));
+    assert_eq!(circuit.failed(CODE), None);
+}
+
+#[test]
+fn half_opens_after_the_cooldown() {
+    let circuit = CircuitBreaker::new(1, Duration::from_millis(50));
+
+    assert_eq!(circuit.failed(CODE), Some(Duration::from_millis(50)));
+    assert!(circuit.open_until(CODE).is_some());
+
+    thread::sleep(Duration::from_millis(80));
+    // Tried once more: a success closes it.
+    assert!(circuit.open_until(CODE).is_none());
+    assert!(circuit.succeeded(CODE));
+    assert!(circuit.open_until(CODE).is_none());
+    assert!(!circuit.succeeded(CODE));
+}
+
+#[test]
+fn reopens_for_twice_as_long_up_to_an_hour() {
+    let circuit = CircuitBreaker::new(1, Duration::from_millis(20));
+
+    assert_eq!(circuit.failed(CODE), Some(Duration::from_millis(20)));
+    thread::sleep(Duration::from_millis(40));
+    // The trial fetch failed too.
+    assert_eq!(circuit.failed(CODE), Some(Duration::from_millis(40)));
+    assert_eq!(circuit.failed(CODE), Some(Duration::from_millis(80)));
+
+    let circuit = CircuitBreaker::new(1, Duration::from_secs(40 * 60));
+    assert_eq!(circuit.failed(CODE), Some(Duration::from_secs(40 * 60)));
+    assert_eq!(circuit.failed(CODE), Some(Duration::from_secs(60 * 60)));
+}
+
+#[test]
+fn never_opens_without_a_limit() {
+    let circuit = CircuitBreaker::new(0, Duration::from_secs(60));
+
+    for _ in 0..100 {
+        assert_eq!(circuit.failed(CODE), None);
+    }
+    assert!(circuit.open_until(CODE).is_none());
+}
+
+#[test]
+fn clones_share_the_circuits() {
+    let circuit = CircuitBreaker::new(1, Duration::from_secs(60));
+
+    circuit.clone().failed(CODE);
+    assert!(circuit.open_until(CODE).is_some());
+}
@@ -0,0 +1,149 @@
+use std::path::PathBuf;
+use scraping_stock::{config::{self, Config}, output::OutputFormat, StockError};
+
+
+const CONFIG: &str = r#"
+interval = 60
+
+[watchlist]
+symbols = ["AAPL:NASDAQ", "BBCA:IDX"]
+thresholds = { AAPL = 2.5 }
+
+[output]
+format = "text"
+extended = true
+
+[profile.server]
+interval = 10
+watchlist.symbols = ["BBCA:IDX", "TLKM:IDX", "GOTO:IDX"]
+
+[profile.server.output]
+format = "json"
+
+[profile.laptop]
+alert_threshold = 1.5
+"#;
+
+fn write_config(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("scraping-stock-config-{}-{}.toml", name, std::process::id()));
+    std::fs::write(&path, CONFIG).unwrap();
+    path
+}
+
+#[test]
+fn lays_the_profile_over_the_file() {
+    let path = write_config("server");
+    let config = Config::load(&path, Some("server")).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(config.interval, Some(10));
+    assert_eq!(config.watchlist.symbols, vec!["BBCA:IDX", "TLKM:IDX", "GOTO:IDX"]);
+    // Tables are merged key by key.
+    assert_eq!(config.watchlist.thresholds.get("AAPL"), Some(&2.5));
+    assert_eq!((config.output.format, config.output.extended), (Some(OutputFormat::Json), Some(true)));
+}
+
+#[test]
+fn ignores_profiles_unless_asked() {
+    let path = write_config("base");
+    let config = Config::load(&path, None).unwrap();
+    let laptop = Config::load(&path, Some("laptop")).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!((config.interval, config.alert_threshold), (Some(60), None));
+    assert_eq!(config.watchlist.symbols, vec!["AAPL:NASDAQ", "BBCA:IDX"]);
+    assert_eq!((laptop.interval, laptop.alert_threshold), (Some(60), Some(1.5)));
+}
+
+#[test]
+fn rejects_unknown_profiles() {
+    let path = write_config("unknown");
+    let loaded = Config::load(&path, Some("work"));
+    std::fs::remove_file(&path).unwrap();
+
+    match loaded {
+        Err(err @ StockError::Invalid { .. }) => assert!(err.to_string().ends_with("expected one of laptop, server"), "{}", err),
+        other => panic!("loaded {:?}", other.map(|config| config.interval)),
+    }
+}
+
+#[test]
+fn edits_the_watchlist_of_the_profile() {
+    let path = write_config("watchlist");
+
+    let server = config::update_watchlist(&path, Some("server"), |symbols| {
+        symbols.retain(|symbol| symbol != "GOTO:IDX");
+        Ok(())
+    }).unwrap();
+    let laptop = config::update_watchlist(&path, Some("laptop"), |symbols| {
+        symbols.push("MSFT:NASDAQ".to_string());
+        Ok(())
+    }).unwrap();
+
+    let base = Config::load(&path, None).unwrap();
+    let reloaded = Config::load(&path, Some("server")).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(server, vec!["BBCA:IDX", "TLKM:IDX"]);
+    assert_eq!(reloaded.watchlist.symbols, server);
+    // The laptop profile has no watchlist of its own.
+    assert_eq!(laptop, vec!["AAPL:NASDAQ", "BBCA:IDX", "MSFT:NASDAQ"]);
+    assert_eq!(base.watchlist.symbols, laptop);
+}
+
+fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+    vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+}
+
+#[test]
+fn lays_the_environment_over_the_file() {
+    let path = write_config("env");
+    let config = Config::discover_with(Some(&path), Some("server"), vars(&[
+        ("SCRAPING_STOCK_INTERVAL", "30"),
+        ("SCRAPING_STOCK_TELEGRAM__CHAT_ID", "987654"),
+        ("SCRAPING_STOCK_TELEGRAM__TOKEN", "123456:abcdef"),
+        ("SCRAPING_STOCK_OUTPUT__EXTENDED", "false"),
+        ("SCRAPING_STOCK_PROFILE", "laptop"),
+        ("HOME", "/root"),
+    ]));
+    let quoted = Config::discover_with(Some(&path), None, vars(&[("SCRAPING_STOCK_TELEGRAM__CHAT_ID", "\"987654\"")]));
+    let negative = Config::discover_with(Some(&path), None, vars(&[("SCRAPING_STOCK_TELEGRAM__CHAT_ID", "-100123")]));
+    std::fs::remove_file(&path).unwrap();
+
+    let config = config.unwrap();
+    assert_eq!(config.interval, Some(30));
+    assert_eq!(config.telegram.token.as_deref(), Some("123456:abcdef"));
+    assert_eq!(config.output.extended, Some(false));
+    // The profile still applies, and the variable of a flag is left alone.
+    assert_eq!(config.output.format, Some(OutputFormat::Json));
+    // A number where the setting takes text is read as text.
+    assert_eq!(config.telegram.chat_id.as_deref(), Some("987654"));
+    assert_eq!(quoted.unwrap().telegram.chat_id.as_deref(), Some("987654"));
+    assert_eq!(negative.unwrap().telegram.chat_id.as_deref(), Some("-100123"));
+}
+
+#[test]
+fn splits_lists_where_the_file_has_one() {
+    let path = write_config("list");
+    let config = Config::discover_with(Some(&path), None, vars(&[("SCRAPING_STOCK_WATCHLIST__SYMBOLS", "MSFT:NASDAQ, GOTO:IDX")]));
+    let toml = Config::discover_with(Some(&path), None, vars(&[("SCRAPING_STOCK_PROXIES", r#"["socks5://proxy:1080"]"#)]));
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(config.unwrap().watchlist.symbols, vec!["MSFT:NASDAQ", "GOTO:IDX"]);
+    assert_eq!(toml.unwrap().proxies, vec!["socks5://proxy:1080"]);
+}
+
+#[test]
+fn rejects_unknown_variables() {
+    let loaded = Config::discover_with(Some(std::path::Path::new("tests/fixtures/none.toml")), None, Vec::new());
+    assert!(matches!(loaded, Err(StockError::Io { .. })));
+
+    let path = write_config("unknown-env");
+    let loaded = Config::discover_with(Some(&path), None, vars(&[("SCRAPING_STOCK_INTERVALS", "30")]));
+    std::fs::remove_file(&path).unwrap();
+
+    match loaded {
+        Err(err @ StockError::Parse { .. }) => assert!(err.to_string().contains("SCRAPING_STOCK_* variables"), "{}", err),
+        other => panic!("loaded {:?}", other.map(|config| config.interval)),
+    }
+}
@@ -0,0 +1,69 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::America::New_York;
+use scraping_stock::{cron::CronSchedule, StockError};
+
+
+fn utc(text: &str) -> DateTime<Utc> {
+    text.parse().unwrap()
+}
+
+fn next(expression: &str, after: &str) -> Option<DateTime<Utc>> {
+    expression.parse::<CronSchedule>().unwrap().next_after(utc(after), &Utc)
+}
+
+#[test]
+fn skips_to_the_next_trading_morning() {
+    assert_eq!(next("*/5 9-16 * * MON-FRI", "2024-05-10T16:53:00Z"), Some(utc("2024-05-10T16:55:00Z")));
+    assert_eq!(next("*/5 9-16 * * MON-FRI", "2024-05-10T16:55:00Z"), Some(utc("2024-05-13T09:00:00Z")));
+}
+
+#[test]
+fn matches_either_day_field_when_both_are_set() {
+    // Every Friday, and every 13th whatever its weekday.
+    assert_eq!(next("0 12 13 * FRI", "2024-09-01T00:00:00Z"), Some(utc("2024-09-06T12:00:00Z")));
+    assert_eq!(next("0 12 13 * FRI", "2024-10-11T12:00:00Z"), Some(utc("2024-10-13T12:00:00Z")));
+    // Only one of them restricts the day otherwise.
+    assert_eq!(next("0 12 13 * *", "2024-09-01T00:00:00Z"), Some(utc("2024-09-13T12:00:00Z")));
+}
+
+#[test]
+fn takes_7_for_sunday() {
+    assert_eq!(next("0 0 * * 7", "2024-05-08T10:00:00Z"), Some(utc("2024-05-12T00:00:00Z")));
+    assert_eq!(next("0 0 * * 7", "2024-05-08T10:00:00Z"), next("0 0 * * SUN", "2024-05-08T10:00:00Z"));
+}
+
+#[test]
+fn steps_from_a_start() {
+    // `5/15` is 5, 20, 35 and 50.
+    assert_eq!(next("5/15 * * * *", "2024-05-08T10:06:00Z"), Some(utc("2024-05-08T10:20:00Z")));
+    assert_eq!(next("5/15 * * * *", "2024-05-08T10:50:00Z"), Some(utc("2024-05-08T11:05:00Z")));
+    assert_eq!(next("0-30/10 * * * *", "2024-05-08T10:30:00Z"), Some(utc("2024-05-08T11:00:00Z")));
+}
+
+#[test]
+fn reads_wall_clock_time_across_dst_changes() {
+    let schedule = "30 2 * * *".parse::<CronSchedule>().unwrap();
+    // 02:30 doesn't happen in New York on 10 March 2024.
+    assert_eq!(schedule.next_after(utc("2024-03-09T12:00:00Z"), &New_York), Some(utc("2024-03-11T06:30:00Z")));
+
+    let schedule = "30 1 * * *".parse::<CronSchedule>().unwrap();
+    // 01:30 happens twice on 3 November 2024, the first time counts.
+    assert_eq!(schedule.next_after(utc("2024-11-03T04:00:00Z"), &New_York), Some(utc("2024-11-03T05:30:00Z")));
+}
+
+#[test]
+fn rejects_schedules_that_never_match() {
+    for expression in ["0 0 30 2 *", "0 0 31 4 *"] {
+        match expression.parse::<CronSchedule>() {
+            Err(err @ StockError::Invalid { .. }) => assert!(err.to_string().ends_with("never matches"), "{}", err),
+            other => panic!("parsed {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn rejects_malformed_fields() {
+    for expression in ["* * * *", "60 * * * *", "* * * JANUARY *", "*/0 * * * *", "30-10 * * * *"] {
+        assert!(matches!(expression.parse::<CronSchedule>(), Err(StockError::Invalid { .. })), "{}", expression);
+    }
+}
@@ -0,0 +1,87 @@
+use scraping_stock::{Decimal, StockError};
+
+
+fn dec(value: &str) -> Decimal {
+    value.parse().unwrap()
+}
+
+#[test]
+fn rounds_the_ninth_digit_when_parsing() {
+    assert_eq!(dec("0.123456784"), dec("0.12345678"));
+    assert_eq!(dec("0.123456785"), dec("0.12345679"));
+    assert_eq!(dec("0.999999995"), dec("1"));
+    assert_eq!(dec("-0.000000005"), dec("-0.00000001"));
+    assert_eq!(dec("+9875"), Decimal::from(9875));
+    assert_eq!(dec(".5"), dec("0.5"));
+}
+
+#[test]
+fn rejects_malformed_numbers() {
+    for value in ["", ".", "-", "1.2.3", "1,5", "1e5", "abc", "99999999999999999999999999999999"] {
+        assert!(matches!(value.parse::<Decimal>(), Err(StockError::Invalid { .. })), "{}", value);
+    }
+}
+
+#[test]
+fn rounds_half_away_from_zero() {
+    assert_eq!(dec("1.005").round_dp(2), dec("1.01"));
+    assert_eq!(dec("1.0049").round_dp(2), dec("1"));
+    assert_eq!(dec("-1.005").round_dp(2), dec("-1.01"));
+    assert_eq!(dec("2.5").round_dp(0), dec("3"));
+    assert_eq!(dec("0.12345678").round_dp(8), dec("0.12345678"));
+    assert_eq!(dec("0.12345678").round_dp(12), dec("0.12345678"));
+}
+
+#[test]
+fn rounds_products_to_eight_digits() {
+    assert_eq!(dec("1.5") * dec("1.5"), dec("2.25"));
+    assert_eq!(dec("0.00000001") * dec("0.5"), dec("0.00000001"));
+    assert_eq!(dec("-0.00000001") * dec("0.5"), dec("-0.00000001"));
+    assert_eq!(dec("0.00000001") * dec("0.4"), Decimal::ZERO);
+    assert_eq!(dec("189.84") * Decimal::from(100), dec("18984"));
+}
+
+#[test]
+fn multiplies_huge_amounts_through_f64() {
+    let trillion = Decimal::from(1_000_000_000_000_000i64);
+
+    // Too large for the exact product of the units, not for the result,
+    // which is then only as exact as an f64.
+    assert!(((trillion * trillion).to_f64() / 1e30 - 1f64).abs() < 1e-12);
+    // Past what a decimal holds, it saturates instead of overflowing.
+    let huge = Decimal::from(i64::MAX) * Decimal::from(i64::MAX);
+    assert!(huge > trillion * trillion);
+}
+
+#[test]
+fn converts_from_and_to_f64() {
+    assert_eq!(Decimal::from_f64(0.1), Some(dec("0.1")));
+    assert_eq!(Decimal::from_f64(189.84).unwrap() - Decimal::from_f64(189.8).unwrap(), dec("0.04"));
+    assert_eq!(Decimal::from_f64(-2.5).unwrap().to_f64(), -2.5);
+    assert_eq!(dec("189.84").to_f64(), 189.84);
+
+    for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, 1e300] {
+        assert_eq!(Decimal::from_f64(value), None, "{}", value);
+    }
+}
+
+#[test]
+fn displays_like_f64() {
+    assert_eq!(dec("100.50").to_string(), "100.5");
+    assert_eq!(Decimal::from(100).to_string(), "100");
+    assert_eq!(dec("-0.5").to_string(), "-0.5");
+    assert_eq!(format!("{:.2}", dec("1.005")), "1.01");
+    assert_eq!(format!("{:.0}", dec("-2.5")), "-3");
+    assert_eq!(format!("{:.10}", dec("0.1")), "0.1000000000");
+    assert_eq!(format!("{:+}", dec("0.5")), "+0.5");
+    // Unlike f64, an amount rounded to nothing has no sign left to show.
+    assert_eq!(format!("{:+.2}", dec("-0.004")), "+0.00");
+}
+
+#[test]
+fn pads_and_aligns_like_f64() {
+    assert_eq!(format!("{:>8.2}", dec("-1.5")), "   -1.50");
+    assert_eq!(format!("{:<8.2}|", dec("1.5")), "1.50    |");
+    assert_eq!(format!("{:^7}", dec("1.5")), "  1.5  ");
+    assert_eq!(format!("{:08.2}", dec("-1.5")), "-0001.50");
+}
@@ -0,0 +1,53 @@
+use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+use scraping_stock::{
+    alert::AlertEvent,
+    notify::{hook::HookNotifier, telegram::TelegramNotifier, Channel, Delivery, Dispatcher, Notifier},
+    Decimal, Stock, StockError,
+};
+
+
+fn event() -> AlertEvent {
+    let mut stock = Stock::new("AAPL".to_string(), "Apple Inc".to_string(), "201.5".parse().unwrap());
+    stock.previous_price = Some(Decimal::from(198));
+    stock.change_pct = Some(1.77);
+
+    AlertEvent::for_rule(&"AAPL above 200".parse().unwrap(), &stock)
+}
+
+/// Counts the alerts it is given.
+struct Counting(Channel, Arc<AtomicUsize>);
+
+impl Notifier for Counting {
+    fn channel(&self) -> Channel {
+        self.0
+    }
+
+    fn notify(&self, _event: &AlertEvent) -> Result<(), StockError> {
+        self.1.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[test]
+fn only_notifies_the_console() {
+    let console = Arc::new(AtomicUsize::new(0));
+    let telegram = Arc::new(AtomicUsize::new(0));
+
+    let mut dispatcher = Dispatcher::dry_run();
+    dispatcher.register(Counting(Channel::Console, console.clone()), Delivery::immediate());
+    dispatcher.register(Counting(Channel::Telegram, telegram.clone()), Delivery::immediate());
+    dispatcher.dispatch(&event());
+
+    assert_eq!((console.load(Ordering::SeqCst), telegram.load(Ordering::SeqCst)), (1, 0));
+    assert_eq!(dispatcher.channels(), vec![Channel::Console, Channel::Telegram]);
+}
+
+#[test]
+fn describes_what_would_be_sent() {
+    let telegram = TelegramNotifier::new("123456:abcdef".to_string(), "987654".to_string())
+        .with_template("{symbol} at {price}".parse().unwrap());
+    let hook = HookNotifier::new("./on-alert.sh {symbol} {change_pct:+.1}".parse().unwrap());
+
+    assert_eq!(telegram.describe(&event()), "AAPL at 201.5");
+    assert_eq!(hook.describe(&event()), "./on-alert.sh AAPL +1.8");
+}
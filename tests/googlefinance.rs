@@ -0,0 +1,386 @@
+use std::{collections::HashMap, fs, path::Path, sync::{atomic::{AtomicUsize, Ordering}, Arc, Mutex}};
+
+use scraping_stock::{
+    http::HttpFetcher,
+    market::MarketStatus,
+    retry::RetryPolicy,
+    scraping::{googlefinance::{self, GoogleFinance}, replay::Recorder, selectors::PageSelectors, FundamentalsProvider, NewsProvider, QuoteProvider},
+    Decimal, Fundamentals, NewsItem, PriceRange, QuoteKind, Stock, StockClient, StockError,
+};
+
+const BASE_URL: &str = "https://www.google.com/finance/quote/";
+const SEARCH_URL: &str = "https://www.google.com/finance?q=";
+
+
+/// Serves fixture pages by URL and remembers what was asked for. Pages
+/// given as rendered are what a browser would return.
+#[derive(Default)]
+struct CannedFetcher {
+    pages: HashMap<String, String>,
+    rendered: HashMap<String, String>,
+    requests: Mutex<Vec<String>>,
+}
+
+impl CannedFetcher {
+    fn with_page(mut self, share_code: &str, fixture: &str) -> Self {
+        self.pages.insert(format!("{}{}", BASE_URL, share_code), read_fixture(fixture));
+        self
+    }
+
+    fn with_rendered_page(mut self, share_code: &str, fixture: &str) -> Self {
+        self.rendered.insert(format!("{}{}", BASE_URL, share_code), read_fixture(fixture));
+        self
+    }
+}
+
+impl HttpFetcher for CannedFetcher {
+    fn fetch_page(&self, address: &str) -> Result<String, StockError> {
+        self.requests.lock().unwrap().push(address.to_string());
+
+        self.pages.get(address)
+            .cloned()
+            .ok_or_else(|| StockError::HttpStatus { status: 404, message: "Not Found".to_string() })
+    }
+
+    fn render_page(&self, address: &str) -> Option<Result<String, StockError>> {
+        if self.rendered.is_empty() {
+            return None;
+        }
+
+        self.requests.lock().unwrap().push(format!("render {}", address));
+
+        Some(self.rendered.get(address)
+            .cloned()
+            .ok_or_else(|| StockError::Render(address.into())))
+    }
+}
+
+/// Reads the fundamentals of the Apple fixture, counting how often.
+#[derive(Default)]
+struct CountingFundamentals {
+    fetches: AtomicUsize,
+}
+
+impl FundamentalsProvider for CountingFundamentals {
+    fn name(&self) -> &str {
+        "counting"
+    }
+
+    fn fetch_fundamentals(&self, _symbol: &str) -> Result<Fundamentals, StockError> {
+        self.fetches.fetch_add(1, Ordering::SeqCst);
+
+        Ok(googlefinance::parse_fundamentals(&read_fixture("aapl_nasdaq.html"), &PageSelectors::google()))
+    }
+}
+
+/// Lists the Apple fixture's headlines plus one more every time.
+#[derive(Default)]
+struct GrowingNews {
+    fetches: AtomicUsize,
+}
+
+impl NewsProvider for GrowingNews {
+    fn name(&self) -> &str {
+        "growing"
+    }
+
+    fn fetch_news(&self, symbol: &str) -> Result<Vec<NewsItem>, StockError> {
+        let fetches = self.fetches.fetch_add(1, Ordering::SeqCst);
+        let mut items = googlefinance::parse_news(&read_fixture("aapl_nasdaq.html"), symbol, &PageSelectors::google());
+
+        items.insert(0, NewsItem { url: format!("https://example.com/{}", fetches), ..items[0].clone() });
+        Ok(items)
+    }
+}
+
+fn read_fixture(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/googlefinance").join(name);
+
+    fs::read_to_string(&path).unwrap_or_else(|err| panic!("{}: {}", path.display(), err))
+}
+
+fn parse(fixture: &str, share_code: &str) -> Result<Stock, StockError> {
+    googlefinance::parse_stock_value(read_fixture(fixture), share_code, &PageSelectors::google())
+}
+
+fn decimal(value: &str) -> Decimal {
+    value.parse().unwrap()
+}
+
+#[test]
+fn parses_usd_quote() {
+    let stock = parse("aapl_nasdaq.html", "aapl:nasdaq").unwrap();
+
+    assert_eq!(stock.symbol, "AAPL");
+    assert_eq!(stock.company_name, "Apple Inc");
+    assert_eq!(stock.price, decimal("189.84"));
+    assert_eq!(stock.currency, "USD");
+    assert_eq!(stock.market_status, None);
+}
+
+#[test]
+fn parses_usd_key_stats() {
+    let details = parse("aapl_nasdaq.html", "AAPL:NASDAQ").unwrap().details;
+
+    assert_eq!(details.previous_close, Some(decimal("188.01")));
+    assert_eq!(details.day_range, Some(PriceRange { low: decimal("187.45"), high: decimal("190.32") }));
+    assert_eq!(details.year_range, Some(PriceRange { low: decimal("164.08"), high: decimal("199.62") }));
+    assert_eq!(details.market_cap, Some(2.95e12));
+    assert_eq!(details.volume, Some(48_090_000));
+}
+
+#[test]
+fn parses_idr_quote() {
+    let stock = parse("bbca_idx.html", "BBCA:IDX").unwrap();
+
+    assert_eq!(stock.symbol, "BBCA");
+    assert_eq!(stock.company_name, "PT Bank Central Asia Tbk");
+    assert_eq!(stock.price, decimal("9875.0"));
+    assert_eq!(stock.currency, "IDR");
+    assert_eq!(stock.market_status, Some(MarketStatus::Closed));
+    assert_eq!(stock.details.previous_close, Some(decimal("9800.0")));
+    assert_eq!(stock.details.day_range, Some(PriceRange { low: decimal("9750.0"), high: decimal("9900.0") }));
+    assert_eq!(stock.details.year_range, None);
+}
+
+#[test]
+fn parses_eur_quote() {
+    let stock = parse("sap_etr.html", "SAP:ETR").unwrap();
+
+    assert_eq!(stock.symbol, "SAP");
+    assert_eq!(stock.company_name, "SAP SE");
+    assert_eq!(stock.price, decimal("1234.56"));
+    assert_eq!(stock.currency, "EUR");
+    assert!(stock.details.is_empty());
+}
+
+#[test]
+fn parses_fx_quote() {
+    let stock = parse("usd_idr_fx.html", "usd-idr:fx").unwrap();
+
+    assert_eq!(stock.symbol, "USD-IDR");
+    assert_eq!(stock.company_name, "United States Dollar to Indonesian Rupiah");
+    assert_eq!(stock.price, decimal("16250.5"));
+    assert_eq!(stock.currency, "IDR");
+    assert_eq!(stock.kind, QuoteKind::Fx);
+}
+
+#[test]
+fn parses_index_quote() {
+    let stock = parse("inx_indexsp.html", ".INX:INDEXSP").unwrap();
+
+    assert_eq!(stock.symbol, ".INX");
+    assert_eq!(stock.company_name, "S&P 500");
+    assert_eq!(stock.price, decimal("5123.41"));
+    assert_eq!(stock.currency, "");
+    assert_eq!(stock.kind, QuoteKind::Index);
+    assert_eq!(stock.details.previous_close, Some(decimal("5087.03")));
+    assert_eq!(stock.details.day_range, Some(PriceRange { low: decimal("5081.44"), high: decimal("5130.70") }));
+}
+
+#[test]
+fn parses_listed_index_in_points() {
+    let stock = parse("composite_idx.html", "COMPOSITE:IDX").unwrap();
+
+    assert_eq!(stock.symbol, "COMPOSITE");
+    assert_eq!(stock.price, decimal("7234.20"));
+    assert_eq!(stock.currency, "");
+    assert_eq!(stock.kind, QuoteKind::Index);
+}
+
+#[test]
+fn falls_back_when_selectors_are_missing() {
+    let html = read_fixture("missing_selectors.html");
+    let (result, diagnostics) = googlefinance::parse_with_diagnostics(&html, "MSFT:NASDAQ", &PageSelectors::google());
+    let stock = result.unwrap();
+
+    assert_eq!(stock.company_name, "Microsoft Corp");
+    assert_eq!(stock.price, decimal("415.5"));
+    assert_eq!(stock.currency, "USD");
+    assert_eq!(diagnostics.price, Some(googlefinance::Strategy::JsonLd));
+    assert_eq!(diagnostics.company_name, Some(googlefinance::Strategy::Title));
+    assert!(diagnostics.is_degraded());
+}
+
+#[test]
+fn defaults_missing_company_name() {
+    let stock = parse("missing_company.html", "F:NYSE").unwrap();
+
+    assert_eq!(stock.company_name, "N/A");
+    assert_eq!(stock.price, decimal("27.1"));
+    assert_eq!(stock.currency, "USD");
+}
+
+#[test]
+fn fails_without_price() {
+    let err = parse("missing_price.html", "AAPL:NASDAQ").unwrap_err();
+
+    assert!(matches!(err, StockError::Selector { .. }));
+    assert!(err.to_string().starts_with("AAPL:NASDAQ: no price found"));
+}
+
+#[test]
+fn fetches_through_injected_fetcher() {
+    let fetcher = CannedFetcher::default()
+        .with_page("AAPL:NASDAQ", "aapl_nasdaq.html")
+        .with_page("BBCA:IDX", "bbca_idx.html");
+    let provider = GoogleFinance::new(fetcher);
+
+    assert_eq!(provider.fetch("AAPL:NASDAQ").unwrap().price, decimal("189.84"));
+    assert_eq!(provider.fetch("BBCA:IDX").unwrap().currency, "IDR");
+}
+
+#[test]
+fn keeps_only_the_pages_that_fail_to_parse() {
+    let dir = std::env::temp_dir().join(format!("scraping-stock-failed-pages-{}", std::process::id()));
+    let recorder = Recorder::failures(&dir).unwrap();
+    let fetcher = CannedFetcher::default()
+        .with_page("AAPL:NASDAQ", "aapl_nasdaq.html")
+        .with_page("MSFT:NASDAQ", "missing_price.html");
+    let provider = GoogleFinance::new(fetcher).record_to(recorder.clone());
+
+    for share_code in ["AAPL:NASDAQ", "MSFT:NASDAQ"] {
+        recorder.settle(share_code, &provider.fetch(share_code));
+    }
+
+    let saved = |name: &str| fs::read_dir(dir.join(name)).map(|entries| entries.count()).unwrap_or(0);
+    let (aapl, msft) = (saved("AAPL_NASDAQ"), saved("MSFT_NASDAQ"));
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!((aapl, msft), (0, 1));
+}
+
+#[test]
+fn requests_quote_url() {
+    let fetcher = CannedFetcher::default();
+
+    googlefinance::fetch_from_google_finance(&fetcher, "SAP:ETR").unwrap_err();
+
+    assert_eq!(*fetcher.requests.lock().unwrap(), vec![format!("{}SAP:ETR", BASE_URL)]);
+}
+
+#[test]
+fn requests_currency_page_without_exchange() {
+    let fetcher = CannedFetcher::default();
+
+    googlefinance::fetch_from_google_finance(&fetcher, "usd-idr:fx").unwrap_err();
+
+    assert_eq!(*fetcher.requests.lock().unwrap(), vec![format!("{}USD-IDR", BASE_URL)]);
+}
+
+#[test]
+fn surfaces_fetch_errors() {
+    let provider = GoogleFinance::new(CannedFetcher::default());
+    let err = provider.fetch("AAPL:NASDAQ").unwrap_err();
+
+    assert!(matches!(err, StockError::HttpStatus { status: 404, .. }));
+}
+
+#[test]
+fn renders_page_without_price() {
+    let fetcher = CannedFetcher::default()
+        .with_page("AAPL:NASDAQ", "missing_price.html")
+        .with_rendered_page("AAPL:NASDAQ", "aapl_nasdaq.html");
+    let provider = GoogleFinance::new(fetcher);
+
+    assert_eq!(provider.fetch("AAPL:NASDAQ").unwrap().price, decimal("189.84"));
+}
+
+#[test]
+fn reports_missing_price_without_browser() {
+    let provider = GoogleFinance::new(CannedFetcher::default().with_page("AAPL:NASDAQ", "missing_price.html"));
+    let err = provider.fetch("AAPL:NASDAQ").unwrap_err();
+
+    assert!(matches!(err, StockError::Selector { .. }));
+}
+
+#[test]
+fn finds_first_listing_on_known_exchange() {
+    let html_content = read_fixture("search_shop.html");
+
+    assert_eq!(googlefinance::parse_search_results(&html_content, "shop").unwrap(), "SHOP:NYSE");
+    assert!(matches!(googlefinance::parse_search_results(&html_content, "SHOPX"), Err(StockError::SymbolNotFound(_))));
+}
+
+#[test]
+fn searches_bare_ticker() {
+    let mut fetcher = CannedFetcher::default();
+    fetcher.pages.insert(format!("{}SHOP", SEARCH_URL), read_fixture("search_shop.html"));
+
+    assert_eq!(googlefinance::search_code(&fetcher, "SHOP").unwrap(), "SHOP:NYSE");
+    assert_eq!(*fetcher.requests.lock().unwrap(), vec![format!("{}SHOP", SEARCH_URL)]);
+}
+
+#[test]
+fn parses_fundamentals() {
+    let fundamentals = googlefinance::parse_fundamentals(&read_fixture("aapl_nasdaq.html"), &PageSelectors::google());
+
+    assert_eq!(fundamentals, Fundamentals {
+        pe_ratio: Some(29.59),
+        eps: Some(decimal("2.18")),
+        dividend_yield: Some(0.51),
+        market_cap: Some(2.95e12),
+    });
+}
+
+#[test]
+fn leaves_missing_fundamentals_empty() {
+    let bca = googlefinance::parse_fundamentals(&read_fixture("bbca_idx.html"), &PageSelectors::google());
+    let missing = googlefinance::parse_fundamentals(&read_fixture("missing_price.html"), &PageSelectors::google());
+
+    assert_eq!((bca.pe_ratio, bca.eps, bca.dividend_yield), (None, None, None));
+    assert!(bca.market_cap.is_some());
+    assert!(missing.is_empty());
+}
+
+#[test]
+fn attaches_cached_fundamentals_to_equity_quotes() {
+    let fundamentals = Arc::new(CountingFundamentals::default());
+    let client = StockClient::with_provider(GoogleFinance::new(CannedFetcher::default().with_page("AAPL:NASDAQ", "aapl_nasdaq.html")))
+        .retry(RetryPolicy::none())
+        .fundamentals_from(fundamentals.clone());
+
+    for _ in 0..2 {
+        let stock = client.fetch_quote("AAPL:NASDAQ").unwrap();
+
+        assert_eq!(stock.fundamentals.unwrap().pe_ratio, Some(29.59));
+    }
+
+    assert_eq!(fundamentals.fetches.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn parses_news() {
+    let items = googlefinance::parse_news(&read_fixture("aapl_nasdaq.html"), "aapl:nasdaq", &PageSelectors::google());
+
+    assert_eq!(items, vec![
+        NewsItem {
+            symbol: "AAPL".to_string(),
+            headline: "Apple posts record buyback as iPhone sales slip".to_string(),
+            source: "Reuters".to_string(),
+            url: "https://www.reuters.com/technology/apple-earnings-2024-05-02/".to_string(),
+            published: Some("3 hours ago".to_string()),
+        },
+        NewsItem {
+            symbol: "AAPL".to_string(),
+            headline: "What to expect from WWDC".to_string(),
+            source: "Google Finance".to_string(),
+            url: format!("{}AAPL:NASDAQ?article=wwdc", BASE_URL),
+            published: None,
+        },
+    ]);
+    assert!(googlefinance::parse_news(&read_fixture("bbca_idx.html"), "BBCA:IDX", &PageSelectors::google()).is_empty());
+}
+
+#[test]
+fn attaches_only_unseen_headlines() {
+    let news = Arc::new(GrowingNews::default());
+    let client = StockClient::with_provider(GoogleFinance::new(CannedFetcher::default().with_page("AAPL:NASDAQ", "aapl_nasdaq.html")))
+        .retry(RetryPolicy::none())
+        .news_from(news.clone());
+
+    assert_eq!(client.fetch_quote("AAPL:NASDAQ").unwrap().news.len(), 3);
+    // Not due again for a while.
+    assert!(client.fetch_quote("AAPL:NASDAQ").unwrap().news.is_empty());
+    assert_eq!(news.fetches.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,57 @@
+use std::time::{Duration, Instant};
+use scraping_stock::{
+    alert::AlertEvent,
+    notify::{hook::{HookCommand, HookNotifier}, Notifier},
+    Decimal, Stock, StockError,
+};
+
+
+fn event() -> AlertEvent {
+    let mut stock = Stock::new("AAPL".to_string(), "Apple Inc".to_string(), "201.5".parse().unwrap());
+    stock.previous_price = Some(Decimal::from(198));
+    stock.change_pct = Some(1.77);
+
+    AlertEvent::for_rule(&"AAPL above 200".parse().unwrap(), &stock)
+}
+
+#[test]
+fn fills_in_each_word() {
+    let command: HookCommand = "./on-alert.sh {symbol} {price:.2} '{company} alert' \"\"".parse().unwrap();
+
+    assert_eq!(command.render(&event()), vec!["./on-alert.sh", "AAPL", "201.50", "Apple Inc alert", ""]);
+}
+
+#[test]
+fn rejects_malformed_commands() {
+    for command in ["", "  ", "notify {volume}", "notify 'unclosed {symbol}"] {
+        assert!(matches!(command.parse::<HookCommand>(), Err(StockError::Invalid { .. })), "{:?} parsed", command);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn runs_the_command_with_the_alert() {
+    let path = std::env::temp_dir().join(format!("scraping-stock-hook-{}.txt", std::process::id()));
+    let command = format!("sh -c 'echo \"$STOCK_SYMBOL $STOCK_CHANGE_PCT $1\" > {0}.tmp && mv {0}.tmp {0}' hook {{price}}", path.display());
+    let notifier = HookNotifier::new(command.parse().unwrap());
+
+    notifier.notify(&event()).unwrap();
+
+    let started = Instant::now();
+
+    while !path.exists() && started.elapsed() < Duration::from_secs(5) {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(written, "AAPL +1.77 201.5\n");
+}
+
+#[test]
+fn fails_on_missing_programs() {
+    let notifier = HookNotifier::new("./no-such-hook {symbol}".parse().unwrap());
+
+    assert!(matches!(notifier.notify(&event()), Err(StockError::Notification(_))));
+}
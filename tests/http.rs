@@ -0,0 +1,137 @@
+use std::{io::{BufRead, BufReader, Write}, net::TcpListener, thread, time::Duration};
+
+use scraping_stock::{http::{self, Fetcher, HttpOptions}, StockError};
+
+
+/// Answers one request on a local port with `response`, handing back the
+/// request's header lines.
+fn serve_once(response: String) -> (String, thread::JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = format!("http://{}/quote", listener.local_addr().unwrap());
+
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let headers = BufReader::new(stream.try_clone().unwrap()).lines()
+            .map(Result::unwrap)
+            .take_while(|line| !line.is_empty())
+            .collect();
+
+        // The client may hang up early on a body it won't read.
+        let _ = stream.write_all(response.as_bytes());
+        headers
+    });
+
+    (address, server)
+}
+
+fn fetcher(max_body_size: u64) -> Fetcher {
+    Fetcher::new(&HttpOptions { max_body_size, ..HttpOptions::default() }).unwrap()
+}
+
+/// Awaits `future` on a single-threaded runtime, like `#[tokio::test]`'s.
+fn block_on<T>(future: impl std::future::Future<Output = T>) -> T {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[test]
+fn asks_for_compressed_responses() {
+    let (address, server) = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello".to_string());
+
+    assert_eq!(fetcher(1024).fetch_page(&address).unwrap(), "hello");
+
+    let accept_encoding = server.join().unwrap().into_iter()
+        .find_map(|line| line.to_lowercase().strip_prefix("accept-encoding: ").map(String::from))
+        .unwrap();
+
+    for encoding in ["gzip", "deflate", "br"] {
+        assert!(accept_encoding.contains(encoding), "{} missing from {}", encoding, accept_encoding);
+    }
+}
+
+#[test]
+fn rejects_declared_length_over_limit() {
+    let body = "x".repeat(2048);
+    let (address, server) = serve_once(format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body));
+
+    let err = fetcher(1024).fetch_page(&address).unwrap_err();
+    server.join().unwrap();
+
+    assert!(matches!(err, StockError::ResponseTooLarge { limit: 1024, .. }));
+    assert_eq!(err.code(), "RESPONSE_TOO_LARGE");
+}
+
+#[test]
+fn rejects_streamed_body_over_limit() {
+    let body = "x".repeat(2048);
+    let (address, server) = serve_once(format!("HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n{}", body));
+
+    let err = fetcher(1024).fetch_page(&address).unwrap_err();
+    server.join().unwrap();
+
+    assert!(matches!(err, StockError::ResponseTooLarge { limit: 1024, .. }));
+}
+
+#[test]
+fn reads_body_up_to_limit() {
+    let body = "x".repeat(1024);
+    let (address, server) = serve_once(format!("HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n{}", body));
+
+    assert_eq!(fetcher(1024).fetch_page(&address).unwrap(), body);
+    server.join().unwrap();
+}
+
+#[test]
+fn answers_from_the_cache_while_debugging() {
+    let (address, server) = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello".to_string());
+    let fetcher = Fetcher::new(&HttpOptions { cache_ttl: Duration::from_secs(60), debug: true, ..HttpOptions::default() }).unwrap();
+
+    assert_eq!(fetcher.fetch_page(&address).unwrap(), "hello");
+    server.join().unwrap();
+    // The server is gone, the page comes from the cache.
+    assert_eq!(fetcher.fetch_page(&address).unwrap(), "hello");
+}
+
+#[test]
+fn fetches_without_blocking() {
+    let (address, server) = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello".to_string());
+    let fetcher = fetcher(1024);
+
+    assert_eq!(block_on(fetcher.async_fetch_page(&address)).unwrap(), "hello");
+    server.join().unwrap();
+}
+
+#[test]
+fn rejects_streamed_body_over_limit_without_blocking() {
+    let body = "x".repeat(2048);
+    let (address, server) = serve_once(format!("HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n{}", body));
+    let fetcher = fetcher(1024);
+
+    let err = block_on(fetcher.async_fetch_page(&address)).unwrap_err();
+    server.join().unwrap();
+
+    assert!(matches!(err, StockError::ResponseTooLarge { limit: 1024, .. }));
+}
+
+#[test]
+fn shares_the_cache_between_blocking_and_async_fetches() {
+    let (address, server) = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello".to_string());
+    let fetcher = Fetcher::new(&HttpOptions { cache_ttl: Duration::from_secs(60), ..HttpOptions::default() }).unwrap();
+
+    assert_eq!(block_on(fetcher.async_fetch_page(&address)).unwrap(), "hello");
+    server.join().unwrap();
+    assert_eq!(fetcher.fetch_page(&address).unwrap(), "hello");
+}
+
+#[test]
+fn masks_credentials_in_logged_urls() {
+    assert_eq!(
+        http::redact("https://www.alphavantage.co/query?function=GLOBAL_QUOTE&symbol=AAPL&apikey=SECRET"),
+        "https://www.alphavantage.co/query?function=GLOBAL_QUOTE&symbol=AAPL&apikey=***",
+    );
+    assert_eq!(http::redact("https://finnhub.io/api/v1/quote?symbol=AAPL&token=SECRET"), "https://finnhub.io/api/v1/quote?symbol=AAPL&token=***");
+    assert_eq!(http::redact("https://www.google.com/finance/quote/AAPL:NASDAQ"), "https://www.google.com/finance/quote/AAPL:NASDAQ");
+}
@@ -0,0 +1,73 @@
+use scraping_stock::{
+    indicators::{self, BollingerBands, Indicators, Macd},
+    rules::{Condition, Rule, RuleSet, Signal, DEFAULT_COOLOFF},
+    Decimal, Stock, StockError,
+};
+
+
+fn quote(price: i64, macd: Option<(f64, f64)>, bands: Option<(f64, f64)>) -> Stock {
+    let mut stock = Stock::new("AAPL".to_string(), "Apple Inc".to_string(), Decimal::from(price));
+
+    stock.indicators = Some(Indicators {
+        ma_window: 20,
+        sma: None,
+        ema: None,
+        rsi_window: 14,
+        rsi: None,
+        macd: macd.map(|(line, signal)| Macd { line, signal, histogram: line - signal }),
+        bollinger: bands.map(|(lower, upper)| BollingerBands { middle: (lower + upper) / 2.0, upper, lower }),
+    });
+    stock
+}
+
+#[test]
+fn computes_macd_once_the_signal_line_fills() {
+    let prices: Vec<f64> = (0..34).map(|step| 100.0 + (step * step) as f64 / 10.0).collect();
+
+    assert_eq!(indicators::macd(&prices[..33]), None);
+
+    let macd = indicators::macd(&prices).unwrap();
+
+    // The fast EMA leads a quickening rise, and the signal trails the line.
+    assert!(macd.line > 0.0);
+    assert!(macd.histogram > 0.0);
+    assert_eq!(indicators::macd(&[150.0; 40]), Some(Macd { line: 0.0, signal: 0.0, histogram: 0.0 }));
+}
+
+#[test]
+fn computes_bollinger_bands() {
+    let prices = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+
+    assert_eq!(indicators::bollinger(&prices, 8, 2.0), Some(BollingerBands { middle: 5.0, upper: 9.0, lower: 1.0 }));
+    assert_eq!(indicators::bollinger(&prices, 9, 2.0), None);
+}
+
+#[test]
+fn parses_signal_rules() {
+    let rule: Rule = "aapl:nasdaq macd_cross_up".parse().unwrap();
+
+    assert_eq!(rule.symbol, "AAPL");
+    assert_eq!(rule.condition, Condition::Cross(Signal::MacdCrossUp));
+    assert_eq!(rule.to_string(), "AAPL macd_cross_up");
+    assert!(matches!("AAPL golden_cross".parse::<Rule>(), Err(StockError::Invalid { .. })));
+}
+
+#[test]
+fn fires_on_a_macd_cross_only() {
+    let mut rules = RuleSet::new(vec!["AAPL macd_cross_up".parse().unwrap()], DEFAULT_COOLOFF);
+
+    // Already above at start-up, or not known yet: no crossing seen.
+    assert!(rules.evaluate(&quote(190, None, None)).is_empty());
+    assert!(rules.evaluate(&quote(190, Some((1.2, 0.8)), None)).is_empty());
+    assert!(rules.evaluate(&quote(188, Some((0.5, 0.7)), None)).is_empty());
+    assert_eq!(rules.evaluate(&quote(191, Some((0.9, 0.7)), None)).len(), 1);
+    assert!(rules.evaluate(&quote(192, Some((1.1, 0.8)), None)).is_empty());
+}
+
+#[test]
+fn fires_when_the_price_leaves_the_bands() {
+    let mut rules = RuleSet::new(vec!["AAPL price_above_upper_band".parse().unwrap()], DEFAULT_COOLOFF);
+
+    assert!(rules.evaluate(&quote(190, None, Some((185.0, 195.0)))).is_empty());
+    assert_eq!(rules.evaluate(&quote(197, None, Some((186.0, 196.0)))).len(), 1);
+}
@@ -0,0 +1,18 @@
+use chrono::{TimeZone, Utc};
+use scraping_stock::{storage::influxdb, Decimal, Stock};
+
+#[test]
+fn escapes_measurement_tags_and_fields() {
+    let stock = Stock::new(
+        "BRK B,=:NYSE".to_string(),
+        r#"Berkshire "B", Inc = Class B \ Co"#.to_string(),
+        Decimal::from(400),
+    )
+    .with_currency("US D".to_string());
+    let timestamp = Utc.with_ymd_and_hms(2024, 1, 2, 15, 4, 5).unwrap();
+
+    assert_eq!(
+        influxdb::line("stock quote,v2", &stock, timestamp),
+        r#"stock\ quote\,v2,symbol=BRK\ B\,\=:NYSE,currency=US\ D price=400,company="Berkshire \"B\", Inc = Class B \\ Co",status="up" 1704207845000000000"#,
+    );
+}
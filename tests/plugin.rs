@@ -0,0 +1,99 @@
+use std::path::PathBuf;
+use scraping_stock::{plugin::Plugin, StockError};
+#[cfg(feature = "wasm")]
+use scraping_stock::{indicators::Indicators, plugin::PluginSignal, Decimal, Stock};
+
+
+/// Buys below an RSI of 30 and sells above 70, holding when it's unknown.
+#[cfg(feature = "wasm")]
+const RSI_STRATEGY: &str = r#"
+(module
+  (func (export "signal")
+    (param $price f64) (param $change_pct f64) (param $sma f64) (param $ema f64) (param $rsi f64)
+    (param $macd f64) (param $macd_signal f64) (param $lower_band f64) (param $upper_band f64)
+    (result i32)
+    (if (f64.lt (local.get $rsi) (f64.const 30)) (then (return (i32.const 1))))
+    (if (f64.gt (local.get $rsi) (f64.const 70)) (then (return (i32.const -1))))
+    (i32.const 0)))
+"#;
+
+fn write_module(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("scraping-stock-plugin-{}-{}.wat", name, std::process::id()));
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+#[cfg(feature = "wasm")]
+fn quote(price: i64, rsi: Option<f64>) -> Stock {
+    let mut stock = Stock::new("AAPL".to_string(), "Apple Inc".to_string(), Decimal::from(price));
+
+    stock.indicators = Some(Indicators { ma_window: 20, sma: None, ema: None, rsi_window: 14, rsi, macd: None, bollinger: None });
+    stock
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn signals_from_the_module() {
+    let path = write_module("rsi", RSI_STRATEGY);
+    let plugin = Plugin::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let mut instance = plugin.instantiate().unwrap();
+    let signals: Vec<PluginSignal> = [Some(25.0), Some(50.0), Some(75.0), None]
+        .into_iter()
+        .map(|rsi| instance.signal(&quote(180, rsi)).unwrap())
+        .collect();
+
+    assert_eq!(signals, vec![PluginSignal::Buy, PluginSignal::Hold, PluginSignal::Sell, PluginSignal::Hold]);
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn paper_trades_on_plugin_signals() {
+    use scraping_stock::simulate::{PaperTrader, Side, SimulationOptions, Strategy};
+
+    let path = write_module("trader", RSI_STRATEGY);
+    let strategy: Strategy = toml::from_str(&format!("symbol = \"AAPL:NASDAQ\"\nplugin = {:?}\nquantity = 10", path.display().to_string())).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let mut trader = PaperTrader::new(SimulationOptions { cash: Decimal::from(10_000), strategies: vec![strategy] });
+    let sides: Vec<Side> = [(180, 25.0), (178, 20.0), (190, 50.0), (200, 75.0), (205, 80.0)]
+        .into_iter()
+        .flat_map(|(price, rsi)| trader.record("AAPL:NASDAQ", &quote(price, Some(rsi))))
+        .map(|trade| trade.side)
+        .collect();
+
+    // Buys once while the signal stays at buy.
+    assert_eq!(sides, vec![Side::Buy, Side::Sell]);
+    assert_eq!(trader.report().realized_pnl, Decimal::from(200));
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn rejects_modules_without_the_signal() {
+    let path = write_module("missing", "(module (func (export \"decide\") (result i32) (i32.const 1)))");
+    let loaded = Plugin::load(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(loaded, Err(StockError::Invalid { .. })));
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn stops_plugins_that_never_return() {
+    let path = write_module("loop", "(module (func (export \"signal\") (param f64 f64 f64 f64 f64 f64 f64 f64 f64) (result i32) (loop $forever (br $forever)) (i32.const 0)))");
+    let plugin = Plugin::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(plugin.instantiate().unwrap().signal(&quote(180, None)), Err(StockError::Runtime { .. })));
+}
+
+#[cfg(not(feature = "wasm"))]
+#[test]
+fn needs_the_wasm_feature() {
+    let path = write_module("disabled", "(module)");
+    let loaded = Plugin::load(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(loaded, Err(StockError::FeatureDisabled(_))));
+}
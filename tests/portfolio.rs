@@ -0,0 +1,95 @@
+use scraping_stock::{portfolio::{Holding, Portfolio}, quotes::QuoteStore, Decimal, Stock};
+
+
+fn dec(value: &str) -> Decimal {
+    value.parse().unwrap()
+}
+
+fn holding(symbol: &str, quantity: &str, cost_basis: &str) -> Holding {
+    Holding { symbol: symbol.to_string(), quantity: dec(quantity), cost_basis: dec(cost_basis) }
+}
+
+fn quotes(prices: &[(&str, &str, &str)]) -> QuoteStore {
+    let store = QuoteStore::new();
+
+    for (symbol, price, currency) in prices {
+        let mut stock = Stock::new(symbol.to_string(), "Test".to_string(), dec(price)).with_currency(currency.to_string());
+        store.update_and_diff(symbol, &mut stock);
+    }
+
+    store
+}
+
+#[test]
+fn values_positions_at_last_quote() {
+    let portfolio = Portfolio::new(vec![holding("AAPL:NASDAQ", "10", "150.25"), holding("MSFT:NASDAQ", "2.5", "400")]);
+    let store = quotes(&[("AAPL:NASDAQ", "189.75", "USD"), ("MSFT:NASDAQ", "380", "USD")]);
+
+    let summary = portfolio.value(&store);
+    let (gain, loss) = (&summary.positions[0], &summary.positions[1]);
+
+    assert_eq!(gain.market_value, dec("1897.5"));
+    assert_eq!(gain.unrealized_pnl, dec("395"));
+    assert!((gain.unrealized_pnl_pct - 26.289517).abs() < 1e-6);
+    assert_eq!(loss.market_value, dec("950"));
+    assert_eq!(loss.unrealized_pnl, dec("-50"));
+    assert!((loss.unrealized_pnl_pct + 5f64).abs() < 1e-9);
+    assert!(summary.unpriced.is_empty());
+}
+
+#[test]
+fn totals_each_currency_apart() {
+    let portfolio = Portfolio::new(vec![
+        holding("AAPL:NASDAQ", "10", "150"),
+        holding("BBCA:IDX", "100", "9000"),
+        holding("MSFT:NASDAQ", "1", "400"),
+    ]);
+    let store = quotes(&[("AAPL:NASDAQ", "160", "USD"), ("BBCA:IDX", "9500", "IDR"), ("MSFT:NASDAQ", "350", "USD")]);
+
+    let summary = portfolio.value(&store);
+    let currencies: Vec<_> = summary.totals.iter().map(|total| total.currency.as_str()).collect();
+
+    assert_eq!(currencies, ["IDR", "USD"]);
+
+    let (idr, usd) = (&summary.totals[0], &summary.totals[1]);
+
+    assert_eq!((idr.cost, idr.market_value, idr.unrealized_pnl), (dec("900000"), dec("950000"), dec("50000")));
+    assert!((idr.unrealized_pnl_pct - 5.555556).abs() < 1e-6);
+    assert_eq!((usd.cost, usd.market_value, usd.unrealized_pnl), (dec("1900"), dec("1950"), dec("50")));
+    assert!((usd.unrealized_pnl_pct - 2.631579).abs() < 1e-6);
+}
+
+#[test]
+fn lists_holdings_without_quote() {
+    let portfolio = Portfolio::new(vec![holding("AAPL:NASDAQ", "1", "100"), holding("TSLA:NASDAQ", "3", "200")]);
+    let store = quotes(&[("AAPL:NASDAQ", "110", "USD")]);
+
+    let summary = portfolio.value(&store);
+
+    assert_eq!(summary.positions.len(), 1);
+    assert_eq!(summary.unpriced, ["TSLA:NASDAQ"]);
+    assert_eq!(summary.totals[0].cost, dec("100"));
+}
+
+#[test]
+fn zero_cost_basis_has_no_percentage() {
+    let portfolio = Portfolio::new(vec![holding("GIFT:NYSE", "5", "0")]);
+    let store = quotes(&[("GIFT:NYSE", "20", "USD")]);
+
+    let summary = portfolio.value(&store);
+
+    assert_eq!(summary.positions[0].unrealized_pnl, dec("100"));
+    assert_eq!(summary.positions[0].unrealized_pnl_pct, 0f64);
+    assert_eq!(summary.totals[0].unrealized_pnl_pct, 0f64);
+}
+
+#[test]
+fn displays_positions_and_totals() {
+    let portfolio = Portfolio::new(vec![holding("MSFT:NASDAQ", "2", "400")]);
+    let store = quotes(&[("MSFT:NASDAQ", "380", "USD")]);
+
+    let summary = portfolio.value(&store);
+
+    assert_eq!(summary.positions[0].to_string(), "MSFT:NASDAQ 2 x 380.00 USD: value 760.00, P&L -40.00 (-5.00%)");
+    assert_eq!(summary.totals[0].to_string(), "Portfolio USD: value 760.00, cost 800.00, P&L -40.00 (-5.00%)");
+}
@@ -0,0 +1,54 @@
+use scraping_stock::{price::{self, Price}, Decimal};
+
+
+fn parsed(text: &str) -> Option<(String, Decimal)> {
+    price::parse_price(text).map(|Price { currency, value }| (currency, value))
+}
+
+fn amount(currency: &str, value: &str) -> Option<(String, Decimal)> {
+    Some((currency.to_string(), value.parse().unwrap()))
+}
+
+#[test]
+fn keeps_the_dot_decimal_where_the_currency_does() {
+    assert_eq!(parsed("$1.234"), amount("USD", "1.234"));
+    assert_eq!(parsed("US$ 0.125"), amount("USD", "0.125"));
+    assert_eq!(parsed("£12.345"), amount("GBP", "12.345"));
+    assert_eq!(parsed("$1,234"), amount("USD", "1234"));
+    assert_eq!(parsed("$1,234,567.89"), amount("USD", "1234567.89"));
+}
+
+#[test]
+fn reads_grouping_dots_elsewhere() {
+    assert_eq!(parsed("Rp 9.875"), amount("IDR", "9875"));
+    assert_eq!(parsed("Rp 9,875"), amount("IDR", "9875"));
+    assert_eq!(parsed("1.234,56 €"), amount("EUR", "1234.56"));
+    assert_eq!(parsed("1.234.567"), amount("", "1234567"));
+}
+
+#[test]
+fn matches_the_longest_suffix_marker() {
+    assert_eq!(parsed("12.50 US$"), amount("USD", "12.50"));
+    assert_eq!(parsed("12.50 CA$"), amount("CAD", "12.50"));
+    assert_eq!(parsed("12.50 S$"), amount("SGD", "12.50"));
+    assert_eq!(parsed("12.50 A$"), amount("AUD", "12.50"));
+}
+
+#[test]
+fn drops_apostrophe_grouping() {
+    assert_eq!(parsed("CHF 1'234.50"), amount("CHF", "1234.50"));
+    assert_eq!(parsed("CHF 1’234"), amount("CHF", "1234"));
+}
+
+#[test]
+fn reads_abbreviated_amounts() {
+    assert_eq!(price::parse_abbreviated("2.87T USD"), Some(2.87e12));
+    assert_eq!(price::parse_abbreviated("850K"), Some(850e3));
+    assert_eq!(price::parse_abbreviated("n/a"), None);
+}
+
+#[test]
+fn groups_rupiah_in_thousands() {
+    assert_eq!(price::format_amount(Decimal::from(9675), "IDR"), "9,675");
+    assert_eq!(price::format_amount("189.5".parse().unwrap(), "USD"), "189.5");
+}
@@ -0,0 +1,179 @@
+use std::{sync::Barrier, thread};
+
+use chrono::{DateTime, Duration, Utc};
+use scraping_stock::{output::{self, SnapshotRecord}, quotes::QuoteStore, Decimal, Status, Stock, Valuation};
+
+const THREADS: usize = 8;
+const UPDATES: i64 = 500;
+
+
+fn quote(symbol: &str, price: i64, fetched_at: DateTime<Utc>) -> Stock {
+    Stock::new(symbol.to_string(), "Test".to_string(), Decimal::from(price)).with_fetched_at(fetched_at)
+}
+
+#[test]
+fn first_quote_has_no_previous() {
+    let store = QuoteStore::new();
+    let mut stock = quote("AAPL", 190, Utc::now());
+
+    let diff = store.update_and_diff("AAPL:NASDAQ", &mut stock).unwrap();
+
+    assert_eq!(diff.valuation, Valuation::First);
+    assert!(diff.previous.is_none());
+    assert_eq!(store.get_last("AAPL:NASDAQ").unwrap().price, Decimal::from(190));
+}
+
+#[test]
+fn diffs_against_last_quote() {
+    let store = QuoteStore::new();
+    let now = Utc::now();
+
+    store.update_and_diff("AAPL:NASDAQ", &mut quote("AAPL", 200, now)).unwrap();
+    let mut stock = quote("AAPL", 190, now + Duration::seconds(10));
+    let diff = store.update_and_diff("AAPL:NASDAQ", &mut stock).unwrap();
+
+    assert_eq!(diff.previous.unwrap().price, Decimal::from(200));
+    assert!(matches!(diff.valuation, Valuation::Down { change_pct, .. } if change_pct == -5.0));
+    assert_eq!(stock.status, Status::Down);
+    assert_eq!(stock.change_abs, Some(Decimal::from(-10)));
+    assert_eq!(store.get_last("AAPL:NASDAQ").unwrap().status, Status::Down);
+}
+
+#[test]
+fn keeps_newer_quote_over_older_one() {
+    let store = QuoteStore::new();
+    let now = Utc::now();
+
+    store.update_and_diff("AAPL:NASDAQ", &mut quote("AAPL", 200, now)).unwrap();
+
+    assert!(store.update_and_diff("AAPL:NASDAQ", &mut quote("AAPL", 150, now - Duration::seconds(1))).is_none());
+    assert_eq!(store.get_last("AAPL:NASDAQ").unwrap().price, Decimal::from(200));
+}
+
+#[test]
+fn enriches_only_newer_quotes_before_storing_them() {
+    let store = QuoteStore::new();
+    let now = Utc::now();
+    let mut enriched = 0;
+
+    store.update_with("AAPL:NASDAQ", &mut quote("AAPL", 200, now), |stock| {
+        enriched += 1;
+        stock.recent_prices = vec![stock.price.to_f64()];
+    }).unwrap();
+    let older = store.update_with("AAPL:NASDAQ", &mut quote("AAPL", 150, now - Duration::seconds(1)), |_| enriched += 1);
+
+    assert!(older.is_none());
+    assert_eq!(enriched, 1);
+    assert_eq!(store.get_last("AAPL:NASDAQ").unwrap().recent_prices, vec![200.0]);
+}
+
+#[test]
+fn modifies_last_quote_in_place() {
+    let store = QuoteStore::with_shards(1);
+
+    assert_eq!(store.modify("AAPL:NASDAQ", |stock| stock.stale = true), None);
+
+    store.update_and_diff("AAPL:NASDAQ", &mut quote("AAPL", 200, Utc::now())).unwrap();
+    store.modify("AAPL:NASDAQ", |stock| stock.stale = true).unwrap();
+
+    assert!(store.get_last("AAPL:NASDAQ").unwrap().stale);
+}
+
+#[test]
+fn updates_symbols_concurrently() {
+    let store = QuoteStore::new();
+    let barrier = Barrier::new(THREADS);
+    let start = Utc::now();
+
+    thread::scope(|scope| {
+        for worker in 0..THREADS {
+            let (store, barrier) = (store.clone(), &barrier);
+
+            scope.spawn(move || {
+                let code = format!("SYM{}:NASDAQ", worker);
+                barrier.wait();
+
+                for price in 1..=UPDATES {
+                    let mut stock = quote(&code, price, start + Duration::milliseconds(price));
+                    let diff = store.update_and_diff(&code, &mut stock).unwrap();
+
+                    // Nobody else writes this code, so the last quote is the one before.
+                    assert_eq!(diff.previous.map(|previous| previous.price), (price > 1).then(|| Decimal::from(price - 1)));
+                }
+            });
+        }
+    });
+
+    assert_eq!(store.len(), THREADS);
+
+    for (code, stock) in store.snapshot() {
+        assert!(code.starts_with("SYM"));
+        assert_eq!(stock.price, Decimal::from(UPDATES));
+    }
+}
+
+#[test]
+fn racing_updates_of_one_symbol_keep_the_latest() {
+    let store = QuoteStore::with_shards(2);
+    let barrier = Barrier::new(THREADS + 1);
+    let start = Utc::now();
+
+    thread::scope(|scope| {
+        for worker in 0..THREADS as i64 {
+            let (store, barrier) = (store.clone(), &barrier);
+
+            // Each worker writes every THREADS-th tick, so they interleave.
+            scope.spawn(move || {
+                barrier.wait();
+
+                for tick in (worker..UPDATES * THREADS as i64).step_by(THREADS) {
+                    store.update_and_diff("AAPL:NASDAQ", &mut quote("AAPL", tick, start + Duration::milliseconds(tick)));
+                }
+            });
+        }
+
+        let (store, barrier) = (store.clone(), &barrier);
+        scope.spawn(move || {
+            barrier.wait();
+
+            // Readers only ever see whole quotes, never going back in time.
+            let mut seen = None;
+            for _ in 0..UPDATES {
+                if let Some(stock) = store.get_last("AAPL:NASDAQ") {
+                    assert!(seen.is_none_or(|seen| stock.fetched_at >= seen));
+                    assert_eq!(stock.price, Decimal::from((stock.fetched_at - start).num_milliseconds()));
+                    seen = Some(stock.fetched_at);
+                }
+            }
+        });
+    });
+
+    let last = store.get_last("AAPL:NASDAQ").unwrap();
+
+    assert_eq!(last.price, Decimal::from(UPDATES * THREADS as i64 - 1));
+    assert_eq!(store.len(), 1);
+}
+
+#[test]
+fn snapshots_every_code() {
+    let store = QuoteStore::with_shards(4);
+    let now = Utc::now();
+    store.update_and_diff("AAPL:NASDAQ", &mut quote("AAPL", 190, now));
+    store.update_and_diff("AAPL:NASDAQ", &mut quote("AAPL", 192, now + Duration::seconds(1)));
+    store.update_and_diff("BBCA:IDX", &mut quote("BBCA", 9500, now));
+
+    let codes = ["AAPL:NASDAQ", "BBCA:IDX", "GOTO:IDX"].map(String::from);
+    let quotes = store.snapshot();
+    let path = std::env::temp_dir().join(format!("scraping-stock-snapshot-{}.json", std::process::id()));
+    output::write_snapshot(Some(&path), &SnapshotRecord::new(&codes, &quotes)).unwrap();
+    let written: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(quotes.len(), 2);
+    assert_eq!(written["quotes"]["AAPL:NASDAQ"]["price"], 192.0);
+    assert_eq!(written["quotes"]["AAPL:NASDAQ"]["status"], "up");
+    assert_eq!(written["quotes"]["BBCA:IDX"]["symbol"], "BBCA");
+    // Codes without a quote yet are there too.
+    assert!(written["quotes"]["GOTO:IDX"].is_null());
+    assert!(written["taken_at"].is_string());
+}
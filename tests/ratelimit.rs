@@ -0,0 +1,59 @@
+use std::time::{Duration, Instant};
+use scraping_stock::{ratelimit::{RateLimit, RateLimits, TokenBucket}, StockError};
+
+
+fn timed(operation: impl FnOnce()) -> Duration {
+    let started = Instant::now();
+    operation();
+    started.elapsed()
+}
+
+#[test]
+fn lets_a_burst_through_then_waits_for_tokens() {
+    let bucket = TokenBucket::new(2, 20.0);
+
+    assert!(timed(|| { bucket.acquire(); bucket.acquire(); }) < Duration::from_millis(25));
+    // Empty now, the next token is due in 50 ms and the one after in 100.
+    let waited = timed(|| { bucket.acquire(); bucket.acquire(); });
+    assert!(waited >= Duration::from_millis(90) && waited < Duration::from_millis(500), "{:?}", waited);
+}
+
+#[test]
+fn refills_up_to_its_capacity() {
+    let bucket = TokenBucket::new(2, 20.0);
+    bucket.acquire();
+    bucket.acquire();
+
+    // Long enough for far more than two tokens, of which two are kept.
+    std::thread::sleep(Duration::from_millis(300));
+    assert!(timed(|| { bucket.acquire(); bucket.acquire(); }) < Duration::from_millis(25));
+    let waited = timed(|| bucket.acquire());
+    assert!(waited >= Duration::from_millis(40), "{:?}", waited);
+}
+
+#[test]
+fn parses_caps() {
+    assert_eq!("Google = 30".parse::<RateLimit>().unwrap(), RateLimit { provider: "google".to_string(), per_minute: 30 });
+
+    for value in ["google", "google=0", "google=fast"] {
+        assert!(matches!(value.parse::<RateLimit>(), Err(StockError::Invalid { .. })), "{}", value);
+    }
+}
+
+#[test]
+fn caps_providers_with_a_default() {
+    let limits = RateLimits::new().set("Yahoo", 60);
+
+    assert_eq!(limits.get("yahoo"), Some(60));
+    assert_eq!(limits.get("google"), None);
+    assert!(limits.get("alphavantage").is_some() && limits.get("finnhub").is_some());
+}
+
+#[test]
+fn waits_for_tokens_on_the_async_timer() {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+    let bucket = TokenBucket::new(1, 20.0);
+
+    let waited = timed(|| runtime.block_on(async { bucket.async_acquire().await; bucket.async_acquire().await; }));
+    assert!(waited >= Duration::from_millis(40) && waited < Duration::from_millis(500), "{:?}", waited);
+}
@@ -0,0 +1,84 @@
+use std::{collections::HashMap, time::Duration};
+use scraping_stock::{retry::RetryPolicy, StockError};
+
+
+fn policy(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy { max_attempts, base_delay: Duration::from_millis(1), max_delay: Duration::from_millis(4), ..RetryPolicy::default() }
+}
+
+fn throttled() -> StockError {
+    StockError::HttpStatus { status: 429, message: "Too Many Requests".to_string() }
+}
+
+#[test]
+fn doubles_the_backoff_up_to_the_cap() {
+    let policy = RetryPolicy { base_delay: Duration::from_millis(100), max_delay: Duration::from_secs(1), ..RetryPolicy::default() };
+
+    for _ in 0..50 {
+        // Jitter keeps every delay between half and all of the exponential one.
+        for (attempt, full) in [(1, 100), (2, 200), (3, 400), (4, 800), (5, 1000), (30, 1000)] {
+            let delay = policy.backoff(attempt);
+            assert!(delay >= Duration::from_millis(full / 2) && delay <= Duration::from_millis(full), "attempt {}: {:?}", attempt, delay);
+        }
+    }
+}
+
+#[test]
+fn retries_transient_failures_only() {
+    let mut calls = 0;
+    let result = policy(3).run("AAPL:NASDAQ", || {
+        calls += 1;
+        match calls {
+            3 => Ok(calls),
+            _ => Err(throttled()),
+        }
+    });
+    assert_eq!((result.unwrap(), calls), (3, 3));
+
+    let mut calls = 0;
+    let result: Result<(), StockError> = policy(3).run("AAPL:NASDAQ", || {
+        calls += 1;
+        Err(StockError::PriceParse("n/a".to_string()))
+    });
+    assert!(matches!(result, Err(StockError::PriceParse(_))));
+    assert_eq!(calls, 1);
+}
+
+#[test]
+fn gives_up_after_the_attempts_of_the_symbol() {
+    let policy = RetryPolicy { max_attempts_per_symbol: HashMap::from([("BBCA".to_string(), 5)]), ..policy(2) };
+    assert_eq!((policy.max_attempts_for("bbca:idx"), policy.max_attempts_for("AAPL:NASDAQ")), (5, 2));
+
+    for (code, attempts) in [("BBCA:IDX", 5), ("AAPL:NASDAQ", 2)] {
+        let mut calls = 0;
+        let result: Result<(), StockError> = policy.run(code, || {
+            calls += 1;
+            Err(throttled())
+        });
+
+        assert!(matches!(result, Err(StockError::HttpStatus { status: 429, .. })));
+        assert_eq!(calls, attempts, "{}", code);
+    }
+
+    assert_eq!(RetryPolicy { max_attempts: 0, ..RetryPolicy::none() }.max_attempts_for("AAPL"), 1);
+}
+
+#[test]
+fn retries_async_operations_the_same() {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+    let mut calls = 0;
+
+    let result = runtime.block_on(policy(3).async_run("AAPL:NASDAQ", || {
+        calls += 1;
+        let calls = calls;
+
+        async move {
+            match calls {
+                2 => Ok(calls),
+                _ => Err(throttled()),
+            }
+        }
+    }));
+
+    assert_eq!((result.unwrap(), calls), (2, 2));
+}
@@ -0,0 +1,36 @@
+use std::time::Duration;
+use scraping_stock::robots::{Robots, MAX_CRAWL_DELAY};
+
+
+fn crawl_delay(value: &str) -> Option<Duration> {
+    Robots::parse(&format!("User-agent: *\nCrawl-delay: {}\n", value)).crawl_delay
+}
+
+#[test]
+fn reads_the_crawl_delay() {
+    assert_eq!(crawl_delay("2.5"), Some(Duration::from_millis(2500)));
+    assert_eq!(crawl_delay("0"), Some(Duration::ZERO));
+}
+
+#[test]
+fn caps_huge_crawl_delays() {
+    for value in ["3600", "1e300", "inf"] {
+        assert_eq!(crawl_delay(value), Some(MAX_CRAWL_DELAY), "{}", value);
+    }
+}
+
+#[test]
+fn ignores_negative_and_nan_crawl_delays() {
+    for value in ["-1", "NaN", "-inf", "soon"] {
+        assert_eq!(crawl_delay(value), None, "{}", value);
+    }
+}
+
+#[test]
+fn prefers_its_own_group_and_the_longest_rule() {
+    let robots = Robots::parse("User-agent: *\nDisallow: /\n\nUser-agent: scraping-stock\nDisallow: /finance\nAllow: /finance/quote\n");
+
+    assert!(robots.is_allowed("/search"));
+    assert!(!robots.is_allowed("/finance?q=AAPL"));
+    assert!(robots.is_allowed("/finance/quote/AAPL:NASDAQ"));
+}
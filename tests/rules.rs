@@ -0,0 +1,62 @@
+use scraping_stock::{
+    indicators::Indicators,
+    rules::{Comparison, Condition, Metric, Rule, RuleSet, DEFAULT_COOLOFF},
+    Decimal, Stock, StockError,
+};
+
+
+fn quote(price: i64, change_pct: f64, rsi: f64) -> Stock {
+    let mut stock = Stock::new("AAPL".to_string(), "Apple Inc".to_string(), Decimal::from(price));
+
+    stock.change_pct = Some(change_pct);
+    stock.indicators = Some(Indicators { ma_window: 20, sma: None, ema: None, rsi_window: 14, rsi: Some(rsi), macd: None, bollinger: None });
+    stock
+}
+
+fn compare(metric: Metric, comparison: Comparison, value: f64) -> Condition {
+    Condition::Compare { metric, comparison, value }
+}
+
+#[test]
+fn binds_and_tighter_than_or() {
+    let rule: Rule = "AAPL price > 200 or change_pct > 1.5 AND rsi < 70".parse().unwrap();
+
+    assert_eq!(rule.condition, Condition::Any(vec![
+        compare(Metric::Price, Comparison::Above, 200.0),
+        Condition::All(vec![
+            compare(Metric::ChangePct, Comparison::Above, 1.5),
+            compare(Metric::Rsi, Comparison::Below, 70.0),
+        ]),
+    ]));
+}
+
+#[test]
+fn writes_combined_rules_back() {
+    for (rule, written) in [
+        ("aapl (above 200 or rsi < 30) and change_pct > 1", "AAPL (price > 200 OR rsi < 30) AND change_pct > 1"),
+        ("AAPL NOT (above 200 OR rsi < 30)", "AAPL NOT (price > 200 OR rsi < 30)"),
+        ("AAPL not macd_cross_down and below 180", "AAPL NOT macd_cross_down AND price < 180"),
+    ] {
+        let parsed: Rule = rule.parse().unwrap();
+
+        assert_eq!(parsed.to_string(), written);
+        assert_eq!(written.parse::<Rule>().unwrap(), parsed);
+    }
+}
+
+#[test]
+fn rejects_malformed_rules() {
+    for rule in ["AAPL", "AAPL above 200 AND", "AAPL (above 200", "AAPL above 200 rsi", "AAPL volume > 3", "AAPL rsi > high"] {
+        assert!(matches!(rule.parse::<Rule>(), Err(StockError::Invalid { .. })), "{} parsed", rule);
+    }
+}
+
+#[test]
+fn fires_once_every_condition_holds() {
+    let mut rules = RuleSet::new(vec!["AAPL price > 200 AND change_pct > 1.5 AND rsi < 70".parse().unwrap()], DEFAULT_COOLOFF);
+
+    assert!(rules.evaluate(&quote(205, 1.0, 60.0)).is_empty());
+    assert!(rules.evaluate(&quote(206, 2.0, 75.0)).is_empty());
+    assert_eq!(rules.evaluate(&quote(208, 2.0, 65.0)).len(), 1);
+    assert!(rules.evaluate(&quote(210, 1.8, 68.0)).is_empty());
+}
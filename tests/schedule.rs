@@ -0,0 +1,178 @@
+use std::{collections::HashMap, time::Duration};
+use chrono::{DateTime, NaiveTime, TimeZone, Utc};
+use scraping_stock::{schedule::{self, ActiveHours, ExchangeSchedule, HumanDuration, Scheduler, MAX_INTERVAL}, watch::WatchOptions, StockError};
+
+
+fn codes() -> Vec<String> {
+    vec!["AAPL:NASDAQ".to_string()]
+}
+
+fn every(secs: u64) -> Scheduler {
+    Scheduler::new(&WatchOptions { interval: Duration::from_secs(secs), ..WatchOptions::default() })
+}
+
+fn at(secs: i64) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 3, 4, 15, 0, 0).unwrap() + chrono::Duration::seconds(secs)
+}
+
+#[test]
+fn polls_on_time_ticks() {
+    let mut scheduler = every(10);
+
+    assert_eq!(scheduler.due_at(&codes(), at(0)), codes());
+    scheduler.polled_at(&codes(), at(1));
+
+    assert!(scheduler.due_at(&codes(), at(9)).is_empty());
+    assert_eq!(scheduler.due_at(&codes(), at(10)), codes());
+}
+
+#[test]
+fn keeps_to_the_ticks_after_a_slow_poll() {
+    let mut scheduler = every(10);
+    scheduler.due_at(&codes(), at(0));
+    scheduler.polled_at(&codes(), at(1));
+
+    // Due at 10, done at 13: the next tick is still at 20.
+    assert_eq!(scheduler.due_at(&codes(), at(10)), codes());
+    scheduler.polled_at(&codes(), at(13));
+
+    assert!(scheduler.due_at(&codes(), at(19)).is_empty());
+    assert_eq!(scheduler.due_at(&codes(), at(20)), codes());
+}
+
+#[test]
+fn skips_missed_ticks() {
+    let mut scheduler = every(10);
+    scheduler.due_at(&codes(), at(0));
+
+    // Done at 35, past the ticks at 10, 20 and 30, which aren't made up.
+    scheduler.polled_at(&codes(), at(35));
+
+    assert!(scheduler.due_at(&codes(), at(39)).is_empty());
+    assert_eq!(scheduler.due_at(&codes(), at(40)), codes());
+}
+
+#[test]
+fn survives_intervals_past_the_last_date() {
+    let mut scheduler = Scheduler::new(&WatchOptions {
+        intervals: HashMap::from([("AAPL:NASDAQ".to_string(), Duration::MAX)]),
+        ..WatchOptions::default()
+    });
+
+    assert_eq!(scheduler.due_at(&codes(), at(0)), codes());
+    scheduler.polled_at(&codes(), at(1));
+    scheduler.polled_at(&codes(), at(2));
+
+    assert!(scheduler.due_at(&codes(), at(1_000_000_000)).is_empty());
+}
+
+#[test]
+fn marks_quotes_stale_after_missed_polls() {
+    let scheduler = Scheduler::new(&WatchOptions { interval: Duration::from_secs(10), stale_after_polls: 3, ..WatchOptions::default() });
+    assert_eq!(scheduler.stale_after(&codes()), vec![("AAPL:NASDAQ".to_string(), Duration::from_secs(30))]);
+
+    let scheduler = Scheduler::new(&WatchOptions { interval: Duration::from_secs(10), stale_after_polls: 0, ..WatchOptions::default() });
+    assert!(scheduler.stale_after(&codes()).is_empty());
+}
+
+#[test]
+fn rejects_absurd_intervals() {
+    assert_eq!(schedule::check_interval(MAX_INTERVAL, "interval").unwrap(), MAX_INTERVAL);
+    assert!(matches!(schedule::check_interval(Duration::from_secs(u64::MAX), "interval"), Err(StockError::Invalid { what: "duration", .. })));
+    assert!(matches!(schedule::parse_entry("AAPL:NASDAQ@400d"), Err(StockError::Invalid { what: "duration", .. })));
+}
+
+fn time(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+fn hours(value: &str) -> ActiveHours {
+    value.parse().unwrap()
+}
+
+#[test]
+fn active_hours_contain_their_window() {
+    let hours = hours("09:00-16:00");
+
+    assert!(hours.contains(time(9, 0)) && hours.contains(time(15, 59)));
+    assert!(!hours.contains(time(8, 59)) && !hours.contains(time(16, 0)));
+}
+
+#[test]
+fn active_hours_wrap_past_midnight() {
+    let hours = hours("22:00-02:00");
+
+    for inside in [time(22, 0), time(23, 30), time(0, 0), time(1, 59)] {
+        assert!(hours.contains(inside), "{}", inside);
+    }
+    for outside in [time(2, 0), time(12, 0), time(21, 59)] {
+        assert!(!hours.contains(outside), "{}", outside);
+    }
+}
+
+#[test]
+fn parses_and_prints_active_hours() {
+    assert_eq!(hours(" 9:30 - 16:00 ").to_string(), "09:30-16:00");
+
+    for value in ["9-5", "09:00", "25:00-26:00", "09:00-16:00-18:00"] {
+        assert!(matches!(value.parse::<ActiveHours>(), Err(StockError::Invalid { what: "schedule", .. })), "{}", value);
+    }
+}
+
+#[test]
+fn reads_exchange_schedules() {
+    let schedule: ExchangeSchedule = toml::from_str("interval_secs = 30\nhours = \"09:00-16:00\"\ntimezone = \"Asia/Jakarta\"\nweekends = true\n").unwrap();
+
+    assert_eq!(schedule, ExchangeSchedule {
+        interval_secs: Some(30),
+        cron: None,
+        hours: Some(hours("09:00-16:00")),
+        timezone: Some(chrono_tz::Asia::Jakarta),
+        weekends: true,
+    });
+
+    assert!(toml::from_str::<ExchangeSchedule>("hours = \"9 to 5\"").is_err());
+    assert!(toml::from_str::<ExchangeSchedule>("interval = 30").is_err());
+}
+
+#[test]
+fn polls_exchanges_within_their_hours() {
+    let schedules = HashMap::from([("idx".to_string(), ExchangeSchedule { hours: Some(hours("09:00-16:00")), ..ExchangeSchedule::default() })]);
+    let mut scheduler = Scheduler::new(&WatchOptions { interval: Duration::from_secs(10), schedules, ..WatchOptions::default() });
+    let codes = vec!["BBCA:IDX".to_string(), "AAPL:NASDAQ".to_string()];
+    let utc = |day, hour| Utc.with_ymd_and_hms(2024, 3, day, hour, 0, 0).unwrap();
+
+    // 10:00 in Jakarta, the exchange's own time zone.
+    assert_eq!(scheduler.due_at(&codes, utc(4, 3)), codes);
+    // 17:00 there, and a Saturday.
+    assert_eq!(scheduler.due_at(&codes, utc(4, 10)), vec!["AAPL:NASDAQ".to_string()]);
+    assert_eq!(scheduler.due_at(&codes, utc(9, 3)), vec!["AAPL:NASDAQ".to_string()]);
+    // Back within its hours the next Monday.
+    assert_eq!(scheduler.due_at(&codes, utc(11, 3)), codes);
+}
+
+#[test]
+fn polls_exchanges_at_their_own_interval() {
+    let schedules = HashMap::from([("IDX".to_string(), ExchangeSchedule { interval_secs: Some(60), ..ExchangeSchedule::default() })]);
+    let mut scheduler = Scheduler::new(&WatchOptions { interval: Duration::from_secs(10), schedules, ..WatchOptions::default() });
+    let codes = vec!["BBCA:IDX".to_string(), "AAPL:NASDAQ".to_string()];
+
+    assert_eq!(scheduler.due_at(&codes, at(0)), codes);
+    scheduler.polled_at(&codes, at(0));
+
+    assert_eq!(scheduler.due_at(&codes, at(10)), vec!["AAPL:NASDAQ".to_string()]);
+    assert!(scheduler.due_at(&codes, at(60)).contains(&"BBCA:IDX".to_string()));
+}
+
+#[test]
+fn parses_watchlist_entries() {
+    assert_eq!(schedule::parse_entry("aapl:nasdaq@5s").unwrap(), ("AAPL:NASDAQ".to_string(), Some(HumanDuration(Duration::from_secs(5)))));
+    assert_eq!(schedule::parse_entry("BBCA:JKT").unwrap(), ("BBCA:IDX".to_string(), None));
+
+    for entry in ["AAPL:NASDAQ@0s", "AAPL:NASDAQ@soon", "AAPL:NOWHERE@5s"] {
+        assert!(matches!(schedule::parse_entry(entry), Err(StockError::Invalid { .. })), "{}", entry);
+    }
+
+    assert_eq!(schedule::exchange_of("BBCA:IDX"), "IDX");
+    assert_eq!(schedule::exchange_of("BTC-USD"), "CRYPTO");
+}
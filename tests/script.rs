@@ -0,0 +1,119 @@
+use std::path::PathBuf;
+use scraping_stock::{script::Script, StockError};
+#[cfg(feature = "lua")]
+use scraping_stock::{indicators::Indicators, Decimal, Stock};
+
+
+fn write_script(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("scraping-stock-script-{}-{}.lua", name, std::process::id()));
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+#[cfg(feature = "lua")]
+fn quote(price: &str, rsi: Option<f64>) -> Stock {
+    let mut stock = Stock::new("AAPL".to_string(), "Apple Inc".to_string(), price.parse().unwrap());
+
+    stock.indicators = Some(Indicators { ma_window: 20, sma: Some(180.0), ema: None, rsi_window: 14, rsi, macd: None, bollinger: None });
+    stock
+}
+
+#[cfg(feature = "lua")]
+fn load(name: &str, content: &str) -> Script {
+    let path = write_script(name, content);
+    let script = Script::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    script
+}
+
+#[cfg(feature = "lua")]
+#[test]
+fn transforms_quotes_and_computes_fields() {
+    let script = load("transform", r#"
+        function transform(quote)
+            if quote.symbol == "MSFT" then return false end
+            quote.company = string.upper(quote.company)
+            quote.fields.above_sma = quote.price - quote.sma
+            quote.fields.tag = "watch"
+        end
+    "#);
+
+    let mut stock = quote("189.5", None);
+    assert!(script.transform(&mut stock).unwrap());
+    assert_eq!(stock.company_name, "APPLE INC");
+    assert_eq!(stock.price, "189.5".parse::<Decimal>().unwrap());
+    assert_eq!(stock.fields["above_sma"], serde_json::json!(9.5));
+    assert!(stock.to_string().ends_with(" above_sma=9.5 tag=watch"));
+
+    let mut other = Stock::new("MSFT".to_string(), "Microsoft".to_string(), Decimal::from(400));
+    assert!(!script.transform(&mut other).unwrap());
+}
+
+#[cfg(feature = "lua")]
+#[test]
+fn replaces_the_quote_with_a_returned_table() {
+    let script = load("replace", "function transform(quote) return { price = quote.price * 2, currency = 'EUR' } end");
+    let mut stock = quote("100", None);
+
+    assert!(script.transform(&mut stock).unwrap());
+    assert_eq!((stock.price, stock.currency.as_str(), stock.company_name.as_str()), (Decimal::from(200), "EUR", "Apple Inc"));
+}
+
+#[cfg(feature = "lua")]
+#[test]
+fn decides_alerts() {
+    let script = load("alert", r#"
+        function alert(quote)
+            if quote.rsi == nil then return nil end
+            if quote.rsi < 30 then return string.format("oversold at RSI %.0f", quote.rsi) end
+            return quote.rsi > 70
+        end
+    "#);
+
+    assert_eq!(script.alert(&quote("180", None)).unwrap(), None);
+    assert_eq!(script.alert(&quote("180", Some(25.0))).unwrap(), Some("oversold at RSI 25".to_string()));
+    assert_eq!(script.alert(&quote("180", Some(50.0))).unwrap(), None);
+    assert!(script.alert(&quote("180", Some(75.0))).unwrap().is_some());
+}
+
+#[cfg(feature = "lua")]
+#[test]
+fn stops_scripts_that_never_return() {
+    let script = load("loop", "function alert(quote) while true do end end");
+
+    assert!(matches!(script.alert(&quote("180", None)), Err(StockError::Runtime { .. })));
+}
+
+#[cfg(feature = "lua")]
+#[test]
+fn reloads_the_changed_file() {
+    let path = write_script("reload", "function alert(quote) return 'first' end");
+    let mut script = Script::load(&path).unwrap();
+
+    std::thread::sleep(std::time::Duration::from_millis(2100));
+    std::fs::write(&path, "function alert(quote) return 'second' end").unwrap();
+    script.reload_if_changed();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(script.alert(&quote("180", None)).unwrap(), Some("second".to_string()));
+}
+
+#[cfg(feature = "lua")]
+#[test]
+fn rejects_broken_scripts() {
+    let path = write_script("broken", "function transform(quote");
+    let loaded = Script::load(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(loaded, Err(StockError::Parse { .. })));
+}
+
+#[cfg(not(feature = "lua"))]
+#[test]
+fn needs_the_lua_feature() {
+    let path = write_script("disabled", "");
+    let loaded = Script::load(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(loaded, Err(StockError::FeatureDisabled(_))));
+}
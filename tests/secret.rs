@@ -0,0 +1,86 @@
+use std::path::PathBuf;
+use scraping_stock::{config::Config, secret, StockError};
+
+
+fn write_file(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("scraping-stock-secret-{}-{}", name, std::process::id()));
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn reads_secrets_from_files() {
+    let token = write_file("token", "123456:abcdef\n");
+    let config = write_file("config.toml", &format!("[telegram]\ntoken = {{ file = {:?} }}\nchat_id = \"987654\"\n\n[postgres]\nurl = \"postgres://localhost/stocks\"\n", token));
+
+    let loaded = Config::load(&config, None);
+    std::fs::remove_file(&token).unwrap();
+    std::fs::remove_file(&config).unwrap();
+
+    let loaded = loaded.unwrap();
+    assert_eq!(loaded.telegram.token.as_deref(), Some("123456:abcdef"));
+    assert_eq!(loaded.postgres.url.as_deref(), Some("postgres://localhost/stocks"));
+}
+
+#[test]
+fn reads_file_variables_over_the_config() {
+    let password = write_file("password", "hunter2\r\n");
+    let config = write_file("env.toml", "[mqtt]\nbroker = \"localhost\"\n");
+    let loaded = Config::discover_with(Some(&config), None, vec![
+        ("SCRAPING_STOCK_MQTT__PASSWORD_FILE".to_string(), password.display().to_string()),
+    ]);
+    std::fs::remove_file(&password).unwrap();
+    std::fs::remove_file(&config).unwrap();
+
+    assert_eq!(loaded.unwrap().mqtt.password.as_deref(), Some("hunter2"));
+}
+
+#[test]
+fn rejects_unknown_sources_and_missing_files() {
+    let unknown = write_file("unknown.toml", "[slack]\nwebhook_url = { vault = \"slack\" }\n");
+    let missing = write_file("missing.toml", "[slack]\nwebhook_url = { file = \"/nonexistent/slack\" }\n");
+    let number = write_file("number.toml", "[email]\npassword = 1234\n");
+
+    let loaded = [Config::load(&unknown, None), Config::load(&missing, None), Config::load(&number, None)];
+    for path in [unknown, missing, number] {
+        std::fs::remove_file(path).unwrap();
+    }
+
+    for loaded in loaded {
+        match loaded {
+            Err(err @ StockError::Parse { .. }) => assert!(err.to_string().contains("slack.webhook_url") || err.to_string().contains("email.password"), "{}", err),
+            other => panic!("loaded {:?}", other.map(|config| config.slack.webhook_url)),
+        }
+    }
+}
+
+#[test]
+fn sets_variables_from_their_files() {
+    let key = write_file("key", "abc123\n");
+    std::env::set_var("SCRAPING_STOCK_TEST_KEY_FILE", &key);
+    std::env::set_var("SCRAPING_STOCK_TEST_BOTH", "plain");
+    std::env::set_var("SCRAPING_STOCK_TEST_BOTH_FILE", &key);
+
+    let loaded = secret::load_env_files(&["SCRAPING_STOCK_TEST_KEY", "SCRAPING_STOCK_TEST_UNSET"]);
+    let both = secret::load_env_files(&["SCRAPING_STOCK_TEST_BOTH"]);
+    std::fs::remove_file(&key).unwrap();
+
+    loaded.unwrap();
+    assert_eq!(std::env::var("SCRAPING_STOCK_TEST_KEY").as_deref(), Ok("abc123"));
+    assert!(std::env::var_os("SCRAPING_STOCK_TEST_UNSET").is_none());
+    assert!(matches!(both, Err(StockError::Invalid { .. })));
+}
+
+#[cfg(not(feature = "keychain"))]
+#[test]
+fn needs_the_keychain_feature() {
+    let config = write_file("keychain.toml", "[finnhub]\napi_key = { keychain = \"finnhub\" }\n");
+    let loaded = Config::load(&config, None);
+    std::fs::remove_file(&config).unwrap();
+
+    assert!(matches!(secret::keychain("finnhub"), Err(StockError::FeatureDisabled(_))));
+    match loaded {
+        Err(err) => assert!(err.to_string().contains("--features keychain"), "{}", err),
+        Ok(config) => panic!("loaded {:?}", config.finnhub.api_key),
+    }
+}
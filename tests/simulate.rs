@@ -0,0 +1,87 @@
+use scraping_stock::{
+    indicators::Indicators,
+    simulate::{PaperTrader, Side, SimulationOptions, Strategy},
+    Decimal, Stock,
+};
+
+
+fn quote(price: i64, rsi: f64) -> Stock {
+    let mut stock = Stock::new("AAPL".to_string(), "Apple Inc".to_string(), Decimal::from(price));
+
+    stock.indicators = Some(Indicators { ma_window: 20, sma: None, ema: None, rsi_window: 14, rsi: Some(rsi), macd: None, bollinger: None });
+    stock
+}
+
+fn trader(cash: i64, quantity: i64) -> PaperTrader {
+    PaperTrader::new(SimulationOptions {
+        cash: Decimal::from(cash),
+        strategies: vec![Strategy {
+            symbol: "AAPL:NASDAQ".to_string(),
+            buy: Some("rsi < 30".parse().unwrap()),
+            sell: Some("rsi > 70".parse().unwrap()),
+            plugin: None,
+            quantity: Decimal::from(quantity),
+        }],
+    })
+}
+
+/// Feeds `quotes` to `trader`, returning the side of every trade made.
+fn trade(trader: &mut PaperTrader, quotes: &[(i64, f64)]) -> Vec<Side> {
+    quotes.iter()
+        .flat_map(|(price, rsi)| trader.record("AAPL:NASDAQ", &quote(*price, *rsi)))
+        .map(|trade| trade.side)
+        .collect()
+}
+
+#[test]
+fn buys_and_sells_on_signals() {
+    let mut trader = trader(10_000, 10);
+
+    assert_eq!(trade(&mut trader, &[(190, 45.0), (180, 25.0), (178, 22.0), (200, 75.0), (205, 80.0)]), vec![Side::Buy, Side::Sell]);
+
+    let report = trader.report();
+
+    assert_eq!(report.realized_pnl, Decimal::from(200));
+    assert_eq!(report.cash, Decimal::from(10_200));
+    assert_eq!(report.equity, Decimal::from(10_200));
+    assert_eq!(report.return_pct, 2.0);
+    assert!(report.positions.is_empty());
+}
+
+#[test]
+fn values_open_positions_at_the_last_price() {
+    let mut trader = trader(10_000, 10);
+
+    trade(&mut trader, &[(180, 25.0), (175, 40.0), (170, 20.0), (186, 50.0)]);
+
+    let report = trader.report();
+
+    assert_eq!(report.trades.len(), 2);
+    assert_eq!(report.cash, Decimal::from(10_000 - 1800 - 1700));
+    assert_eq!(report.positions[0].quantity, Decimal::from(20));
+    assert_eq!(report.unrealized_pnl, Decimal::from(20 * 186 - 3500));
+    assert_eq!(report.equity, Decimal::from(10_000 - 3500 + 20 * 186));
+}
+
+#[test]
+fn skips_buys_beyond_the_cash_left() {
+    let mut trader = trader(1_000, 10);
+
+    assert!(trade(&mut trader, &[(180, 25.0)]).is_empty());
+    assert_eq!(trader.report().cash, Decimal::from(1_000));
+}
+
+#[test]
+fn ignores_other_symbols() {
+    let mut trader = trader(10_000, 10);
+
+    assert!(trader.record("MSFT:NASDAQ", &quote(180, 25.0)).is_empty());
+}
+
+#[test]
+fn reads_strategies_from_the_config() {
+    let strategy: Strategy = toml::from_str("symbol = \"AAPL:NASDAQ\"\nbuy = \"rsi < 30 AND NOT macd_cross_down\"\nsell = \"rsi > 70\"\nquantity = 10").unwrap();
+
+    assert_eq!(strategy.buy.unwrap().to_string(), "rsi < 30 AND NOT macd_cross_down");
+    assert!(toml::from_str::<Strategy>("symbol = \"AAPL:NASDAQ\"\nbuy = \"rsi <\"\nsell = \"rsi > 70\"\nquantity = 10").is_err());
+}
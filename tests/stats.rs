@@ -0,0 +1,55 @@
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+use scraping_stock::{stats::Stats, StockError};
+
+
+fn codes() -> Vec<String> {
+    vec!["AAPL:NASDAQ".to_string(), "BBCA:IDX".to_string(), "GOTO:IDX".to_string()]
+}
+
+#[test]
+fn counts_failures_by_kind() {
+    let stats = Stats::new();
+    let throttled = StockError::HttpStatus { status: 429, message: "Too Many Requests".to_string() };
+
+    stats.record("AAPL:NASDAQ", Duration::from_millis(200), None);
+    stats.record("AAPL:NASDAQ", Duration::from_millis(300), Some(&throttled));
+    stats.record("AAPL:NASDAQ", Duration::from_millis(100), Some(&StockError::Batch(Arc::new(throttled))));
+    stats.record("BBCA:IDX", Duration::from_millis(50), Some(&StockError::PriceParse("n/a".to_string())));
+
+    let report = stats.report(&codes());
+    let aapl = &report.symbols["AAPL:NASDAQ"];
+    assert_eq!((aapl.fetches, aapl.failures, aapl.parse_failures), (3, 2, 0));
+    assert_eq!(aapl.http_errors, BTreeMap::from([(429, 2)]));
+    assert_eq!(report.symbols["BBCA:IDX"].parse_failures, 1);
+    // Codes not fetched yet are reported too.
+    assert_eq!(report.symbols["GOTO:IDX"].success_rate, None);
+    assert_eq!((report.total.fetches, report.total.failures), (4, 3));
+    assert_eq!(report.total.success_rate, Some(0.25));
+}
+
+#[test]
+fn takes_percentiles_of_the_latencies() {
+    let stats = Stats::new();
+    for millis in 1..=100 {
+        stats.record("AAPL:NASDAQ", Duration::from_millis(millis), None);
+    }
+    stats.record("BBCA:IDX", Duration::from_millis(500), None);
+
+    let report = stats.report(&codes());
+    let aapl = &report.symbols["AAPL:NASDAQ"];
+    assert_eq!((aapl.p50_ms, aapl.p90_ms, aapl.p99_ms), (Some(50), Some(90), Some(99)));
+    assert_eq!(report.symbols["BBCA:IDX"].p50_ms, Some(500));
+    assert_eq!(report.total.p99_ms, Some(100));
+    assert_eq!(report.symbols["GOTO:IDX"].p50_ms, None);
+}
+
+#[test]
+fn summarizes_in_a_line() {
+    let stats = Stats::new();
+    stats.record("AAPL:NASDAQ", Duration::from_millis(120), None);
+    stats.record("BBCA:IDX", Duration::from_millis(80), Some(&StockError::HttpStatus { status: 503, message: "Service Unavailable".to_string() }));
+
+    let summary = stats.summary(&codes());
+    assert!(summary.starts_with("Since "), "{}", summary);
+    assert!(summary.ends_with(": 2 fetches, 50.0% ok, 1 HTTP 503, p50 80 ms, p90 120 ms, p99 120 ms; most failures: BBCA:IDX (1 of 1)"), "{}", summary);
+}
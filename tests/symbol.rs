@@ -0,0 +1,48 @@
+use std::collections::BTreeMap;
+use scraping_stock::{symbol, StockError};
+
+
+#[test]
+fn completes_well_known_tickers() {
+    assert_eq!(symbol::normalize("aapl").unwrap(), "AAPL:NASDAQ");
+    assert_eq!(symbol::normalize(" BBCA ").unwrap(), "BBCA:IDX");
+    assert_eq!(symbol::normalize("brk.b").unwrap(), "BRK.B:NYSE");
+}
+
+#[test]
+fn rejects_unknown_bare_tickers() {
+    let err = symbol::normalize("ZZZQ").unwrap_err();
+
+    assert!(matches!(err, StockError::Invalid { what: "symbol", .. }));
+    assert!(err.to_string().contains("expected SYMBOL:EXCHANGE"));
+}
+
+#[test]
+fn resolves_other_tickers_once() {
+    let searched = std::cell::RefCell::new(Vec::new());
+    let search = |ticker: &str| {
+        searched.borrow_mut().push(ticker.to_string());
+        Ok(format!("{}:tse", ticker))
+    };
+
+    let tickers = symbol::resolve_tickers(["shop", "AAPL", "MSFT:NASDAQ", "BTC-USD", "^GSPC", "SHOP"], search).unwrap();
+
+    assert_eq!(*searched.borrow(), vec!["SHOP"]);
+    assert_eq!(tickers, BTreeMap::from([("SHOP".to_string(), "SHOP:TSE".to_string())]));
+    assert_eq!(symbol::resolved(" Shop", &tickers), "SHOP:TSE");
+    assert_eq!(symbol::resolved("AAPL", &tickers), "AAPL");
+}
+
+#[test]
+fn keeps_resolved_tickers_out_of_normalize() {
+    symbol::resolve_tickers(["ZZZR"], |ticker| Ok(format!("{}:TSE", ticker))).unwrap();
+
+    assert!(symbol::normalize("ZZZR").is_err());
+}
+
+#[test]
+fn fails_on_tickers_not_found() {
+    let err = symbol::resolve_tickers(["NOPE"], |ticker| Err(StockError::SymbolNotFound(ticker.to_string()))).unwrap_err();
+
+    assert!(matches!(err, StockError::SymbolNotFound(ticker) if ticker == "NOPE"));
+}
@@ -0,0 +1,85 @@
+use scraping_stock::{template::{Fields, Template, Value, MAX_WIDTH}, Decimal, StockError};
+
+
+struct Quote;
+
+impl Fields for Quote {
+    const NAMES: &'static [&'static str] = &["symbol", "price", "change", "volume"];
+
+    fn value(&self, name: &str) -> Value {
+        match name {
+            "symbol" => Value::Text("AAPL".to_string()),
+            "price" => Value::Number("189.845".parse::<Decimal>().unwrap()),
+            "change" => Value::Change("-1.5".parse::<Decimal>().unwrap()),
+            _ => Value::Missing,
+        }
+    }
+}
+
+fn render(template: &str) -> String {
+    template.parse::<Template>().unwrap().render(&Quote)
+}
+
+fn is_invalid(template: &str) -> bool {
+    matches!(template.parse::<Template>(), Err(StockError::Invalid { what: "template", .. }))
+}
+
+#[test]
+fn fills_in_placeholders() {
+    assert_eq!(render("{symbol} {price} {change} {volume}"), "AAPL 189.845 -1.50 -");
+}
+
+#[test]
+fn aligns_and_fills() {
+    assert_eq!(render("[{symbol:>6}]"), "[  AAPL]");
+    assert_eq!(render("[{symbol:6}]"), "[AAPL  ]");
+    assert_eq!(render("[{symbol:*^8}]"), "[**AAPL**]");
+    // Numbers line up on the right unless told otherwise.
+    assert_eq!(render("[{price:10.1}]"), "[     189.8]");
+    assert_eq!(render("[{price:<10.1}]"), "[189.8     ]");
+    assert_eq!(render("[{symbol:2}]"), "[AAPL]");
+}
+
+#[test]
+fn signs_and_pads_with_zeros() {
+    assert_eq!(render("{price:+.2}"), "+189.85");
+    assert_eq!(render("{change:+}"), "-1.5");
+    assert_eq!(render("{change:08.2}"), "-0001.50");
+    assert_eq!(render("{price:+09.1}"), "+000189.8");
+}
+
+#[test]
+fn rounds_numbers_and_cuts_text_to_the_precision() {
+    assert_eq!(render("{price:.0}"), "190");
+    assert_eq!(render("{change:.3}"), "-1.500");
+    assert_eq!(render("{symbol:.2}"), "AA");
+}
+
+#[test]
+fn escapes_braces() {
+    assert_eq!(render("{{{symbol}}} }}"), "{AAPL} }");
+}
+
+#[test]
+fn checks_placeholder_names() {
+    let template: Template = "{symbol} {bid}".parse().unwrap();
+
+    let err = template.check_for::<Quote>().unwrap_err();
+    assert!(err.to_string().contains("unknown placeholder {bid}"), "{}", err);
+    assert!("{symbol:>8}".parse::<Template>().unwrap().check_for::<Quote>().is_ok());
+}
+
+#[test]
+fn rejects_malformed_templates() {
+    for template in ["{symbol", "{}", "{ :>5}", "price}", "{price:x}", "{price:.}", "{price:10.2.1}", "{price:+-5}"] {
+        assert!(is_invalid(template), "{}", template);
+    }
+}
+
+#[test]
+fn caps_width_and_precision() {
+    assert_eq!(render(&format!("{{symbol:{}}}", MAX_WIDTH)).len(), MAX_WIDTH);
+    assert!(is_invalid(&format!("{{price:{}}}", MAX_WIDTH + 1)));
+    assert!(is_invalid("{price:999999999}"));
+    assert!(is_invalid("{price:.999999999}"));
+}
@@ -0,0 +1,29 @@
+use scraping_stock::{scraping::yahoofinance, Decimal, StockError};
+
+
+#[test]
+fn parses_the_chart_meta() {
+    let json_content = r#"{"chart":{"result":[{"meta":{"regularMarketPrice":183.05,"chartPreviousClose":181.2,"currency":"USD","instrumentType":"EQUITY","longName":"Apple Inc."}}],"error":null}}"#;
+
+    let stock = yahoofinance::parse_stock_value(json_content.to_string(), "AAPL:NASDAQ").unwrap();
+
+    assert_eq!((stock.symbol.as_str(), stock.currency.as_str(), stock.price), ("AAPL", "USD", "183.05".parse::<Decimal>().unwrap()));
+    assert_eq!(stock.details.previous_close, Some("181.2".parse().unwrap()));
+}
+
+#[test]
+fn rejects_a_chart_without_a_price() {
+    let json_content = r#"{"chart":{"result":[{"meta":{"currency":"USD","instrumentType":"EQUITY","longName":"Apple Inc."}}],"error":null}}"#;
+
+    match yahoofinance::parse_stock_value(json_content.to_string(), "AAPL:NASDAQ") {
+        Err(err @ StockError::PriceParse(_)) => assert!(err.to_string().contains("AAPL:NASDAQ"), "{}", err),
+        other => panic!("parsed {:?}", other.map(|stock| stock.price)),
+    }
+}
+
+#[test]
+fn reports_unknown_symbols() {
+    let json_content = r#"{"chart":{"result":null,"error":{"code":"Not Found","description":"No data found, symbol may be delisted"}}}"#;
+
+    assert!(matches!(yahoofinance::parse_stock_value(json_content.to_string(), "XYZ:NASDAQ"), Err(StockError::SymbolNotFound(_))));
+}